@@ -0,0 +1,316 @@
+//! Command line: subcommands, their flags, and the exit codes failures map to.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+/// Exit codes, so scripts can tell failures apart. Errors without a code of their own
+/// exit with [`FAILURE`].
+pub mod exit {
+    /// The run, or any step of the command, failed.
+    pub const FAILURE: i32 = 1;
+    /// A path lies outside `--data-root` or `--output-root`.
+    pub const PATH: i32 = 2;
+    /// The orchestration or plugin is invalid: it does not parse, fails validation, or
+    /// fails verification.
+    pub const INVALID: i32 = 3;
+    /// Unknown flags, missing arguments, or flags that do not fit together.
+    pub const USAGE: i32 = 64;
+    /// Interrupted by Ctrl-C.
+    pub const CANCELLED: i32 = 130;
+}
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0    success
+  1    failure
+  2    path outside --data-root or --output-root
+  3    invalid orchestration or plugin
+  64   usage error
+  130  cancelled";
+
+/// Failures with an exit code of their own.
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(m) | CliError::Invalid(m) => f.write_str(m),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[derive(Parser)]
+#[command(name = "crusty-cli", version, about = "Run Crusty-TTS orchestrations and manage their plugins")]
+#[command(after_help = EXIT_CODES)]
+pub struct Cli {
+    /// Report format: `json` prints one JSON document on stdout and errors as JSON on
+    /// stderr.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run an orchestration.
+    Run(RunArgs),
+    /// Render every file an input glob or directory matches.
+    Batch(BatchArgs),
+    /// Check an orchestration and its plugins without running them.
+    Validate(OrchestrationArgs),
+    /// Show what a run would do, without starting any plugin.
+    Plan(OrchestrationArgs),
+    /// List, inspect and verify plugins.
+    #[command(subcommand)]
+    Plugins(PluginsCommand),
+    /// Write an orchestration from discovered plugins, prompting for what flags leave out.
+    Configure(ConfigureArgs),
+    /// Start a project: a starter orchestration and input text.
+    Init(InitArgs),
+    /// Write crusty.lock with the hash of every plugin an orchestration uses.
+    Lock(OrchestrationArgs),
+    /// Sign a plugin directory, writing its plugin.sig.
+    Sign(SignArgs),
+    /// Generate an ed25519 signing key and print its public key.
+    Keygen(KeygenArgs),
+}
+
+#[derive(Args)]
+pub struct OrchestrationArgs {
+    /// Orchestration file, v1 or graph.
+    #[arg(default_value = "orchestration.cr")]
+    pub orchestration: PathBuf,
+    /// Directory plugin modules are relative to [default: the orchestration's directory].
+    #[arg(short, long, value_name = "DIR")]
+    pub plugins: Option<PathBuf>,
+}
+
+/// Settings shared by `run` and `batch`.
+#[derive(Args)]
+pub struct ExecArgs {
+    #[command(flatten)]
+    pub orchestration: OrchestrationArgs,
+    /// Fail when a plugin differs from crusty.lock, instead of warning.
+    #[arg(long)]
+    pub locked: bool,
+    // Checked before each plugin runs: under `require`, the run fails on a plugin that is
+    // not signed by a trusted key.
+    #[command(flatten)]
+    pub trust: TrustArgs,
+    /// Confine the input and source files to this directory.
+    #[arg(long, value_name = "DIR")]
+    pub data_root: Option<PathBuf>,
+    /// Confine every written file to this directory.
+    #[arg(long, value_name = "DIR")]
+    pub output_root: Option<PathBuf>,
+    /// Serve deterministic stages from this synthesis cache.
+    #[arg(long, value_name = "DIR", env = "CRUSTY_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Evict the cache's oldest entries beyond this size.
+    #[arg(long, value_name = "MB")]
+    pub cache_max_mb: Option<u64>,
+    /// Write every stage's input and output here, replacing `debug.keep_intermediates`.
+    #[arg(long, value_name = "DIR")]
+    pub keep_intermediates: Option<String>,
+    /// Checkpoint TTS chunks here.
+    #[arg(long, value_name = "DIR")]
+    pub work_dir: Option<PathBuf>,
+    /// Continue from the chunks checkpointed in --work-dir.
+    #[arg(long, requires = "work_dir")]
+    pub resume: bool,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub exec: ExecArgs,
+    /// Input file, replacing `[input] source`; `-` reads stdin.
+    #[arg(short, long, value_name = "PATH")]
+    pub input: Option<String>,
+    /// Output file, replacing `[output] path`; `-` streams the audio to stdout.
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct BatchArgs {
+    #[command(flatten)]
+    pub exec: ExecArgs,
+    /// Glob pattern or directory, replacing `[input] source`.
+    #[arg(short, long, value_name = "PATTERN")]
+    pub input: Option<String>,
+    /// Output path template, replacing `[output] path`: {stem}, {name}, {dir} and {ext}
+    /// are filled per input.
+    #[arg(short, long, value_name = "TEMPLATE")]
+    pub output: Option<String>,
+    /// Inputs rendered at a time [default: number of CPUs].
+    #[arg(short, long)]
+    pub jobs: Option<NonZeroUsize>,
+}
+
+/// Which plugins to discover, and which of them to trust.
+#[derive(Args)]
+pub struct PluginDirArgs {
+    /// Plugins directory.
+    #[arg(short, long, value_name = "DIR", default_value = "plugins")]
+    pub plugins: PathBuf,
+    #[command(flatten)]
+    pub trust: TrustArgs,
+}
+
+/// Which plugins to trust: the signature policy and the keys it accepts.
+#[derive(Args)]
+pub struct TrustArgs {
+    /// `allow`, `warn` or `require` signed plugins.
+    #[arg(long, value_name = "POLICY", env = "CRUSTY_SIGNATURE_POLICY")]
+    pub signature_policy: Option<String>,
+    /// Trusted public keys: a TOML file of `[[key]]` tables, each with a `name` and a hex
+    /// `public_key` (as printed by `keygen`).
+    #[arg(long, value_name = "FILE", env = "CRUSTY_TRUSTED_KEYS")]
+    pub trusted_keys: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum PluginsCommand {
+    /// List the plugins discovered in a directory.
+    List(PluginDirArgs),
+    /// Show a plugin's manifest: type, capabilities and options.
+    Inspect(PluginArgs),
+    /// Run the conformance suite against a plugin.
+    Verify(PluginArgs),
+    /// Create a plugin: manifest, entrypoint and a test fixture for `plugins verify`.
+    New(NewPluginArgs),
+}
+
+#[derive(Args)]
+pub struct PluginArgs {
+    /// Plugin name, or the path to its directory.
+    pub name: String,
+    #[command(flatten)]
+    pub dir: PluginDirArgs,
+}
+
+#[derive(Args)]
+pub struct NewPluginArgs {
+    /// Plugin name, also the name of its directory.
+    pub name: String,
+    /// Pipeline stage the plugin serves.
+    #[arg(long = "type", value_enum, default_value_t = Stage::Tts)]
+    pub stage: Stage,
+    /// Language of the entrypoint.
+    #[arg(long, value_enum, default_value_t = Lang::Python)]
+    pub lang: Lang,
+    /// `env` (PLUGIN_INPUT and raw stdout) or `framed` (handshake and frames).
+    #[arg(long, value_enum, default_value_t = Wire::Env)]
+    pub transport: Wire,
+    /// Directory to create the plugin in.
+    #[arg(short, long, value_name = "DIR", default_value = "plugins")]
+    pub plugins: PathBuf,
+    /// Overwrite the files of an existing plugin directory.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Stage {
+    Pre,
+    Tts,
+    Converter,
+    Post,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    Rust,
+    Python,
+    Sh,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Wire {
+    Env,
+    Framed,
+}
+
+#[derive(Args)]
+pub struct ConfigureArgs {
+    #[command(flatten)]
+    pub dir: PluginDirArgs,
+    /// Orchestration file to write.
+    #[arg(short, long, value_name = "PATH", default_value = "orchestration.cr")]
+    pub output: PathBuf,
+    /// Overwrite the orchestration file if it exists.
+    #[arg(long)]
+    pub force: bool,
+    /// TTS plugin. With it nothing is prompted for: options not given with --set take
+    /// their defaults.
+    #[arg(long, value_name = "NAME")]
+    pub tts: Option<String>,
+    /// Pre-processors, in order.
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', requires = "tts")]
+    pub pre: Vec<String>,
+    /// Audio converters, in order.
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', requires = "tts")]
+    pub converters: Vec<String>,
+    /// Post-processors, in order.
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', requires = "tts")]
+    pub post: Vec<String>,
+    /// Set a plugin option, e.g. `tts.voice=en_gb` or `normalize.level=-16`.
+    #[arg(long = "set", value_name = "PLUGIN.OPTION=VALUE")]
+    pub set: Vec<String>,
+    /// `[meta] name` [default: configured-pipeline].
+    #[arg(long)]
+    pub name: Option<String>,
+    /// `[meta] author` [default: $USER].
+    #[arg(long)]
+    pub author: Option<String>,
+    /// `[input] source` [default: input.txt].
+    #[arg(long, value_name = "PATH")]
+    pub input: Option<String>,
+    /// `[output] path` [default: output/out.<ext>, from the last stage's output type].
+    #[arg(long, value_name = "PATH")]
+    pub audio: Option<String>,
+}
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Project directory.
+    #[arg(default_value = ".")]
+    pub dir: PathBuf,
+    /// Overwrite files that already exist.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct SignArgs {
+    /// Plugin directory.
+    pub plugin: PathBuf,
+    /// Secret key file written by `keygen`.
+    #[arg(long, value_name = "FILE")]
+    pub key: PathBuf,
+}
+
+#[derive(Args)]
+pub struct KeygenArgs {
+    /// File to write the secret key to.
+    pub out: PathBuf,
+    /// Replace an existing key file.
+    #[arg(long)]
+    pub force: bool,
+}
+
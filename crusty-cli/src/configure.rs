@@ -0,0 +1,332 @@
+//! `configure`: write an orchestration from discovered plugins. Flags pick the plugins and
+//! set their options; without `--tts`, whatever they leave out is prompted for on stdin.
+//! Option values are checked against each plugin's `[options]` schema, and the pipeline
+//! is validated before anything is written.
+
+use crate::cli::{CliError, ConfigureArgs, Format};
+use crate::{output_extension, plugins, print_json};
+use anyhow::Result;
+use crusty_core::orchestration::{Input, Meta, Output, PluginConfig, TtsConfig};
+use crusty_core::{validate_graph_fallbacks, validate_graph_types, Graph, OptionSpec, Orchestration, Plugin, PluginType};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Plugins picked for each stage.
+struct Selection<'a> {
+    pre: Vec<&'a Plugin>,
+    tts: &'a Plugin,
+    converters: Vec<&'a Plugin>,
+    post: Vec<&'a Plugin>,
+}
+
+impl<'a> Selection<'a> {
+    /// In pipeline order.
+    fn plugins(&self) -> impl Iterator<Item = &'a Plugin> + '_ {
+        self.pre
+            .iter()
+            .copied()
+            .chain([self.tts])
+            .chain(self.converters.iter().copied())
+            .chain(self.post.iter().copied())
+    }
+
+    /// Plugin whose output the orchestration writes.
+    fn last(&self) -> &'a Plugin {
+        self.post.last().or(self.converters.last()).copied().unwrap_or(self.tts)
+    }
+}
+
+pub fn run(args: &ConfigureArgs, format: Format) -> Result<()> {
+    let registry = plugins::load_registry(&args.dir)?;
+    let mut all = registry.all();
+    all.sort_by(|a, b| a.name.cmp(&b.name));
+    if all.is_empty() {
+        anyhow::bail!("no plugins found in {}", args.dir.plugins.display());
+    }
+    let interactive = args.tts.is_none();
+    if args.output.exists() && !args.force {
+        let overwrite = interactive
+            && prompt(&format!("{} exists; overwrite it? [y/N]: ", args.output.display()))?.eq_ignore_ascii_case("y");
+        if !overwrite {
+            return Err(CliError::Usage(format!("{} already exists (use --force to overwrite)", args.output.display())).into());
+        }
+    }
+
+    let selection = match &args.tts {
+        Some(tts) => Selection {
+            pre: pick(&all, &args.pre, PluginType::Pre, "--pre")?,
+            tts: pick(&all, std::slice::from_ref(tts), PluginType::Tts, "--tts")?[0],
+            converters: pick(&all, &args.converters, PluginType::Converter, "--converters")?,
+            post: pick(&all, &args.post, PluginType::Post, "--post")?,
+        },
+        None => prompt_selection(&all)?,
+    };
+    let sets = parse_sets(&args.set)?;
+    for (plugin, option, _) in &sets {
+        if *plugin != "tts" && !selection.plugins().any(|p| p.name == *plugin) {
+            return Err(CliError::Usage(format!("--set {}.{}: {} is not in the pipeline", plugin, option, plugin)).into());
+        }
+    }
+
+    // Modules are written relative to the orchestration's directory.
+    let base = match args.output.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let config = |p: &Plugin| -> Result<PluginConfig> {
+        let given = sets
+            .iter()
+            .filter(|(plugin, _, _)| *plugin == p.name || (*plugin == "tts" && p.name == selection.tts.name))
+            .map(|&(_, option, value)| (option, value))
+            .collect();
+        let options = plugin_options(p, &given, interactive)?;
+        Ok(PluginConfig {
+            name: p.name.clone(),
+            module: module_path(p, &base),
+            enabled: true,
+            options: (!options.is_empty()).then_some(toml::Value::Table(options)),
+            retry: None,
+        })
+    };
+    let configs = |plugins: &[&Plugin]| -> Result<Option<Vec<PluginConfig>>> {
+        let configs = plugins.iter().map(|p| config(p)).collect::<Result<Vec<_>>>()?;
+        Ok((!configs.is_empty()).then_some(configs))
+    };
+    let pre_processors = configs(&selection.pre)?;
+    // All options go in `[tts] options`, which keeps their types; the voice, rate and
+    // pitch fields are for hand-written orchestrations.
+    let tts = config(selection.tts)?;
+    let tts = TtsConfig {
+        name: tts.name,
+        module: tts.module,
+        voice: None,
+        rate: None,
+        pitch: None,
+        output_format: None,
+        chunking: None,
+        retry: None,
+        fallbacks: None,
+        options: tts.options,
+    };
+    let audio_converters = configs(&selection.converters)?;
+    let post_processors = configs(&selection.post)?;
+
+    let ask = |flag: &Option<String>, question: &str, default: String| -> Result<String> {
+        match flag {
+            Some(value) => Ok(value.clone()),
+            None if interactive => {
+                let answer = prompt(&format!("{} [{}]: ", question, default))?;
+                Ok(if answer.is_empty() { default } else { answer })
+            }
+            None => Ok(default),
+        }
+    };
+    let name = ask(&args.name, "Orchestration name", "configured-pipeline".to_string())?;
+    let author = ask(&args.author, "Author", std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()))?;
+    let source = ask(&args.input, "Input file", "input.txt".to_string())?;
+    let path = ask(&args.audio, "Output file", format!("output/out.{}", output_extension(selection.last())))?;
+
+    let orchestration = Orchestration {
+        meta: Meta {
+            name,
+            version: "0.1.0".to_string(),
+            author,
+        },
+        input: Input {
+            r#type: "text".to_string(),
+            source,
+            ssml: None,
+        },
+        pre_processors,
+        tts,
+        audio_converters,
+        post_processors,
+        output: Some(Output {
+            r#type: "file".to_string(),
+            path,
+            overwrite: Some(true),
+        }),
+        outputs: None,
+        debug: None,
+        speakers: None,
+        dialogue: None,
+    };
+    let graph = Graph::from_orchestration(&orchestration);
+    validate_graph_types(&graph, &base)
+        .and_then(|_| validate_graph_fallbacks(&graph, &base))
+        .map_err(|e| CliError::Invalid(format!("the configured pipeline does not validate: {:#}", e)))?;
+
+    std::fs::create_dir_all(&base)?;
+    std::fs::write(&args.output, toml::to_string_pretty(&orchestration)?)?;
+    eprintln!("Pipeline orchestration saved to {}", args.output.display());
+    if format == Format::Json {
+        let plugins: Vec<&str> = selection.plugins().map(|p| p.name.as_str()).collect();
+        print_json(serde_json::json!({"path": args.output, "plugins": plugins}))?;
+    }
+    Ok(())
+}
+
+/// Plugins named by a `flag`, which must be of `kind`.
+fn pick<'a>(all: &[&'a Plugin], names: &[String], kind: PluginType, flag: &str) -> Result<Vec<&'a Plugin>> {
+    names
+        .iter()
+        .map(|name| {
+            let p = all
+                .iter()
+                .find(|p| p.name == *name)
+                .ok_or_else(|| CliError::Usage(format!("{}: no plugin {:?}", flag, name)))?;
+            if p.plugin_type != kind {
+                return Err(CliError::Usage(format!("{}: {} is a {} plugin", flag, name, p.plugin_type.as_str())).into());
+            }
+            Ok(*p)
+        })
+        .collect()
+}
+
+/// List the plugins and ask for a selection until it is one the pipeline can use.
+fn prompt_selection<'a>(all: &[&'a Plugin]) -> Result<Selection<'a>> {
+    eprintln!("Found {} plugins:", all.len());
+    for (i, p) in all.iter().enumerate() {
+        eprintln!("  {}. {} ({}, {})", i + 1, p.name, p.plugin_type.as_str(), p.signature.as_str());
+    }
+    loop {
+        let line = prompt("Select plugins for pipeline (comma-separated, e.g. 1,2): ")?;
+        match parse_selection(all, &line) {
+            Ok(selection) => return Ok(selection),
+            Err(e) => eprintln!("  {}", e),
+        }
+    }
+}
+
+fn parse_selection<'a>(all: &[&'a Plugin], line: &str) -> Result<Selection<'a>, String> {
+    let mut picked: Vec<&Plugin> = Vec::new();
+    for item in line.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let p = item
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| all.get(i))
+            .ok_or_else(|| format!("{:?} is not a plugin number (1-{})", item, all.len()))?;
+        if picked.iter().any(|q| q.name == p.name) {
+            return Err(format!("{} is selected twice", p.name));
+        }
+        picked.push(p);
+    }
+    let of = |kind| picked.iter().copied().filter(|p| p.plugin_type == kind).collect::<Vec<_>>();
+    let tts = of(PluginType::Tts);
+    if tts.len() != 1 {
+        let names: Vec<&str> = tts.iter().map(|p| p.name.as_str()).collect();
+        return Err(match names.len() {
+            0 => "select a TTS plugin".to_string(),
+            _ => format!("select one TTS plugin, not {}", names.join(", ")),
+        });
+    }
+    Ok(Selection {
+        pre: of(PluginType::Pre),
+        tts: tts[0],
+        converters: of(PluginType::Converter),
+        post: of(PluginType::Post),
+    })
+}
+
+/// `--set PLUGIN.OPTION=VALUE` flags as `(plugin, option, value)`.
+fn parse_sets(sets: &[String]) -> Result<Vec<(&str, &str, &str)>> {
+    sets.iter()
+        .map(|s| {
+            let parsed = s
+                .split_once('=')
+                .and_then(|(target, value)| target.split_once('.').map(|(plugin, option)| (plugin, option, value)));
+            match parsed {
+                Some((plugin, option, value)) if !plugin.is_empty() && !option.is_empty() => Ok((plugin, option, value)),
+                _ => Err(CliError::Usage(format!("--set {:?}: expected PLUGIN.OPTION=VALUE", s)).into()),
+            }
+        })
+        .collect()
+}
+
+/// Options for `p`: the `given` values, then for the rest of its schema an answer to a
+/// prompt or, non-interactively, the default. Without a schema any option is a string.
+fn plugin_options(p: &Plugin, given: &BTreeMap<&str, &str>, interactive: bool) -> Result<toml::value::Table> {
+    let specs = p.manifest.as_ref().map(|m| m.option_specs()).unwrap_or_default();
+    let mut options = toml::value::Table::new();
+    for (&option, &value) in given {
+        let value = match specs.iter().find(|s| s.name == option) {
+            Some(spec) => spec.parse(value).map_err(|e| CliError::Usage(format!("--set {}: {:#}", p.name, e)))?,
+            None if specs.is_empty() => toml::Value::String(value.to_string()),
+            None => {
+                let known: Vec<&str> = specs.iter().map(|s| s.name.as_str()).collect();
+                return Err(CliError::Usage(format!("--set {}.{}: no such option (it has {})", p.name, option, known.join(", "))).into());
+            }
+        };
+        options.insert(option.to_string(), value);
+    }
+    for spec in specs.iter().filter(|s| !given.contains_key(s.name.as_str())) {
+        let value = if interactive {
+            prompt_option(p, spec)?
+        } else {
+            spec.default
+                .as_deref()
+                .map(|d| spec.parse(d))
+                .transpose()
+                .map_err(|e| CliError::Invalid(format!("plugin {}: bad default: {:#}", p.name, e)))?
+        };
+        if let Some(value) = value {
+            options.insert(spec.name.clone(), value);
+        }
+    }
+    Ok(options)
+}
+
+/// Ask for `spec`'s value until it checks out; an empty answer takes the default, or
+/// leaves the option unset when there is none.
+fn prompt_option(p: &Plugin, spec: &OptionSpec) -> Result<Option<toml::Value>> {
+    if let Some(d) = &spec.description {
+        eprintln!("  {}: {}", spec.name, d);
+    }
+    let kind = if spec.choices.is_empty() { spec.kind.as_str().to_string() } else { spec.choices.join("|") };
+    let default = spec.default.as_deref().map(|d| format!(" [{}]", d)).unwrap_or_default();
+    loop {
+        let answer = prompt(&format!("  {} {} ({}){}: ", p.name, spec.name, kind, default))?;
+        let value = match (answer.as_str(), &spec.default) {
+            ("", None) => return Ok(None),
+            ("", Some(d)) => d.as_str(),
+            (answer, _) => answer,
+        };
+        match spec.parse(value) {
+            Ok(v) => return Ok(Some(v)),
+            Err(e) => eprintln!("  {:#}", e),
+        }
+    }
+}
+
+/// Print `question` on stderr and read a line of stdin.
+fn prompt(question: &str) -> Result<String> {
+    eprint!("{}", question);
+    io::stderr().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        anyhow::bail!("stdin closed before configure finished");
+    }
+    Ok(line.trim().to_string())
+}
+
+/// `p`'s directory relative to `base`, where the orchestration is written.
+fn module_path(p: &Plugin, base: &Path) -> String {
+    let Ok(path) = Path::new(&p.path).canonicalize() else { return p.path.clone() };
+    // `base` need not exist yet.
+    let Ok(base) = base.canonicalize().or_else(|_| std::path::absolute(base)) else {
+        return path.to_string_lossy().to_string();
+    };
+    let common = path.components().zip(base.components()).take_while(|(a, b)| a == b).count();
+    // Sharing no more than the root, an absolute path reads better than a `../..` chain.
+    if common <= 1 {
+        return path.to_string_lossy().to_string();
+    }
+    let mut rel = PathBuf::new();
+    for _ in base.components().skip(common) {
+        rel.push("..");
+    }
+    rel.extend(path.components().skip(common));
+    rel.to_string_lossy().to_string()
+}
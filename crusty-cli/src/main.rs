@@ -1,284 +1,733 @@
-//! Crusty-TTS CLI: run pipeline, or interactive configure to write orchestration.cr.
+//! Crusty-TTS CLI: run or batch-render orchestrations, validate and plan them, list,
+//! inspect and verify plugins, write orchestrations (`configure`, `init`), lock plugin
+//! hashes and sign plugins. `crusty-cli --help` lists the subcommands and exit codes.
+//!
+//! With `--format json`, each command prints one JSON document on stdout and a failure
+//! as `{"error", "kind", "exit_code"}` on stderr; progress and diagnostics always go to
+//! stderr.
+
+mod cli;
+mod configure;
+mod plugins;
+mod scaffold;
 
 use anyhow::Result;
+use clap::Parser;
+use cli::{exit, BatchArgs, Cli, CliError, Command, ExecArgs, Format, InitArgs, KeygenArgs, OrchestrationArgs, RunArgs, SignArgs};
 use crusty_core::{
-    execute_pipeline, Orchestration, PluginRegistry, PluginType,
-    orchestration::{Meta, Input, Output, PluginConfig, TtsConfig},
+    execute_batch, execute_graph_report, is_cancelled, Batch, BatchItem, CacheUse, CancellationToken, SynthesisCache, DEFAULT_CACHE_MAX_BYTES, ExecutionContext, IntegrityMode, LockCheck, Lockfile,
+    Graph, NodeKind, PathError, PathPolicy, PipelineObserver, PipelineOutput, Plugin, PluginRegistry, ProgressFrame, Ssml, SSML_TYPE, StageInfo, read_input, validate_graph_fallbacks, validate_graph_types, validate_ssml,
+    artifacts::extension_for, graph,
 };
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::env;
 
-fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.get(1).map(|s| s.as_str()) == Some("configure") {
-        run_configure(&args[2..])?;
-        return Ok(());
+fn main() {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            // `--help` and `--version` are not errors.
+            let code = if e.use_stderr() { exit::USAGE } else { 0 };
+            let _ = e.print();
+            std::process::exit(code);
+        }
+    };
+    let format = cli.format;
+    if let Err(e) = dispatch(cli) {
+        let (code, kind) = classify(&e);
+        match format {
+            Format::Json => eprintln!("{}", serde_json::json!({"error": format!("{:#}", e), "kind": kind, "exit_code": code})),
+            Format::Text if code == exit::CANCELLED => eprintln!("Cancelled"),
+            Format::Text => eprintln!("Error: {:#}", e),
+        }
+        std::process::exit(code);
+    }
+}
+
+fn dispatch(cli: Cli) -> Result<()> {
+    let format = cli.format;
+    match cli.command {
+        Command::Run(args) => run_pipeline(args, format),
+        Command::Batch(args) => run_batch(args, format),
+        Command::Validate(args) => run_validate(&args, format),
+        Command::Plan(args) => run_plan(&args, format),
+        Command::Plugins(command) => plugins::run(command, format),
+        Command::Configure(args) => configure::run(&args, format),
+        Command::Init(args) => run_init(&args, format),
+        Command::Lock(args) => run_lock(&args, format),
+        Command::Sign(args) => run_sign(&args, format),
+        Command::Keygen(args) => run_keygen(&args, format),
+    }
+}
+
+/// Exit code of a failed command, and the `kind` reported with `--format json`.
+fn classify(e: &anyhow::Error) -> (i32, &'static str) {
+    if e.downcast_ref::<PathError>().is_some() {
+        return (exit::PATH, "path");
     }
-    run_pipeline(&args)?;
+    if is_cancelled(e) {
+        return (exit::CANCELLED, "cancelled");
+    }
+    match e.downcast_ref::<CliError>() {
+        Some(CliError::Usage(_)) => (exit::USAGE, "usage"),
+        Some(CliError::Invalid(_)) => (exit::INVALID, "invalid"),
+        None => (exit::FAILURE, "failure"),
+    }
+}
+
+/// Print a command's `--format json` report.
+fn print_json(value: serde_json::Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&value)?);
     Ok(())
 }
 
-fn run_configure(args: &[String]) -> Result<()> {
-    let plugins_dir = args
-        .iter()
-        .position(|a| a == "--plugins" || a == "-p")
-        .and_then(|i| args.get(i + 1))
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("plugins"));
-
-    let registry = PluginRegistry::load_plugins(&plugins_dir)?;
-    let all: Vec<_> = registry.all().into_iter().cloned().collect();
-
-    if all.is_empty() {
-        eprintln!("No plugins found in {}", plugins_dir.display());
-        return Ok(());
-    }
-
-    eprintln!("Found {} plugins:", all.len());
-    for (i, p) in all.iter().enumerate() {
-        eprintln!("  {}. {} ({})", i + 1, p.name, p.plugin_type.as_str());
-    }
-
-    eprint!("Select plugins for pipeline (comma-separated, e.g. 1,2): ");
-    io::stderr().flush()?;
-    let mut line = String::new();
-    io::stdin().read_line(&mut line)?;
-    let indices: Vec<usize> = line
-        .trim()
-        .split(',')
-        .filter_map(|s| s.trim().parse::<usize>().ok().map(|n| n.saturating_sub(1)))
-        .collect();
-
-    let mut pre = Vec::new();
-    let mut tts_plugin = None;
-    let mut converters = Vec::new();
-    let mut post = Vec::new();
-
-    for &idx in &indices {
-        let Some(p) = all.get(idx) else { continue };
-        match p.plugin_type {
-            PluginType::Pre => pre.push(p.clone()),
-            PluginType::Tts => {
-                if tts_plugin.is_none() {
-                    tts_plugin = Some(p.clone());
-                }
-            }
-            PluginType::Converter => converters.push(p.clone()),
-            PluginType::Post => post.push(p.clone()),
+/// Load an orchestration; one that does not parse is invalid.
+fn load_graph(path: &Path) -> Result<Graph> {
+    if !path.is_file() {
+        anyhow::bail!("no orchestration at {}", path.display());
+    }
+    Graph::load_path(path).map_err(|e| CliError::Invalid(format!("{}: {:#}", path.display(), e)).into())
+}
+
+/// `--plugins`, else the orchestration's directory.
+fn plugin_base(args: &OrchestrationArgs) -> PathBuf {
+    args.plugins.clone().unwrap_or_else(|| {
+        args.orchestration
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf()
+    })
+}
+
+/// File extension for `p`'s output: that of its first declared output type, else `wav`.
+fn output_extension(p: &Plugin) -> &'static str {
+    let output = p.manifest.as_ref().and_then(|m| m.capabilities.as_ref()).and_then(|c| c.output.as_ref());
+    output.and_then(|o| o.first()).map_or("wav", |t| extension_for(t))
+}
+
+/// Problems with the input as a run would read it, the orchestration's `[input]` source
+/// being relative to its directory. SSML is checked against the elements each TTS engine
+/// declares; those findings are warnings, as the run downgrades such SSML.
+fn check_input(graph: &Graph, orchestration: &Path, plugin_base: &Path, registry: &PluginRegistry) -> (Vec<String>, Vec<String>) {
+    let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+    let base = orchestration.parent().unwrap_or_else(|| Path::new("."));
+    if let Ok(bytes) = std::fs::read(base.join(&graph.input.source)) {
+        match read_input(&graph.input, &bytes, Some(registry)) {
+            Ok(input) if input.content_type == SSML_TYPE => match Ssml::parse(&input.text) {
+                Ok(doc) => warnings.extend(validate_ssml(graph, &doc, plugin_base)),
+                Err(e) => errors.push(format!("input: {}", e)),
+            },
+            Ok(_) => {}
+            Err(e) => errors.push(format!("input: {:#}", e)),
         }
     }
+    (errors, warnings)
+}
 
-    let tts_plugin = match tts_plugin {
-        Some(p) => p,
+/// `init [dir]`: write a starter orchestration.cr and input.txt. The TTS stage uses the
+/// first TTS plugin in `dir/plugins`, if there is one.
+fn run_init(args: &InitArgs, format: Format) -> Result<()> {
+    let orchestration_path = args.dir.join("orchestration.cr");
+    let input_path = args.dir.join("input.txt");
+    for path in [&orchestration_path, &input_path] {
+        if path.exists() && !args.force {
+            return Err(CliError::Usage(format!("{} already exists (use --force to overwrite)", path.display())).into());
+        }
+    }
+    let plugins_dir = args.dir.join("plugins");
+    let tts = PluginRegistry::load_plugins(&plugins_dir)
+        .ok()
+        .and_then(|r| r.tts.iter().min_by(|a, b| a.name.cmp(&b.name)).cloned());
+    let (tts_name, module, ext) = match &tts {
+        Some(p) => {
+            let dir = Path::new(&p.path).file_name().unwrap_or_default().to_string_lossy().to_string();
+            (p.name.clone(), format!("plugins/{}", dir), output_extension(p))
+        }
         None => {
-            eprintln!("No TTS plugin selected. Select at least one TTS.");
-            return Ok(());
+            eprintln!("warning: no TTS plugin in {}; set [tts] before running", plugins_dir.display());
+            ("my-tts".to_string(), "plugins/my-tts".to_string(), "wav")
         }
     };
+    let name = std::fs::canonicalize(&args.dir)
+        .ok()
+        .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "crusty-project".to_string());
+    let author = env::var("USER").unwrap_or_else(|_| "unknown".to_string());
+    let orchestration = format!(
+        r#"# Crusty-TTS orchestration: `crusty-cli run` reads [input], synthesizes it with [tts]
+# and writes [output].
 
-    // Prompt for options per selected plugin
-    let pre_configs: Vec<PluginConfig> = pre
-        .iter()
-        .map(|p| prompt_plugin_options(p, &plugins_dir))
-        .collect();
-    let tts_config = plugin_to_tts_config(&tts_plugin, &plugins_dir);
-    let conv_configs: Vec<PluginConfig> = converters
-        .iter()
-        .map(|p| prompt_plugin_options(p, &plugins_dir))
-        .collect();
-    let post_configs: Vec<PluginConfig> = post
-        .iter()
-        .map(|p| prompt_plugin_options(p, &plugins_dir))
-        .collect();
-
-    let orchestration = Orchestration {
-        meta: Meta {
-            name: "configured-pipeline".to_string(),
-            version: "0.1.0".to_string(),
-            author: "Crusty-TTS".to_string(),
-        },
-        input: Input {
-            r#type: "text".to_string(),
-            source: "input.txt".to_string(),
-        },
-        pre_processors: if pre_configs.is_empty() {
-            None
-        } else {
-            Some(pre_configs)
-        },
-        tts: tts_config,
-        audio_converters: if conv_configs.is_empty() {
-            None
-        } else {
-            Some(conv_configs)
-        },
-        post_processors: if post_configs.is_empty() {
-            None
-        } else {
-            Some(post_configs)
-        },
-        output: Output {
-            r#type: "file".to_string(),
-            path: "output/out.bin".to_string(),
-            overwrite: Some(true),
-        },
-    };
+[meta]
+name = {name:?}
+version = "0.1.0"
+author = {author:?}
+
+[input]
+type = "text"
+source = "input.txt"
 
-    let toml = toml::to_string_pretty(&orchestration)?;
-    let out_path = PathBuf::from("orchestration.cr");
-    std::fs::write(&out_path, toml)?;
-    eprintln!("Pipeline orchestration saved to {}", out_path.display());
+[tts]
+name = {tts_name:?}
+module = {module:?}
+
+# Split long input before synthesis (uncomment to enable).
+# [tts.chunking]
+# mode = "sentence"      # or "paragraph"
+# max_chars = 1000
+# parallelism = 2
+# silence_ms = 250
+
+[output]
+type = "file"
+path = "output/out.{ext}"
+overwrite = true
+"#
+    );
+    std::fs::create_dir_all(&args.dir)?;
+    std::fs::write(&orchestration_path, orchestration)?;
+    std::fs::write(&input_path, "Hello from Crusty-TTS.\n")?;
+    eprintln!("Wrote {} and {}", orchestration_path.display(), input_path.display());
+    if format == Format::Json {
+        print_json(serde_json::json!({
+            "files": [orchestration_path, input_path],
+            "tts": tts.map(|p| p.name),
+        }))?;
+    }
     Ok(())
 }
 
-fn prompt_plugin_options(p: &crusty_core::Plugin, _plugins_dir: &Path) -> PluginConfig {
-    let path = Path::new(&p.path);
-    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    let module = path
-        .strip_prefix(&cwd)
-        .unwrap_or(path)
-        .to_string_lossy()
-        .to_string();
-    let mut options = toml::value::Table::new();
-    for (k, v) in &p.options {
-        eprint!("  {} [{}] for {}: ", k, v, p.name);
-        io::stderr().flush().ok();
-        let mut line = String::new();
-        io::stdin().read_line(&mut line).ok();
-        let val = line.trim();
-        let value = if val.is_empty() {
-            toml::Value::String(v.clone())
-        } else {
-            toml::Value::String(val.to_string())
+/// `validate`: check that the orchestration parses, its plugins exist and their types
+/// connect, and the input reads, without running anything.
+fn run_validate(args: &OrchestrationArgs, format: Format) -> Result<()> {
+    let graph = load_graph(&args.orchestration)?;
+    let plugin_base = plugin_base(args);
+    let mut errors = Vec::new();
+    for (name, module) in graph.stage_modules() {
+        let dir = plugin_base.join(module);
+        if !dir.join("plugin.toml").is_file() {
+            errors.push(format!("plugin {}: no plugin.toml in {}", name, dir.display()));
+        }
+    }
+    // Types are only worth checking once every plugin is there.
+    if errors.is_empty() {
+        if let Err(e) = validate_graph_types(&graph, &plugin_base) {
+            errors.push(format!("types: {:#}", e));
+        } else if let Err(e) = validate_graph_fallbacks(&graph, &plugin_base) {
+            errors.push(format!("fallbacks: {:#}", e));
+        }
+    }
+    let tts_module = graph.nodes.iter().find(|n| n.kind == NodeKind::Tts).map(|n| plugin_base.join(n.module()));
+    let registry_dir = tts_module.as_deref().and_then(Path::parent).unwrap_or(&plugin_base);
+    let registry = PluginRegistry::load_plugins(registry_dir).unwrap_or_default();
+    let (input_errors, warnings) = check_input(&graph, &args.orchestration, &plugin_base, &registry);
+    errors.extend(input_errors);
+
+    if format == Format::Json {
+        print_json(serde_json::json!({"valid": errors.is_empty(), "errors": errors, "warnings": warnings}))?;
+    } else {
+        for w in &warnings {
+            eprintln!("warning: {}", w);
+        }
+        for e in &errors {
+            eprintln!("error: {}", e);
+        }
+        if errors.is_empty() {
+            eprintln!("{} is valid", args.orchestration.display());
+        }
+    }
+    if !errors.is_empty() {
+        let problems = if errors.len() == 1 { "problem" } else { "problems" };
+        return Err(CliError::Invalid(format!("{} has {} {}", args.orchestration.display(), errors.len(), problems)).into());
+    }
+    Ok(())
+}
+
+/// `plan`: show what a run would do without starting any plugin. Plugins are discovered
+/// next to the TTS module.
+fn run_plan(args: &OrchestrationArgs, format: Format) -> Result<()> {
+    let graph = load_graph(&args.orchestration)?;
+    let plugin_base = plugin_base(args);
+    let tts_module = graph.nodes.iter().find(|n| n.kind == NodeKind::Tts).map(|n| plugin_base.join(n.module()));
+    let registry_dir = tts_module.as_deref().and_then(Path::parent).unwrap_or(&plugin_base);
+    let registry = PluginRegistry::load_plugins(registry_dir).unwrap_or_default();
+    let mut plan = crusty_core::plan_graph(&graph, &registry, &plugin_base, &PathPolicy::default());
+    let (errors, warnings) = check_input(&graph, &args.orchestration, &plugin_base, &registry);
+    plan.warnings.extend(errors.into_iter().chain(warnings));
+    if format == Format::Json {
+        return print_json(serde_json::to_value(&plan)?);
+    }
+    let outputs: Vec<&str> = plan.output.iter().chain(plan.outputs.values()).map(String::as_str).collect();
+    println!("Plan for {}: {} -> {}", plan.name, plan.input, outputs.join(", "));
+    for (i, stage) in plan.stages.iter().enumerate() {
+        let plugin = match (&stage.plugin, &stage.version) {
+            (Some(p), Some(v)) => format!("{} {}", p, v),
+            (Some(p), None) => p.clone(),
+            (None, _) => "<unresolved>".to_string(),
+        };
+        let wire = match (stage.transport, stage.lifecycle) {
+            (Some(t), Some(l)) => format!(", {}/{}", t, l),
+            _ => String::new(),
         };
-        options.insert(k.clone(), value);
+        let branch = stage.branch.as_ref().map(|b| format!(" [output {}]", b)).unwrap_or_default();
+        println!(
+            "{:>3}. {} {} -> {} ({}{}){}",
+            i + 1,
+            stage.kind,
+            stage.name,
+            plugin,
+            stage.backend,
+            wire,
+            branch
+        );
+        // Only wiring that differs from "previous stage's output" is worth printing.
+        let follows_previous = match i.checked_sub(1) {
+            None => stage.inputs.iter().all(|r| graph::ref_node(r) == graph::INPUT_NODE),
+            Some(prev) => stage.inputs == [format!("{}.out", plan.stages[prev].node)],
+        };
+        if !follows_previous {
+            println!("     inputs:  {}", stage.inputs.join(", "));
+        }
+        if let Some(exec) = &stage.executable {
+            println!("     exec:    {}", exec.display());
+        }
+        println!("     types:   {} -> {}", stage.input_type, stage.output_type);
+        if !stage.options.is_empty() {
+            let opts: Vec<String> = stage.options.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            println!("     options: {}", opts.join(", "));
+        }
+        let timeout = stage.timeout_ms.map(|t| format!("{} ms", t)).unwrap_or_else(|| "none".to_string());
+        println!(
+            "     timeout: {}; sandbox: {}; cache: {}",
+            timeout,
+            stage.sandbox,
+            if stage.cacheable { "yes" } else { "no" }
+        );
+        if let Some(c) = &stage.chunking {
+            println!(
+                "     chunks:  {:?}, max {} chars, {} in parallel, {} ms gap",
+                c.mode, c.max_chars, c.parallelism, c.silence_ms
+            );
+        }
+        if let Some(r) = &stage.retry {
+            println!("     retry:   {} attempts, {} ms backoff", r.attempts, r.backoff_ms);
+        }
+        if !stage.fallbacks.is_empty() {
+            println!("     fallback: {}", stage.fallbacks.join(", "));
+        }
+        if !stage.speakers.is_empty() {
+            let speakers: Vec<String> = stage.speakers.iter().map(|(s, e)| format!("{} ({})", s, e)).collect();
+            println!("     speakers: {}", speakers.join(", "));
+        }
     }
-    PluginConfig {
-        name: p.name.clone(),
-        module,
-        enabled: true,
-        options: Some(toml::Value::Table(options)),
+    for w in &plan.warnings {
+        eprintln!("warning: {}", w);
     }
+    Ok(())
 }
 
-fn plugin_to_tts_config(p: &crusty_core::Plugin, _plugins_dir: &Path) -> TtsConfig {
-    let path = Path::new(&p.path);
-    let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    let module = path
-        .strip_prefix(&cwd)
-        .unwrap_or(path)
-        .to_string_lossy()
-        .to_string();
-    TtsConfig {
-        name: p.name.clone(),
-        module,
-        voice: p.options.get("voice").cloned(),
-        rate: p.options.get("rate").and_then(|s| s.parse().ok()),
-        pitch: p.options.get("pitch").and_then(|s| s.parse().ok()),
-        output_format: Some("wav".to_string()),
+/// `lock`: write crusty.lock next to the orchestration.
+fn run_lock(args: &OrchestrationArgs, format: Format) -> Result<()> {
+    let graph = load_graph(&args.orchestration)?;
+    let lock = Lockfile::for_modules(&graph.stage_modules(), &plugin_base(args))?;
+    let lock_path = Lockfile::path_for(&args.orchestration);
+    lock.save(&lock_path)?;
+    for p in &lock.plugins {
+        eprintln!("  {} {} {}", p.name, p.version, p.hash);
+    }
+    eprintln!("Locked {} plugins in {}", lock.plugins.len(), lock_path.display());
+    if format == Format::Json {
+        print_json(serde_json::json!({"path": lock_path, "plugins": lock.plugins}))?;
     }
+    Ok(())
 }
 
-fn run_pipeline(args: &[String]) -> Result<()> {
-    let (orchestration_path, plugin_dir, input_override, output_override) = parse_args(args)?;
+/// `sign <plugin-dir> --key <secret-key-file>`: write plugin.sig over the directory hash.
+fn run_sign(args: &SignArgs, format: Format) -> Result<()> {
+    let key = crusty_core::trust::parse_signing_key(&std::fs::read_to_string(&args.key)?)?;
+    let hash = crusty_core::trust::sign_plugin_dir(&args.plugin, &key)?;
+    let public_key = crusty_core::trust::public_key_hex(&key);
+    eprintln!("Signed {} ({}) with key {}", args.plugin.display(), hash, public_key);
+    if format == Format::Json {
+        print_json(serde_json::json!({"plugin": args.plugin, "hash": hash, "public_key": public_key}))?;
+    }
+    Ok(())
+}
 
-    let mut orchestration = Orchestration::load_path(&orchestration_path)?;
-    let plugin_base = plugin_dir.unwrap_or_else(|| {
-        orchestration_path
-            .parent()
-            .unwrap_or_else(|| Path::new("."))
-            .to_path_buf()
-    });
+/// `keygen <secret-key-file>`: write a new ed25519 seed (hex) and print the public key.
+fn run_keygen(args: &KeygenArgs, format: Format) -> Result<()> {
+    let out = &args.out;
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).map_err(|e| anyhow::anyhow!("generate key: {}", e))?;
+    let hex = crusty_core::lock::to_hex(&seed);
+    if args.force {
+        // Removed rather than truncated, so the new file is created with owner-only permissions.
+        match std::fs::remove_file(out) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = match options.open(out) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(CliError::Usage(format!("{} already exists (use --force to overwrite)", out.display())).into());
+        }
+        r => r?,
+    };
+    writeln!(file, "{}", hex)?;
+    let key = crusty_core::trust::parse_signing_key(&hex)?;
+    let public_key = crusty_core::trust::public_key_hex(&key);
+    eprintln!("Wrote secret key to {}", out.display());
+    match format {
+        Format::Json => print_json(serde_json::json!({"path": out, "public_key": public_key}))?,
+        Format::Text => println!("{}", public_key),
+    }
+    Ok(())
+}
+
+/// What `run` and `batch` share: the orchestration with its paths resolved and the
+/// context it runs in.
+struct Prepared {
+    orchestration: Graph,
+    plugin_base: PathBuf,
+    ctx: ExecutionContext,
+}
+
+fn prepare(args: ExecArgs, input_override: Option<String>) -> Result<Prepared> {
+    let ExecArgs {
+        orchestration: orchestration_args,
+        locked,
+        trust,
+        data_root,
+        output_root,
+        cache_dir,
+        cache_max_mb,
+        keep_intermediates,
+        work_dir,
+        resume,
+    } = args;
+    let paths = PathPolicy {
+        data_root,
+        output_root,
+        ..Default::default()
+    };
+
+    // v1 orchestrations and v2 graphs both run as a graph.
+    let orchestration_path = &orchestration_args.orchestration;
+    let mut orchestration = load_graph(orchestration_path)?;
+    let plugin_base = plugin_base(&orchestration_args);
     let input_path = PathBuf::from(
         input_override.unwrap_or_else(|| orchestration.input.source.clone())
     );
-    if input_path.is_relative() {
+    if input_path == Path::new("-") {
+        // Read from stdin by `run`.
+        orchestration.input.source = "-".to_string();
+    } else if paths.data_root.is_some() {
+        // Relative to (and confined to) the data root; resolved by the executor.
+        orchestration.input.source = input_path.to_string_lossy().to_string();
+    } else if input_path.is_relative() {
         let base = orchestration_path.parent().unwrap_or_else(|| Path::new("."));
         orchestration.input.source = base.join(input_path).to_string_lossy().to_string();
     } else {
         orchestration.input.source = input_path.to_string_lossy().to_string();
     }
+    // Source nodes (music beds and the like) are relative to the orchestration too.
+    if paths.data_root.is_none() {
+        let base = orchestration_path.parent().unwrap_or_else(|| Path::new("."));
+        for path in orchestration.nodes.iter_mut().filter_map(|n| n.path.as_mut()) {
+            if Path::new(path.as_str()).is_relative() {
+                *path = base.join(&path).to_string_lossy().to_string();
+            }
+        }
+    }
+
+    // crusty.lock next to the orchestration: drift warns by default, fails with --locked.
+    let lock_path = Lockfile::path_for(orchestration_path);
+    let lock = if locked {
+        Some(LockCheck {
+            lock: Lockfile::load(&lock_path)?,
+            mode: IntegrityMode::Enforce,
+        })
+    } else if lock_path.exists() {
+        Some(LockCheck {
+            lock: Lockfile::load(&lock_path)?,
+            mode: IntegrityMode::Warn,
+        })
+    } else {
+        None
+    };
+    // --signature-policy and --trusted-keys are checked before each plugin runs.
+    let trust = plugins::trust_config(&trust)?;
+    // --cache-dir (or CRUSTY_CACHE_DIR) enables the synthesis cache.
+    let cache = match cache_dir {
+        Some(dir) => {
+            let max_bytes = cache_max_mb.map(|mb| mb * 1024 * 1024).unwrap_or(DEFAULT_CACHE_MAX_BYTES);
+            Some(Arc::new(SynthesisCache::open(&dir, max_bytes)?))
+        }
+        None => None,
+    };
+    // Ctrl-C cancels the run, which kills the running plugin's process group.
+    let cancel = CancellationToken::new();
+    let handler_token = cancel.clone();
+    ctrlc::set_handler(move || handler_token.cancel())?;
+    // --keep-intermediates overrides `debug.keep_intermediates` in the orchestration.
+    let keep_intermediates = keep_intermediates.map(|d| paths.resolve_output(&d)).transpose()?;
+    let ctx = ExecutionContext {
+        lock: lock.map(Arc::new),
+        trust: Some(Arc::new(trust)),
+        paths,
+        cache,
+        cancel: Some(cancel),
+        keep_intermediates,
+        // --work-dir checkpoints TTS chunks; --resume continues from them.
+        work_dir,
+        resume,
+        ..Default::default()
+    };
+    Ok(Prepared {
+        orchestration,
+        plugin_base,
+        ctx,
+    })
+}
 
-    let audio = execute_pipeline(&orchestration, &plugin_base)?;
+/// `run`: execute the orchestration and write its outputs.
+fn run_pipeline(args: RunArgs, format: Format) -> Result<()> {
+    let output_override = args.output;
+    let to_stdout = output_override.as_deref() == Some("-");
+    if to_stdout && format == Format::Json {
+        return Err(CliError::Usage("--format json reports on stdout, so it cannot take --output -".to_string()).into());
+    }
+    let Prepared {
+        orchestration,
+        plugin_base,
+        mut ctx,
+    } = prepare(args.exec, args.input)?;
+    let progress = io::stderr().is_terminal().then(|| Arc::new(ProgressBar::default()));
+    ctx.observer = progress.clone().map(|p| p as Arc<dyn PipelineObserver>);
+    // `--input -` reads the input text from stdin.
+    if orchestration.input.source == "-" {
+        let mut input = Vec::new();
+        io::stdin().read_to_end(&mut input)?;
+        ctx.input = Some(input.into());
+    }
 
-    match output_override.as_deref() {
-        Some("-") => {
-            io::stdout().write_all(&audio)?;
-            io::stdout().flush()?;
+    // Check output paths before running, so a rejected path costs no synthesis.
+    // `--output` replaces the main `[output]`; `-` streams it to stdout as it is
+    // synthesized, so the CLI can sit in a pipe.
+    if to_stdout {
+        ctx.stream = Some(Arc::new(Mutex::new(io::stdout())));
+    }
+    if output_override.is_some() && orchestration.output.is_none() {
+        return Err(CliError::Usage("--output replaces [output], which this orchestration does not have".to_string()).into());
+    }
+    let out_path = match output_override.as_deref() {
+        Some("-") => None,
+        Some(path) => Some(ctx.paths.resolve_output(path)?),
+        None => orchestration.output.as_ref().map(|o| ctx.paths.resolve_output(&o.path)).transpose()?,
+    };
+    let branch_paths = orchestration
+        .outputs
+        .iter()
+        .map(|b| ctx.paths.resolve_output(&b.path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let result = execute_graph_report(&orchestration, &plugin_base, &ctx);
+    if let Some(p) = &progress {
+        p.finish();
+    }
+    let output = result?;
+    for stage in output.report.stages.iter().filter(|s| s.cache != CacheUse::Off) {
+        match stage.cache {
+            CacheUse::Bypass => eprintln!("cache: {} {} bypassed (non-deterministic)", stage.kind, stage.name),
+            _ => eprintln!(
+                "cache: {} {} {} hit, {} miss",
+                stage.kind, stage.name, stage.cache_hits, stage.cache_misses
+            ),
         }
-        Some(path) => {
-            let out_path = PathBuf::from(path);
-            if let Some(p) = out_path.parent() {
-                std::fs::create_dir_all(p)?;
-            }
-            std::fs::write(path, &audio)?;
-            eprintln!("Wrote {} bytes to {}", audio.len(), path);
+    }
+    for stage in output.report.stages.iter().filter(|s| s.resumed > 0) {
+        eprintln!("resume: {} {} reused {} checkpointed chunks", stage.kind, stage.name, stage.resumed);
+    }
+    if !output.report.chapters.is_empty() {
+        let titles: Vec<&str> = output.report.chapters.iter().map(|c| c.title.as_str()).collect();
+        eprintln!("input: {} chapters: {}", titles.len(), titles.join(", "));
+    }
+    for stage in output.report.stages.iter().filter(|s| s.engine != s.name) {
+        eprintln!("{} {}: synthesized by fallback {}", stage.kind, stage.name, stage.engine);
+    }
+
+    // With `--output -` the audio has already been streamed.
+    let mut written = Vec::new();
+    if let Some(out_path) = out_path {
+        write_output(&out_path, &output.audio)?;
+        written.push(serde_json::json!({
+            "name": null,
+            "path": out_path,
+            "content_type": output.audio_type,
+            "bytes": output.audio.len(),
+        }));
+    }
+    for (branch, path) in output.outputs.iter().zip(&branch_paths) {
+        write_output(path, &branch.audio)?;
+        written.push(serde_json::json!({
+            "name": branch.name,
+            "path": path,
+            "content_type": branch.audio_type,
+            "bytes": branch.audio.len(),
+        }));
+    }
+    if format == Format::Json {
+        print_json(serde_json::json!({"outputs": written, "report": output.report}))?;
+    }
+    Ok(())
+}
+
+/// `batch`: render every file the input glob or directory matches, each to the output
+/// paths its templates give. Failed inputs are reported at the end, not fatal on the way.
+fn run_batch(args: BatchArgs, format: Format) -> Result<()> {
+    let Prepared {
+        orchestration,
+        plugin_base,
+        ctx,
+    } = prepare(args.exec, args.input)?;
+    if orchestration.input.source == "-" {
+        return Err(CliError::Usage("a batch reads its inputs from files; use `run` to read stdin".to_string()).into());
+    }
+    let batch = Batch::expand(&orchestration.input.source, &ctx.paths)?;
+    // `--output` replaces the `[output]` template; `[[outputs]]` paths are templates too.
+    let main_template = match args.output {
+        Some(template) if orchestration.output.is_some() => Some(template),
+        Some(_) => return Err(CliError::Usage("--output replaces [output], which this orchestration does not have".to_string()).into()),
+        None => orchestration.output.as_ref().map(|o| o.path.clone()),
+    };
+    let templates: Vec<&str> = main_template.iter().map(String::as_str).chain(orchestration.outputs.iter().map(|b| b.path.as_str())).collect();
+    for template in &templates {
+        batch.check_template(template).map_err(|e| CliError::Invalid(format!("{:#}", e)))?;
+    }
+    let jobs = args.jobs.map_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()), |n| n.get());
+    let total = batch.inputs.len();
+    eprintln!("batch: {} inputs, {} at a time", total, jobs);
+
+    let done = Mutex::new(0);
+    let deliver = |input: &Path, output: PipelineOutput| -> Result<Vec<String>> {
+        let mut written = Vec::new();
+        if let Some(template) = &main_template {
+            let path = ctx.paths.resolve_output(&batch.output_path(template, input, &output.audio_type)?)?;
+            write_output(&path, &output.audio)?;
+            written.push(path.display().to_string());
         }
-        None => {
-            let out_path = PathBuf::from(&orchestration.output.path);
-            if let Some(p) = out_path.parent() {
-                std::fs::create_dir_all(p)?;
-            }
-            std::fs::write(&orchestration.output.path, &audio)?;
-            eprintln!("Wrote {} bytes to {}", audio.len(), orchestration.output.path);
+        for (branch, template) in output.outputs.iter().zip(orchestration.outputs.iter().map(|b| &b.path)) {
+            let path = ctx.paths.resolve_output(&batch.output_path(template, input, &branch.audio_type)?)?;
+            write_output(&path, &branch.audio)?;
+            written.push(path.display().to_string());
         }
+        Ok(written)
+    };
+    let finished = |item: &BatchItem| {
+        let mut done = done.lock().unwrap();
+        *done += 1;
+        match &item.error {
+            None => eprintln!("[{}/{}] ok {} ({:.1}s)", done, total, item.input.display(), item.elapsed_ms as f64 / 1000.0),
+            Some(e) => eprintln!("[{}/{}] FAILED {}: {}", done, total, item.input.display(), e),
+        }
+    };
+    let report = execute_batch(&orchestration, &batch, &plugin_base, &ctx, jobs, deliver, finished);
+
+    if format == Format::Json {
+        print_json(serde_json::to_value(&report)?)?;
+    }
+    eprintln!("batch: {} succeeded, {} failed", report.succeeded(), report.failed());
+    for item in report.items.iter().filter(|i| i.error.is_some()) {
+        eprintln!("  {}: {}", item.input.display(), item.error.as_deref().unwrap_or_default());
+    }
+    if ctx.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+        return Err(crusty_core::Cancelled.into());
+    }
+    if report.failed() > 0 {
+        anyhow::bail!("{} of {} inputs failed", report.failed(), total);
     }
     Ok(())
 }
 
-fn parse_args(
-    args: &[String],
-) -> Result<(PathBuf, Option<PathBuf>, Option<String>, Option<String>)> {
-    let mut orch = PathBuf::from("orchestration.cr");
-    let mut plugin_dir = None;
-    let mut input_override = None;
-    let mut output_override = None;
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "run" => {
-                i += 1;
-                if i < args.len() {
-                    orch = PathBuf::from(&args[i]);
-                    i += 1;
-                }
-            }
-            "--orchestration" | "-o" => {
-                i += 1;
-                if i < args.len() {
-                    orch = PathBuf::from(&args[i]);
-                    i += 1;
-                }
-            }
-            "--plugins" | "-p" => {
-                i += 1;
-                if i < args.len() {
-                    plugin_dir = Some(PathBuf::from(&args[i]));
-                    i += 1;
-                }
-            }
-            "--input" | "-i" => {
-                i += 1;
-                if i < args.len() {
-                    input_override = Some(args[i].clone());
-                    i += 1;
-                }
-            }
-            "--output" => {
-                i += 1;
-                if i < args.len() {
-                    output_override = Some(args[i].clone());
-                    i += 1;
-                }
-            }
-            _ => {
-                if orch == PathBuf::from("orchestration.cr") && !args[i].starts_with('-') {
-                    orch = PathBuf::from(&args[i]);
-                }
-                i += 1;
-            }
+fn write_output(path: &Path, audio: &[u8]) -> Result<()> {
+    if let Some(p) = path.parent() {
+        std::fs::create_dir_all(p)?;
+    }
+    std::fs::write(path, audio)?;
+    eprintln!("Wrote {} bytes to {}", audio.len(), path.display());
+    Ok(())
+}
+
+/// Single-line progress display on a terminal stderr.
+#[derive(Default)]
+struct ProgressBar {
+    /// Overall fraction and label last drawn.
+    state: Mutex<(f64, String)>,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 30;
+
+    fn draw(&self, fraction: f64, label: String) {
+        let mut state = self.state.lock().unwrap();
+        // Parallel chunks can report out of order; never move the bar backwards.
+        state.0 = state.0.max(fraction);
+        state.1 = label;
+        let filled = (state.0 * Self::WIDTH as f64).round() as usize;
+        eprint!(
+            "\r\x1b[2K[{}{}] {:>3}% {}",
+            "#".repeat(filled),
+            ".".repeat(Self::WIDTH - filled),
+            (state.0 * 100.0).round(),
+            state.1
+        );
+        let _ = io::stderr().flush();
+    }
+
+    fn finish(&self) {
+        if !self.state.lock().unwrap().1.is_empty() {
+            eprintln!();
         }
     }
-    Ok((orch, plugin_dir, input_override, output_override))
+}
+
+impl PipelineObserver for ProgressBar {
+    fn stage_started(&self, stage: &StageInfo, _bytes_in: usize) {
+        self.draw(stage.overall_fraction(0.0), format!("{} {}", stage.kind, stage.name));
+    }
+
+    fn stage_finished(&self, stage: &StageInfo, _bytes_out: usize) {
+        self.draw(stage.overall_fraction(1.0), format!("{} {}", stage.kind, stage.name));
+    }
+
+    fn chunk_finished(&self, stage: &StageInfo, done: usize, total: usize) {
+        self.draw(
+            stage.overall_fraction(done as f64 / total as f64),
+            format!("{} {} ({}/{} chunks)", stage.kind, stage.name, done, total),
+        );
+    }
+
+    fn plugin_progress(&self, stage: &StageInfo, progress: &ProgressFrame) {
+        let fraction = progress.fraction.unwrap_or(0.0);
+        let label = match &progress.message {
+            Some(m) => format!("{} {}: {}", stage.kind, stage.name, m),
+            None => format!("{} {}", stage.kind, stage.name),
+        };
+        self.draw(stage.overall_fraction(fraction), label);
+    }
+
+    fn warning(&self, message: &str) {
+        eprint!("\r\x1b[2K");
+        eprintln!("warning: {}", message);
+        let (fraction, label) = self.state.lock().unwrap().clone();
+        self.draw(fraction, label);
+    }
 }
@@ -0,0 +1,161 @@
+//! `plugins` subcommands: list, inspect and verify the plugins of a directory, and create
+//! new ones (see [`crate::scaffold`]).
+
+use crate::cli::{CliError, Format, PluginArgs, PluginDirArgs, PluginsCommand, TrustArgs};
+use crate::print_json;
+use anyhow::Result;
+use crusty_core::trust::verify_plugin_signature;
+use crusty_core::{verify_plugin_dir, PluginManifest, PluginRegistry, SignaturePolicy, TrustConfig, TrustedKeys};
+use std::path::{Path, PathBuf};
+
+pub fn run(command: PluginsCommand, format: Format) -> Result<()> {
+    match command {
+        PluginsCommand::List(args) => list(&args, format),
+        PluginsCommand::Inspect(args) => inspect(&args, format),
+        PluginsCommand::Verify(args) => verify(&args, format),
+        PluginsCommand::New(args) => crate::scaffold::run(&args, format),
+    }
+}
+
+/// Signature policy and trusted keys from `--signature-policy` / `--trusted-keys`.
+pub fn trust_config(args: &TrustArgs) -> Result<TrustConfig> {
+    let policy = args
+        .signature_policy
+        .as_deref()
+        .map(str::parse::<SignaturePolicy>)
+        .transpose()
+        .map_err(|e| CliError::Usage(format!("--signature-policy: {}", e)))?
+        .unwrap_or_default();
+    let keys = args.trusted_keys.as_deref().map(TrustedKeys::load).transpose()?.unwrap_or_default();
+    Ok(TrustConfig { policy, keys })
+}
+
+/// Discover the plugins of `--plugins`, reporting trust problems on stderr.
+pub fn load_registry(args: &PluginDirArgs) -> Result<PluginRegistry> {
+    let registry = PluginRegistry::load_plugins_with_trust(&args.plugins, &trust_config(&args.trust)?)
+        .map_err(|e| anyhow::anyhow!("load plugins from {}: {:#}", args.plugins.display(), e))?;
+    for w in &registry.warnings {
+        eprintln!("warning: {}", w);
+    }
+    for (name, reason) in &registry.rejected {
+        eprintln!("Skipped plugin {}: {}", name, reason);
+    }
+    Ok(registry)
+}
+
+/// Directory of the plugin `args.name` names: a plugin directory, or a plugin discovered
+/// in `--plugins`.
+fn plugin_dir(args: &PluginArgs) -> Result<PathBuf> {
+    let path = Path::new(&args.name);
+    if path.join("plugin.toml").is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let registry = load_registry(&args.dir)?;
+    match registry.get(&args.name) {
+        Some(p) => Ok(PathBuf::from(&p.path)),
+        None => anyhow::bail!("no plugin {:?} in {}", args.name, args.dir.plugins.display()),
+    }
+}
+
+/// `plugins list`: name, type, version and signature of each discovered plugin.
+fn list(args: &PluginDirArgs, format: Format) -> Result<()> {
+    let registry = load_registry(args)?;
+    let mut plugins = registry.all();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    if format == Format::Json {
+        let plugins: Vec<_> = plugins
+            .iter()
+            .map(|p| {
+                serde_json::json!({
+                    "name": p.name,
+                    "type": p.plugin_type.as_str(),
+                    "version": p.manifest.as_ref().map(|m| &m.version),
+                    "description": p.manifest.as_ref().and_then(|m| m.description.as_ref()),
+                    "path": p.path,
+                    "signature": p.signature,
+                })
+            })
+            .collect();
+        return print_json(serde_json::Value::Array(plugins));
+    }
+    if plugins.is_empty() {
+        eprintln!("No plugins found in {}", args.plugins.display());
+    }
+    for p in plugins {
+        let version = p.manifest.as_ref().map_or("-", |m| m.version.as_str());
+        println!("{:<24} {:<10} {:<10} {:<10} {}", p.name, p.plugin_type.as_str(), version, p.signature.as_str(), p.path);
+    }
+    Ok(())
+}
+
+/// `plugins inspect`: the plugin's manifest, with defaults applied.
+fn inspect(args: &PluginArgs, format: Format) -> Result<()> {
+    let dir = plugin_dir(args)?;
+    let toml_path = dir.join("plugin.toml");
+    let manifest: PluginManifest = toml::from_str(&std::fs::read_to_string(&toml_path)?)
+        .map_err(|e| CliError::Invalid(format!("{}: {}", toml_path.display(), e)))?;
+    let signature = verify_plugin_signature(&dir, &trust_config(&args.dir.trust)?.keys);
+    let caps = manifest.capabilities.clone().unwrap_or_default();
+    let options = manifest.default_options();
+    if format == Format::Json {
+        return print_json(serde_json::json!({
+            "name": manifest.name,
+            "version": manifest.version,
+            "type": manifest.r#type,
+            "description": manifest.description,
+            "path": dir,
+            "api_version": manifest.api_version,
+            "entrypoint": manifest.entrypoint,
+            "transport": manifest.transport().as_str(),
+            "lifecycle": manifest.lifecycle().as_str(),
+            "deterministic": manifest.is_deterministic(),
+            "timeout_ms": manifest.timeout_ms,
+            "capabilities": {
+                "input": caps.input,
+                "output": caps.output,
+                "ssml_elements": caps.ssml_elements,
+            },
+            "options": options,
+            "signature": signature,
+        }));
+    }
+    println!("{} {}", manifest.name, manifest.version);
+    if let Some(d) = &manifest.description {
+        println!("  {}", d);
+    }
+    println!("  path:      {}", dir.display());
+    println!("  type:      {}", manifest.r#type.as_deref().unwrap_or("-"));
+    println!("  wire:      {}/{}", manifest.transport().as_str(), manifest.lifecycle().as_str());
+    let types = |t: &Option<Vec<String>>| t.as_ref().map_or("-".to_string(), |t| t.join(", "));
+    println!("  input:     {}", types(&caps.input));
+    println!("  output:    {}", types(&caps.output));
+    if let Some(t) = manifest.timeout_ms {
+        println!("  timeout:   {} ms", t);
+    }
+    println!("  cache:     {}", if manifest.is_deterministic() { "yes" } else { "no (non-deterministic)" });
+    println!("  signature: {}", signature.as_str());
+    let mut options: Vec<_> = options.into_iter().collect();
+    options.sort();
+    for (k, v) in options {
+        println!("  option {} = {:?}", k, v);
+    }
+    Ok(())
+}
+
+/// `plugins verify`: run the conformance suite; a failed check makes the plugin invalid.
+fn verify(args: &PluginArgs, format: Format) -> Result<()> {
+    let report = verify_plugin_dir(&plugin_dir(args)?);
+    if format == Format::Json {
+        print_json(serde_json::to_value(&report)?)?;
+    } else {
+        eprintln!("Verifying {} ({})", report.plugin, report.path);
+        for c in &report.checks {
+            eprintln!("  {:<4} {:<14} {}", c.status.as_str().to_uppercase(), c.name, c.message);
+        }
+        eprintln!("{}", if report.passed() { "Verification passed" } else { "Verification failed" });
+    }
+    if !report.passed() {
+        return Err(CliError::Invalid(format!("plugin {} failed verification", report.plugin)).into());
+    }
+    Ok(())
+}
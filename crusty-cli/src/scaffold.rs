@@ -0,0 +1,747 @@
+//! `plugins new`: generate a plugin for a stage, language and transport. The entrypoint is
+//! a working stub (pre-processors collapse whitespace, TTS engines write silence, audio
+//! stages pass audio through) with a fixture `plugins verify` checks it against.
+
+use crate::cli::{CliError, Format, Lang, NewPluginArgs, Stage, Wire};
+use crate::print_json;
+use anyhow::{Context, Result};
+use crusty_core::audio::DEFAULT_RAW_FORMAT;
+use crusty_core::{Audio, PROTOCOL_VERSION};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Input of the pre-processor fixture, and what collapsing its whitespace gives.
+const PRE_INPUT: &str = "  Hello,   world!\n\nThis is  Crusty-TTS.  \n";
+const PRE_EXPECTED: &str = "Hello, world! This is Crusty-TTS.";
+/// Input of the TTS fixture.
+const TTS_INPUT: &str = "Hello from Crusty-TTS.";
+/// Samples of silence the TTS stub writes per character: 60 ms at 22050 Hz.
+const SAMPLES_PER_CHAR: usize = 1323;
+
+impl Stage {
+    fn as_str(self) -> &'static str {
+        match self {
+            Stage::Pre => "pre",
+            Stage::Tts => "tts",
+            Stage::Converter => "converter",
+            Stage::Post => "post",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Stage::Pre => "pre-processor",
+            Stage::Tts => "TTS engine",
+            Stage::Converter => "audio converter",
+            Stage::Post => "post-processor",
+        }
+    }
+
+    fn takes_text(self) -> bool {
+        matches!(self, Stage::Pre | Stage::Tts)
+    }
+
+    fn input_type(self) -> &'static str {
+        if self.takes_text() {
+            "text/plain"
+        } else {
+            "audio/wav"
+        }
+    }
+
+    fn output_type(self) -> &'static str {
+        if self == Stage::Pre {
+            "text/plain"
+        } else {
+            "audio/wav"
+        }
+    }
+
+    /// Option schema of the stub: name, type, default and description.
+    fn options(self) -> &'static [(&'static str, &'static str, &'static str, &'static str)] {
+        match self {
+            Stage::Pre => &[("collapse_whitespace", "bool", "true", "Collapse runs of whitespace into single spaces")],
+            Stage::Tts => &[
+                ("voice", "string", "default", "Voice to speak with"),
+                ("rate", "float", "1.0", "Speaking rate; 2.0 is twice as fast"),
+            ],
+            Stage::Converter | Stage::Post => &[("gain", "float", "1.0", "Gain applied to the samples")],
+        }
+    }
+}
+
+impl Wire {
+    fn as_str(self) -> &'static str {
+        match self {
+            Wire::Env => "env",
+            Wire::Framed => "framed",
+        }
+    }
+}
+
+/// A file of the generated plugin, relative to its directory.
+struct File {
+    path: &'static str,
+    contents: Vec<u8>,
+    executable: bool,
+}
+
+impl File {
+    fn new(path: &'static str, contents: impl Into<Vec<u8>>) -> Self {
+        File { path, contents: contents.into(), executable: false }
+    }
+}
+
+pub fn run(args: &NewPluginArgs, format: Format) -> Result<()> {
+    let name = &args.name;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(CliError::Usage(format!("plugin name {:?}: use letters, digits, - and _", name)).into());
+    }
+    let dir = args.plugins.join(name);
+    let occupied = std::fs::read_dir(&dir).is_ok_and(|mut d| d.next().is_some());
+    if occupied && !args.force {
+        return Err(CliError::Usage(format!("{} already exists (use --force to overwrite)", dir.display())).into());
+    }
+
+    let files = plugin_files(args);
+    for f in &files {
+        let path = dir.join(f.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, &f.contents).with_context(|| format!("write {}", path.display()))?;
+        if f.executable {
+            make_executable(&path)?;
+        }
+    }
+
+    let paths: Vec<PathBuf> = files.iter().map(|f| dir.join(f.path)).collect();
+    eprintln!("Created {} {} plugin {} in {}", args.transport.as_str(), args.stage.title(), name, dir.display());
+    for p in &paths {
+        eprintln!("  {}", p.display());
+    }
+    if args.lang == Lang::Rust {
+        eprintln!("Build it with `cargo build --release --manifest-path {}`", dir.join("Cargo.toml").display());
+    }
+    eprintln!("Check it with `crusty-cli plugins verify {}`", dir.display());
+    if format == Format::Json {
+        print_json(serde_json::json!({"path": dir, "files": paths}))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Manifest, entrypoint and fixtures of the plugin `args` describe.
+fn plugin_files(args: &NewPluginArgs) -> Vec<File> {
+    let mut files = vec![File::new("plugin.toml", manifest(args))];
+    match args.lang {
+        Lang::Python => files.push(File { executable: true, ..File::new("run.py", python(args)) }),
+        Lang::Sh => files.push(File { executable: true, ..File::new("run.sh", shell(args)) }),
+        Lang::Rust => {
+            files.push(File::new("Cargo.toml", cargo_toml(&args.name)));
+            files.push(File::new("src/main.rs", rust(args)));
+            files.push(File::new(".gitignore", "/target\n"));
+        }
+    }
+    files.extend(fixtures(args.stage));
+    files
+}
+
+fn manifest(args: &NewPluginArgs) -> String {
+    let stage = args.stage;
+    let mut m = format!(
+        "name = {:?}\nversion = \"0.1.0\"\nprotocol_version = {:?}\ntype = {:?}\ndescription = \"{} stub generated by crusty-cli plugins new\"\n",
+        args.name,
+        PROTOCOL_VERSION,
+        stage.as_str(),
+        stage.title(),
+    );
+    if args.lang == Lang::Rust {
+        m += &format!("entrypoint = \"target/release/{}\"\n", args.name);
+    }
+    if args.transport == Wire::Framed {
+        m += "transport = \"framed\"\n";
+    }
+    m += &format!("\n[capabilities]\ninput = [{:?}]\noutput = [{:?}]\n\n[options]\n", stage.input_type(), stage.output_type());
+    for (name, kind, default, description) in stage.options() {
+        m += &format!("{} = {{ type = {:?}, default = {:?}, description = {:?} }}\n", name, kind, default, description);
+    }
+    m
+}
+
+/// `fixtures/input.*` and the `fixtures/expected.*` the stub turns it into.
+fn fixtures(stage: Stage) -> Vec<File> {
+    match stage {
+        Stage::Pre => vec![File::new("fixtures/input.txt", PRE_INPUT), File::new("fixtures/expected.txt", PRE_EXPECTED)],
+        Stage::Tts => {
+            let samples = TTS_INPUT.chars().count() * SAMPLES_PER_CHAR;
+            let silence = Audio { format: DEFAULT_RAW_FORMAT, data: vec![0; samples * 2] };
+            vec![File::new("fixtures/input.txt", TTS_INPUT), File::new("fixtures/expected.wav", silence.to_wav())]
+        }
+        Stage::Converter | Stage::Post => {
+            let wav = Audio::silence(DEFAULT_RAW_FORMAT, Duration::from_millis(100)).to_wav();
+            vec![File::new("fixtures/input.wav", wav.clone()), File::new("fixtures/expected.wav", wav)]
+        }
+    }
+}
+
+/// Fill a template's `@NAME@`, `@TITLE@` and `@TRANSPORT@`.
+fn fill(template: &str, args: &NewPluginArgs) -> String {
+    template
+        .replace("@NAME@", &args.name)
+        .replace("@TITLE@", args.stage.title())
+        .replace("@TRANSPORT@", args.transport.as_str())
+}
+
+fn python(args: &NewPluginArgs) -> String {
+    let framed = args.transport == Wire::Framed;
+    let mut imports = vec!["json"];
+    if !framed {
+        imports.push("os");
+    }
+    if framed || args.stage == Stage::Tts {
+        imports.push("struct");
+    }
+    imports.push("sys");
+    let mut s = fill(PY_HEADER, args);
+    for i in imports {
+        s += &format!("import {}\n", i);
+    }
+    s += PY_ERROR;
+    s += match args.transport {
+        Wire::Env if args.stage.takes_text() => PY_ENV.replace("@READ@", r#"os.environb.get(b"PLUGIN_INPUT", b"")"#),
+        Wire::Env => PY_ENV.replace("@READ@", "sys.stdin.buffer.read()"),
+        Wire::Framed => PY_FRAMED.to_string(),
+    }
+    .as_str();
+    s += match args.stage {
+        Stage::Pre => PY_PRE,
+        Stage::Tts => PY_TTS,
+        Stage::Converter | Stage::Post => PY_PASS,
+    };
+    s + PY_MAIN
+}
+
+fn shell(args: &NewPluginArgs) -> String {
+    let framed = args.transport == Wire::Framed;
+    let mut s = fill(SH_HEADER, args);
+    if framed || args.stage == Stage::Tts {
+        s += SH_LE;
+    }
+    if framed {
+        s += SH_FRAMED;
+    } else {
+        s += SH_ENV;
+        s += if args.stage.takes_text() { SH_ENV_TEXT } else { SH_ENV_AUDIO };
+    }
+    for (name, _, default, _) in args.stage.options() {
+        s += &if framed {
+            format!("{name}=$(option {name})\n{name}=${{{name}:-{default}}}\n")
+        } else {
+            format!("{}=${{PLUGIN_OPT_{}:-{}}}\n", name, name.to_uppercase(), default)
+        };
+    }
+    s += match args.stage {
+        Stage::Pre => SH_PRE,
+        Stage::Tts => SH_TTS,
+        Stage::Converter | Stage::Post => SH_PASS,
+    };
+    s + "emit\n"
+}
+
+fn cargo_toml(name: &str) -> String {
+    format!(
+        "[package]\nname = {:?}\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nserde_json = \"1\"\n\n# Built on its own, not as part of an enclosing workspace.\n[workspace]\n",
+        name
+    )
+}
+
+fn rust(args: &NewPluginArgs) -> String {
+    let framed = args.transport == Wire::Framed;
+    let mut s = fill(RS_HEADER, args);
+    s += if framed || !args.stage.takes_text() { "use std::io::{self, Read, Write};\n" } else { "use std::io::{self, Write};\n" };
+    s += RS_COMMON;
+    s += match args.transport {
+        Wire::Env if args.stage.takes_text() => {
+            RS_ENV.replace("@READ@", "let input = std::env::var_os(\"PLUGIN_INPUT\").map(|v| v.into_encoded_bytes()).unwrap_or_default();")
+        }
+        Wire::Env => RS_ENV.replace(
+            "@READ@",
+            "let mut input = Vec::new();\n    io::stdin().read_to_end(&mut input).map_err(|e| e.to_string())?;",
+        ),
+        Wire::Framed => RS_FRAMED.to_string(),
+    }
+    .as_str();
+    s += match args.stage {
+        Stage::Pre => RS_PRE,
+        Stage::Tts => RS_TTS,
+        Stage::Converter | Stage::Post => RS_PASS,
+    };
+    s
+}
+
+const PY_HEADER: &str = r#"#!/usr/bin/env python3
+"""@NAME@: Crusty-TTS @TITLE@ (@TRANSPORT@ transport).
+
+Generated by `crusty-cli plugins new`: replace the stub in `process` with the real work,
+and fixtures/expected.* with what it gives for fixtures/input.*.
+"""
+"#;
+
+const PY_ERROR: &str = r#"
+
+class PluginError(Exception):
+    """A failure to report to Crusty, such as an invalid option."""
+"#;
+
+const PY_ENV: &str = r#"
+
+def read_input():
+    """Input bytes, and options from the PLUGIN_OPT_* variables."""
+    options = {
+        key[len("PLUGIN_OPT_"):].lower(): value
+        for key, value in os.environ.items()
+        if key.startswith("PLUGIN_OPT_")
+    }
+    return @READ@, options
+
+
+def write_output(data):
+    sys.stdout.buffer.write(data)
+    sys.stdout.buffer.flush()
+
+
+def fail(message):
+    """Report an error as a JSON line on stderr and exit non-zero."""
+    print(json.dumps({"type": "error", "message": message, "fatal": True}), file=sys.stderr)
+    sys.exit(1)
+"#;
+
+const PY_FRAMED: &str = r#"
+
+def read_frame():
+    """Payload of the next frame, or None at end of input."""
+    header = sys.stdin.buffer.read(4)
+    if len(header) < 4:
+        return None
+    (length,) = struct.unpack("<I", header)
+    return sys.stdin.buffer.read(length)
+
+
+def write_frame(payload):
+    sys.stdout.buffer.write(struct.pack("<I", len(payload)) + payload)
+    sys.stdout.buffer.flush()
+
+
+def read_input():
+    """Input bytes and options: a JSON handshake frame whose config holds the options,
+    then payload frames up to end of input or an empty (EOS) frame."""
+    handshake = read_frame()
+    if handshake is None:
+        fail("no handshake frame")
+    options = {key: str(value) for key, value in (json.loads(handshake).get("config") or {}).items()}
+    chunks = []
+    while True:
+        chunk = read_frame()
+        if not chunk:
+            break
+        chunks.append(chunk)
+    return b"".join(chunks), options
+
+
+def write_output(data):
+    """The output frame, then EOS. Empty output sends no frame: it would read as EOS."""
+    if data:
+        write_frame(data)
+    write_frame(b"")
+
+
+def fail(message):
+    """Report an error frame and exit non-zero."""
+    write_frame(json.dumps({"type": "error", "message": message, "fatal": True}).encode())
+    sys.exit(1)
+"#;
+
+const PY_PRE: &str = r#"
+
+def process(data, options):
+    """Collapse runs of whitespace into single spaces."""
+    collapse = options.get("collapse_whitespace", "true")
+    if collapse not in ("true", "false"):
+        raise PluginError(f"collapse_whitespace must be true or false, not {collapse!r}")
+    text = data.decode("utf-8")
+    if collapse == "true":
+        text = " ".join(text.split())
+    return text.encode("utf-8")
+"#;
+
+const PY_TTS: &str = r#"
+SAMPLE_RATE = 22050
+
+
+def number(options, name, default):
+    value = options.get(name, default)
+    try:
+        return float(value)
+    except ValueError:
+        raise PluginError(f"{name} must be a number, not {value!r}") from None
+
+
+def process(data, options):
+    """Synthesize the text as 16-bit mono WAV. This stub writes 60 ms of silence per
+    character; a real engine speaks with options["voice"] here."""
+    rate = number(options, "rate", "1.0")
+    if not rate > 0:
+        raise PluginError(f"rate must be positive, not {rate}")
+    text = data.decode("utf-8")
+    samples = int(len(text) * SAMPLE_RATE * 60 / 1000 / rate)
+    return wav(b"\0\0" * samples)
+
+
+def wav(pcm):
+    """A WAV file around 16-bit mono PCM."""
+    header = struct.pack(
+        "<4sI4s4sIHHIIHH4sI",
+        b"RIFF", 36 + len(pcm), b"WAVE", b"fmt ", 16, 1, 1,
+        SAMPLE_RATE, SAMPLE_RATE * 2, 2, 16, b"data", len(pcm),
+    )
+    return header + pcm
+"#;
+
+const PY_PASS: &str = r#"
+
+def number(options, name, default):
+    value = options.get(name, default)
+    try:
+        return float(value)
+    except ValueError:
+        raise PluginError(f"{name} must be a number, not {value!r}") from None
+
+
+def process(data, options):
+    """Pass the audio through. A real plugin re-encodes or processes it here, scaling the
+    samples by options["gain"]."""
+    number(options, "gain", "1.0")
+    return data
+"#;
+
+const PY_MAIN: &str = r#"
+
+def main():
+    data, options = read_input()
+    try:
+        output = process(data, options)
+    except (PluginError, UnicodeDecodeError) as e:
+        fail(str(e))
+    write_output(output)
+
+
+if __name__ == "__main__":
+    main()
+"#;
+
+const SH_HEADER: &str = r#"#!/bin/sh
+# @NAME@: Crusty-TTS @TITLE@ (@TRANSPORT@ transport).
+#
+# Generated by `crusty-cli plugins new`: replace the stub below with the real work, and
+# fixtures/expected.* with what it gives for fixtures/input.*.
+set -eu
+
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+"#;
+
+const SH_LE: &str = r#"
+# Write $1 as $2 little-endian bytes.
+le() {
+    n=$1 i=0
+    while [ "$i" -lt "$2" ]; do
+        printf "\\$(printf '%03o' $((n & 255)))"
+        n=$((n >> 8)) i=$((i + 1))
+    done
+}
+"#;
+
+const SH_ENV: &str = r#"
+# Report an error as a JSON line on stderr and exit non-zero.
+fail() {
+    printf '{"type":"error","message":"%s","fatal":true}\n' "$1" >&2
+    exit 1
+}
+
+emit() {
+    cat "$tmp/out"
+}
+
+# Options come from the PLUGIN_OPT_* variables.
+"#;
+
+const SH_ENV_TEXT: &str = "printf '%s' \"${PLUGIN_INPUT:-}\" > \"$tmp/in\"\n";
+
+const SH_ENV_AUDIO: &str = "cat > \"$tmp/in\"\n";
+
+const SH_FRAMED: &str = r#"
+# Length of the next frame; fails at end of input.
+read_len() {
+    dd bs=1 count=4 2>/dev/null | od -An -tu1 | {
+        read -r a b c d && [ -n "$d" ] && echo $((a | b << 8 | c << 16 | d << 24))
+    }
+}
+
+# The file $1 as a frame.
+frame() {
+    le "$(($(wc -c < "$1")))" 4
+    cat "$1"
+}
+
+# Report an error frame and exit non-zero.
+fail() {
+    printf '{"type":"error","message":"%s","fatal":true}' "$1" > "$tmp/error"
+    frame "$tmp/error"
+    exit 1
+}
+
+# The output frame, then EOS. Empty output sends no frame: it would read as EOS.
+emit() {
+    if [ -s "$tmp/out" ]; then
+        frame "$tmp/out"
+    fi
+    le 0 4
+}
+
+# A JSON handshake frame whose config holds the options, then payload frames up to end
+# of input or an empty (EOS) frame.
+len=$(read_len) || fail "no handshake frame"
+head -c "$len" > "$tmp/handshake"
+: > "$tmp/in"
+while len=$(read_len) && [ "$len" -gt 0 ]; do
+    head -c "$len" >> "$tmp/in"
+done
+
+# Value of option $1 (Crusty sends option values as strings).
+option() {
+    sed -n "s/.*\"$1\":\"\([^\"]*\)\".*/\1/p" "$tmp/handshake"
+}
+"#;
+
+const SH_PRE: &str = r#"
+# Collapse runs of whitespace into single spaces.
+case $collapse_whitespace in
+true)
+    set -f
+    # shellcheck disable=SC2046
+    set -- $(cat "$tmp/in")
+    printf '%s' "$*" > "$tmp/out"
+    ;;
+false) cp "$tmp/in" "$tmp/out" ;;
+*) fail "collapse_whitespace must be true or false, not $collapse_whitespace" ;;
+esac
+"#;
+
+const SH_TTS: &str = r#"
+# Synthesize the text as 16-bit mono WAV. This stub writes 60 ms of silence per
+# character; a real engine speaks with $voice here.
+case $rate in
+'' | *[!0-9.]* | *.*.* | .) fail "rate must be a number, not $rate" ;;
+esac
+text=$(cat "$tmp/in")
+samples=$(awk -v n="${#text}" -v r="$rate" 'BEGIN { if (r <= 0) exit 1; printf "%d", n * 22050 * 60 / 1000 / r }') ||
+    fail "rate must be positive, not $rate"
+bytes=$((samples * 2))
+{
+    printf 'RIFF'; le $((36 + bytes)) 4; printf 'WAVEfmt '
+    le 16 4; le 1 2; le 1 2; le 22050 4; le 44100 4; le 2 2; le 16 2
+    printf 'data'; le "$bytes" 4
+    head -c "$bytes" /dev/zero
+} > "$tmp/out"
+"#;
+
+const SH_PASS: &str = r#"
+# Pass the audio through. A real plugin re-encodes or processes it here, scaling the
+# samples by $gain.
+case $gain in
+'' | *[!0-9.]* | *.*.* | .) fail "gain must be a number, not $gain" ;;
+esac
+cp "$tmp/in" "$tmp/out"
+"#;
+
+const RS_HEADER: &str = r#"//! @NAME@: Crusty-TTS @TITLE@ (@TRANSPORT@ transport).
+//!
+//! Generated by `crusty-cli plugins new`: replace the stub in `process` with the real
+//! work, and fixtures/expected.* with what it gives for fixtures/input.*.
+
+use std::collections::HashMap;
+"#;
+
+const RS_COMMON: &str = r#"use std::process::exit;
+
+type Options = HashMap<String, String>;
+
+fn main() {
+    let (input, options) = read_input().unwrap_or_else(|e| fail(&e));
+    match process(&input, &options) {
+        Ok(output) => write_output(&output).unwrap_or_else(|e| fail(&e.to_string())),
+        Err(e) => fail(&e),
+    }
+}
+"#;
+
+const RS_ENV: &str = r#"
+/// Input bytes, and options from the PLUGIN_OPT_* variables.
+fn read_input() -> Result<(Vec<u8>, Options), String> {
+    let options = std::env::vars()
+        .filter_map(|(k, v)| k.strip_prefix("PLUGIN_OPT_").map(|k| (k.to_lowercase(), v)))
+        .collect();
+    @READ@
+    Ok((input, options))
+}
+
+fn write_output(output: &[u8]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(output)?;
+    stdout.flush()
+}
+
+/// Report an error as a JSON line on stderr and exit non-zero.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", serde_json::json!({"type": "error", "message": message, "fatal": true}));
+    exit(1)
+}
+"#;
+
+const RS_FRAMED: &str = r#"
+/// Payload of the next frame, or `None` at end of input.
+fn read_frame(r: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut payload = vec![0u8; u32::from_le_bytes(len) as usize];
+    r.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+fn write_frame(w: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// Input bytes and options: a JSON handshake frame whose config holds the options, then
+/// payload frames up to end of input or an empty (EOS) frame.
+fn read_input() -> Result<(Vec<u8>, Options), String> {
+    let mut stdin = io::stdin().lock();
+    let handshake = read_frame(&mut stdin).map_err(|e| e.to_string())?.ok_or("no handshake frame")?;
+    let handshake: serde_json::Value =
+        serde_json::from_slice(&handshake).map_err(|e| format!("bad handshake: {}", e))?;
+    let options = handshake["config"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| (k.clone(), v.as_str().map_or_else(|| v.to_string(), str::to_string)))
+        .collect();
+    let mut input = Vec::new();
+    while let Some(chunk) = read_frame(&mut stdin).map_err(|e| e.to_string())? {
+        if chunk.is_empty() {
+            break;
+        }
+        input.extend_from_slice(&chunk);
+    }
+    Ok((input, options))
+}
+
+/// The output frame, then EOS. Empty output sends no frame: it would read as EOS.
+fn write_output(output: &[u8]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if !output.is_empty() {
+        write_frame(&mut stdout, output)?;
+    }
+    write_frame(&mut stdout, &[])
+}
+
+/// Report an error frame and exit non-zero.
+fn fail(message: &str) -> ! {
+    let frame = serde_json::json!({"type": "error", "message": message, "fatal": true});
+    let _ = write_frame(&mut io::stdout().lock(), frame.to_string().as_bytes());
+    exit(1)
+}
+"#;
+
+const RS_PRE: &str = r#"
+/// Collapse runs of whitespace into single spaces.
+fn process(input: &[u8], options: &Options) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(input).map_err(|e| format!("input is not UTF-8: {}", e))?;
+    match options.get("collapse_whitespace").map_or("true", String::as_str) {
+        "true" => Ok(text.split_whitespace().collect::<Vec<_>>().join(" ").into_bytes()),
+        "false" => Ok(input.to_vec()),
+        other => Err(format!("collapse_whitespace must be true or false, not {:?}", other)),
+    }
+}
+"#;
+
+const RS_TTS: &str = r#"
+const SAMPLE_RATE: u32 = 22050;
+
+fn number(options: &Options, name: &str, default: &str) -> Result<f64, String> {
+    let value = options.get(name).map_or(default, String::as_str);
+    value.parse().map_err(|_| format!("{} must be a number, not {:?}", name, value))
+}
+
+/// Synthesize the text as 16-bit mono WAV. This stub writes 60 ms of silence per
+/// character; a real engine speaks with `options["voice"]` here.
+fn process(input: &[u8], options: &Options) -> Result<Vec<u8>, String> {
+    let rate = number(options, "rate", "1.0")?;
+    if rate.is_nan() || rate <= 0.0 {
+        return Err(format!("rate must be positive, not {}", rate));
+    }
+    let text = std::str::from_utf8(input).map_err(|e| format!("input is not UTF-8: {}", e))?;
+    let samples = (text.chars().count() as f64 * f64::from(SAMPLE_RATE) * 60.0 / 1000.0 / rate) as usize;
+    Ok(wav(&vec![0; samples * 2]))
+}
+
+/// A WAV file around 16-bit mono PCM.
+fn wav(pcm: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(44 + pcm.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + pcm.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    out.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(pcm.len() as u32).to_le_bytes());
+    out.extend_from_slice(pcm);
+    out
+}
+"#;
+
+const RS_PASS: &str = r#"
+fn number(options: &Options, name: &str, default: &str) -> Result<f64, String> {
+    let value = options.get(name).map_or(default, String::as_str);
+    value.parse().map_err(|_| format!("{} must be a number, not {:?}", name, value))
+}
+
+/// Pass the audio through. A real plugin re-encodes or processes it here, scaling the
+/// samples by `options["gain"]`.
+fn process(input: &[u8], options: &Options) -> Result<Vec<u8>, String> {
+    number(options, "gain", "1.0")?;
+    Ok(input.to_vec())
+}
+"#;
@@ -10,11 +10,20 @@ fn crusty_cli_bin() -> PathBuf {
 }
 
 #[test]
-fn cli_help_or_unknown_exits() {
-    // Running with no args tries to load orchestration.cr from cwd and may fail - that's ok
-    let out = Command::new(crusty_cli_bin()).current_dir(std::env::temp_dir()).output().unwrap();
-    // May succeed (if orchestration.cr exists in temp) or fail (no such file)
-    assert!(out.status.code().is_some());
+fn cli_help_and_usage_errors_exit_with_codes() {
+    let out = Command::new(crusty_cli_bin()).arg("--help").output().unwrap();
+    assert_eq!(out.status.code(), Some(0));
+    let help = String::from_utf8_lossy(&out.stdout);
+    for command in ["run", "validate", "plan", "plugins", "configure", "init"] {
+        assert!(help.contains(command), "{help}");
+    }
+    assert!(help.contains("Exit codes"), "{help}");
+
+    // Unknown flags are usage errors, not ignored.
+    let out = Command::new(crusty_cli_bin()).args(["run", "--bogus"]).output().unwrap();
+    assert_eq!(out.status.code(), Some(64));
+    let out = Command::new(crusty_cli_bin()).output().unwrap();
+    assert_eq!(out.status.code(), Some(64));
 }
 
 #[test]
@@ -67,7 +76,7 @@ path = "{}"
     fs::write(base.join("orchestration.cr"), orch).unwrap();
 
     let out = Command::new(crusty_cli_bin())
-        .arg("orchestration.cr")
+        .args(["run", "orchestration.cr", "--format", "json"])
         .current_dir(base)
         .output()
         .unwrap();
@@ -75,4 +84,480 @@ path = "{}"
     assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
     let out_bytes = fs::read(base.join("out.bin")).unwrap();
     assert_eq!(out_bytes, b"Hello");
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(report["outputs"][0]["bytes"], 5);
+    assert_eq!(report["report"]["stages"][0]["name"], "tts-stub");
+
+    // The plugin is unsigned, so it does not run when signatures are required.
+    let out = Command::new(crusty_cli_bin())
+        .args(["run", "orchestration.cr", "--signature-policy", "require"])
+        .current_dir(base)
+        .env_remove("CRUSTY_TRUSTED_KEYS")
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("signature check failed: plugin tts-stub: unsigned"), "{stderr}");
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_verify_reports_failures_with_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let plugin = dir.path().join("broken");
+    fs::create_dir_all(&plugin).unwrap();
+    fs::write(
+        plugin.join("plugin.toml"),
+        "name = \"broken\"\nversion = \"0.1\"\ntype = \"tts\"\n",
+    )
+    .unwrap();
+    let run_sh = plugin.join("run.sh");
+    fs::write(&run_sh, "#!/bin/sh\nexit 1\n").unwrap();
+    fs::set_permissions(&run_sh, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["plugins", "verify", plugin.to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(report["plugin"], "broken");
+    let sample = report["checks"].as_array().unwrap().iter().find(|c| c["name"] == "sample_run").unwrap();
+    assert_eq!(sample["status"], "fail");
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_rejects_paths_outside_roots() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::create_dir_all(base.join("data")).unwrap();
+    fs::create_dir_all(base.join("out")).unwrap();
+    fs::write(base.join("secret.txt"), "secret").unwrap();
+    let orch = r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "t"
+[input]
+type = "text"
+source = "../secret.txt"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[output]
+type = "file"
+path = "../escaped.bin"
+"#;
+    fs::write(base.join("orchestration.cr"), orch).unwrap();
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["run", "orchestration.cr", "--data-root", "data", "--output-root", "out"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(2), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("outside the output root"));
+    assert!(!base.join("escaped.bin").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_plan_reports_stages_without_running_plugins() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(
+        tts_dir.join("plugin.toml"),
+        "name = \"tts-stub\"\nversion = \"0.3\"\ntype = \"tts\"\n[options]\nvoice = { default = \"en\" }\n",
+    )
+    .unwrap();
+    // Running the plugin would leave a marker.
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(&run_sh, "#!/bin/sh\ntouch ran\n").unwrap();
+    fs::set_permissions(&run_sh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(
+        base.join("orchestration.cr"),
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "t"
+[input]
+type = "text"
+source = "input.txt"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+    )
+    .unwrap();
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["plan", "orchestration.cr", "--format", "json"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let plan: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let tts = &plan["stages"][0];
+    assert_eq!(tts["plugin"], "tts-stub");
+    assert_eq!(tts["version"], "0.3");
+    assert_eq!(tts["transport"], "env");
+    assert_eq!(tts["options"]["voice"], "en");
+    assert!(!base.join("ran").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_batch_renders_each_input_and_reports_failures() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::create_dir_all(base.join("chapters")).unwrap();
+    for (name, text) in [("one.txt", "One"), ("two.txt", "fail"), ("three.txt", "Three")] {
+        fs::write(base.join("chapters").join(name), text).unwrap();
+    }
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(
+        tts_dir.join("plugin.toml"),
+        "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n[capabilities]\ninput = [\"text/plain\"]\noutput = [\"audio/wav\"]\n",
+    )
+    .unwrap();
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(
+        &run_sh,
+        "#!/bin/sh\n[ \"$PLUGIN_INPUT\" = fail ] && { echo 'no voice' >&2; exit 1; }\nprintf '%s' \"$PLUGIN_INPUT\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&run_sh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(
+        base.join("orchestration.cr"),
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "t"
+[input]
+type = "text"
+source = "chapters/*.txt"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[output]
+type = "file"
+path = "out/{stem}.{ext}"
+"#,
+    )
+    .unwrap();
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["batch", "orchestration.cr", "--jobs", "2", "--format", "json"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(1), "stderr: {stderr}");
+    assert!(stderr.contains("batch: 2 succeeded, 1 failed"), "stderr: {stderr}");
+    assert!(stderr.contains("1 of 3 inputs failed"), "stderr: {stderr}");
+    assert_eq!(fs::read(base.join("out/one.wav")).unwrap(), b"One");
+    assert_eq!(fs::read(base.join("out/three.wav")).unwrap(), b"Three");
+    assert!(!base.join("out/two.wav").exists());
+
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let items = report["items"].as_array().unwrap();
+    let inputs: Vec<&str> = items.iter().map(|i| i["input"].as_str().unwrap().rsplit('/').next().unwrap()).collect();
+    assert_eq!(inputs, ["one.txt", "three.txt", "two.txt"]);
+    assert!(items[2]["error"].as_str().unwrap().contains("plugin exited"), "{report}");
+
+    // A plain run refuses a batch source.
+    let out = Command::new(crusty_cli_bin()).args(["run", "orchestration.cr"]).current_dir(base).output().unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("render it as a batch"));
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_reads_stdin_and_writes_stdout() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(tts_dir.join("plugin.toml"), "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(&run_sh, "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+    fs::set_permissions(&run_sh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(
+        base.join("x.cr"),
+        "[meta]\nname = \"test\"\nversion = \"0.1\"\nauthor = \"t\"\n[input]\ntype = \"text\"\nsource = \"missing.txt\"\n\
+         [tts]\nname = \"tts-stub\"\nmodule = \"plugins/tts-stub\"\n[output]\ntype = \"file\"\npath = \"out.bin\"\n",
+    )
+    .unwrap();
+
+    let mut child = Command::new(crusty_cli_bin())
+        .args(["run", "x.cr", "-i", "-", "--output", "-"])
+        .current_dir(base)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"hi").unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(out.stdout, b"hi");
+    assert!(!base.join("out.bin").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_plugins_list_and_inspect_report_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    for (name, kind) in [("tts-stub", "tts"), ("norm", "pre")] {
+        let plugin = base.join("plugins").join(name);
+        fs::create_dir_all(&plugin).unwrap();
+        fs::write(
+            plugin.join("plugin.toml"),
+            format!("name = \"{name}\"\nversion = \"0.2\"\ntype = \"{kind}\"\n[options]\nvoice = {{ default = \"en\" }}\n"),
+        )
+        .unwrap();
+        let run_sh = plugin.join("run.sh");
+        fs::write(&run_sh, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&run_sh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["plugins", "list", "--format", "json"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let list: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let names: Vec<&str> = list.as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["norm", "tts-stub"]);
+    assert_eq!(list[1]["type"], "tts");
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["plugins", "inspect", "tts-stub", "--format", "json"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let manifest: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(manifest["version"], "0.2");
+    assert_eq!(manifest["options"]["voice"], "en");
+    assert_eq!(manifest["signature"]["status"], "unsigned");
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["plugins", "inspect", "nope", "--format", "json"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(1));
+    let error: serde_json::Value = serde_json::from_slice(&out.stderr).unwrap();
+    assert_eq!(error["kind"], "failure");
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_init_writes_a_project_that_validates() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(
+        tts_dir.join("plugin.toml"),
+        "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n[capabilities]\ninput = [\"text/plain\"]\noutput = [\"audio/mpeg\"]\n",
+    )
+    .unwrap();
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(&run_sh, "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+    fs::set_permissions(&run_sh, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let out = Command::new(crusty_cli_bin()).arg("init").current_dir(base).output().unwrap();
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let orchestration = fs::read_to_string(base.join("orchestration.cr")).unwrap();
+    assert!(orchestration.contains("module = \"plugins/tts-stub\""), "{orchestration}");
+    assert!(orchestration.contains("path = \"output/out.mp3\""), "{orchestration}");
+
+    // Existing files are kept unless forced.
+    let out = Command::new(crusty_cli_bin()).arg("init").current_dir(base).output().unwrap();
+    assert_eq!(out.status.code(), Some(64));
+
+    let out = Command::new(crusty_cli_bin())
+        .args(["validate", "--format", "json"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(report["valid"], true);
+
+    // A missing plugin makes the orchestration invalid.
+    fs::remove_dir_all(&tts_dir).unwrap();
+    let out = Command::new(crusty_cli_bin())
+        .args(["validate", "--format", "json"])
+        .current_dir(base)
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(3));
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert!(report["errors"][0].as_str().unwrap().contains("no plugin.toml"), "{report}");
+    let error: serde_json::Value = serde_json::from_slice(&out.stderr).unwrap();
+    assert_eq!(error["exit_code"], 3);
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_configure_checks_options_against_the_schema() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    let plugin = |name: &str, manifest: &str| {
+        let plugin = base.join("plugins").join(name);
+        fs::create_dir_all(&plugin).unwrap();
+        fs::write(plugin.join("plugin.toml"), manifest).unwrap();
+        let run_sh = plugin.join("run.sh");
+        fs::write(&run_sh, "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+        fs::set_permissions(&run_sh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    plugin(
+        "tts-stub",
+        "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n[capabilities]\ninput = [\"text/plain\"]\noutput = [\"audio/wav\"]\n\
+         [options]\nvoice = { type = \"string\", default = \"en_us\" }\nrate = { type = \"float\", default = \"1.0\" }\n\
+         style = { choices = [\"calm\", \"bright\"], default = \"calm\" }\n",
+    );
+    plugin(
+        "lame",
+        "name = \"lame\"\nversion = \"0.1\"\ntype = \"converter\"\n[capabilities]\ninput = [\"audio/raw\"]\noutput = [\"audio/mpeg\"]\n",
+    );
+    let configure = |args: &[&str], stdin: &str| {
+        let mut child = Command::new(crusty_cli_bin())
+            .arg("configure")
+            .args(args)
+            .current_dir(base)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    // Flags alone: typed values, defaults for the rest, nothing read from stdin.
+    let out = configure(&["--tts", "tts-stub", "--set", "tts.rate=1.5", "--name", "demo"], "");
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let orchestration: toml::Value = toml::from_str(&fs::read_to_string(base.join("orchestration.cr")).unwrap()).unwrap();
+    assert_eq!(orchestration["meta"]["name"].as_str(), Some("demo"));
+    assert_eq!(orchestration["tts"]["module"].as_str(), Some("plugins/tts-stub"));
+    let options = &orchestration["tts"]["options"];
+    assert_eq!(options["rate"].as_float(), Some(1.5));
+    assert_eq!(options["voice"].as_str(), Some("en_us"));
+    assert_eq!(options["style"].as_str(), Some("calm"));
+    assert_eq!(orchestration["output"]["path"].as_str(), Some("output/out.wav"));
+
+    let out = configure(&["--tts", "tts-stub", "--set", "tts.style=loud", "-o", "x.cr"], "");
+    assert_eq!(out.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("not one of calm, bright"));
+    let out = configure(&["--tts", "tts-stub", "--set", "tts.pitch=2", "-o", "x.cr"], "");
+    assert_eq!(out.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("no such option"));
+    // An existing file is kept without --force.
+    let out = configure(&["--tts", "tts-stub"], "");
+    assert_eq!(out.status.code(), Some(64));
+    // A pipeline whose types do not connect is not written.
+    let out = configure(&["--tts", "tts-stub", "--converters", "lame", "-o", "x.cr"], "");
+    assert_eq!(out.status.code(), Some(3), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    assert!(!base.join("x.cr").exists());
+
+    // Prompts: bad answers are asked again, empty ones take the default.
+    let answers = "7\n2\nfast\n2\nbright\n\n\n\n\n\n";
+    let out = configure(&["-o", "asked.cr"], answers);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "stderr: {stderr}");
+    assert!(stderr.contains("\"7\" is not a plugin number (1-2)"), "{stderr}");
+    assert!(stderr.contains("\"fast\" is not a number"), "{stderr}");
+    let orchestration: toml::Value = toml::from_str(&fs::read_to_string(base.join("asked.cr")).unwrap()).unwrap();
+    let options = &orchestration["tts"]["options"];
+    assert_eq!(options["rate"].as_float(), Some(2.0));
+    assert_eq!(options["style"].as_str(), Some("bright"));
+    assert_eq!(options["voice"].as_str(), Some("en_us"));
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_plugins_new_scaffolds_plugins_that_verify() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    let cli = |args: &[&str]| Command::new(crusty_cli_bin()).args(args).current_dir(base).output().unwrap();
+
+    let cases = [
+        ("py-pre", "pre", "python", "env"),
+        ("py-tts", "tts", "python", "framed"),
+        ("sh-tts", "tts", "sh", "env"),
+        ("sh-post", "post", "sh", "framed"),
+        ("py-converter", "converter", "python", "env"),
+    ];
+    for (name, stage, lang, transport) in cases {
+        let out = cli(&["plugins", "new", name, "--type", stage, "--lang", lang, "--transport", transport]);
+        assert!(out.status.success(), "{name}: {}", String::from_utf8_lossy(&out.stderr));
+
+        let out = cli(&["plugins", "verify", name, "--format", "json"]);
+        assert!(out.status.success(), "{name}: {}", String::from_utf8_lossy(&out.stdout));
+        let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+        let fixture = report["checks"].as_array().unwrap().iter().find(|c| c["name"] == "fixture").unwrap();
+        assert_eq!(fixture["status"], "pass", "{name}: {fixture}");
+    }
+
+    // Rust plugins point their manifest at the release binary they build.
+    let out = cli(&["plugins", "new", "rs-tts", "--lang", "rust", "--transport", "framed", "--format", "json"]);
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let created: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(created["files"].as_array().unwrap().len(), 6);
+    let manifest = fs::read_to_string(base.join("plugins/rs-tts/plugin.toml")).unwrap();
+    assert!(manifest.contains("entrypoint = \"target/release/rs-tts\""), "{manifest}");
+    assert!(base.join("plugins/rs-tts/src/main.rs").is_file());
+
+    // Existing plugins are kept unless --force; names must be plain.
+    assert_eq!(cli(&["plugins", "new", "py-pre"]).status.code(), Some(64));
+    assert!(cli(&["plugins", "new", "py-pre", "--force"]).status.success());
+    assert_eq!(cli(&["plugins", "new", "../escape"]).status.code(), Some(64));
+}
+
+#[test]
+#[cfg(unix)]
+fn cli_keygen_writes_owner_only_keys_and_keeps_existing_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let key = dir.path().join("release.key");
+    let keygen = |args: &[&str]| Command::new(crusty_cli_bin()).arg("keygen").arg(&key).args(args).output().unwrap();
+
+    let out = keygen(&[]);
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    let public_key = String::from_utf8(out.stdout).unwrap();
+    assert_eq!(public_key.trim().len(), 64, "{public_key}");
+    assert_eq!(fs::metadata(&key).unwrap().permissions().mode() & 0o777, 0o600);
+    let seed = fs::read_to_string(&key).unwrap();
+    assert_eq!(seed.trim().len(), 64, "{seed}");
+
+    // An existing key is never silently replaced.
+    let out = keygen(&[]);
+    assert_eq!(out.status.code(), Some(64));
+    assert_eq!(fs::read_to_string(&key).unwrap(), seed);
+
+    fs::set_permissions(&key, fs::Permissions::from_mode(0o644)).unwrap();
+    let out = keygen(&["--force"]);
+    assert!(out.status.success(), "stderr: {}", String::from_utf8_lossy(&out.stderr));
+    assert_ne!(fs::read_to_string(&key).unwrap(), seed);
+    assert_eq!(fs::metadata(&key).unwrap().permissions().mode() & 0o777, 0o600);
 }
@@ -0,0 +1,174 @@
+//! Intermediate artifacts: every stage's input and output written to a debug directory,
+//! with a `manifest.json` listing them, so a bad render can be traced to one stage.
+
+use crate::observer::StageInfo;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// File name of the listing written next to the artifacts.
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// One written buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// File name inside the artifact directory.
+    pub file: String,
+    pub content_type: String,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageArtifacts {
+    pub index: usize,
+    pub kind: String,
+    pub name: String,
+    pub input: Option<Artifact>,
+    /// `None` when the stage failed.
+    pub output: Option<Artifact>,
+}
+
+/// Contents of `manifest.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactManifest {
+    pub stages: Vec<StageArtifacts>,
+}
+
+impl ArtifactManifest {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let s = fs::read_to_string(dir.join(MANIFEST_NAME))?;
+        Ok(serde_json::from_str(&s)?)
+    }
+
+    /// The artifact stored as `file`, if the manifest lists it.
+    pub fn find(&self, file: &str) -> Option<&Artifact> {
+        self.stages
+            .iter()
+            .flat_map(|s| [s.input.as_ref(), s.output.as_ref()])
+            .flatten()
+            .find(|a| a.file == file)
+    }
+}
+
+/// File extension for a content type (parameters ignored).
+pub fn extension_for(content_type: &str) -> &'static str {
+    match crate::audio::base_type(content_type) {
+        "text/plain" | "text" => "txt",
+        "text/ssml" | "application/ssml+xml" => "ssml",
+        "text/markdown" => "md",
+        "text/html" => "html",
+        "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+        "audio/raw" | "audio/pcm" | "audio/L16" => "pcm",
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/ogg" | "audio/opus" => "ogg",
+        "audio/flac" => "flac",
+        "application/json" => "json",
+        _ => "bin",
+    }
+}
+
+/// Writes artifacts as stages run. The manifest is rewritten after every buffer, so it
+/// is complete up to the point where a failing run stopped.
+#[derive(Debug)]
+pub struct IntermediateWriter {
+    dir: PathBuf,
+    manifest: Mutex<ArtifactManifest>,
+}
+
+impl IntermediateWriter {
+    pub fn create(dir: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("create intermediates dir {:?}: {}", dir, e))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            manifest: Mutex::new(ArtifactManifest::default()),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn stage_input(&self, stage: &StageInfo, bytes: &[u8], content_type: &str) -> anyhow::Result<()> {
+        self.write(stage, "input", bytes, content_type)
+    }
+
+    pub fn stage_output(&self, stage: &StageInfo, bytes: &[u8], content_type: &str) -> anyhow::Result<()> {
+        self.write(stage, "output", bytes, content_type)
+    }
+
+    fn write(&self, stage: &StageInfo, role: &str, bytes: &[u8], content_type: &str) -> anyhow::Result<()> {
+        let file = format!(
+            "{:02}-{}-{}.{}.{}",
+            stage.index,
+            sanitize(&stage.kind),
+            sanitize(&stage.name),
+            role,
+            extension_for(content_type)
+        );
+        fs::write(self.dir.join(&file), bytes)?;
+        let artifact = Artifact {
+            file,
+            content_type: content_type.to_string(),
+            bytes: bytes.len(),
+        };
+        let mut manifest = self.manifest.lock().unwrap();
+        let entry = match manifest.stages.iter_mut().position(|s| s.index == stage.index) {
+            Some(i) => &mut manifest.stages[i],
+            None => {
+                // Independent graph nodes run concurrently; keep the listing in stage order.
+                let at = manifest.stages.partition_point(|s| s.index < stage.index);
+                manifest.stages.insert(at, StageArtifacts {
+                    index: stage.index,
+                    kind: stage.kind.clone(),
+                    name: stage.name.clone(),
+                    input: None,
+                    output: None,
+                });
+                &mut manifest.stages[at]
+            }
+        };
+        if role == "input" {
+            entry.input = Some(artifact);
+        } else {
+            entry.output = Some(artifact);
+        }
+        fs::write(self.dir.join(MANIFEST_NAME), serde_json::to_vec_pretty(&*manifest)?)?;
+        Ok(())
+    }
+}
+
+/// Lower-case file-name-safe form of a stage kind or name.
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_named_artifacts_and_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = IntermediateWriter::create(&dir.path().join("debug")).unwrap();
+        let stage = StageInfo { index: 1, total: 2, kind: "TTS".into(), name: "my voice".into(), branch: None };
+        writer.stage_input(&stage, b"hello", "text/plain").unwrap();
+        writer.stage_output(&stage, b"RIFF", "audio/wav;rate=22050").unwrap();
+        let manifest = ArtifactManifest::load(writer.dir()).unwrap();
+        assert_eq!(manifest.stages.len(), 1);
+        let output = manifest.stages[0].output.as_ref().unwrap();
+        assert_eq!(output.file, "01-tts-my_voice.output.wav");
+        assert_eq!(manifest.find("01-tts-my_voice.input.txt").unwrap().bytes, 5);
+        assert_eq!(fs::read(writer.dir().join(&output.file)).unwrap(), b"RIFF");
+        assert!(manifest.find("../etc/passwd").is_none());
+    }
+
+    #[test]
+    fn extensions_follow_content_type() {
+        assert_eq!(extension_for("audio/raw;rate=8000;channels=1;format=s16le"), "pcm");
+        assert_eq!(extension_for("audio/mpeg"), "mp3");
+        assert_eq!(extension_for("application/x-unknown"), "bin");
+    }
+}
@@ -0,0 +1,483 @@
+//! Built-in PCM audio handling: WAV parse/write, `audio/raw` with explicit format
+//! parameters, concatenation with header fix-up, silence, and inspection.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Sample encoding of interleaved little-endian PCM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleFormat {
+    S16,
+    S24,
+    S32,
+    F32,
+    F64,
+}
+
+impl SampleFormat {
+    pub fn bits(self) -> u16 {
+        match self {
+            SampleFormat::S16 => 16,
+            SampleFormat::S24 => 24,
+            SampleFormat::S32 | SampleFormat::F32 => 32,
+            SampleFormat::F64 => 64,
+        }
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, SampleFormat::F32 | SampleFormat::F64)
+    }
+
+    /// Name used in `audio/raw;format=...` (`s16le`, `f32le`, ...).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SampleFormat::S16 => "s16le",
+            SampleFormat::S24 => "s24le",
+            SampleFormat::S32 => "s32le",
+            SampleFormat::F32 => "f32le",
+            SampleFormat::F64 => "f64le",
+        }
+    }
+
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        Ok(match s {
+            "s16le" | "s16" => SampleFormat::S16,
+            "s24le" | "s24" => SampleFormat::S24,
+            "s32le" | "s32" => SampleFormat::S32,
+            "f32le" | "f32" => SampleFormat::F32,
+            "f64le" | "f64" => SampleFormat::F64,
+            _ => anyhow::bail!("unsupported raw sample format {:?}", s),
+        })
+    }
+
+    fn from_wav(tag: u16, bits: u16) -> anyhow::Result<Self> {
+        Ok(match (tag, bits) {
+            (WAVE_FORMAT_PCM, 16) => SampleFormat::S16,
+            (WAVE_FORMAT_PCM, 24) => SampleFormat::S24,
+            (WAVE_FORMAT_PCM, 32) => SampleFormat::S32,
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => SampleFormat::F32,
+            (WAVE_FORMAT_IEEE_FLOAT, 64) => SampleFormat::F64,
+            _ => anyhow::bail!("unsupported WAV encoding: format tag {} at {} bits", tag, bits),
+        })
+    }
+}
+
+/// Sample rate, channel count and encoding of a PCM stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: SampleFormat,
+}
+
+/// Format assumed for bare `audio/raw` with no parameters: 22.05 kHz mono s16le.
+pub const DEFAULT_RAW_FORMAT: AudioFormat = AudioFormat {
+    sample_rate: 22050,
+    channels: 1,
+    sample_format: SampleFormat::S16,
+};
+
+impl AudioFormat {
+    /// Bytes per frame (one sample for every channel).
+    pub fn block_align(&self) -> usize {
+        usize::from(self.channels) * usize::from(self.sample_format.bits() / 8)
+    }
+
+    /// Parse `audio/raw` parameters (`audio/raw;rate=24000;channels=2;format=s16le`);
+    /// anything unspecified falls back to [`DEFAULT_RAW_FORMAT`].
+    pub fn from_raw_mime(content_type: &str) -> anyhow::Result<Self> {
+        let mut parts = content_type.split(';').map(str::trim);
+        let base = parts.next().unwrap_or_default();
+        if base != "audio/raw" {
+            anyhow::bail!("not an audio/raw content type: {}", content_type);
+        }
+        let mut format = DEFAULT_RAW_FORMAT;
+        for param in parts.filter(|p| !p.is_empty()) {
+            let (key, value) = param
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("malformed audio/raw parameter {:?}", param))?;
+            match key.trim() {
+                "rate" => format.sample_rate = value.trim().parse()?,
+                "channels" => format.channels = value.trim().parse()?,
+                "format" => format.sample_format = SampleFormat::parse(value.trim())?,
+                other => anyhow::bail!("unknown audio/raw parameter {:?}", other),
+            }
+        }
+        if format.sample_rate == 0 || format.channels == 0 {
+            anyhow::bail!("audio/raw rate and channels must be non-zero");
+        }
+        Ok(format)
+    }
+
+    /// The `audio/raw;...` content type describing this format.
+    pub fn raw_mime(&self) -> String {
+        format!(
+            "audio/raw;rate={};channels={};format={}",
+            self.sample_rate,
+            self.channels,
+            self.sample_format.as_str()
+        )
+    }
+}
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Decoded PCM: format plus interleaved sample bytes (always whole frames).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Audio {
+    pub format: AudioFormat,
+    pub data: Vec<u8>,
+}
+
+impl Audio {
+    /// Raw PCM in the given format; a trailing partial frame is dropped.
+    pub fn from_raw(mut data: Vec<u8>, format: AudioFormat) -> Self {
+        data.truncate(data.len() - data.len() % format.block_align());
+        Self { format, data }
+    }
+
+    /// `duration` of digital silence.
+    pub fn silence(format: AudioFormat, duration: Duration) -> Self {
+        let frames = (duration.as_secs_f64() * f64::from(format.sample_rate)).round() as usize;
+        Self {
+            format,
+            data: vec![0u8; frames * format.block_align()],
+        }
+    }
+
+    /// Parse a RIFF/WAVE file. Data sizes of 0 or `0xFFFFFFFF` (written by streaming
+    /// encoders that cannot seek back) mean "until end of file"; any other size larger
+    /// than the buffer is an error.
+    pub fn parse_wav(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            anyhow::bail!("missing RIFF/WAVE header");
+        }
+        let mut pos = 12;
+        let mut format = None;
+        while pos + 8 <= bytes.len() {
+            let id = &bytes[pos..pos + 4];
+            let size = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap());
+            let body = pos + 8;
+            if id == b"fmt " {
+                let fmt = bytes
+                    .get(body..body + size as usize)
+                    .filter(|f| f.len() >= 16)
+                    .ok_or_else(|| anyhow::anyhow!("truncated fmt chunk"))?;
+                let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+                let mut tag = u16_at(0);
+                if tag == WAVE_FORMAT_EXTENSIBLE {
+                    if fmt.len() < 26 {
+                        anyhow::bail!("truncated WAVE_FORMAT_EXTENSIBLE fmt chunk");
+                    }
+                    // First two bytes of the sub-format GUID carry the real format tag.
+                    tag = u16_at(24);
+                }
+                let channels = u16_at(2);
+                let sample_rate = u32::from_le_bytes(fmt[4..8].try_into().unwrap());
+                if channels == 0 || sample_rate == 0 {
+                    anyhow::bail!("WAV declares {} channels at {} Hz", channels, sample_rate);
+                }
+                format = Some(AudioFormat {
+                    sample_rate,
+                    channels,
+                    sample_format: SampleFormat::from_wav(tag, u16_at(14))?,
+                });
+            } else if id == b"data" {
+                let format = format.ok_or_else(|| anyhow::anyhow!("data chunk before fmt chunk"))?;
+                let available = bytes.len() - body;
+                let len = match size {
+                    0 | u32::MAX => available,
+                    n if n as usize > available => {
+                        anyhow::bail!("data chunk claims {} bytes, only {} present", n, available)
+                    }
+                    n => n as usize,
+                };
+                return Ok(Self::from_raw(bytes[body..body + len].to_vec(), format));
+            }
+            pos = body + size as usize + (size as usize & 1);
+        }
+        anyhow::bail!("no data chunk")
+    }
+
+    /// Canonical 44-byte-header WAV.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_len = self.data.len() as u32;
+        let mut w = Vec::with_capacity(44 + self.data.len() + 1);
+        w.extend_from_slice(&wav_header(self.format, Some(data_len)));
+        w.extend_from_slice(&self.data);
+        if data_len & 1 == 1 {
+            w.push(0);
+        }
+        w
+    }
+
+    pub fn frames(&self) -> usize {
+        self.data.len() / self.format.block_align()
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / f64::from(self.format.sample_rate))
+    }
+
+    /// Append another stream of the same format.
+    pub fn append(&mut self, other: &Audio) -> anyhow::Result<()> {
+        if other.format != self.format {
+            anyhow::bail!("cannot concatenate {:?} with {:?}", other.format, self.format);
+        }
+        self.data.extend_from_slice(&other.data);
+        Ok(())
+    }
+
+    pub fn append_silence(&mut self, duration: Duration) {
+        let silence = Audio::silence(self.format, duration);
+        self.data.extend_from_slice(&silence.data);
+    }
+
+    /// Add another stream of the same format sample by sample, saturating integer
+    /// samples. The result is as long as the longer stream.
+    pub fn overlay(&mut self, other: &Audio) -> anyhow::Result<()> {
+        if other.format != self.format {
+            anyhow::bail!("cannot overlay {:?} onto {:?}", other.format, self.format);
+        }
+        if other.data.len() > self.data.len() {
+            self.data.resize(other.data.len(), 0);
+        }
+        let format = self.format.sample_format;
+        let width = usize::from(format.bits() / 8);
+        for (a, b) in self.data.chunks_exact_mut(width).zip(other.data.chunks_exact(width)) {
+            match format {
+                SampleFormat::F32 => {
+                    let sum = f32::from_le_bytes(a.try_into().unwrap()) + f32::from_le_bytes(b.try_into().unwrap());
+                    a.copy_from_slice(&sum.to_le_bytes());
+                }
+                SampleFormat::F64 => {
+                    let sum = f64::from_le_bytes(a.try_into().unwrap()) + f64::from_le_bytes(b.try_into().unwrap());
+                    a.copy_from_slice(&sum.to_le_bytes());
+                }
+                _ => {
+                    let max = (1i64 << (format.bits() - 1)) - 1;
+                    let sum = (int_sample(a) + int_sample(b)).clamp(-max - 1, max);
+                    a.copy_from_slice(&sum.to_le_bytes()[..width]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn info(&self) -> AudioInfo {
+        AudioInfo {
+            sample_rate: self.format.sample_rate,
+            channels: self.format.channels,
+            sample_format: self.format.sample_format,
+            bits_per_sample: self.format.sample_format.bits(),
+            frames: self.frames() as u64,
+            duration_secs: self.duration().as_secs_f64(),
+        }
+    }
+}
+
+/// Sign-extended value of one little-endian integer sample.
+fn int_sample(bytes: &[u8]) -> i64 {
+    let shift = 64 - 8 * bytes.len();
+    let v = bytes.iter().rev().fold(0i64, |acc, b| (acc << 8) | i64::from(*b));
+    (v << shift) >> shift
+}
+
+/// Summary of a decoded stream, for reports and API metadata.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: SampleFormat,
+    pub bits_per_sample: u16,
+    pub frames: u64,
+    pub duration_secs: f64,
+}
+
+/// Base MIME type without parameters (`audio/raw;rate=8000` -> `audio/raw`).
+pub fn base_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+
+/// Decode `audio/wav` or `audio/raw[;params]` bytes.
+pub fn decode(bytes: &[u8], content_type: &str) -> anyhow::Result<Audio> {
+    match base_type(content_type) {
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Audio::parse_wav(bytes),
+        "audio/raw" => Ok(Audio::from_raw(bytes.to_vec(), AudioFormat::from_raw_mime(content_type)?)),
+        other => anyhow::bail!("no built-in decoder for {}", other),
+    }
+}
+
+/// Inspect bytes of a known content type.
+pub fn inspect(bytes: &[u8], content_type: &str) -> anyhow::Result<AudioInfo> {
+    decode(bytes, content_type).map(|a| a.info())
+}
+
+/// 44-byte WAV header for `data_len` bytes of `format` samples. `None` writes the
+/// `0xFFFFFFFF` sizes of a stream whose length is not known up front.
+pub fn wav_header(format: AudioFormat, data_len: Option<u32>) -> Vec<u8> {
+    let block_align = format.block_align() as u16;
+    let tag = if format.sample_format.is_float() { WAVE_FORMAT_IEEE_FLOAT } else { WAVE_FORMAT_PCM };
+    let riff_len = data_len.map_or(u32::MAX, |n| 36 + n + (n & 1));
+    let mut w = Vec::with_capacity(44);
+    w.extend_from_slice(b"RIFF");
+    w.extend_from_slice(&riff_len.to_le_bytes());
+    w.extend_from_slice(b"WAVEfmt ");
+    w.extend_from_slice(&16u32.to_le_bytes());
+    w.extend_from_slice(&tag.to_le_bytes());
+    w.extend_from_slice(&format.channels.to_le_bytes());
+    w.extend_from_slice(&format.sample_rate.to_le_bytes());
+    w.extend_from_slice(&(format.sample_rate * u32::from(block_align)).to_le_bytes());
+    w.extend_from_slice(&block_align.to_le_bytes());
+    w.extend_from_slice(&format.sample_format.bits().to_le_bytes());
+    w.extend_from_slice(b"data");
+    w.extend_from_slice(&data_len.unwrap_or(u32::MAX).to_le_bytes());
+    w
+}
+
+/// Inspect bytes of unknown type: WAV is recognized by its header, anything else is `None`.
+pub fn sniff(bytes: &[u8]) -> Option<AudioInfo> {
+    (bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE"))
+        .then(|| Audio::parse_wav(bytes).ok())
+        .flatten()
+        .map(|a| a.info())
+}
+
+/// Join encoded streams of one content type, with `silence` between consecutive parts.
+/// WAV and raw PCM are decoded and re-encoded so the result has a single, correct header;
+/// other types cannot be decoded, so they are concatenated byte-wise and `silence` is
+/// not inserted.
+pub fn stitch(parts: &[Vec<u8>], content_type: &str, silence: Duration) -> anyhow::Result<Vec<u8>> {
+    stitch_with_gaps(parts, content_type, &vec![silence; parts.len().saturating_sub(1)])
+}
+
+/// [`stitch`] with its own silence between each pair of parts: `gaps[i]` goes between
+/// `parts[i]` and `parts[i + 1]`.
+pub fn stitch_with_gaps(parts: &[Vec<u8>], content_type: &str, gaps: &[Duration]) -> anyhow::Result<Vec<u8>> {
+    let base = base_type(content_type);
+    let is_wav = matches!(base, "audio/wav" | "audio/x-wav" | "audio/wave");
+    if !is_wav && base != "audio/raw" {
+        return Ok(parts.concat());
+    }
+    let mut joined: Option<Audio> = None;
+    for (i, part) in parts.iter().enumerate() {
+        let audio = decode(part, content_type).map_err(|e| anyhow::anyhow!("part {}: {}", i + 1, e))?;
+        match &mut joined {
+            None => joined = Some(audio),
+            Some(acc) => {
+                acc.append_silence(gaps.get(i - 1).copied().unwrap_or_default());
+                acc.append(&audio).map_err(|e| anyhow::anyhow!("part {}: {}", i + 1, e))?;
+            }
+        }
+    }
+    Ok(match joined {
+        None => Vec::new(),
+        Some(a) if is_wav => a.to_wav(),
+        Some(a) => a.data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(format: AudioFormat, frames: usize, byte: u8) -> Audio {
+        Audio::from_raw(vec![byte; frames * format.block_align()], format)
+    }
+
+    #[test]
+    fn wav_roundtrip_for_every_sample_format() {
+        for sample_format in [SampleFormat::S16, SampleFormat::S24, SampleFormat::S32, SampleFormat::F32, SampleFormat::F64] {
+            let format = AudioFormat { sample_rate: 48000, channels: 2, sample_format };
+            let audio = tone(format, 480, 7);
+            let parsed = Audio::parse_wav(&audio.to_wav()).unwrap();
+            assert_eq!(parsed, audio);
+            assert_eq!(parsed.duration(), Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn parse_wav_skips_extra_chunks_and_rejects_truncation() {
+        let mut wav = tone(DEFAULT_RAW_FORMAT, 100, 1).to_wav();
+        // Insert an odd-sized LIST chunk (with pad byte) between fmt and data.
+        let list = [b"LIST".as_slice(), &3u32.to_le_bytes(), b"abc\0"].concat();
+        wav.splice(36..36, list);
+        assert_eq!(Audio::parse_wav(&wav).unwrap().frames(), 100);
+
+        wav.truncate(wav.len() - 10);
+        assert!(Audio::parse_wav(&wav).unwrap_err().to_string().contains("claims"));
+        assert!(Audio::parse_wav(b"RIFF\0\0\0\0WAVE").is_err());
+        assert!(Audio::parse_wav(b"not audio").is_err());
+    }
+
+    #[test]
+    fn streaming_wav_size_placeholder_reads_to_end() {
+        let mut wav = tone(DEFAULT_RAW_FORMAT, 50, 3).to_wav();
+        wav[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(Audio::parse_wav(&wav).unwrap().frames(), 50);
+
+        let tone = tone(DEFAULT_RAW_FORMAT, 30, 5);
+        let streamed = [wav_header(tone.format, None), tone.data.clone()].concat();
+        assert_eq!(Audio::parse_wav(&streamed).unwrap(), tone);
+    }
+
+    #[test]
+    fn raw_mime_parameters() {
+        assert_eq!(AudioFormat::from_raw_mime("audio/raw").unwrap(), DEFAULT_RAW_FORMAT);
+        let f = AudioFormat::from_raw_mime("audio/raw; rate=24000;channels=2;format=f32le").unwrap();
+        assert_eq!((f.sample_rate, f.channels, f.sample_format), (24000, 2, SampleFormat::F32));
+        assert_eq!(AudioFormat::from_raw_mime(&f.raw_mime()).unwrap(), f);
+        assert!(AudioFormat::from_raw_mime("audio/raw;format=u8").is_err());
+        assert!(AudioFormat::from_raw_mime("audio/raw;rate=0").is_err());
+        assert!(AudioFormat::from_raw_mime("audio/wav").is_err());
+    }
+
+    #[test]
+    fn stitch_wav_produces_single_header_with_silence() {
+        let a = tone(DEFAULT_RAW_FORMAT, 100, 1).to_wav();
+        let b = tone(DEFAULT_RAW_FORMAT, 200, 2).to_wav();
+        let out = stitch(&[a, b], "audio/wav", Duration::from_millis(10)).unwrap();
+        assert_eq!(out.windows(4).filter(|w| *w == b"RIFF").count(), 1);
+        let joined = Audio::parse_wav(&out).unwrap();
+        assert_eq!(joined.frames(), 100 + 221 + 200);
+        assert_eq!(joined.data[..200], [1u8; 200]);
+        assert_eq!(joined.data[200..642], [0u8; 442]);
+    }
+
+    #[test]
+    fn stitch_rejects_mismatched_formats_and_passes_through_unknown_types() {
+        let mono = tone(DEFAULT_RAW_FORMAT, 10, 1).to_wav();
+        let stereo = tone(AudioFormat { channels: 2, ..DEFAULT_RAW_FORMAT }, 10, 1).to_wav();
+        assert!(stitch(&[mono, stereo], "audio/wav", Duration::ZERO).is_err());
+        let out = stitch(&[b"ab".to_vec(), b"cd".to_vec()], "audio/mpeg", Duration::from_secs(1)).unwrap();
+        assert_eq!(out, b"abcd");
+    }
+
+    #[test]
+    fn overlay_sums_saturates_and_extends() {
+        let s16 = |samples: &[i16]| Audio::from_raw(samples.iter().flat_map(|s| s.to_le_bytes()).collect(), DEFAULT_RAW_FORMAT);
+        let mut voice = s16(&[1000, -30000, 5]);
+        voice.overlay(&s16(&[24, -10000, 5, 7])).unwrap();
+        assert_eq!(voice, s16(&[1024, i16::MIN, 10, 7]));
+
+        let s24 = AudioFormat { sample_format: SampleFormat::S24, ..DEFAULT_RAW_FORMAT };
+        let mut a = Audio::from_raw(vec![0xff, 0xff, 0xff], s24); // -1
+        a.overlay(&Audio::from_raw(vec![3, 0, 0], s24)).unwrap();
+        assert_eq!(a.data, [2, 0, 0]);
+        assert!(a.overlay(&tone(DEFAULT_RAW_FORMAT, 1, 0)).is_err());
+    }
+
+    #[test]
+    fn inspect_and_sniff_report_metadata() {
+        let wav = Audio::silence(DEFAULT_RAW_FORMAT, Duration::from_millis(500)).to_wav();
+        let info = sniff(&wav).unwrap();
+        assert_eq!((info.sample_rate, info.channels, info.bits_per_sample), (22050, 1, 16));
+        assert!((info.duration_secs - 0.5).abs() < 1e-3);
+        assert!(sniff(b"ID3 mp3 data").is_none());
+        let raw = inspect(&[0u8; 9600], "audio/raw;rate=48000;channels=2;format=s16le").unwrap();
+        assert_eq!(raw.frames, 2400);
+    }
+}
@@ -0,0 +1,277 @@
+//! Batch runs: one orchestration rendering many inputs, each to its own outputs.
+//!
+//! The input source is a glob pattern (`chapters/*.md`, `docs/**/*.html`) or a directory,
+//! whose files are taken in name order. Output paths are templates filled per input:
+//!
+//! - `{stem}`: input file name without its extension
+//! - `{name}`: input file name
+//! - `{dir}`: input's directory relative to the batch root (empty for files directly in it)
+//! - `{ext}`: extension of the output's content type, e.g. `wav` or `mp3`
+
+use crate::artifacts::extension_for;
+use crate::graph::Graph;
+use crate::paths::PathPolicy;
+use crate::pipeline::{execute_graph_report, ExecutionContext, PipelineOutput};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Whether `source` is a glob pattern rather than a path.
+pub fn is_pattern(source: &str) -> bool {
+    source.contains(['*', '?', '['])
+}
+
+/// Inputs of a batch run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch {
+    /// Directory the source's matches are taken from; `{dir}` is relative to it.
+    pub root: PathBuf,
+    /// Files to render, sorted.
+    pub inputs: Vec<PathBuf>,
+}
+
+impl Batch {
+    /// Expand `source`, a glob pattern or a directory. It is resolved like an `[input]`
+    /// source, so with a data root every match must lie within it. Hidden files are
+    /// skipped.
+    pub fn expand(source: &str, paths: &PathPolicy) -> anyhow::Result<Self> {
+        // The literal directory prefix of the pattern, and the pattern below it.
+        let components: Vec<&str> = source.split('/').collect();
+        let literal = components.iter().take_while(|c| !is_pattern(c)).count();
+        let (prefix, pattern) = if literal == components.len() {
+            (source.to_string(), None)
+        } else {
+            let prefix = components[..literal].join("/");
+            let prefix = if prefix.is_empty() && source.starts_with('/') { "/".to_string() } else { prefix };
+            (prefix, Some(components[literal..].join("/")))
+        };
+        let root = paths.resolve_input(if prefix.is_empty() { "." } else { &prefix })?;
+        if !root.is_dir() {
+            anyhow::bail!("batch input {:?} is neither a glob pattern nor a directory", source);
+        }
+        let options = glob::MatchOptions {
+            require_literal_leading_dot: true,
+            ..Default::default()
+        };
+        let full = format!("{}/{}", glob::Pattern::escape(&root.to_string_lossy()), pattern.as_deref().unwrap_or("*"));
+        let mut inputs = Vec::new();
+        for entry in glob::glob_with(&full, options).map_err(|e| anyhow::anyhow!("batch input {:?}: {}", source, e))? {
+            let path = entry?;
+            if !path.is_file() {
+                continue;
+            }
+            // A match may be a symlink out of the data root.
+            inputs.push(paths.resolve_input(&path.to_string_lossy())?);
+        }
+        inputs.sort();
+        if inputs.is_empty() {
+            anyhow::bail!("batch input {:?} matches no files", source);
+        }
+        Ok(Self { root, inputs })
+    }
+
+    /// `input`'s directory relative to the root, `/`-separated.
+    fn dir_of(&self, input: &Path) -> String {
+        let rel = input.strip_prefix(&self.root).unwrap_or(input);
+        let dir = rel.parent().unwrap_or(Path::new(""));
+        dir.components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(s.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Fill `template` for `input`, whose output has `content_type`.
+    pub fn output_path(&self, template: &str, input: &Path, content_type: &str) -> anyhow::Result<String> {
+        self.fill(template, input, extension_for(content_type))
+    }
+
+    fn fill(&self, template: &str, input: &Path, ext: &str) -> anyhow::Result<String> {
+        let dir = self.dir_of(input);
+        let template = if dir.is_empty() { template.replace("{dir}/", "") } else { template.to_string() };
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("output template {:?}: unclosed {{", template))?;
+            let value = match &rest[start + 1..start + end] {
+                "stem" => input.file_stem().unwrap_or_default().to_string_lossy(),
+                "name" => input.file_name().unwrap_or_default().to_string_lossy(),
+                "dir" => dir.as_str().into(),
+                "ext" => ext.into(),
+                other => anyhow::bail!("output template {:?}: unknown placeholder {{{}}}", template, other),
+            };
+            out.push_str(&value);
+            rest = &rest[start + end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Check that `template` gives every input its own path.
+    pub fn check_template(&self, template: &str) -> anyhow::Result<()> {
+        let mut seen: HashMap<String, &Path> = HashMap::new();
+        for input in &self.inputs {
+            // Every output of a run has the same content type, so `{ext}` cannot tell
+            // inputs apart.
+            let path = self.fill(template, input, "{ext}")?;
+            if let Some(other) = seen.insert(path.clone(), input) {
+                anyhow::bail!(
+                    "output template {:?} gives {} and {} the same path {:?} (vary it with {{stem}}, {{name}} or {{dir}})",
+                    template,
+                    other.display(),
+                    input.display(),
+                    path
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one input of a batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BatchItem {
+    pub input: PathBuf,
+    /// Paths written for it.
+    pub outputs: Vec<String>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Per-input account of a batch run, in input order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BatchReport {
+    pub items: Vec<BatchItem>,
+}
+
+impl BatchReport {
+    pub fn succeeded(&self) -> usize {
+        self.items.iter().filter(|i| i.error.is_none()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.items.len() - self.succeeded()
+    }
+}
+
+/// Render every input of `batch` with `graph`, `parallelism` at a time. A failed input
+/// is recorded and the batch goes on; once `ctx.cancel` fires, the inputs not yet started
+/// fail as cancelled.
+///
+/// `deliver` receives each successful run's output and returns the paths it wrote;
+/// `finished` is told about each input as it completes. With `ctx.work_dir` or
+/// `ctx.keep_intermediates`, each input gets its own subdirectory named after its path
+/// below the batch root.
+pub fn execute_batch(
+    graph: &Graph,
+    batch: &Batch,
+    plugin_base: &Path,
+    ctx: &ExecutionContext,
+    parallelism: usize,
+    deliver: impl Fn(&Path, PipelineOutput) -> anyhow::Result<Vec<String>> + Sync,
+    finished: impl Fn(&BatchItem) + Sync,
+) -> BatchReport {
+    let next = AtomicUsize::new(0);
+    let items: Mutex<Vec<Option<BatchItem>>> = Mutex::new(vec![None; batch.inputs.len()]);
+    let run = |input: &Path| -> anyhow::Result<Vec<String>> {
+        if ctx.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Err(crate::cancel::Cancelled.into());
+        }
+        let mut graph = graph.clone();
+        graph.input.source = input.to_string_lossy().to_string();
+        let rel = input.strip_prefix(&batch.root).unwrap_or(input);
+        let ctx = ExecutionContext {
+            work_dir: ctx.work_dir.as_ref().map(|d| d.join(rel)),
+            keep_intermediates: ctx.keep_intermediates.as_ref().map(|d| d.join(rel)),
+            ..ctx.clone()
+        };
+        deliver(input, execute_graph_report(&graph, plugin_base, &ctx)?)
+    };
+    std::thread::scope(|s| {
+        for _ in 0..parallelism.clamp(1, batch.inputs.len().max(1)) {
+            s.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(input) = batch.inputs.get(index) else {
+                    break;
+                };
+                let start = Instant::now();
+                let result = run(input);
+                let item = BatchItem {
+                    input: input.clone(),
+                    error: result.as_ref().err().map(|e| format!("{:#}", e)),
+                    outputs: result.unwrap_or_default(),
+                    elapsed_ms: start.elapsed().as_millis() as u64,
+                };
+                finished(&item);
+                items.lock().unwrap()[index] = Some(item);
+            });
+        }
+    });
+    BatchReport {
+        items: items.into_inner().unwrap().into_iter().flatten().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn expand_matches_globs_and_directories_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        for f in ["b.md", "a.md", "notes.txt", ".hidden.md", "part/c.md", "part/deep/d.md"] {
+            fs::create_dir_all(base.join(f).parent().unwrap()).unwrap();
+            fs::write(base.join(f), "x").unwrap();
+        }
+        let names = |batch: &Batch| -> Vec<String> {
+            batch.inputs.iter().map(|p| p.strip_prefix(&batch.root).unwrap().to_string_lossy().to_string()).collect()
+        };
+        let paths = PathPolicy::default();
+        let glob = Batch::expand(&format!("{}/*.md", base.display()), &paths).unwrap();
+        assert_eq!(glob.root, base);
+        assert_eq!(names(&glob), ["a.md", "b.md"]);
+        let deep = Batch::expand(&format!("{}/**/*.md", base.display()), &paths).unwrap();
+        assert_eq!(names(&deep), ["a.md", "b.md", "part/c.md", "part/deep/d.md"]);
+        let listed = Batch::expand(&base.display().to_string(), &paths).unwrap();
+        assert_eq!(names(&listed), ["a.md", "b.md", "notes.txt"]);
+
+        let err = Batch::expand(&format!("{}/*.epub", base.display()), &paths).unwrap_err();
+        assert!(err.to_string().contains("matches no files"), "{err}");
+        assert!(Batch::expand(&base.join("a.md").display().to_string(), &paths).is_err());
+
+        // With a data root, sources are relative to it and may not leave it.
+        let confined = PathPolicy {
+            data_root: Some(base.join("part")),
+            ..Default::default()
+        };
+        assert_eq!(names(&Batch::expand("**/*.md", &confined).unwrap()), ["c.md", "deep/d.md"]);
+        assert!(Batch::expand("../*.md", &confined).is_err());
+    }
+
+    #[test]
+    fn output_templates_fill_per_input_and_must_not_collide() {
+        let batch = Batch {
+            root: PathBuf::from("/data"),
+            inputs: vec![PathBuf::from("/data/one.md"), PathBuf::from("/data/vol2/one.md")],
+        };
+        let fill = |template: &str, input: &str| batch.output_path(template, Path::new(input), "audio/mpeg").unwrap();
+        assert_eq!(fill("out/{stem}.{ext}", "/data/one.md"), "out/one.mp3");
+        assert_eq!(fill("out/{dir}/{stem}.{ext}", "/data/one.md"), "out/one.mp3");
+        assert_eq!(fill("out/{dir}/{name}.wav", "/data/vol2/one.md"), "out/vol2/one.md.wav");
+        assert!(batch.output_path("out/{title}.wav", Path::new("/data/one.md"), "").is_err());
+
+        assert!(batch.check_template("out/{dir}/{stem}.{ext}").is_ok());
+        let err = batch.check_template("out/{stem}.{ext}").unwrap_err();
+        assert!(err.to_string().contains("the same path \"out/one.{ext}\""), "{err}");
+    }
+}
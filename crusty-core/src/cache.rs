@@ -0,0 +1,279 @@
+//! Content-addressed synthesis cache: stage outputs stored on disk under a SHA-256 key
+//! of everything that determines them, with a size limit and least-recently-used eviction.
+
+use crate::lock::to_hex;
+use crate::plugin::PluginOptions;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Default size limit for a cache directory.
+pub const DEFAULT_CACHE_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+const ENTRY_EXT: &str = "entry";
+
+/// Everything that determines a stage's output.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheKey<'a> {
+    pub plugin: &'a str,
+    pub version: &'a str,
+    /// `hash_plugin_dir` of the plugin directory.
+    pub plugin_hash: &'a str,
+    pub input_type: &'a str,
+    pub output_type: &'a str,
+    pub options: &'a PluginOptions,
+    pub input: &'a [u8],
+}
+
+impl CacheKey<'_> {
+    /// Hex SHA-256 over length-prefixed fields; options are hashed in sorted key order.
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        let mut field = |bytes: &[u8]| {
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+        field(self.plugin.as_bytes());
+        field(self.version.as_bytes());
+        field(self.plugin_hash.as_bytes());
+        field(self.input_type.as_bytes());
+        field(self.output_type.as_bytes());
+        let mut options: Vec<_> = self.options.iter().collect();
+        options.sort();
+        field(&(options.len() as u64).to_le_bytes());
+        for (k, v) in options {
+            field(k.as_bytes());
+            field(v.as_bytes());
+        }
+        field(self.input);
+        to_hex(&hasher.finalize())
+    }
+}
+
+/// On-disk store. Each entry is `<dir>/<key[..2]>/<key>.entry`: the content type, a
+/// newline, then the output bytes. Hits refresh the entry's mtime, which eviction uses
+/// as the recency order.
+#[derive(Debug)]
+pub struct SynthesisCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// Running total of entry bytes, loaded at open and resynced from disk whenever
+    /// eviction scans the directory. Also serializes writes and eviction within this
+    /// process.
+    size: Mutex<u64>,
+}
+
+impl SynthesisCache {
+    pub fn open(dir: &Path, max_bytes: u64) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("create cache dir {:?}: {}", dir, e))?;
+        let mut cache = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            size: Mutex::new(0),
+        };
+        *cache.size.get_mut().unwrap() = cache.entries().iter().map(|e| e.1).sum();
+        Ok(cache)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{}.{}", key, ENTRY_EXT))
+    }
+
+    /// Cached output and content type for `key`, if present.
+    pub fn get(&self, key: &str) -> Option<(Vec<u8>, String)> {
+        let path = self.entry_path(key);
+        let bytes = fs::read(&path).ok()?;
+        let newline = bytes.iter().position(|&b| b == b'\n')?;
+        let content_type = std::str::from_utf8(&bytes[..newline]).ok()?.to_string();
+        if let Ok(f) = File::options().write(true).open(&path) {
+            let _ = f.set_modified(SystemTime::now());
+        }
+        Some((bytes[newline + 1..].to_vec(), content_type))
+    }
+
+    /// Store an entry (written to a temp file and renamed into place), then evict if the
+    /// cache went over its size limit. Outputs larger than the limit are not stored.
+    pub fn put(&self, key: &str, output: &[u8], content_type: &str) -> anyhow::Result<()> {
+        let len = (content_type.len() + 1 + output.len()) as u64;
+        if len > self.max_bytes {
+            return Ok(());
+        }
+        let mut size = self.size.lock().unwrap();
+        let path = self.entry_path(key);
+        let replaced = fs::metadata(&path).map_or(0, |m| m.len());
+        let parent = path.parent().unwrap();
+        fs::create_dir_all(parent)?;
+        let tmp = parent.join(format!(".{}.{}.tmp", key, std::process::id()));
+        {
+            let mut f = File::create(&tmp)?;
+            f.write_all(content_type.as_bytes())?;
+            f.write_all(b"\n")?;
+            f.write_all(output)?;
+        }
+        fs::rename(&tmp, &path)?;
+        *size = (*size + len).saturating_sub(replaced);
+        if *size > self.max_bytes {
+            self.evict_locked(&mut size);
+        }
+        Ok(())
+    }
+
+    /// Total bytes of all entries.
+    pub fn size_bytes(&self) -> u64 {
+        *self.size.lock().unwrap()
+    }
+
+    /// Remove least-recently-used entries until the cache fits its limit. Returns the
+    /// number of entries removed.
+    pub fn evict(&self) -> anyhow::Result<usize> {
+        let mut size = self.size.lock().unwrap();
+        Ok(self.evict_locked(&mut size))
+    }
+
+    /// Scan the directory and remove least-recently-used entries down to 90% of the
+    /// limit, so the scan is not repeated on each of the next few writes.
+    fn evict_locked(&self, size: &mut u64) -> usize {
+        let mut entries = self.entries();
+        *size = entries.iter().map(|e| e.1).sum();
+        if *size <= self.max_bytes {
+            return 0;
+        }
+        let target = self.max_bytes - self.max_bytes / 10;
+        entries.sort_by_key(|e| e.2);
+        let mut removed = 0;
+        for (path, len, _) in entries {
+            if *size <= target {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                *size -= len;
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Remove every entry.
+    pub fn clear(&self) -> anyhow::Result<()> {
+        let mut size = self.size.lock().unwrap();
+        for (path, len, _) in self.entries() {
+            fs::remove_file(path)?;
+            *size = size.saturating_sub(len);
+        }
+        Ok(())
+    }
+
+    /// `(path, size, mtime)` of every entry.
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let mut out = Vec::new();
+        let Ok(shards) = fs::read_dir(&self.dir) else { return out };
+        for shard in shards.flatten().filter(|s| s.path().is_dir()) {
+            let Ok(files) = fs::read_dir(shard.path()) else { continue };
+            for file in files.flatten() {
+                let path = file.path();
+                if path.extension().is_none_or(|e| e != ENTRY_EXT) {
+                    continue;
+                }
+                if let Ok(meta) = file.metadata() {
+                    out.push((path, meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn key<'a>(opts: &'a PluginOptions, input: &'a [u8]) -> CacheKey<'a> {
+        CacheKey {
+            plugin: "tts",
+            version: "1.0",
+            plugin_hash: "sha256:abc",
+            input_type: "text/plain",
+            output_type: "audio/wav",
+            options: opts,
+            input,
+        }
+    }
+
+    #[test]
+    fn digest_covers_every_field_and_ignores_option_order() {
+        let mut a = PluginOptions::new();
+        a.insert("voice".into(), "en".into());
+        a.insert("rate".into(), "1.0".into());
+        let mut b = PluginOptions::new();
+        b.insert("rate".into(), "1.0".into());
+        b.insert("voice".into(), "en".into());
+        assert_eq!(key(&a, b"hi").digest(), key(&b, b"hi").digest());
+        assert_ne!(key(&a, b"hi").digest(), key(&a, b"ho").digest());
+        let other_version = CacheKey { version: "1.1", ..key(&a, b"hi") };
+        assert_ne!(key(&a, b"hi").digest(), other_version.digest());
+        let other_hash = CacheKey { plugin_hash: "sha256:def", ..key(&a, b"hi") };
+        assert_ne!(key(&a, b"hi").digest(), other_hash.digest());
+        let other_type = CacheKey { output_type: "audio/raw", ..key(&a, b"hi") };
+        assert_ne!(key(&a, b"hi").digest(), other_type.digest());
+    }
+
+    #[test]
+    fn put_then_get_roundtrips() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SynthesisCache::open(dir.path(), DEFAULT_CACHE_MAX_BYTES).unwrap();
+        let k = key(&PluginOptions::new(), b"hello").digest();
+        assert!(cache.get(&k).is_none());
+        cache.put(&k, b"\x00\x01audio\n", "audio/raw;rate=8000").unwrap();
+        assert_eq!(cache.get(&k), Some((b"\x00\x01audio\n".to_vec(), "audio/raw;rate=8000".to_string())));
+    }
+
+    #[test]
+    fn eviction_removes_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SynthesisCache::open(dir.path(), 250).unwrap();
+        let opts = PluginOptions::new();
+        let keys: Vec<String> = [b"a", b"b", b"c"].iter().map(|i| key(&opts, *i).digest()).collect();
+        cache.put(&keys[0], &[0u8; 100], "audio/raw").unwrap();
+        cache.put(&keys[1], &[0u8; 100], "audio/raw").unwrap();
+        // Make "a" the oldest on disk, then touch it with a hit so "b" becomes the LRU entry.
+        let old = SystemTime::now() - Duration::from_secs(60);
+        for k in &keys[..2] {
+            File::options().write(true).open(cache.entry_path(k)).unwrap().set_modified(old).unwrap();
+        }
+        File::options()
+            .write(true)
+            .open(cache.entry_path(&keys[1]))
+            .unwrap()
+            .set_modified(old - Duration::from_secs(60))
+            .unwrap();
+        assert!(cache.get(&keys[0]).is_some());
+        cache.put(&keys[2], &[0u8; 100], "audio/raw").unwrap();
+        assert!(cache.get(&keys[0]).is_some());
+        assert!(cache.get(&keys[1]).is_none(), "least recently used entry is evicted");
+        assert!(cache.get(&keys[2]).is_some());
+        assert!(cache.size_bytes() <= 250);
+    }
+
+    #[test]
+    fn size_is_tracked_across_opens_and_oversized_outputs_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = PluginOptions::new();
+        let (a, b) = (key(&opts, b"a").digest(), key(&opts, b"b").digest());
+        let cache = SynthesisCache::open(dir.path(), 250).unwrap();
+        cache.put(&a, &[0u8; 100], "audio/raw").unwrap();
+        cache.put(&a, &[0u8; 100], "audio/raw").unwrap();
+        assert_eq!(cache.size_bytes(), 110, "rewriting an entry replaces its size");
+        cache.put(&b, &[0u8; 300], "audio/raw").unwrap();
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&a).is_some(), "an oversized output evicts nothing");
+        assert_eq!(SynthesisCache::open(dir.path(), 250).unwrap().size_bytes(), 110);
+    }
+}
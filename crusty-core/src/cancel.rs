@@ -0,0 +1,125 @@
+//! Cooperative cancellation: a shared flag checked between stages and chunks, and polled
+//! while plugin processes run so the current stage's process group can be killed.
+
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often running plugins are checked for cancellation.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Cloneable handle; cancelling any clone cancels them all.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// `Err(Cancelled)` once cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Error returned by a pipeline that stopped because its token was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("pipeline cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// True when `err` (or anything in its context chain) is [`Cancelled`].
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| e.is::<Cancelled>())
+}
+
+/// Kill the process group led by `pid` (plugins are spawned as group leaders, so this
+/// also reaches anything they started).
+pub(crate) fn kill_process_group(pid: u32) {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+/// Wait for `child`, killing its process group if `cancel` fires or `timeout` passes first.
+pub(crate) fn wait_cancellable(
+    child: &mut Child,
+    cancel: Option<&CancellationToken>,
+    timeout: Option<Duration>,
+) -> anyhow::Result<std::process::ExitStatus> {
+    if cancel.is_none() && timeout.is_none() {
+        return Ok(child.wait()?);
+    }
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        let cancelled = cancel.is_some_and(CancellationToken::is_cancelled);
+        let timed_out = deadline.is_some_and(|d| Instant::now() >= d);
+        if cancelled || timed_out {
+            kill_process_group(child.id());
+            let _ = child.kill();
+            let _ = child.wait();
+            if cancelled {
+                return Err(Cancelled.into());
+            }
+            anyhow::bail!("plugin timed out after {} ms", timeout.unwrap_or_default().as_millis());
+        }
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Put a plugin command in its own process group so cancellation can kill its whole tree.
+pub(crate) fn own_process_group(cmd: &mut std::process::Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_state() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(Cancelled));
+        let err = anyhow::Error::from(Cancelled).context("stage TTS t");
+        assert!(is_cancelled(&err));
+        assert!(!is_cancelled(&anyhow::anyhow!("other")));
+    }
+}
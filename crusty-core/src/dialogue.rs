@@ -0,0 +1,128 @@
+//! Dialogue scripts: split speaker-tagged input into lines for the speakers configured
+//! in `[speakers.*]`.
+//!
+//! Two forms are accepted. A JSON array of `{"speaker": "...", "text": "..."}` objects,
+//! or plain text where a line starting with `NAME:` opens a new line for that speaker:
+//!
+//! ```text
+//! ALICE: Did you hear that?
+//! BOB: Hear what?
+//! It was nothing.
+//! ```
+//!
+//! Only configured speakers count as tags (case-insensitive, spaces and hyphens read as
+//! `_`), so `Note: ...` in prose stays text. Untagged lines continue the current line;
+//! after a blank line, untagged text is narration, voiced by `[tts]` itself.
+
+use serde::Deserialize;
+
+/// One line of a script.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DialogueLine {
+    /// Key of the `[speakers.*]` entry; `None` for narration.
+    #[serde(default)]
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+/// Form a tag or speaker key is compared in.
+fn normalize(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c == ' ' || c == '-' { '_' } else { c.to_ascii_lowercase() })
+        .collect()
+}
+
+/// Split `script` into lines for `speakers` (the `[speakers.*]` keys). Lines keep the key
+/// as configured; empty lines are dropped.
+pub fn parse_script(script: &str, speakers: &[&str]) -> anyhow::Result<Vec<DialogueLine>> {
+    let key = |name: &str| speakers.iter().find(|k| normalize(k) == normalize(name)).map(|k| k.to_string());
+    if script.trim_start().starts_with('[') {
+        let lines: Vec<DialogueLine> =
+            serde_json::from_str(script).map_err(|e| anyhow::anyhow!("dialogue script is not valid JSON: {}", e))?;
+        return lines
+            .into_iter()
+            .enumerate()
+            .filter(|(_, l)| !l.text.trim().is_empty())
+            .map(|(i, l)| {
+                let speaker = match l.speaker.as_deref() {
+                    None => None,
+                    Some(s) => Some(key(s).ok_or_else(|| {
+                        anyhow::anyhow!("line {}: unknown speaker {:?} (add [speakers.{}])", i + 1, s, normalize(s))
+                    })?),
+                };
+                Ok(DialogueLine { speaker, text: l.text.trim().to_string() })
+            })
+            .collect();
+    }
+
+    let mut lines: Vec<DialogueLine> = Vec::new();
+    // Whether the last line may still be continued by untagged text.
+    let mut open = false;
+    for raw in script.lines() {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            open = false;
+            continue;
+        }
+        let tagged = raw
+            .split_once(':')
+            .and_then(|(tag, rest)| key(tag).map(|speaker| (speaker, rest.trim())));
+        match tagged {
+            Some((speaker, rest)) => lines.push(DialogueLine {
+                speaker: Some(speaker),
+                text: rest.to_string(),
+            }),
+            None if open => {
+                let last = lines.last_mut().unwrap();
+                if !last.text.is_empty() {
+                    last.text.push(' ');
+                }
+                last.text.push_str(raw);
+            }
+            None => lines.push(DialogueLine {
+                speaker: None,
+                text: raw.to_string(),
+            }),
+        }
+        open = true;
+    }
+    lines.retain(|l| !l.text.is_empty());
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(speaker: Option<&str>, text: &str) -> DialogueLine {
+        DialogueLine {
+            speaker: speaker.map(str::to_string),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn tagged_text_splits_on_configured_speakers_only() {
+        let script = "Chapter one.\n\nALICE: Did you hear that?\nNote: it was late.\nDr Smith: Hear what?\n\nThe house was quiet.\nbob:\n";
+        let lines = parse_script(script, &["alice", "dr_smith", "bob"]).unwrap();
+        assert_eq!(
+            lines,
+            [
+                line(None, "Chapter one."),
+                line(Some("alice"), "Did you hear that? Note: it was late."),
+                line(Some("dr_smith"), "Hear what?"),
+                line(None, "The house was quiet."),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_scripts_name_speakers_explicitly() {
+        let script = r#"[{"speaker": "ALICE", "text": " Hi "}, {"text": "She waved."}, {"speaker": "bob", "text": ""}]"#;
+        let lines = parse_script(script, &["alice", "bob"]).unwrap();
+        assert_eq!(lines, [line(Some("alice"), "Hi"), line(None, "She waved.")]);
+        let err = parse_script(r#"[{"speaker": "carol", "text": "Hey"}]"#, &["alice"]).unwrap_err();
+        assert!(err.to_string().contains("unknown speaker \"carol\""), "{err}");
+    }
+}
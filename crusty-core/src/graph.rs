@@ -0,0 +1,628 @@
+//! Orchestration v2: a DAG of named `[[nodes]]` wired by `inputs = ["node.port"]`.
+//! v1 orchestrations convert to the equivalent linear graph, so both run on one executor.
+
+use crate::orchestration::{
+    default_output_type, speaker_engines, ChunkingConfig, DebugConfig, DialogueConfig, Input, Meta, Orchestration,
+    PluginConfig, RetryPolicy, SpeakerConfig,
+};
+use crate::pipeline::tts_options;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// `version` of the graph format.
+pub const GRAPH_VERSION: u32 = 2;
+
+/// Name the `[input]` text is referenced by, as `input.text`.
+pub const INPUT_NODE: &str = "input";
+const INPUT_PORT: &str = "text";
+/// The single output port of every node.
+const OUT_PORT: &str = "out";
+
+/// Orchestration graph (`version = 2`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Graph {
+    pub version: u32,
+    pub meta: Meta,
+    pub input: Input,
+    pub nodes: Vec<Node>,
+    /// Main output; its audio is what `execute_*` returns.
+    #[serde(default)]
+    pub output: Option<Sink>,
+    /// Further named outputs.
+    #[serde(default)]
+    pub outputs: Vec<Sink>,
+    #[serde(default)]
+    pub debug: Option<DebugConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeKind {
+    PreProcessor,
+    Tts,
+    Converter,
+    PostProcessor,
+    /// Built-in: joins or overlays its audio inputs.
+    Mix,
+    /// Built-in: reads a file, such as a music bed.
+    Source,
+}
+
+impl NodeKind {
+    /// Stage kind shown in reports and progress, as for v1 stages.
+    pub fn label(self) -> &'static str {
+        match self {
+            NodeKind::PreProcessor => "pre-processor",
+            NodeKind::Tts => "TTS",
+            NodeKind::Converter => "converter",
+            NodeKind::PostProcessor => "post-processor",
+            NodeKind::Mix => "mix",
+            NodeKind::Source => "source",
+        }
+    }
+
+    /// Whether the node runs a plugin (and so needs a `module`).
+    pub fn is_plugin(self) -> bool {
+        !matches!(self, NodeKind::Mix | NodeKind::Source)
+    }
+}
+
+/// One `[[nodes]]` entry.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Node {
+    /// Unique within the graph; referenced by other nodes' `inputs`.
+    pub name: String,
+    pub kind: NodeKind,
+    /// Plugin the node runs, and the name native plugins are looked up by. Defaults to `name`.
+    #[serde(default)]
+    pub plugin: Option<String>,
+    #[serde(default)]
+    pub module: Option<String>,
+    /// `"node.port"` references; the port may be left out. `input.text` is the `[input]` text.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub options: Option<toml::Value>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// TTS nodes: as `[tts.chunking]`.
+    #[serde(default)]
+    pub chunking: Option<ChunkingConfig>,
+    /// TTS nodes: as `[[tts.fallbacks]]`; each gets this node's options overlaid with its own.
+    #[serde(default)]
+    pub fallbacks: Option<Vec<PluginConfig>>,
+    /// TTS nodes: as `[speakers.*]`; speakers' engines get this node's options overlaid
+    /// with their own.
+    #[serde(default)]
+    pub speakers: Option<BTreeMap<String, SpeakerConfig>>,
+    /// TTS nodes with speakers: as `[dialogue]`.
+    #[serde(default)]
+    pub dialogue: Option<DialogueConfig>,
+    /// Mix nodes.
+    #[serde(default)]
+    pub mix: Option<MixConfig>,
+    /// Source nodes: file to read, resolved like the `[input]` source.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Source nodes: content type of `path`; guessed from its extension when unset.
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Groups the node in reports; v1 `[[outputs]]` branches convert with their name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+impl Node {
+    fn new(name: String, kind: NodeKind, input: String) -> Self {
+        Self {
+            name,
+            kind,
+            plugin: None,
+            module: None,
+            inputs: vec![input],
+            options: None,
+            retry: None,
+            chunking: None,
+            fallbacks: None,
+            speakers: None,
+            dialogue: None,
+            mix: None,
+            path: None,
+            content_type: None,
+            branch: None,
+        }
+    }
+
+    /// Plugin name for plugin nodes; the node name for built-ins.
+    pub fn plugin_name(&self) -> &str {
+        self.plugin.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn module(&self) -> &str {
+        self.module.as_deref().unwrap_or_default()
+    }
+
+    /// Names of the nodes this one reads from, in `inputs` order.
+    pub fn input_nodes(&self) -> impl Iterator<Item = &str> {
+        self.inputs.iter().map(|r| ref_node(r))
+    }
+
+    /// Enabled fallbacks in the order they are tried.
+    pub fn fallbacks(&self) -> impl Iterator<Item = &PluginConfig> {
+        self.fallbacks.iter().flatten().filter(|f| f.enabled)
+    }
+
+    /// Whether the node voices a dialogue script: a TTS node with speakers.
+    pub fn is_dialogue(&self) -> bool {
+        self.kind == NodeKind::Tts && self.speakers.as_ref().is_some_and(|s| !s.is_empty())
+    }
+
+    /// Engines other than the node's own that its speakers use, as `(name, module)`.
+    pub fn speaker_engines(&self) -> Vec<(&str, &str)> {
+        speaker_engines(self.speakers.as_ref(), self.plugin_name(), self.module())
+    }
+
+    /// Content type a source node's file is read as.
+    pub fn source_type(&self) -> String {
+        if let Some(t) = &self.content_type {
+            return t.clone();
+        }
+        let ext = self.path.as_deref().and_then(|p| std::path::Path::new(p).extension()).and_then(|e| e.to_str());
+        match ext.map(str::to_ascii_lowercase).as_deref() {
+            Some("wav") => "audio/wav",
+            Some("mp3") => "audio/mpeg",
+            Some("ogg" | "opus") => "audio/ogg",
+            Some("flac") => "audio/flac",
+            Some("txt") => "text/plain",
+            _ => "application/octet-stream",
+        }
+        .to_string()
+    }
+}
+
+/// `mix = { mode, silence_ms }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MixConfig {
+    #[serde(default)]
+    pub mode: MixMode,
+    /// Concat mode: silence between inputs.
+    #[serde(default)]
+    pub silence_ms: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MixMode {
+    /// Inputs one after another.
+    #[default]
+    Concat,
+    /// Inputs summed sample by sample, as long as the longest.
+    Overlay,
+}
+
+/// `[output]` or one `[[outputs]]` entry: where a node's output is written.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Sink {
+    /// Required and unique for `[[outputs]]`; unused for `[output]`.
+    #[serde(default)]
+    pub name: String,
+    /// Node output written here.
+    pub from: String,
+    #[serde(default = "default_output_type")]
+    pub r#type: String,
+    pub path: String,
+    #[serde(default)]
+    pub overwrite: Option<bool>,
+}
+
+/// Node name of a `"node.port"` reference.
+pub fn ref_node(reference: &str) -> &str {
+    reference.split_once('.').map_or(reference, |(node, _)| node)
+}
+
+impl Graph {
+    /// Parse either format: `version = 2` as a graph, anything else as a v1 orchestration
+    /// converted with [`Graph::from_orchestration`].
+    pub fn from_toml(s: &str) -> anyhow::Result<Self> {
+        let value: toml::Value = toml::from_str(s)?;
+        match value.get("version").map(|v| v.as_integer()) {
+            None | Some(Some(1)) => Ok(Self::from_orchestration(&Orchestration::from_toml(s)?)),
+            Some(Some(2)) => {
+                let graph: Graph = value.try_into()?;
+                graph.topological_order()?;
+                Ok(graph)
+            }
+            Some(v) => anyhow::bail!("unsupported orchestration version {}", v.map_or("?".to_string(), |v| v.to_string())),
+        }
+    }
+
+    pub fn load_path(path: &std::path::Path) -> anyhow::Result<Self> {
+        let s = std::fs::read_to_string(path)?;
+        Self::from_toml(&s)
+    }
+
+    /// The linear graph a v1 orchestration describes: input, pre-processors, TTS, the main
+    /// chain into `[output]`, and one chain per `[[outputs]]` branch reading the TTS node.
+    /// Disabled plugins are left out. Node names are the plugin names, prefixed with the
+    /// branch name inside branches and numbered where they would clash.
+    pub fn from_orchestration(orch: &Orchestration) -> Self {
+        let mut taken: HashSet<String> = HashSet::from([INPUT_NODE.to_string()]);
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut push = |mut node: Node, plugin: &str, module: &str| -> String {
+            let base = node.name.replace('.', "_");
+            let mut name = base.clone();
+            let mut n = 1;
+            while !taken.insert(name.clone()) {
+                n += 1;
+                name = format!("{}-{}", base, n);
+            }
+            node.name = name.clone();
+            node.plugin = Some(plugin.to_string());
+            node.module = Some(module.to_string());
+            nodes.push(node);
+            format!("{}.{}", name, OUT_PORT)
+        };
+
+        let mut last = format!("{}.{}", INPUT_NODE, INPUT_PORT);
+        for p in orch.pre_processors.iter().flatten().filter(|p| p.enabled) {
+            let node = Node {
+                options: p.options.clone(),
+                retry: p.retry,
+                ..Node::new(p.name.clone(), NodeKind::PreProcessor, last)
+            };
+            last = push(node, &p.name, &p.module);
+        }
+        let tts = &orch.tts;
+        let options = tts_options(tts).into_iter().map(|(k, v)| (k, toml::Value::String(v))).collect();
+        let node = Node {
+            options: Some(toml::Value::Table(options)),
+            retry: tts.retry,
+            chunking: tts.chunking.clone(),
+            fallbacks: tts.fallbacks.clone(),
+            speakers: orch.speakers.clone(),
+            dialogue: orch.dialogue,
+            ..Node::new(tts.name.clone(), NodeKind::Tts, last)
+        };
+        let tts_out = push(node, &tts.name, &tts.module);
+
+        let mut chain = |branch: Option<&str>,
+                         converters: &Option<Vec<PluginConfig>>,
+                         post: &Option<Vec<PluginConfig>>|
+         -> String {
+            let plugins = converters
+                .iter()
+                .flatten()
+                .map(|p| (NodeKind::Converter, p))
+                .chain(post.iter().flatten().map(|p| (NodeKind::PostProcessor, p)));
+            let mut last = tts_out.clone();
+            for (kind, p) in plugins.filter(|(_, p)| p.enabled) {
+                let name = match branch {
+                    Some(b) => format!("{}-{}", b, p.name),
+                    None => p.name.clone(),
+                };
+                let node = Node {
+                    options: p.options.clone(),
+                    retry: p.retry,
+                    branch: branch.map(str::to_string),
+                    ..Node::new(name, kind, last)
+                };
+                last = push(node, &p.name, &p.module);
+            }
+            last
+        };
+        let main = chain(None, &orch.audio_converters, &orch.post_processors);
+        let output = orch.output.as_ref().map(|o| Sink {
+            name: String::new(),
+            from: main,
+            r#type: o.r#type.clone(),
+            path: o.path.clone(),
+            overwrite: o.overwrite,
+        });
+        let outputs = orch
+            .branches()
+            .iter()
+            .map(|b| Sink {
+                name: b.name.clone(),
+                from: chain(Some(&b.name), &b.converters, &b.post_processors),
+                r#type: b.r#type.clone(),
+                path: b.path.clone(),
+                overwrite: b.overwrite,
+            })
+            .collect();
+
+        Self {
+            version: GRAPH_VERSION,
+            meta: orch.meta.clone(),
+            input: orch.input.clone(),
+            nodes,
+            output,
+            outputs,
+            debug: orch.debug.clone(),
+        }
+    }
+
+    pub fn node(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+
+    /// `[output]`, then each `[[outputs]]` entry.
+    pub fn sinks(&self) -> impl Iterator<Item = &Sink> {
+        self.output.iter().chain(&self.outputs)
+    }
+
+    /// Plugins the graph runs as `(name, module)`, TTS fallbacks and speaker engines
+    /// included, in node order.
+    pub fn stage_modules(&self) -> Vec<(&str, &str)> {
+        self.nodes
+            .iter()
+            .filter(|n| n.kind.is_plugin())
+            .flat_map(|n| {
+                std::iter::once((n.plugin_name(), n.module()))
+                    .chain(n.fallbacks().map(|f| (f.name.as_str(), f.module.as_str())))
+                    .chain(n.speaker_engines())
+            })
+            .collect()
+    }
+
+    /// Check the graph's structure and return its nodes in an order where every node comes
+    /// after its inputs. Among nodes that are ready together, declaration order wins.
+    pub fn topological_order(&self) -> anyhow::Result<Vec<&Node>> {
+        if self.version != GRAPH_VERSION {
+            anyhow::bail!("unsupported graph version {}", self.version);
+        }
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.name.is_empty() || node.name.contains('.') || node.name == INPUT_NODE {
+                anyhow::bail!("invalid node name {:?}: names must be non-empty, without '.', and not {:?}", node.name, INPUT_NODE);
+            }
+            if index.insert(&node.name, i).is_some() {
+                anyhow::bail!("duplicate node name {:?}", node.name);
+            }
+        }
+        let check_ref = |owner: &str, reference: &str| -> anyhow::Result<()> {
+            let (node, port) = match reference.split_once('.') {
+                Some((node, port)) => (node, Some(port)),
+                None => (reference, None),
+            };
+            let expected = if node == INPUT_NODE {
+                INPUT_PORT
+            } else if index.contains_key(node) {
+                OUT_PORT
+            } else {
+                anyhow::bail!("{}: unknown node {:?}", owner, node);
+            };
+            if port.is_some_and(|p| p != expected) {
+                anyhow::bail!("{}: node {:?} has no port {:?} (only {:?})", owner, node, port.unwrap(), expected);
+            }
+            Ok(())
+        };
+
+        for node in &self.nodes {
+            let owner = format!("node {}", node.name);
+            let arity_ok = match node.kind {
+                NodeKind::Source => node.inputs.is_empty(),
+                NodeKind::Mix => !node.inputs.is_empty(),
+                _ => node.inputs.len() == 1,
+            };
+            if !arity_ok {
+                let expected = match node.kind {
+                    NodeKind::Source => "no inputs",
+                    NodeKind::Mix => "at least one input",
+                    _ => "exactly one input",
+                };
+                anyhow::bail!("{}: {} nodes take {}, got {}", owner, node.kind.label(), expected, node.inputs.len());
+            }
+            if node.kind.is_plugin() && node.module.is_none() {
+                anyhow::bail!("{}: {} nodes need a module", owner, node.kind.label());
+            }
+            if node.kind == NodeKind::Source && node.path.is_none() {
+                anyhow::bail!("{}: source nodes need a path", owner);
+            }
+            if node.is_dialogue() {
+                if node.chunking.is_some() {
+                    anyhow::bail!("{}: chunking does not apply to dialogue, which is synthesized a line at a time", owner);
+                }
+                for (speaker, config) in node.speakers.iter().flatten() {
+                    if config.engine(node.plugin_name(), node.module()).1.is_empty() {
+                        anyhow::bail!("{}: speaker {} uses TTS {:?} but has no module", owner, speaker, config.tts.as_deref().unwrap_or_default());
+                    }
+                }
+            }
+            for r in &node.inputs {
+                check_ref(&owner, r)?;
+            }
+        }
+
+        if self.output.is_none() && self.outputs.is_empty() {
+            anyhow::bail!("graph needs an [output] or at least one [[outputs]] entry");
+        }
+        if let Some(o) = &self.output {
+            check_ref("[output]", &o.from)?;
+        }
+        for (i, o) in self.outputs.iter().enumerate() {
+            if o.name.is_empty() {
+                anyhow::bail!("[[outputs]] entry {} needs a name", i + 1);
+            }
+            if self.outputs[..i].iter().any(|other| other.name == o.name) {
+                anyhow::bail!("duplicate [[outputs]] name {:?}", o.name);
+            }
+            check_ref(&format!("output {}", o.name), &o.from)?;
+        }
+
+        // Kahn's algorithm over node indices.
+        let deps: Vec<Vec<usize>> = self
+            .nodes
+            .iter()
+            .map(|n| n.input_nodes().filter_map(|d| index.get(d).copied()).collect())
+            .collect();
+        let mut pending: Vec<usize> = deps.iter().map(Vec::len).collect();
+        let mut ready: BTreeSet<usize> = (0..self.nodes.len()).filter(|&i| pending[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(i) = ready.pop_first() {
+            order.push(i);
+            for (j, d) in deps.iter().enumerate() {
+                for _ in d.iter().filter(|&&d| d == i) {
+                    pending[j] -= 1;
+                    if pending[j] == 0 {
+                        ready.insert(j);
+                    }
+                }
+            }
+        }
+        if order.len() < self.nodes.len() {
+            // Every unordered node waits on another unordered one; walking those links
+            // from any of them must revisit a node.
+            let mut path = vec![(0..self.nodes.len()).find(|&i| pending[i] > 0).unwrap()];
+            loop {
+                let next = deps[*path.last().unwrap()].iter().copied().find(|&d| pending[d] > 0).unwrap();
+                if let Some(start) = path.iter().position(|&i| i == next) {
+                    let mut cycle: Vec<&str> = path[start..].iter().map(|&i| self.nodes[i].name.as_str()).collect();
+                    cycle.push(&self.nodes[next].name);
+                    // Walked against the edges; report in data-flow order.
+                    cycle.reverse();
+                    anyhow::bail!("graph has a cycle: {}", cycle.join(" -> "));
+                }
+                path.push(next);
+            }
+        }
+        Ok(order.into_iter().map(|i| &self.nodes[i]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAD: &str = r#"
+version = 2
+[meta]
+name = "drama"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+"#;
+
+    fn graph(body: &str) -> anyhow::Result<Graph> {
+        Graph::from_toml(&format!("{HEAD}{body}"))
+    }
+
+    #[test]
+    fn parses_and_orders_a_graph() {
+        let g = graph(
+            r#"
+[[nodes]]
+name = "mixdown"
+kind = "mix"
+inputs = ["alice.out", "bob"]
+mix = { mode = "overlay" }
+[[nodes]]
+name = "alice"
+kind = "tts"
+module = "plugins/piper"
+inputs = ["input.text"]
+options = { voice = "alice" }
+[[nodes]]
+name = "bob"
+kind = "tts"
+plugin = "piper"
+module = "plugins/piper"
+inputs = ["input.text"]
+[output]
+from = "mixdown.out"
+path = "out.wav"
+"#,
+        )
+        .unwrap();
+        let order: Vec<&str> = g.topological_order().unwrap().iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(order, ["alice", "bob", "mixdown"]);
+        assert_eq!(g.node("mixdown").unwrap().mix.unwrap().mode, MixMode::Overlay);
+        assert_eq!(g.stage_modules(), [("alice", "plugins/piper"), ("piper", "plugins/piper")]);
+        assert_eq!(g.output.as_ref().unwrap().r#type, "file");
+    }
+
+    #[test]
+    fn rejects_cycles_bad_references_and_arity() {
+        let node = |name: &str, input: &str| {
+            format!("[[nodes]]\nname = \"{name}\"\nkind = \"converter\"\nmodule = \"m\"\ninputs = [\"{input}\"]\n")
+        };
+        let out = "[output]\nfrom = \"a\"\npath = \"o\"\n";
+        let err = |body: String| graph(&body).unwrap_err().to_string();
+
+        let e = err(format!("{}{}{}{}", node("a", "c.out"), node("b", "a.out"), node("c", "b"), out));
+        assert_eq!(e, "graph has a cycle: a -> b -> c -> a");
+        assert!(err(format!("{}{}", node("a", "nope.out"), out)).contains("unknown node \"nope\""));
+        assert!(err(format!("{}{}", node("a", "input.out"), out)).contains("has no port \"out\""));
+        assert!(err(format!("{}{}{}", node("a", "input"), node("a", "input"), out)).contains("duplicate node"));
+        assert!(err(node("a", "input")).contains("needs an [output]"));
+        let mix = "[[nodes]]\nname = \"a\"\nkind = \"mix\"\n";
+        assert!(err(format!("{mix}{out}")).contains("at least one input"));
+        assert!(Graph::from_toml(&HEAD.replace("version = 2", "version = 3")).unwrap_err().to_string().contains("version 3"));
+    }
+
+    #[test]
+    fn converts_v1_to_a_linear_graph() {
+        let orch = Orchestration::from_toml(
+            r#"
+[meta]
+name = "book"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[[pre_processors]]
+name = "clean"
+module = "plugins/clean"
+[[pre_processors]]
+name = "skip"
+module = "plugins/skip"
+enabled = false
+[tts]
+name = "tts"
+module = "plugins/tts"
+voice = "en"
+options = { style = "calm", voice = "en_gb" }
+[[post_processors]]
+name = "norm"
+module = "plugins/norm"
+[output]
+type = "file"
+path = "out.wav"
+[[outputs]]
+name = "mp3"
+path = "out.mp3"
+[[outputs.converters]]
+name = "lame"
+module = "plugins/lame"
+[[outputs]]
+name = "raw"
+path = "out.pcm"
+"#,
+        )
+        .unwrap();
+        let g = Graph::from_orchestration(&orch);
+        let wiring: Vec<(&str, &str, Option<&str>)> =
+            g.nodes.iter().map(|n| (n.name.as_str(), n.inputs[0].as_str(), n.branch.as_deref())).collect();
+        assert_eq!(
+            wiring,
+            [
+                ("clean", "input.text", None),
+                ("tts", "clean.out", None),
+                ("norm", "tts.out", None),
+                ("mp3-lame", "tts.out", Some("mp3")),
+            ]
+        );
+        assert_eq!(g.node("mp3-lame").unwrap().plugin_name(), "lame");
+        // `[tts] options` are overlaid on voice, rate and pitch.
+        let tts_options = g.node("tts").unwrap().options.as_ref().unwrap();
+        assert_eq!(tts_options["voice"].as_str(), Some("en_gb"));
+        assert_eq!(tts_options["style"].as_str(), Some("calm"));
+        assert_eq!(g.output.as_ref().unwrap().from, "norm.out");
+        let sinks: Vec<(&str, &str)> = g.outputs.iter().map(|o| (o.name.as_str(), o.from.as_str())).collect();
+        assert_eq!(sinks, [("mp3", "mp3-lame.out"), ("raw", "tts.out")]);
+        assert_eq!(g.stage_modules(), orch.stage_modules());
+        assert_eq!(g.topological_order().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,167 @@
+//! Checkpoint journal for resumable renders: completed TTS chunks are written to a work
+//! directory with a `journal.json` describing them, so a failed or cancelled run can
+//! restart from the first incomplete chunk.
+
+use crate::lock::to_hex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Journal file name inside the work directory.
+pub const JOURNAL_NAME: &str = "journal.json";
+
+const CHUNK_DIR: &str = "chunks";
+
+/// One synthesized chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalChunk {
+    /// SHA-256 of the chunk text; a chunk is only reused for identical text.
+    pub digest: String,
+    /// TTS engine that produced it (the `[tts]` plugin or a fallback).
+    pub engine: String,
+    /// Path relative to the work directory.
+    pub file: String,
+    pub content_type: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    /// [`run_fingerprint`] of the run that wrote the journal.
+    pub fingerprint: String,
+    /// Chunks by stage index, then chunk index; a graph can have several TTS stages.
+    pub chunks: BTreeMap<usize, BTreeMap<usize, JournalChunk>>,
+}
+
+/// Fingerprint of what a render's audio depends on besides its text: the orchestration
+/// (as JSON) and a `(name, hash)` per plugin.
+pub fn run_fingerprint(orchestration_json: &str, plugins: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(orchestration_json.as_bytes());
+    for (name, hash) in plugins {
+        hasher.update([0]);
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update(hash.as_bytes());
+    }
+    to_hex(&hasher.finalize())
+}
+
+fn text_digest(text: &str) -> String {
+    to_hex(&Sha256::digest(text.as_bytes()))
+}
+
+/// Open journal for one run. Writes are safe from concurrent chunk workers.
+#[derive(Debug)]
+pub struct Checkpoint {
+    dir: PathBuf,
+    journal: Mutex<Journal>,
+}
+
+impl Checkpoint {
+    /// Open `dir` for a run with `fingerprint`. With `resume`, completed chunks of an
+    /// earlier run are kept, and an earlier run with another fingerprint is an error;
+    /// without it, any earlier journal is discarded.
+    pub fn open(dir: &Path, fingerprint: &str, resume: bool) -> anyhow::Result<Self> {
+        let path = dir.join(JOURNAL_NAME);
+        let journal = if resume && path.exists() {
+            let journal: Journal = serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| anyhow::anyhow!("read checkpoint journal {:?}: {}", path, e))?;
+            if journal.fingerprint != fingerprint {
+                anyhow::bail!(
+                    "cannot resume from {:?}: the orchestration or its plugins changed since the checkpoint was written",
+                    dir
+                );
+            }
+            journal
+        } else {
+            if dir.join(CHUNK_DIR).exists() {
+                fs::remove_dir_all(dir.join(CHUNK_DIR))?;
+            }
+            Journal {
+                fingerprint: fingerprint.to_string(),
+                chunks: BTreeMap::new(),
+            }
+        };
+        fs::create_dir_all(dir.join(CHUNK_DIR))
+            .map_err(|e| anyhow::anyhow!("create work dir {:?}: {}", dir, e))?;
+        let checkpoint = Self {
+            dir: dir.to_path_buf(),
+            journal: Mutex::new(journal),
+        };
+        checkpoint.write_journal(&checkpoint.journal.lock().unwrap())?;
+        Ok(checkpoint)
+    }
+
+    /// Chunks recorded so far.
+    pub fn completed(&self) -> usize {
+        self.journal.lock().unwrap().chunks.values().map(BTreeMap::len).sum()
+    }
+
+    /// Output of chunk `index` of `stage` if `engine` already synthesized this exact `text`.
+    pub fn restore(&self, stage: usize, index: usize, engine: &str, text: &str) -> Option<(Vec<u8>, String)> {
+        let chunk = self.journal.lock().unwrap().chunks.get(&stage)?.get(&index).cloned()?;
+        if chunk.engine != engine || chunk.digest != text_digest(text) {
+            return None;
+        }
+        let bytes = fs::read(self.dir.join(&chunk.file)).ok()?;
+        Some((bytes, chunk.content_type))
+    }
+
+    /// Record chunk `index` of `stage`. The audio is written before the journal names it.
+    pub fn save(&self, stage: usize, index: usize, engine: &str, text: &str, output: &(Vec<u8>, String)) -> anyhow::Result<()> {
+        let file = format!("{}/{:02}-{:05}.bin", CHUNK_DIR, stage, index);
+        fs::write(self.dir.join(&file), &output.0)?;
+        let mut journal = self.journal.lock().unwrap();
+        journal.chunks.entry(stage).or_default().insert(
+            index,
+            JournalChunk {
+                digest: text_digest(text),
+                engine: engine.to_string(),
+                file,
+                content_type: output.1.clone(),
+            },
+        );
+        self.write_journal(&journal)
+    }
+
+    /// Replace the journal atomically, so a crash never leaves it half-written.
+    fn write_journal(&self, journal: &Journal) -> anyhow::Result<()> {
+        let tmp = self.dir.join(format!("{}.tmp", JOURNAL_NAME));
+        fs::write(&tmp, serde_json::to_vec_pretty(journal)?)?;
+        fs::rename(&tmp, self.dir.join(JOURNAL_NAME))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_keeps_matching_chunks_and_rejects_changed_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = (b"pcm".to_vec(), "audio/raw".to_string());
+        let cp = Checkpoint::open(dir.path(), "fp1", false).unwrap();
+        cp.save(1, 3, "tts", "Hello.", &out).unwrap();
+        drop(cp);
+
+        let cp = Checkpoint::open(dir.path(), "fp1", true).unwrap();
+        assert_eq!(cp.completed(), 1);
+        assert_eq!(cp.restore(1, 3, "tts", "Hello."), Some(out.clone()));
+        assert_eq!(cp.restore(1, 3, "tts", "Hello!"), None);
+        assert_eq!(cp.restore(1, 3, "backup", "Hello."), None);
+        assert_eq!(cp.restore(1, 4, "tts", "Hello."), None);
+        assert_eq!(cp.restore(2, 3, "tts", "Hello."), None);
+
+        let err = Checkpoint::open(dir.path(), "fp2", true).unwrap_err();
+        assert!(err.to_string().contains("changed since the checkpoint"), "{err}");
+
+        // A fresh run starts over.
+        let cp = Checkpoint::open(dir.path(), "fp2", false).unwrap();
+        assert_eq!(cp.completed(), 0);
+        assert!(!dir.path().join("chunks/01-00003.bin").exists());
+    }
+}
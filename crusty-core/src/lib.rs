@@ -1,17 +1,56 @@
 //! Crusty-TTS core: orchestration, plugin registry, pipeline execution, protocol.
 
+pub mod artifacts;
+pub mod audio;
+pub mod batch;
+pub mod cache;
+pub mod cancel;
+pub mod dialogue;
+pub mod graph;
+pub mod journal;
+pub mod lock;
+pub mod observer;
 pub mod orchestration;
+pub mod paths;
 pub mod pipeline;
+pub mod plan;
 pub mod plugin;
 pub mod plugin_runner;
 pub mod protocol;
+pub mod reader;
 pub mod registry;
+pub mod segment;
+pub mod ssml;
+pub mod trust;
 pub mod validate;
+pub mod verify;
+pub mod worker;
 
-pub use orchestration::{Orchestration, Output, PipelineOrchestration, PipelineSection, PluginConfig, TtsConfig};
-pub use pipeline::{execute_pipeline, run_pipeline_from_plugins};
-pub use plugin::{Plugin, PluginManifest, PluginOptions, PluginType, PostProcessor, PreProcessor, Tts};
+pub use artifacts::{ArtifactManifest, IntermediateWriter};
+pub use audio::{Audio, AudioFormat, AudioInfo, SampleFormat};
+pub use batch::{execute_batch, Batch, BatchItem, BatchReport};
+pub use cache::{CacheKey, SynthesisCache, DEFAULT_CACHE_MAX_BYTES};
+pub use cancel::{is_cancelled, CancellationToken, Cancelled};
+pub use dialogue::{parse_script, DialogueLine};
+pub use graph::{Graph, MixConfig, MixMode, Node, NodeKind, Sink, GRAPH_VERSION};
+pub use journal::{Checkpoint, Journal, JOURNAL_NAME};
+pub use lock::{hash_plugin_dir, IntegrityMode, LockCheck, LockDrift, Lockfile, LOCKFILE_NAME};
+pub use observer::{PipelineObserver, StageInfo};
+pub use orchestration::{ChunkMode, ChunkingConfig, DebugConfig, DialogueConfig, Orchestration, Output, OutputBranch, PipelineOrchestration, PipelineSection, PluginConfig, RetryPolicy, SpeakerConfig, TtsConfig};
+pub use paths::{PathError, PathPolicy};
+pub use pipeline::{
+    execute_graph_report, execute_pipeline, execute_pipeline_report, execute_pipeline_with, run_pipeline_from_plugins, BranchOutput, CacheUse,
+    resolve_plugin_executable, ExecutionContext, PipelineOutput, PipelineReport, StageReport,
+};
+pub use plan::{plan, plan_graph, ExecutionPlan, PlannedStage};
+pub use plugin::{Lifecycle, NativePlugin, OptionKind, OptionSpec, Plugin, PluginBackend, PluginManifest, PluginOptions, PluginType, PostProcessor, PreProcessor, Transport, Tts};
 pub use plugin_runner::{run_subprocess_plugin, run_subprocess_plugin_framed, verify_plugin};
-pub use protocol::{Handshake, ErrorFrame, PROTOCOL_VERSION};
+pub use protocol::{Handshake, ErrorFrame, ProgressFrame, PROTOCOL_VERSION};
+pub use reader::{read_input, Chapter, Document, InputReader, InputText};
 pub use registry::PluginRegistry;
-pub use validate::validate_orchestration_types;
+pub use segment::segment;
+pub use ssml::{Ssml, SSML_TYPE};
+pub use trust::{SignaturePolicy, SignatureStatus, TrustConfig, TrustedKeys};
+pub use validate::{validate_graph_fallbacks, validate_graph_types, validate_orchestration_types, validate_ssml, validate_tts_fallbacks};
+pub use verify::{verify_plugin_dir, CheckResult, CheckStatus, VerificationReport};
+pub use worker::{WorkerPool, WorkerPoolConfig};
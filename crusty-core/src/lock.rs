@@ -0,0 +1,337 @@
+//! Plugin integrity: content hashes of plugin directories and the `crusty.lock` file
+//! recording which plugin versions an orchestration resolved to.
+
+use crate::orchestration::Orchestration;
+use crate::pipeline::load_manifest;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Lockfile name, written next to the orchestration file.
+pub const LOCKFILE_NAME: &str = "crusty.lock";
+
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Top-level files excluded from the hash: the detached signature signs the hash itself.
+const IGNORED_FILES: &[&str] = &[crate::trust::SIGNATURE_FILE];
+
+/// SHA-256 over a plugin directory: every file's relative path, executable bit and
+/// contents, in sorted path order. Returned as `sha256:<hex>`. A symlink to a file is
+/// hashed by the contents it resolves to, wherever they are; a symlink to a directory
+/// must stay inside the plugin directory, whose files are hashed anyway.
+///
+/// Interpreter caches such as `__pycache__` are hashed too, since Python loads a `.pyc`
+/// in place of its source. A plugin that writes one on its first run drifts from its lock
+/// and signature: lock and sign it after that run, or ship it with the cache disabled
+/// (`sys.dont_write_bytecode = True` before its imports).
+pub fn hash_plugin_dir(dir: &Path) -> anyhow::Result<String> {
+    let root = std::fs::canonicalize(dir).map_err(|e| anyhow::anyhow!("resolve {:?}: {}", dir, e))?;
+    let mut files = Vec::new();
+    collect_files(dir, dir, &mut files)?;
+    files.sort();
+    let mut hasher = Sha256::new();
+    for rel in &files {
+        let path = dir.join(rel);
+        let mut meta = std::fs::symlink_metadata(&path)?;
+        hasher.update(rel.as_bytes());
+        hasher.update([0]);
+        if meta.file_type().is_symlink() {
+            let target = std::fs::canonicalize(&path).map_err(|e| anyhow::anyhow!("symlink {}: {}", rel, e))?;
+            meta = std::fs::metadata(&target)?;
+            hasher.update(b"L");
+            if meta.is_dir() {
+                if !target.starts_with(&root) {
+                    anyhow::bail!("symlink {} leads out of the plugin directory to {:?}", rel, target);
+                }
+                hasher.update(std::fs::read_link(&path)?.to_string_lossy().as_bytes());
+                hasher.update([0]);
+                continue;
+            }
+        }
+        hasher.update(if is_executable(&meta) { b"X" } else { b"F" });
+        let contents = std::fs::read(&path)?;
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+        hasher.update([0]);
+    }
+    Ok(format!("sha256:{}", to_hex(&hasher.finalize())))
+}
+
+/// Lowercase hex of `bytes`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<String>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir).map_err(|e| anyhow::anyhow!("read {:?}: {}", dir, e))? {
+        let entry = entry?;
+        let path = entry.path();
+        let ft = entry.file_type()?;
+        if ft.is_dir() {
+            collect_files(root, &path, out)?;
+        } else {
+            if dir == root && IGNORED_FILES.iter().any(|f| entry.file_name() == *f) {
+                continue;
+            }
+            let rel = path.strip_prefix(root)?;
+            let rel: Vec<_> = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            out.push(rel.join("/"));
+        }
+    }
+    Ok(())
+}
+
+/// One resolved plugin in `crusty.lock`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPlugin {
+    pub name: String,
+    pub version: String,
+    /// Module path as written in the orchestration (relative to the plugin base).
+    pub root: String,
+    pub hash: String,
+}
+
+/// `crusty.lock`: hashes of every plugin an orchestration uses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default, rename = "plugin")]
+    pub plugins: Vec<LockedPlugin>,
+}
+
+/// A plugin whose directory no longer matches the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LockDrift {
+    pub name: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl std::fmt::Display for LockDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.expected, &self.actual) {
+            (None, _) => write!(f, "plugin {} is not in the lockfile", self.name),
+            (Some(_), None) => write!(f, "plugin {} could not be hashed", self.name),
+            (Some(e), Some(a)) => write!(f, "plugin {} changed: locked {}, found {}", self.name, e, a),
+        }
+    }
+}
+
+/// What the executor does when a plugin drifts from the lockfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityMode {
+    /// Print a warning and run anyway.
+    Warn,
+    /// Refuse to run the plugin.
+    Enforce,
+}
+
+/// Lockfile plus policy, handed to the executor through `ExecutionContext`.
+#[derive(Debug, Clone)]
+pub struct LockCheck {
+    pub lock: Lockfile,
+    pub mode: IntegrityMode,
+}
+
+impl Lockfile {
+    /// Hash every enabled plugin the orchestration references.
+    pub fn generate(orch: &Orchestration, plugin_base: &Path) -> anyhow::Result<Self> {
+        Self::for_modules(&orch.stage_modules(), plugin_base)
+    }
+
+    /// Hash each `(name, module)` plugin, such as [`crate::Graph::stage_modules`].
+    pub fn for_modules(modules: &[(&str, &str)], plugin_base: &Path) -> anyhow::Result<Self> {
+        let mut plugins: Vec<LockedPlugin> = Vec::new();
+        for &(name, module) in modules {
+            if plugins.iter().any(|p| p.name == name) {
+                continue;
+            }
+            let dir = plugin_base.join(module);
+            let hash = hash_plugin_dir(&dir).map_err(|e| anyhow::anyhow!("hash plugin {}: {}", name, e))?;
+            let version = load_manifest(&dir).map(|m| m.version).unwrap_or_default();
+            plugins.push(LockedPlugin {
+                name: name.to_string(),
+                version,
+                root: module.to_string(),
+                hash,
+            });
+        }
+        Ok(Self {
+            version: LOCKFILE_VERSION,
+            plugins,
+        })
+    }
+
+    /// `crusty.lock` next to the given orchestration file.
+    pub fn path_for(orchestration_path: &Path) -> PathBuf {
+        orchestration_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(LOCKFILE_NAME)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("read {:?}: {}", path, e))?;
+        let lock: Lockfile = toml::from_str(&s)?;
+        if lock.version != LOCKFILE_VERSION {
+            anyhow::bail!("unsupported lockfile version {}", lock.version);
+        }
+        Ok(lock)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let body = toml::to_string_pretty(self)?;
+        std::fs::write(path, format!("# Generated by crusty. Do not edit.\n{}", body))?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedPlugin> {
+        self.plugins.iter().find(|p| p.name == name)
+    }
+
+    /// Compare one plugin directory against its entry (looked up by name, so the same
+    /// lock works wherever the plugin root is mounted).
+    pub fn check_plugin(&self, name: &str, dir: &Path) -> Result<(), LockDrift> {
+        let expected = self.get(name).map(|p| p.hash.clone());
+        let actual = hash_plugin_dir(dir).ok();
+        match (&expected, &actual) {
+            (Some(e), Some(a)) if e == a => Ok(()),
+            _ => Err(LockDrift {
+                name: name.to_string(),
+                expected,
+                actual,
+            }),
+        }
+    }
+
+    /// Every drifted or unlocked plugin in the orchestration.
+    pub fn verify(&self, orch: &Orchestration, plugin_base: &Path) -> Vec<LockDrift> {
+        self.verify_modules(&orch.stage_modules(), plugin_base)
+    }
+
+    /// Every drifted or unlocked plugin among `(name, module)` pairs.
+    pub fn verify_modules(&self, modules: &[(&str, &str)], plugin_base: &Path) -> Vec<LockDrift> {
+        modules
+            .iter()
+            .filter_map(|&(name, module)| self.check_plugin(name, &plugin_base.join(module)).err())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn setup() -> (tempfile::TempDir, Orchestration) {
+        let dir = tempfile::tempdir().unwrap();
+        let tts = dir.path().join("plugins/tts");
+        fs::create_dir_all(tts.join("lib")).unwrap();
+        fs::write(tts.join("plugin.toml"), "name = \"tts\"\nversion = \"1.2.0\"\n").unwrap();
+        fs::write(tts.join("run.sh"), "#!/bin/sh\ncat\n").unwrap();
+        fs::write(tts.join("lib/voice.dat"), "weights").unwrap();
+        let orch = Orchestration::from_toml(
+            r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "tts"
+module = "plugins/tts"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        )
+        .unwrap();
+        (dir, orch)
+    }
+
+    #[test]
+    fn hash_is_stable_and_content_sensitive() {
+        let (dir, _) = setup();
+        let tts = dir.path().join("plugins/tts");
+        let a = hash_plugin_dir(&tts).unwrap();
+        assert!(a.starts_with("sha256:"));
+        assert_eq!(a, hash_plugin_dir(&tts).unwrap());
+        fs::create_dir_all(tts.join("__pycache__")).unwrap();
+        fs::write(tts.join("__pycache__/x.pyc"), "cache").unwrap();
+        let b = hash_plugin_dir(&tts).unwrap();
+        assert_ne!(a, b, "interpreter caches are loaded, so they are hashed");
+        fs::write(tts.join("lib/voice.dat"), "tampered").unwrap();
+        assert_ne!(b, hash_plugin_dir(&tts).unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_are_hashed_by_what_they_resolve_to() {
+        let (dir, _) = setup();
+        let tts = dir.path().join("plugins/tts");
+        fs::write(dir.path().join("script.sh"), "#!/bin/sh\n").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("script.sh"), tts.join("helper.sh")).unwrap();
+        let a = hash_plugin_dir(&tts).unwrap();
+        fs::write(dir.path().join("script.sh"), "#!/bin/sh\nrm -rf /\n").unwrap();
+        assert_ne!(a, hash_plugin_dir(&tts).unwrap(), "the link target changed");
+
+        std::os::unix::fs::symlink("lib", tts.join("data")).unwrap();
+        assert!(hash_plugin_dir(&tts).is_ok());
+        std::os::unix::fs::symlink(dir.path(), tts.join("outside")).unwrap();
+        let err = hash_plugin_dir(&tts).unwrap_err();
+        assert!(err.to_string().contains("leads out of the plugin directory"), "{err}");
+    }
+
+    #[test]
+    fn generate_save_load_verify() {
+        let (dir, orch) = setup();
+        let lock = Lockfile::generate(&orch, dir.path()).unwrap();
+        assert_eq!(lock.plugins.len(), 1);
+        assert_eq!(lock.plugins[0].version, "1.2.0");
+        assert_eq!(lock.plugins[0].root, "plugins/tts");
+
+        let path = dir.path().join(LOCKFILE_NAME);
+        lock.save(&path).unwrap();
+        let loaded = Lockfile::load(&path).unwrap();
+        assert_eq!(loaded, lock);
+        assert!(loaded.verify(&orch, dir.path()).is_empty());
+
+        fs::write(dir.path().join("plugins/tts/run.sh"), "#!/bin/sh\nrm -rf /\n").unwrap();
+        let drift = loaded.verify(&orch, dir.path());
+        assert_eq!(drift.len(), 1);
+        assert!(drift[0].to_string().contains("changed"));
+    }
+
+    #[test]
+    fn unlocked_plugin_is_drift() {
+        let (dir, orch) = setup();
+        let lock = Lockfile { version: LOCKFILE_VERSION, plugins: vec![] };
+        let drift = lock.verify(&orch, dir.path());
+        assert_eq!(drift.len(), 1);
+        assert!(drift[0].to_string().contains("not in the lockfile"));
+    }
+}
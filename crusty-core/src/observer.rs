@@ -0,0 +1,58 @@
+//! Progress events: a [`PipelineObserver`] passed through `ExecutionContext` is told when
+//! stages start and finish, when chunks complete, what plugins report, and about warnings.
+
+use crate::protocol::ProgressFrame;
+use serde::Serialize;
+
+/// Position of a stage in the run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StageInfo {
+    /// Zero-based position among the run's enabled stages.
+    pub index: usize,
+    /// Number of enabled stages in the run.
+    pub total: usize,
+    pub kind: String,
+    pub name: String,
+    /// `[[outputs]]` branch the stage belongs to; `None` for the main chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+impl StageInfo {
+    /// Overall completed fraction of the run when this stage is `stage_fraction` done.
+    pub fn overall_fraction(&self, stage_fraction: f64) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        (self.index as f64 + stage_fraction.clamp(0.0, 1.0)) / self.total as f64
+    }
+}
+
+/// Callbacks from a running pipeline. Every method defaults to doing nothing. Calls can
+/// come from several threads at once (chunks are synthesized in parallel).
+pub trait PipelineObserver: Send + Sync {
+    fn stage_started(&self, _stage: &StageInfo, _bytes_in: usize) {}
+
+    fn stage_finished(&self, _stage: &StageInfo, _bytes_out: usize) {}
+
+    /// A chunk of a chunked TTS stage finished; `done` counts completed chunks.
+    fn chunk_finished(&self, _stage: &StageInfo, _done: usize, _total: usize) {}
+
+    /// A progress line from the plugin's stderr.
+    fn plugin_progress(&self, _stage: &StageInfo, _progress: &ProgressFrame) {}
+
+    fn warning(&self, _message: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overall_fraction_spans_stages() {
+        let stage = |index| StageInfo { index, total: 4, kind: "TTS".into(), name: "t".into(), branch: None };
+        assert_eq!(stage(0).overall_fraction(0.0), 0.0);
+        assert_eq!(stage(1).overall_fraction(0.5), 0.375);
+        assert_eq!(stage(3).overall_fraction(2.0), 1.0);
+    }
+}
@@ -2,6 +2,7 @@
 //! Supports both Foldedbits-style (meta/input/pre_processors/tts/...) and pipeline-order style.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Full orchestration config (orchestration.cr).
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -15,7 +16,20 @@ pub struct Orchestration {
     pub audio_converters: Option<Vec<PluginConfig>>,
     #[serde(default)]
     pub post_processors: Option<Vec<PluginConfig>>,
-    pub output: Output,
+    /// Destination of the main chain (TTS, then `audio_converters` and `post_processors`).
+    #[serde(default)]
+    pub output: Option<Output>,
+    /// `[[outputs]]`: further deliverables, each converted from the TTS output by its own branch.
+    #[serde(default)]
+    pub outputs: Option<Vec<OutputBranch>>,
+    #[serde(default)]
+    pub debug: Option<DebugConfig>,
+    /// `[speakers.NAME]`: turns the input into a dialogue script, each speaker voiced by
+    /// its own engine. See [`crate::dialogue`].
+    #[serde(default)]
+    pub speakers: Option<BTreeMap<String, SpeakerConfig>>,
+    #[serde(default)]
+    pub dialogue: Option<DialogueConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -27,8 +41,25 @@ pub struct Meta {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Input {
+    /// Reader for `source`: `text`, `ssml`, `markdown`, `html`, `epub` or a registered
+    /// one. See [`crate::reader`].
     pub r#type: String,
     pub source: String,
+    /// Whether extracted documents become SSML (default) or plain text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssml: Option<bool>,
+}
+
+impl Input {
+    /// Content type the pipeline starts from.
+    pub fn content_type(&self) -> &'static str {
+        match self.r#type.as_str() {
+            "text" => "text/plain",
+            "ssml" => crate::ssml::SSML_TYPE,
+            _ if self.ssml == Some(false) => "text/plain",
+            _ => crate::ssml::SSML_TYPE,
+        }
+    }
 }
 
 /// Per-plugin entry in orchestration (pre, converter, post).
@@ -40,6 +71,32 @@ pub struct PluginConfig {
     pub enabled: bool,
     #[serde(default)]
     pub options: Option<toml::Value>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+}
+
+/// `retry = { attempts, backoff_ms }`: how often a failing stage is tried before it fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RetryPolicy {
+    /// Total tries, including the first.
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    /// Wait before the first retry; doubles for each further retry.
+    #[serde(default)]
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: default_attempts(),
+            backoff_ms: 0,
+        }
+    }
+}
+
+fn default_attempts() -> u32 {
+    1
 }
 
 fn default_true() -> bool {
@@ -54,6 +111,132 @@ pub struct TtsConfig {
     pub rate: Option<f32>,
     pub pitch: Option<f32>,
     pub output_format: Option<String>,
+    /// `[tts.chunking]`: split long input and synthesize chunks in parallel.
+    #[serde(default)]
+    pub chunking: Option<ChunkingConfig>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Engines tried in order when this one still fails after its retries. Each gets the
+    /// `[tts]` options, overlaid with its own `options`.
+    #[serde(default)]
+    pub fallbacks: Option<Vec<PluginConfig>>,
+    /// Further plugin options, overlaid on voice, rate and pitch.
+    #[serde(default)]
+    pub options: Option<toml::Value>,
+}
+
+/// How `[tts.chunking]` splits text before synthesis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkMode {
+    /// Pack whole sentences up to the budget.
+    #[default]
+    Sentence,
+    /// Pack whole paragraphs (blank-line separated) up to the budget.
+    Paragraph,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChunkingConfig {
+    #[serde(default)]
+    pub mode: ChunkMode,
+    /// Character budget per TTS call.
+    #[serde(default = "default_max_chars")]
+    pub max_chars: usize,
+    /// Chunks synthesized concurrently.
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+    /// Silence inserted between chunks.
+    #[serde(default)]
+    pub silence_ms: u32,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            mode: ChunkMode::default(),
+            max_chars: default_max_chars(),
+            parallelism: default_parallelism(),
+            silence_ms: 0,
+        }
+    }
+}
+
+fn default_max_chars() -> usize {
+    1000
+}
+
+fn default_parallelism() -> usize {
+    2
+}
+
+/// One `[speakers.NAME]` entry. Unset fields come from `[tts]`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SpeakerConfig {
+    /// TTS plugin voicing this speaker, looked up like `[tts].name`.
+    #[serde(default)]
+    pub tts: Option<String>,
+    /// Plugin directory for `tts`; required when `tts` is not the `[tts]` plugin.
+    #[serde(default)]
+    pub module: Option<String>,
+    pub voice: Option<String>,
+    pub rate: Option<f32>,
+    pub pitch: Option<f32>,
+    /// Further plugin options, overlaid last.
+    #[serde(default)]
+    pub options: Option<toml::Value>,
+}
+
+impl SpeakerConfig {
+    /// `(name, module)` of the engine voicing this speaker for a TTS stage running
+    /// `tts_name` from `tts_module`.
+    pub fn engine<'a>(&'a self, tts_name: &'a str, tts_module: &'a str) -> (&'a str, &'a str) {
+        match (&self.tts, &self.module) {
+            (Some(name), Some(module)) => (name, module),
+            (Some(name), None) => (name, if name == tts_name { tts_module } else { "" }),
+            (None, module) => (tts_name, module.as_deref().unwrap_or(tts_module)),
+        }
+    }
+}
+
+/// Engines other than the stage's own that `speakers` use, once each, in speaker order.
+pub(crate) fn speaker_engines<'a>(
+    speakers: Option<&'a BTreeMap<String, SpeakerConfig>>,
+    tts_name: &'a str,
+    tts_module: &'a str,
+) -> Vec<(&'a str, &'a str)> {
+    let mut engines: Vec<(&str, &str)> = Vec::new();
+    for s in speakers.into_iter().flatten().map(|(_, s)| s) {
+        let engine = s.engine(tts_name, tts_module);
+        if engine != (tts_name, tts_module) && !engines.contains(&engine) {
+            engines.push(engine);
+        }
+    }
+    engines
+}
+
+/// `[dialogue]`: how a speaker script is voiced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DialogueConfig {
+    /// Silence between lines.
+    #[serde(default = "default_gap_ms")]
+    pub gap_ms: u32,
+    /// Lines synthesized concurrently.
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+}
+
+impl Default for DialogueConfig {
+    fn default() -> Self {
+        Self {
+            gap_ms: default_gap_ms(),
+            parallelism: default_parallelism(),
+        }
+    }
+}
+
+fn default_gap_ms() -> u32 {
+    300
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -64,6 +247,35 @@ pub struct Output {
     pub overwrite: Option<bool>,
 }
 
+/// One `[[outputs]]` entry: a file written from the TTS output through the branch's own
+/// converters and post-processors.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OutputBranch {
+    /// Unique among the outputs; labels the branch in reports and daemon URLs.
+    pub name: String,
+    #[serde(default = "default_output_type")]
+    pub r#type: String,
+    pub path: String,
+    #[serde(default)]
+    pub overwrite: Option<bool>,
+    #[serde(default)]
+    pub converters: Option<Vec<PluginConfig>>,
+    #[serde(default)]
+    pub post_processors: Option<Vec<PluginConfig>>,
+}
+
+pub(crate) fn default_output_type() -> String {
+    "file".to_string()
+}
+
+/// `[debug]`: troubleshooting aids, off by default.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DebugConfig {
+    /// Directory to write every stage's input and output to, with a `manifest.json`.
+    #[serde(default)]
+    pub keep_intermediates: Option<String>,
+}
+
 /// Alternative format: pipeline order + plugin options (from interactive CLI).
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PipelineOrchestration {
@@ -81,14 +293,75 @@ impl Orchestration {
     /// Load from TOML string.
     pub fn from_toml(s: &str) -> anyhow::Result<Self> {
         let o: Orchestration = toml::from_str(s)?;
+        o.check_outputs()?;
         Ok(o)
     }
 
+    /// An orchestration needs somewhere to write, and the main chain's converters and
+    /// post-processors need the `[output]` they feed.
+    fn check_outputs(&self) -> anyhow::Result<()> {
+        let branches = self.outputs.as_deref().unwrap_or_default();
+        if self.output.is_none() {
+            if branches.is_empty() {
+                anyhow::bail!("orchestration needs an [output] or at least one [[outputs]] entry");
+            }
+            if self.audio_converters.iter().chain(&self.post_processors).flatten().any(|p| p.enabled) {
+                anyhow::bail!(
+                    "audio_converters and post_processors feed [output]; without one, move them into an [[outputs]] branch"
+                );
+            }
+        }
+        for (i, b) in branches.iter().enumerate() {
+            if branches[..i].iter().any(|other| other.name == b.name) {
+                anyhow::bail!("duplicate [[outputs]] name {:?}", b.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// `[[outputs]]` branches, in order.
+    pub fn branches(&self) -> &[OutputBranch] {
+        self.outputs.as_deref().unwrap_or_default()
+    }
+
     /// Load from file path.
     pub fn load_path(path: &std::path::Path) -> anyhow::Result<Self> {
         let s = std::fs::read_to_string(path)?;
         Self::from_toml(&s)
     }
+
+    /// Enabled plugins in pipeline order as `(name, module)`: pre, tts, its fallbacks and
+    /// speaker engines, converters, post, then each output branch's converters and
+    /// post-processors.
+    pub fn stage_modules(&self) -> Vec<(&str, &str)> {
+        fn enabled(list: &Option<Vec<PluginConfig>>) -> impl Iterator<Item = (&str, &str)> {
+            list.iter()
+                .flatten()
+                .filter(|p| p.enabled)
+                .map(|p| (p.name.as_str(), p.module.as_str()))
+        }
+        enabled(&self.pre_processors)
+            .chain(std::iter::once((self.tts.name.as_str(), self.tts.module.as_str())))
+            .chain(enabled(&self.tts.fallbacks))
+            .chain(speaker_engines(self.speakers.as_ref(), &self.tts.name, &self.tts.module))
+            .chain(enabled(&self.audio_converters))
+            .chain(enabled(&self.post_processors))
+            .chain(self.branches().iter().flat_map(|b| enabled(&b.converters).chain(enabled(&b.post_processors))))
+            .collect()
+    }
+
+    /// Enabled TTS fallbacks in the order they are tried.
+    pub fn tts_fallbacks(&self) -> impl Iterator<Item = &PluginConfig> {
+        self.tts.fallbacks.iter().flatten().filter(|f| f.enabled)
+    }
+
+    /// Number of stages a run goes through; TTS fallbacks and speaker engines run within
+    /// the TTS stage.
+    pub fn stage_count(&self) -> usize {
+        self.stage_modules().len()
+            - self.tts_fallbacks().count()
+            - speaker_engines(self.speakers.as_ref(), &self.tts.name, &self.tts.module).len()
+    }
 }
 
 #[cfg(test)]
@@ -120,7 +393,7 @@ path = "out.bin"
         assert_eq!(o.meta.name, "test");
         assert_eq!(o.input.source, "input.txt");
         assert_eq!(o.tts.name, "tts");
-        assert_eq!(o.output.path, "out.bin");
+        assert_eq!(o.output.unwrap().path, "out.bin");
         assert!(o.pre_processors.is_none());
         assert!(o.audio_converters.is_none());
         assert!(o.post_processors.is_none());
@@ -161,6 +434,91 @@ path = "out.mp3"
         assert_eq!(o.pre_processors.as_ref().unwrap()[0].name, "pre");
         assert_eq!(o.post_processors.as_ref().unwrap().len(), 1);
         assert_eq!(o.tts.voice.as_deref(), Some("en"));
+        assert_eq!(
+            o.stage_modules(),
+            vec![("pre", "plugins/pre"), ("tts", "plugins/tts"), ("post", "plugins/post")]
+        );
+    }
+
+    #[test]
+    fn from_toml_retry_and_fallbacks() {
+        let s = MINIMAL_ORCH.replace(
+            "module = \"plugins/tts\"\n",
+            r#"module = "plugins/tts"
+retry = { attempts = 3, backoff_ms = 50 }
+[[tts.fallbacks]]
+name = "backup"
+module = "plugins/backup"
+retry = { backoff_ms = 10 }
+[[tts.fallbacks]]
+name = "off"
+module = "plugins/off"
+enabled = false
+"#,
+        );
+        let o = Orchestration::from_toml(&s).unwrap();
+        assert_eq!(o.tts.retry, Some(RetryPolicy { attempts: 3, backoff_ms: 50 }));
+        let fallbacks: Vec<_> = o.tts_fallbacks().collect();
+        assert_eq!(fallbacks.len(), 1);
+        assert_eq!(fallbacks[0].retry, Some(RetryPolicy { attempts: 1, backoff_ms: 10 }));
+        assert_eq!(o.stage_modules().len(), 2);
+        assert_eq!(o.stage_count(), 1);
+    }
+
+    #[test]
+    fn from_toml_speakers() {
+        let s = format!(
+            "{MINIMAL_ORCH}{}",
+            r#"
+[speakers.alice]
+voice = "en-f1"
+[speakers.bob]
+tts = "piper"
+module = "plugins/piper"
+voice = "en-m2"
+[speakers.carol]
+tts = "piper"
+module = "plugins/piper"
+[dialogue]
+gap_ms = 500
+"#
+        );
+        let o = Orchestration::from_toml(&s).unwrap();
+        let speakers = o.speakers.as_ref().unwrap();
+        assert_eq!(speakers["alice"].engine("tts", "plugins/tts"), ("tts", "plugins/tts"));
+        assert_eq!(speakers["bob"].engine("tts", "plugins/tts"), ("piper", "plugins/piper"));
+        assert_eq!(o.dialogue, Some(DialogueConfig { gap_ms: 500, parallelism: 2 }));
+        assert_eq!(o.stage_modules(), vec![("tts", "plugins/tts"), ("piper", "plugins/piper")]);
+        assert_eq!(o.stage_count(), 1);
+    }
+
+    #[test]
+    fn from_toml_output_branches() {
+        let head = MINIMAL_ORCH.split("[output]").next().unwrap();
+        let branches = r#"
+[[outputs]]
+name = "wav"
+path = "out.wav"
+[[outputs]]
+name = "mp3"
+path = "out.mp3"
+[[outputs.converters]]
+name = "lame"
+module = "plugins/lame"
+"#;
+        let o = Orchestration::from_toml(&format!("{head}{branches}")).unwrap();
+        assert!(o.output.is_none());
+        assert_eq!(o.branches().len(), 2);
+        assert_eq!(o.branches()[0].r#type, "file");
+        assert_eq!(o.branches()[1].converters.as_ref().unwrap()[0].name, "lame");
+        assert_eq!(o.stage_count(), 2);
+
+        let err = Orchestration::from_toml(head).unwrap_err();
+        assert!(err.to_string().contains("needs an [output]"), "{err}");
+        let dup = format!("{head}{branches}[[outputs]]\nname = \"wav\"\npath = \"again.wav\"\n");
+        assert!(Orchestration::from_toml(&dup).unwrap_err().to_string().contains("duplicate"));
+        let stray = format!("{head}[[post_processors]]\nname = \"p\"\nmodule = \"plugins/p\"\n{branches}");
+        assert!(Orchestration::from_toml(&stray).unwrap_err().to_string().contains("[[outputs]] branch"));
     }
 
     #[test]
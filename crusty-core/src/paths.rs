@@ -0,0 +1,233 @@
+//! Path confinement: canonicalized resolution of plugin modules, input sources and
+//! output paths, so orchestrations from untrusted callers cannot escape their roots.
+
+use std::path::{Component, Path, PathBuf};
+
+/// A path that resolves outside the root it is confined to, or cannot be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    /// Plugin module resolves outside every plugin root.
+    ModuleEscapesRoot { module: String },
+    /// Manifest `entrypoint` resolves outside its plugin directory.
+    EntrypointEscapesPlugin { entrypoint: String },
+    /// Input source resolves outside the data root.
+    InputOutsideDataRoot { path: PathBuf },
+    /// Output path resolves outside the output root.
+    OutputOutsideRoot { path: PathBuf },
+    /// Path (or the root itself) does not exist.
+    NotFound { path: PathBuf },
+}
+
+impl PathError {
+    /// Stable identifier for API responses.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PathError::ModuleEscapesRoot { .. } => "module_escapes_root",
+            PathError::EntrypointEscapesPlugin { .. } => "entrypoint_escapes_plugin",
+            PathError::InputOutsideDataRoot { .. } => "input_outside_data_root",
+            PathError::OutputOutsideRoot { .. } => "output_outside_root",
+            PathError::NotFound { .. } => "not_found",
+        }
+    }
+}
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::ModuleEscapesRoot { module } => write!(f, "plugin module {:?} escapes the plugin roots", module),
+            PathError::EntrypointEscapesPlugin { entrypoint } => {
+                write!(f, "entrypoint {:?} escapes the plugin directory", entrypoint)
+            }
+            PathError::InputOutsideDataRoot { path } => write!(f, "input {:?} is outside the data root", path),
+            PathError::OutputOutsideRoot { path } => write!(f, "output {:?} is outside the output root", path),
+            PathError::NotFound { path } => write!(f, "path {:?} does not exist", path),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Roots that modules, inputs and outputs must stay under.
+#[derive(Debug, Clone, Default)]
+pub struct PathPolicy {
+    /// Extra roots modules may live under, besides the plugin base dir passed to the executor.
+    pub plugin_roots: Vec<PathBuf>,
+    /// When set, input sources must resolve under it (relative sources are joined to it).
+    pub data_root: Option<PathBuf>,
+    /// When set, outputs must resolve under it (relative paths are joined to it).
+    pub output_root: Option<PathBuf>,
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, PathError> {
+    path.canonicalize().map_err(|_| PathError::NotFound { path: path.to_path_buf() })
+}
+
+/// Resolve a manifest `entrypoint` against `plugin_dir`. The canonical result must lie under
+/// the plugin directory, which is what lockfiles and signatures hash.
+pub fn resolve_entrypoint(plugin_dir: &Path, entrypoint: &str) -> Result<PathBuf, PathError> {
+    let dir = canonical(plugin_dir)?;
+    let resolved = canonical(&dir.join(entrypoint))?;
+    if !resolved.starts_with(&dir) {
+        return Err(PathError::EntrypointEscapesPlugin { entrypoint: entrypoint.to_string() });
+    }
+    Ok(resolved)
+}
+
+impl PathPolicy {
+    /// Resolve `module` against `plugin_base`; the canonical result must lie under the base
+    /// or one of `plugin_roots` (so `../` and symlinks out are rejected).
+    pub fn resolve_module(&self, plugin_base: &Path, module: &str) -> Result<PathBuf, PathError> {
+        // `Path::parent` of a bare file name is empty; that means the working directory.
+        let plugin_base = if plugin_base.as_os_str().is_empty() { Path::new(".") } else { plugin_base };
+        let resolved = canonical(&plugin_base.join(module))?;
+        let roots = std::iter::once(plugin_base).chain(self.plugin_roots.iter().map(PathBuf::as_path));
+        for root in roots {
+            if root.canonicalize().is_ok_and(|r| resolved.starts_with(r)) {
+                return Ok(resolved);
+            }
+        }
+        Err(PathError::ModuleEscapesRoot { module: module.to_string() })
+    }
+
+    /// Resolve an input source. Unconfined (returned as given) when no data root is set.
+    pub fn resolve_input(&self, source: &str) -> Result<PathBuf, PathError> {
+        let Some(root) = &self.data_root else {
+            return Ok(PathBuf::from(source));
+        };
+        let root = canonical(root)?;
+        let resolved = canonical(&root.join(source))?;
+        if !resolved.starts_with(&root) {
+            return Err(PathError::InputOutsideDataRoot { path: PathBuf::from(source) });
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve an output path, which may not exist yet: the nearest existing ancestor is
+    /// canonicalized and the remaining components must not contain `..`.
+    pub fn resolve_output(&self, path: &str) -> Result<PathBuf, PathError> {
+        let Some(root) = &self.output_root else {
+            return Ok(PathBuf::from(path));
+        };
+        let root = canonical(root)?;
+        let joined = root.join(path);
+        let mut existing = joined.as_path();
+        let mut tail = Vec::new();
+        while !existing.exists() {
+            let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                return Err(PathError::OutputOutsideRoot { path: PathBuf::from(path) });
+            };
+            tail.push(name.to_os_string());
+            existing = parent;
+        }
+        // `..` inside the not-yet-existing part cannot be canonicalized; refuse it.
+        if tail.iter().any(|n| Path::new(n).components().any(|c| c == Component::ParentDir)) {
+            return Err(PathError::OutputOutsideRoot { path: PathBuf::from(path) });
+        }
+        let mut resolved = canonical(existing)?;
+        for name in tail.iter().rev() {
+            resolved.push(name);
+        }
+        if !resolved.starts_with(&root) {
+            return Err(PathError::OutputOutsideRoot { path: PathBuf::from(path) });
+        }
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn roots() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for d in ["plugins/tts", "data/books", "out", "secret"] {
+            fs::create_dir_all(dir.path().join(d)).unwrap();
+        }
+        fs::write(dir.path().join("data/books/ch1.txt"), "x").unwrap();
+        fs::write(dir.path().join("secret/key"), "x").unwrap();
+        dir
+    }
+
+    #[test]
+    fn module_must_stay_under_plugin_base() {
+        let dir = roots();
+        let base = dir.path().join("plugins");
+        let policy = PathPolicy::default();
+        assert!(policy.resolve_module(&base, "tts").is_ok());
+        assert_eq!(
+            policy.resolve_module(&base, "../secret").unwrap_err().kind(),
+            "module_escapes_root"
+        );
+        assert_eq!(
+            policy.resolve_module(&base, dir.path().join("secret").to_str().unwrap()).unwrap_err().kind(),
+            "module_escapes_root"
+        );
+        assert_eq!(policy.resolve_module(&base, "missing").unwrap_err().kind(), "not_found");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn module_symlink_out_of_root_is_rejected_unless_root_allowed() {
+        let dir = roots();
+        let base = dir.path().join("plugins");
+        std::os::unix::fs::symlink(dir.path().join("secret"), base.join("link")).unwrap();
+        let mut policy = PathPolicy::default();
+        assert!(policy.resolve_module(&base, "link").is_err());
+        policy.plugin_roots.push(dir.path().join("secret"));
+        assert!(policy.resolve_module(&base, "link").is_ok());
+    }
+
+    #[test]
+    fn entrypoint_must_stay_under_plugin_dir() {
+        let dir = roots();
+        let plugin = dir.path().join("plugins/tts");
+        fs::create_dir_all(plugin.join("target/release")).unwrap();
+        fs::write(plugin.join("target/release/tts"), "x").unwrap();
+        assert_eq!(
+            resolve_entrypoint(&plugin, "target/release/tts").unwrap(),
+            plugin.join("target/release/tts").canonicalize().unwrap()
+        );
+        assert_eq!(
+            resolve_entrypoint(&plugin, "../../secret/key").unwrap_err(),
+            PathError::EntrypointEscapesPlugin { entrypoint: "../../secret/key".into() }
+        );
+        let absolute = dir.path().join("secret/key");
+        assert_eq!(
+            resolve_entrypoint(&plugin, absolute.to_str().unwrap()).unwrap_err().kind(),
+            "entrypoint_escapes_plugin"
+        );
+        assert_eq!(resolve_entrypoint(&plugin, "missing").unwrap_err().kind(), "not_found");
+    }
+
+    #[test]
+    fn input_confined_to_data_root() {
+        let dir = roots();
+        let policy = PathPolicy {
+            data_root: Some(dir.path().join("data")),
+            ..Default::default()
+        };
+        assert!(policy.resolve_input("books/ch1.txt").is_ok());
+        assert_eq!(
+            policy.resolve_input("../secret/key").unwrap_err(),
+            PathError::InputOutsideDataRoot { path: "../secret/key".into() }
+        );
+        assert!(policy.resolve_input(dir.path().join("secret/key").to_str().unwrap()).is_err());
+        // Unconfined without a data root.
+        assert!(PathPolicy::default().resolve_input("/etc/hostname").is_ok());
+    }
+
+    #[test]
+    fn output_confined_to_output_root() {
+        let dir = roots();
+        let policy = PathPolicy {
+            output_root: Some(dir.path().join("out")),
+            ..Default::default()
+        };
+        let ok = policy.resolve_output("new/dir/a.wav").unwrap();
+        assert!(ok.ends_with("out/new/dir/a.wav"));
+        assert_eq!(policy.resolve_output("../secret/x").unwrap_err().kind(), "output_outside_root");
+        assert_eq!(policy.resolve_output("new/../../x").unwrap_err().kind(), "output_outside_root");
+        assert!(policy.resolve_output("/tmp/x.wav").is_err());
+    }
+}
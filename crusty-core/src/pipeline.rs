@@ -1,119 +1,1354 @@
-//! Pipeline execution: pre -> TTS -> converter -> post using subprocess runner.
+//! Pipeline execution: orchestration graphs (v1 files as their linear graph) run node by
+//! node on subprocess or native plugins.
 
-use crate::orchestration::Orchestration;
-use crate::plugin::{PluginOptions, PluginType};
-use crate::plugin_runner::run_subprocess_plugin;
-use std::path::Path;
+use crate::artifacts::IntermediateWriter;
+use crate::audio;
+use crate::batch::is_pattern;
+use crate::cache::{CacheKey, SynthesisCache};
+use crate::cancel::{is_cancelled, CancellationToken, POLL_INTERVAL};
+use crate::dialogue::parse_script;
+use crate::journal::{run_fingerprint, Checkpoint};
+use crate::lock::{hash_plugin_dir, IntegrityMode, LockCheck};
+use crate::trust::{verify_plugin_signature, SignaturePolicy, TrustConfig};
+use crate::observer::{PipelineObserver, StageInfo};
+use crate::graph::{ref_node, Graph, MixConfig, MixMode, Node, NodeKind, INPUT_NODE};
+use crate::orchestration::{ChunkingConfig, Orchestration, RetryPolicy, SpeakerConfig, TtsConfig};
+use crate::paths::{resolve_entrypoint, PathError, PathPolicy};
+use crate::plugin::{Lifecycle, NativePlugin, PluginBackend, PluginManifest, PluginOptions, PluginType, Transport};
+use crate::plugin_runner::{run_env, run_framed, run_subprocess_plugin, ProgressFn, RunControl};
+use crate::protocol::{Handshake, ProgressFrame};
+use crate::reader::{read_input, Chapter};
+use crate::registry::PluginRegistry;
+use crate::segment::segment;
+use crate::ssml::{self, Ssml, SSML_TYPE};
+use crate::validate::validate_graph_fallbacks;
+use crate::worker::WorkerPool;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
-/// Resolved plugin executable path (e.g. plugins/name/run.sh or entrypoint from manifest).
-fn plugin_executable(plugin_dir: &str, _manifest: Option<&crate::plugin::PluginManifest>) -> Option<std::path::PathBuf> {
+/// Per-run settings shared by CLI and daemon. `Default` gives plain `execute_pipeline` behavior.
+#[derive(Clone, Default)]
+pub struct ExecutionContext {
+    /// Warm workers for `lifecycle = "persistent"` plugins. When unset, a pool scoped to
+    /// the run is used, so workers are still reused across stages of one pipeline.
+    pub workers: Option<Arc<WorkerPool>>,
+    /// Lockfile to check each plugin directory against before it runs.
+    pub lock: Option<Arc<LockCheck>>,
+    /// Signature policy and trusted keys each plugin directory is checked against before
+    /// it runs: unverified plugins fail the run under `Require` and warn under `Warn`.
+    pub trust: Option<Arc<TrustConfig>>,
+    /// Roots plugin modules and the input source are confined to. Modules are always
+    /// confined to the plugin base dir; the input only when `data_root` is set.
+    pub paths: PathPolicy,
+    /// Content-addressed store for stage outputs; deterministic stages are served from it.
+    pub cache: Option<Arc<SynthesisCache>>,
+    /// Receives stage, chunk and plugin progress events.
+    pub observer: Option<Arc<dyn PipelineObserver>>,
+    /// Native plugins registered here run in-process for stages with the same name;
+    /// every other stage runs its `module` as a subprocess.
+    pub registry: Option<Arc<PluginRegistry>>,
+    /// Stops the run: the running stage's process group is killed, no later stage or
+    /// chunk starts, and the pipeline returns [`crate::Cancelled`].
+    pub cancel: Option<CancellationToken>,
+    /// Write every stage's input and output here (see [`crate::artifacts`]). Takes
+    /// precedence over the orchestration's `debug.keep_intermediates`, which is resolved
+    /// through [`PathPolicy::resolve_output`].
+    pub keep_intermediates: Option<PathBuf>,
+    /// Checkpoint completed TTS chunks here (see [`crate::journal`]).
+    pub work_dir: Option<PathBuf>,
+    /// Reuse the chunks already checkpointed in `work_dir`. Fails if the orchestration or
+    /// any of its plugins changed since they were written.
+    pub resume: bool,
+    /// Input bytes to read instead of the `[input]` source file, such as stdin. The
+    /// source then only names the input in errors.
+    pub input: Option<Arc<[u8]>>,
+    /// Receives the `[output]` audio as it is produced: a chunked TTS node writes each
+    /// chunk once all before it are written, with WAV sent under a streaming header
+    /// (see [`audio::wav_header`]); any other node writes its output when it finishes.
+    /// The returned [`PipelineOutput::audio`] is unaffected.
+    pub stream: Option<Arc<Mutex<dyn Write + Send>>>,
+}
+
+/// Whether a stage's outputs went through the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheUse {
+    /// No cache configured for the run.
+    Off,
+    /// Plugin declares `deterministic = false`, or is native (it has no directory to hash).
+    Bypass,
+    On,
+}
+
+/// What one stage did during a run. Chunked TTS calls are folded into one entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StageReport {
+    /// Position among the run's stages, as in [`StageInfo::index`].
+    pub index: usize,
+    pub kind: String,
+    pub name: String,
+    pub branch: Option<String>,
+    /// Plugin invocations requested (cache hits included).
+    pub calls: usize,
+    pub cache: CacheUse,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+    /// Plugin that produced the stage's output: the stage's own, or the TTS fallback used.
+    pub engine: String,
+    /// Failed tries that were retried or handed to a fallback.
+    pub retries: usize,
+    /// TTS chunks restored from the checkpoint journal instead of synthesized.
+    pub resumed: usize,
+}
+
+/// Per-stage account of a pipeline run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PipelineReport {
+    pub stages: Vec<StageReport>,
+    /// Chapters the input reader found, at offsets into the text the pipeline started from.
+    pub chapters: Vec<Chapter>,
+}
+
+impl PipelineReport {
+    pub fn cache_hits(&self) -> usize {
+        self.stages.iter().map(|s| s.cache_hits).sum()
+    }
+
+    pub fn cache_misses(&self) -> usize {
+        self.stages.iter().map(|s| s.cache_misses).sum()
+    }
+}
+
+/// Final audio of a run with its content type and report.
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    /// Audio for `[output]`. Without one, v1 runs return the TTS output and graph runs nothing.
+    pub audio: Vec<u8>,
+    pub audio_type: String,
+    /// One per `[[outputs]]` entry, in order.
+    pub outputs: Vec<BranchOutput>,
+    pub report: PipelineReport,
+}
+
+/// Audio produced by one `[[outputs]]` branch, for the caller to write to `path`.
+#[derive(Debug, Clone)]
+pub struct BranchOutput {
+    pub name: String,
+    pub path: String,
+    pub audio: Vec<u8>,
+    pub audio_type: String,
+}
+
+/// Resolved plugin executable path: manifest `entrypoint`, else run.sh / run.py in the plugin
+/// dir. `None` when none exists; an entrypoint outside the plugin dir is an error.
+pub(crate) fn plugin_executable(plugin_dir: &str, manifest: Option<&PluginManifest>) -> Result<Option<PathBuf>, PathError> {
+    if let Some(entry) = manifest.and_then(|m| m.entrypoint.as_deref()) {
+        return match resolve_entrypoint(Path::new(plugin_dir), entry) {
+            Ok(path) => Ok(Some(path)),
+            Err(PathError::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        };
+    }
     let run_sh = Path::new(plugin_dir).join("run.sh");
     if run_sh.exists() {
-        return Some(run_sh);
+        return Ok(Some(run_sh));
     }
     let run_py = Path::new(plugin_dir).join("run.py");
     if run_py.exists() {
-        return Some(run_py);
+        return Ok(Some(run_py));
+    }
+    Ok(None)
+}
+
+/// Executable a run starts for the plugin directory `plugin_dir`, resolved through its
+/// manifest as [`plugin_executable`] does.
+pub fn resolve_plugin_executable(plugin_dir: &Path) -> Result<Option<PathBuf>, PathError> {
+    let Some(dir) = plugin_dir.to_str() else {
+        return Ok(None);
+    };
+    plugin_executable(dir, load_manifest(plugin_dir).as_ref())
+}
+
+pub(crate) fn load_manifest(plugin_dir: &Path) -> Option<PluginManifest> {
+    let s = std::fs::read_to_string(plugin_dir.join("plugin.toml")).ok()?;
+    toml::from_str(&s).ok()
+}
+
+/// Manifest option defaults overlaid with the stage's own options.
+pub(crate) fn effective_options(manifest: Option<&PluginManifest>, overrides: &PluginOptions) -> PluginOptions {
+    let mut opts = manifest.map(PluginManifest::default_options).unwrap_or_default();
+    opts.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+    opts
+}
+
+/// Pick the input type for a stage: the current type if the plugin accepts it, else its first declared input.
+pub(crate) fn negotiate_input(current: &str, declared: Option<&Vec<String>>) -> String {
+    match declared {
+        Some(d) if !d.is_empty() && !d.iter().any(|t| t == current) => d[0].clone(),
+        _ => current.to_string(),
+    }
+}
+
+/// What a stage runs.
+enum StageTarget<'a> {
+    /// Plugin directory, run as a subprocess.
+    Dir { plugin: String, dir: PathBuf },
+    Native(&'a NativePlugin),
+}
+
+/// Run a native plugin on `input`, returning its output and content type.
+fn run_native_plugin(
+    native: &NativePlugin,
+    input: &[u8],
+    input_type: &str,
+    opts: &PluginOptions,
+) -> anyhow::Result<StageOutput> {
+    let text = || {
+        std::str::from_utf8(input).map_err(|_| anyhow::anyhow!("native plugin {} expects UTF-8 text", native.name()))
+    };
+    let mut out = Vec::new();
+    let out_type = match native {
+        NativePlugin::Pre(p) => {
+            p.process(text()?, opts, &mut out)?;
+            input_type.to_string()
+        }
+        NativePlugin::Tts(t) => {
+            t.synthesize(text()?, opts, &mut out)?;
+            t.output_type().to_string()
+        }
+        NativePlugin::Post(p) | NativePlugin::Converter(p) => {
+            p.process(&mut &input[..], opts, &mut out)?;
+            p.output_type(input_type)
+        }
+    };
+    Ok((out, out_type))
+}
+
+/// Runs single stages, dispatching on the plugin's backend and, for subprocesses, its
+/// declared transport and lifecycle.
+struct StageRunner<'a> {
+    workers: &'a WorkerPool,
+    lock: Option<&'a LockCheck>,
+    trust: Option<&'a TrustConfig>,
+    /// Plugin dirs already checked against the lockfile and signature policy in this run.
+    verified: Mutex<HashSet<PathBuf>>,
+    cache: Option<&'a SynthesisCache>,
+    /// Directory hashes computed for cache keys in this run.
+    dir_hashes: Mutex<HashMap<PathBuf, String>>,
+    report: Mutex<PipelineReport>,
+    observer: Option<&'a dyn PipelineObserver>,
+    cancel: Option<&'a CancellationToken>,
+    intermediates: Option<IntermediateWriter>,
+    checkpoint: Option<Checkpoint>,
+    stream: Option<OutputStream<'a>>,
+}
+
+/// Writes the `[output]` node's audio to [`ExecutionContext::stream`].
+struct OutputStream<'a> {
+    /// Position of the node, as in [`StageInfo::index`].
+    stage: usize,
+    out: &'a Mutex<dyn Write + Send>,
+    state: Mutex<StreamState>,
+}
+
+#[derive(Default)]
+struct StreamState {
+    /// Whether any audio has been written.
+    started: bool,
+    /// Content type of the first part, and its format when it is decoded to PCM.
+    first: Option<(String, Option<audio::AudioFormat>)>,
+}
+
+impl OutputStream<'_> {
+    /// Write the next part of the node's audio, after `gap` of silence. WAV and raw PCM
+    /// parts are decoded so the stream keeps the first part's header; other types are
+    /// written as they are.
+    fn write_part(&self, (bytes, content_type): &StageOutput, gap: Duration) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let data = match &state.first {
+            None => {
+                let base = audio::base_type(content_type);
+                let is_wav = matches!(base, "audio/wav" | "audio/x-wav" | "audio/wave");
+                if is_wav || base == "audio/raw" {
+                    let part = audio::decode(bytes, content_type)?;
+                    state.first = Some((content_type.clone(), Some(part.format)));
+                    match is_wav {
+                        true => [audio::wav_header(part.format, None), part.data].concat(),
+                        false => part.data,
+                    }
+                } else {
+                    state.first = Some((content_type.clone(), None));
+                    bytes.clone()
+                }
+            }
+            Some((_, Some(format))) => {
+                let mut pcm = audio::Audio::silence(*format, gap);
+                pcm.append(&audio::decode(bytes, content_type)?)?;
+                pcm.data
+            }
+            Some((first, None)) if first == content_type => bytes.clone(),
+            Some((first, None)) => anyhow::bail!("cannot stream {} after {}", content_type, first),
+        };
+        state.started = true;
+        let mut out = self.out.lock().unwrap();
+        out.write_all(&data)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn started(&self) -> bool {
+        self.state.lock().unwrap().started
+    }
+
+    /// Write the node's whole output, unless its parts were already streamed.
+    fn finish(&self, (bytes, _): &StageOutput) -> anyhow::Result<()> {
+        if self.started() {
+            return Ok(());
+        }
+        self.state.lock().unwrap().started = true;
+        let mut out = self.out.lock().unwrap();
+        out.write_all(bytes)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+impl StageRunner<'_> {
+    /// Hash-check a plugin and its signature once per run; drift and unverified
+    /// signatures are errors or warnings depending on the lock mode and signature policy.
+    fn check_integrity(&self, name: &str, plugin_dir: &Path) -> anyhow::Result<()> {
+        if self.lock.is_none() && self.trust.is_none() {
+            return Ok(());
+        }
+        if self.verified.lock().unwrap().contains(plugin_dir) {
+            return Ok(());
+        }
+        if let Some(check) = self.lock {
+            if let Err(drift) = check.lock.check_plugin(name, plugin_dir) {
+                match check.mode {
+                    IntegrityMode::Enforce => anyhow::bail!("integrity check failed: {}", drift),
+                    IntegrityMode::Warn => self.warn(&drift.to_string()),
+                }
+            }
+        }
+        if let Some(trust) = self.trust {
+            if let Some(reason) = verify_plugin_signature(plugin_dir, &trust.keys).problem() {
+                match trust.policy {
+                    SignaturePolicy::Require => anyhow::bail!("signature check failed: plugin {}: {}", name, reason),
+                    _ => self.warn(&format!("plugin {}: {}", name, reason)),
+                }
+            }
+        }
+        self.verified.lock().unwrap().insert(plugin_dir.to_path_buf());
+        Ok(())
+    }
+
+    fn dir_hash(&self, plugin_dir: &Path) -> anyhow::Result<String> {
+        if let Some(h) = self.dir_hashes.lock().unwrap().get(plugin_dir) {
+            return Ok(h.clone());
+        }
+        let hash = hash_plugin_dir(plugin_dir)?;
+        self.dir_hashes.lock().unwrap().insert(plugin_dir.to_path_buf(), hash.clone());
+        Ok(hash)
+    }
+
+    /// Warnings go to the observer when there is one, else to stderr.
+    fn warn(&self, message: &str) {
+        match self.observer {
+            Some(o) => o.warning(message),
+            None => eprintln!("warning: {}", message),
+        }
+    }
+
+    fn check_cancelled(&self) -> anyhow::Result<()> {
+        if let Some(cancel) = self.cancel {
+            cancel.check()?;
+        }
+        Ok(())
+    }
+
+    /// Report a stage start; fails instead if the run has been cancelled.
+    fn started(&self, stage: &StageInfo, input: &[u8], input_type: &str) -> anyhow::Result<()> {
+        self.check_cancelled()?;
+        if let Some(o) = self.observer {
+            o.stage_started(stage, input.len());
+        }
+        if let Some(w) = &self.intermediates {
+            if let Err(e) = w.stage_input(stage, input, input_type) {
+                self.warn(&format!("saving input of {} {} failed: {}", stage.kind, stage.name, e));
+            }
+        }
+        Ok(())
+    }
+
+    fn finished(&self, stage: &StageInfo, output: &[u8], output_type: &str) {
+        if let Some(o) = self.observer {
+            o.stage_finished(stage, output.len());
+        }
+        if let Some(w) = &self.intermediates {
+            if let Err(e) = w.stage_output(stage, output, output_type) {
+                self.warn(&format!("saving output of {} {} failed: {}", stage.kind, stage.name, e));
+            }
+        }
+    }
+
+    /// Apply `f` to the stage's report entry, creating it on first use.
+    fn with_entry(&self, stage: &StageInfo, f: impl FnOnce(&mut StageReport)) {
+        let mut report = self.report.lock().unwrap();
+        // Independent graph nodes run concurrently; keep entries in stage order.
+        let at = report.stages.partition_point(|s| s.index < stage.index);
+        if report.stages.get(at).is_none_or(|s| s.index != stage.index) {
+            report.stages.insert(
+                at,
+                StageReport {
+                    index: stage.index,
+                    kind: stage.kind.clone(),
+                    name: stage.name.clone(),
+                    branch: stage.branch.clone(),
+                    calls: 0,
+                    cache: CacheUse::Off,
+                    cache_hits: 0,
+                    cache_misses: 0,
+                    engine: stage.name.clone(),
+                    retries: 0,
+                    resumed: 0,
+                },
+            );
+        }
+        f(&mut report.stages[at]);
+    }
+
+    fn record(&self, stage: &StageInfo, cache: CacheUse, hit: Option<bool>) {
+        self.with_entry(stage, |entry| {
+            entry.calls += 1;
+            entry.cache = cache;
+            match hit {
+                Some(true) => entry.cache_hits += 1,
+                Some(false) => entry.cache_misses += 1,
+                None => {}
+            }
+        });
+    }
+
+    /// Run one plugin under `retry`: failures are retried after a doubling backoff,
+    /// except cancellation.
+    #[allow(clippy::too_many_arguments)]
+    fn run_retrying(
+        &self,
+        stage: &StageInfo,
+        target: &StageTarget<'_>,
+        input: &[u8],
+        input_type: &str,
+        default_output: &str,
+        opts: &PluginOptions,
+        retry: &RetryPolicy,
+    ) -> anyhow::Result<StageOutput> {
+        let attempts = retry.attempts.max(1);
+        let mut backoff = Duration::from_millis(retry.backoff_ms);
+        let mut attempt = 1;
+        loop {
+            match self.run(stage, target, input, input_type, default_output, opts) {
+                Ok(out) => return Ok(out),
+                Err(e) if attempt >= attempts || is_cancelled(&e) => return Err(e),
+                Err(e) => {
+                    self.with_entry(stage, |entry| entry.retries += 1);
+                    self.warn(&format!(
+                        "{} {} failed (attempt {}/{}), retrying: {:#}",
+                        stage.kind, stage.name, attempt, attempts, e
+                    ));
+                    self.sleep(backoff)?;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Synthesize TTS chunk `index` with `engine` from `text` of `text_type`, reusing the
+    /// checkpointed copy if there is one and checkpointing new output.
+    fn run_chunk(
+        &self,
+        stage: &StageInfo,
+        engine: &TtsEngine<'_>,
+        index: usize,
+        text: &str,
+        text_type: &str,
+    ) -> anyhow::Result<StageOutput> {
+        if let Some(out) = self.checkpoint.as_ref().and_then(|c| c.restore(stage.index, index, engine.name, text)) {
+            self.with_entry(stage, |entry| entry.resumed += 1);
+            return Ok(out);
+        }
+        let out = self.run_retrying(
+            stage,
+            &engine.target,
+            text.as_bytes(),
+            text_type,
+            "audio/raw",
+            &engine.opts,
+            &engine.retry,
+        )?;
+        if let Some(checkpoint) = &self.checkpoint {
+            if let Err(e) = checkpoint.save(stage.index, index, engine.name, text, &out) {
+                self.warn(&format!("checkpointing chunk {} of {} {} failed: {}", index + 1, stage.kind, stage.name, e));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Sleep for `duration`, waking early with an error if the run is cancelled.
+    fn sleep(&self, duration: Duration) -> anyhow::Result<()> {
+        let deadline = std::time::Instant::now() + duration;
+        loop {
+            self.check_cancelled()?;
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            std::thread::sleep(left.min(POLL_INTERVAL));
+        }
+    }
+
+    /// Run one plugin. Returns its output and the negotiated output type.
+    fn run(
+        &self,
+        stage: &StageInfo,
+        target: &StageTarget<'_>,
+        input: &[u8],
+        input_type: &str,
+        default_output: &str,
+        opts: &PluginOptions,
+    ) -> anyhow::Result<StageOutput> {
+        match target {
+            StageTarget::Dir { plugin, dir } => {
+                self.run_subprocess(stage, plugin, dir, input, input_type, default_output, opts)
+            }
+            StageTarget::Native(native) => self.run_native(stage, native, input, input_type, opts),
+        }
+    }
+
+    fn run_native(
+        &self,
+        stage: &StageInfo,
+        native: &NativePlugin,
+        input: &[u8],
+        input_type: &str,
+        opts: &PluginOptions,
+    ) -> anyhow::Result<StageOutput> {
+        self.check_cancelled()?;
+        let fits = matches!(
+            (stage.kind.as_str(), native),
+            ("pre-processor", NativePlugin::Pre(_))
+                | ("TTS", NativePlugin::Tts(_))
+                | ("converter" | "post-processor", NativePlugin::Post(_) | NativePlugin::Converter(_))
+        );
+        if !fits {
+            anyhow::bail!(
+                "native plugin {} is a {} plugin and cannot run as {}",
+                native.name(),
+                native.plugin_type().as_str(),
+                stage.kind
+            );
+        }
+        let out = run_native_plugin(native, input, input_type, opts)
+            .map_err(|e| e.context(format!("{} {}", stage.kind, native.name())))?;
+        let cache_use = if self.cache.is_some() { CacheUse::Bypass } else { CacheUse::Off };
+        self.record(stage, cache_use, None);
+        Ok(out)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn run_subprocess(
+        &self,
+        stage: &StageInfo,
+        name: &str,
+        plugin_dir: &Path,
+        input: &[u8],
+        input_type: &str,
+        default_output: &str,
+        opts: &PluginOptions,
+    ) -> anyhow::Result<StageOutput> {
+        let label = format!("{} {}", stage.kind, name);
+        self.check_integrity(name, plugin_dir)?;
+        let manifest = load_manifest(plugin_dir);
+        let opts = &effective_options(manifest.as_ref(), opts);
+        let exec = plugin_executable(plugin_dir.to_str().unwrap(), manifest.as_ref())
+            .map_err(|e| anyhow::Error::new(e).context(label.clone()))?
+            .ok_or_else(|| anyhow::anyhow!("no executable for {}", label))?;
+        let caps = manifest.as_ref().and_then(|m| m.capabilities.as_ref());
+        let in_type = negotiate_input(input_type, caps.and_then(|c| c.input.as_ref()));
+        let out_type = caps
+            .and_then(|c| c.output.as_ref())
+            .and_then(|o| o.first().cloned())
+            .unwrap_or_else(|| default_output.to_string());
+
+        let deterministic = manifest.as_ref().is_none_or(|m| m.is_deterministic());
+        let cache = self.cache.filter(|_| deterministic);
+        let cache_use = match (self.cache, cache) {
+            (None, _) => CacheUse::Off,
+            (Some(_), None) => CacheUse::Bypass,
+            (Some(_), Some(_)) => CacheUse::On,
+        };
+        let key = match cache {
+            Some(_) => Some(
+                CacheKey {
+                    plugin: name,
+                    version: manifest.as_ref().map(|m| m.version.as_str()).unwrap_or_default(),
+                    plugin_hash: &self.dir_hash(plugin_dir)?,
+                    input_type: &in_type,
+                    output_type: &out_type,
+                    options: opts,
+                    input,
+                }
+                .digest(),
+            ),
+            None => None,
+        };
+        if let (Some(cache), Some(key)) = (cache, &key) {
+            if let Some(hit) = cache.get(key) {
+                self.record(stage, cache_use, Some(true));
+                return Ok(hit);
+            }
+        }
+
+        let observer = self.observer;
+        let forward = |p: &ProgressFrame| {
+            if let Some(o) = observer {
+                o.plugin_progress(stage, p);
+            }
+        };
+        // Persistent workers share one stderr across jobs, so only one-shot plugins report progress.
+        let progress: Option<ProgressFn<'_>> = observer.map(|_| &forward as ProgressFn<'_>);
+        let timeout = manifest.as_ref().and_then(|m| m.timeout_ms).map(Duration::from_millis);
+        let control = RunControl { progress, cancel: self.cancel, timeout };
+        let transport = manifest.as_ref().map(|m| m.transport()).unwrap_or(Transport::Env);
+        let lifecycle = manifest.as_ref().map(|m| m.lifecycle()).unwrap_or(Lifecycle::Oneshot);
+        let out = match (lifecycle, transport) {
+            (Lifecycle::Persistent, _) => {
+                let handshake = Handshake::new(&in_type, &out_type, options_to_json(opts));
+                let settings = manifest.as_ref().and_then(|m| m.worker.as_ref());
+                self.workers.run_cancellable(&exec, settings, &handshake, input, self.cancel, timeout)?
+            }
+            (Lifecycle::Oneshot, Transport::Framed) => {
+                let handshake = Handshake::new(&in_type, &out_type, options_to_json(opts));
+                run_framed(exec.to_str().unwrap(), &handshake, input, opts, &control)?
+            }
+            (Lifecycle::Oneshot, Transport::Env) => {
+                run_env(exec.to_str().unwrap(), input, opts, &control)?
+            }
+        };
+        if let (Some(cache), Some(key)) = (cache, &key) {
+            if let Err(e) = cache.put(key, &out, &out_type) {
+                self.warn(&format!("cache write for {} failed: {}", label, e));
+            }
+        }
+        self.record(stage, cache_use, key.as_ref().map(|_| false));
+        Ok((out, out_type))
     }
-    None
+}
+
+fn options_to_json(opts: &PluginOptions) -> serde_json::Value {
+    serde_json::Value::Object(
+        opts.iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+            .collect(),
+    )
 }
 
 /// Execute full orchestration: load input, run pre -> tts -> converters -> post, write output.
 pub fn execute_pipeline(orchestration: &Orchestration, plugin_base_dir: &Path) -> anyhow::Result<Vec<u8>> {
-    let input_path = Path::new(&orchestration.input.source);
-    let mut text = std::fs::read_to_string(input_path)
-        .map_err(|e| anyhow::anyhow!("read input {:?}: {}", input_path, e))?;
-
-    // Pre-processors
-    if let Some(ref pre) = orchestration.pre_processors {
-        for p in pre.iter().filter(|p| p.enabled) {
-            let plugin_dir = plugin_base_dir.join(&p.module);
-            let exec = plugin_executable(plugin_dir.to_str().unwrap(), None)
-                .ok_or_else(|| anyhow::anyhow!("no executable for pre-processor {}", p.name))?;
-            let opts = options_from_toml(p.options.as_ref());
-            let out = run_subprocess_plugin(exec.to_str().unwrap(), text.as_bytes(), &opts)?;
-            text = String::from_utf8(out).map_err(|_| anyhow::anyhow!("pre-processor must return UTF-8 text"))?;
-        }
-    }
-
-    // TTS
-    let plugin_dir = plugin_base_dir.join(&orchestration.tts.module);
-    let exec = plugin_executable(plugin_dir.to_str().unwrap(), None)
-        .ok_or_else(|| anyhow::anyhow!("no executable for TTS {}", orchestration.tts.name))?;
-    let mut opts = PluginOptions::new();
-    if let Some(v) = &orchestration.tts.voice {
-        opts.insert("voice".into(), v.clone());
+    execute_pipeline_with(orchestration, plugin_base_dir, &ExecutionContext::default())
+}
+
+/// Like [`execute_pipeline`], with explicit per-run settings.
+pub fn execute_pipeline_with(
+    orchestration: &Orchestration,
+    plugin_base_dir: &Path,
+    ctx: &ExecutionContext,
+) -> anyhow::Result<Vec<u8>> {
+    execute_pipeline_report(orchestration, plugin_base_dir, ctx).map(|o| o.audio)
+}
+
+/// Like [`execute_pipeline_with`], also returning the output type and per-stage report.
+/// The orchestration runs as its [`Graph::from_orchestration`] graph.
+pub fn execute_pipeline_report(
+    orchestration: &Orchestration,
+    plugin_base_dir: &Path,
+    ctx: &ExecutionContext,
+) -> anyhow::Result<PipelineOutput> {
+    let graph = Graph::from_orchestration(orchestration);
+    // Without an `[output]`, the TTS output is returned.
+    let main = match &graph.output {
+        Some(o) => Some(o.from.as_str()),
+        None => graph.nodes.iter().find(|n| n.kind == NodeKind::Tts).map(|n| n.name.as_str()),
+    };
+    let (done, report) = run_graph(&graph, main, plugin_base_dir, ctx)?;
+    Ok(graph_output(&graph, main, done, report))
+}
+
+/// Execute an orchestration graph. Each node starts as soon as all its inputs are ready,
+/// so independent branches run in parallel. `audio` is the `[output]` (empty without
+/// one) and `outputs` holds each `[[outputs]]` entry.
+pub fn execute_graph_report(graph: &Graph, plugin_base_dir: &Path, ctx: &ExecutionContext) -> anyhow::Result<PipelineOutput> {
+    let main = graph.output.as_ref().map(|o| o.from.as_str());
+    let (done, report) = run_graph(graph, main, plugin_base_dir, ctx)?;
+    Ok(graph_output(graph, main, done, report))
+}
+
+/// Outputs of finished nodes by node name; `input` holds the input text.
+type NodeOutputs = HashMap<String, Arc<StageOutput>>;
+
+fn graph_output(graph: &Graph, main: Option<&str>, mut done: NodeOutputs, report: PipelineReport) -> PipelineOutput {
+    let refs: Vec<&str> = main.into_iter().chain(graph.outputs.iter().map(|o| o.from.as_str())).map(ref_node).collect();
+    // Move each output out of the map on its last use; only shared ones are copied.
+    let mut take = |i: usize| -> StageOutput {
+        let node = refs[i];
+        let out = if refs[i + 1..].contains(&node) { done.get(node).cloned() } else { done.remove(node) };
+        out.map(|o| Arc::try_unwrap(o).unwrap_or_else(|o| (*o).clone())).unwrap_or_default()
+    };
+    let (audio, audio_type) = if main.is_some() { take(0) } else { StageOutput::default() };
+    let first = usize::from(main.is_some());
+    let outputs = graph
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(i, sink)| {
+            let (audio, audio_type) = take(first + i);
+            BranchOutput {
+                name: sink.name.clone(),
+                path: sink.path.clone(),
+                audio,
+                audio_type,
+            }
+        })
+        .collect();
+    PipelineOutput {
+        audio,
+        audio_type,
+        outputs,
+        report,
     }
-    if let Some(r) = orchestration.tts.rate {
-        opts.insert("rate".into(), r.to_string());
+}
+
+/// What the node threads of a run share: finished outputs, and whether any node failed.
+struct Flow {
+    done: NodeOutputs,
+    failed: bool,
+}
+
+/// Run every node of `graph`; `main` is the output sent to `ctx.stream`.
+fn run_graph(
+    graph: &Graph,
+    main: Option<&str>,
+    plugin_base_dir: &Path,
+    ctx: &ExecutionContext,
+) -> anyhow::Result<(NodeOutputs, PipelineReport)> {
+    let order = graph.topological_order()?;
+    let local_pool;
+    let workers = match ctx.workers.as_deref() {
+        Some(pool) => pool,
+        None => {
+            local_pool = WorkerPool::default();
+            &local_pool
+        }
+    };
+    let debug_dir = graph.debug.as_ref().and_then(|d| d.keep_intermediates.as_deref());
+    let intermediates = match (&ctx.keep_intermediates, debug_dir) {
+        (Some(dir), _) => Some(IntermediateWriter::create(dir)?),
+        (None, Some(dir)) => Some(IntermediateWriter::create(&ctx.paths.resolve_output(dir)?)?),
+        (None, None) => None,
+    };
+    let checkpoint = match &ctx.work_dir {
+        Some(dir) => Some(Checkpoint::open(
+            dir,
+            &checkpoint_fingerprint(graph, plugin_base_dir, ctx)?,
+            ctx.resume,
+        )?),
+        None if ctx.resume => anyhow::bail!("resuming needs a work directory"),
+        None => None,
+    };
+    // TTS fallbacks and speaker engines are type-checked against their node's neighbours
+    // before anything runs.
+    if graph.nodes.iter().any(|n| n.fallbacks().next().is_some() || !n.speaker_engines().is_empty()) {
+        validate_graph_fallbacks(graph, plugin_base_dir)?;
     }
-    if let Some(p) = orchestration.tts.pitch {
-        opts.insert("pitch".into(), p.to_string());
+    let runner = StageRunner {
+        workers,
+        lock: ctx.lock.as_deref(),
+        trust: ctx.trust.as_deref().filter(|t| t.policy != SignaturePolicy::AllowUnsigned),
+        verified: Mutex::new(HashSet::new()),
+        cache: ctx.cache.as_deref(),
+        dir_hashes: Mutex::new(HashMap::new()),
+        report: Mutex::new(PipelineReport::default()),
+        observer: ctx.observer.as_deref(),
+        cancel: ctx.cancel.as_ref(),
+        intermediates,
+        checkpoint,
+        stream: match (&ctx.stream, main.map(ref_node)) {
+            (Some(out), Some(main)) => order.iter().position(|n| n.name == main).map(|stage| OutputStream {
+                stage,
+                out: &**out,
+                state: Mutex::default(),
+            }),
+            _ => None,
+        },
+    };
+
+    let input = match &ctx.input {
+        Some(bytes) => read_input(&graph.input, bytes, ctx.registry.as_deref())
+            .map_err(|e| e.context(format!("input {:?}", graph.input.source)))?,
+        None => {
+            if is_pattern(&graph.input.source) {
+                anyhow::bail!("input {:?} is a pattern; render it as a batch", graph.input.source);
+            }
+            let input_path = ctx.paths.resolve_input(&graph.input.source)?;
+            if input_path.is_dir() {
+                anyhow::bail!("input {:?} is a directory; render it as a batch", graph.input.source);
+            }
+            let bytes = std::fs::read(&input_path).map_err(|e| anyhow::anyhow!("read input {:?}: {}", input_path, e))?;
+            read_input(&graph.input, &bytes, ctx.registry.as_deref())
+                .map_err(|e| e.context(format!("input {:?}", input_path)))?
+        }
+    };
+    runner.report.lock().unwrap().chapters = input.chapters;
+    let flow = Mutex::new(Flow {
+        done: HashMap::from([(INPUT_NODE.to_string(), Arc::new((input.text.into_bytes(), input.content_type.to_string())))]),
+        failed: false,
+    });
+    let ready = Condvar::new();
+    let first_error: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+    std::thread::scope(|s| {
+        for (index, &node) in order.iter().enumerate() {
+            let stage = StageInfo {
+                index,
+                total: order.len(),
+                kind: node.kind.label().to_string(),
+                name: node.plugin_name().to_string(),
+                branch: node.branch.clone(),
+            };
+            let (runner, flow, ready, first_error) = (&runner, &flow, &ready, &first_error);
+            s.spawn(move || {
+                let inputs = {
+                    let mut flow = flow.lock().unwrap();
+                    loop {
+                        // Nothing new starts once a node has failed.
+                        if flow.failed {
+                            return;
+                        }
+                        let inputs: Option<Vec<_>> = node.input_nodes().map(|n| flow.done.get(n).cloned()).collect();
+                        if let Some(inputs) = inputs {
+                            break inputs;
+                        }
+                        flow = ready.wait(flow).unwrap();
+                    }
+                };
+                let result = run_node(runner, ctx, plugin_base_dir, &stage, node, &inputs).and_then(|out| {
+                    match &runner.stream {
+                        Some(stream) if stream.stage == index => stream.finish(&out)?,
+                        _ => {}
+                    }
+                    Ok(out)
+                });
+                let mut flow = flow.lock().unwrap();
+                match result {
+                    Ok(out) => {
+                        flow.done.insert(node.name.clone(), Arc::new(out));
+                    }
+                    Err(e) => {
+                        flow.failed = true;
+                        first_error.lock().unwrap().get_or_insert(e);
+                    }
+                }
+                ready.notify_all();
+            });
+        }
+    });
+    if let Some(e) = first_error.into_inner().unwrap() {
+        return Err(e);
+    }
+    Ok((flow.into_inner().unwrap().done, runner.report.into_inner().unwrap()))
+}
+
+/// A native plugin registered under `name`, else the plugin directory `module`.
+fn stage_target<'a>(ctx: &'a ExecutionContext, plugin_base_dir: &Path, name: &str, module: &str) -> anyhow::Result<StageTarget<'a>> {
+    match ctx.registry.as_deref().and_then(|r| r.native(name)) {
+        Some(native) => Ok(StageTarget::Native(native)),
+        None => Ok(StageTarget::Dir {
+            plugin: name.to_string(),
+            dir: ctx.paths.resolve_module(plugin_base_dir, module)?,
+        }),
     }
-    let mut audio = run_subprocess_plugin(exec.to_str().unwrap(), text.as_bytes(), &opts)?;
+}
 
-    // Audio converters
-    if let Some(ref conv) = orchestration.audio_converters {
-        for c in conv.iter().filter(|c| c.enabled) {
-            let plugin_dir = plugin_base_dir.join(&c.module);
-            let exec = plugin_executable(plugin_dir.to_str().unwrap(), None)
-                .ok_or_else(|| anyhow::anyhow!("no executable for converter {}", c.name))?;
-            let opts = options_from_toml(c.options.as_ref());
-            audio = run_subprocess_plugin(exec.to_str().unwrap(), &audio, &opts)?;
+/// Run one node on the outputs of its inputs, in `inputs` order.
+fn run_node(
+    runner: &StageRunner<'_>,
+    ctx: &ExecutionContext,
+    plugin_base_dir: &Path,
+    stage: &StageInfo,
+    node: &Node,
+    inputs: &[Arc<StageOutput>],
+) -> anyhow::Result<StageOutput> {
+    let name = node.plugin_name();
+    let opts = options_from_toml(node.options.as_ref());
+    let retry = node.retry.unwrap_or_default();
+    match node.kind {
+        NodeKind::PreProcessor => {
+            // Text keeps its type (plain or SSML) unless the plugin declares another.
+            let (text, text_type) = &*inputs[0];
+            runner.started(stage, text, text_type)?;
+            let (out, out_type) = runner.run_retrying(
+                stage,
+                &stage_target(ctx, plugin_base_dir, name, node.module())?,
+                text,
+                text_type,
+                text_type,
+                &opts,
+                &retry,
+            )?;
+            runner.finished(stage, &out, &out_type);
+            if std::str::from_utf8(&out).is_err() {
+                anyhow::bail!("pre-processor must return UTF-8 text");
+            }
+            Ok((out, out_type))
+        }
+        NodeKind::Tts => {
+            let text = std::str::from_utf8(&inputs[0].0).map_err(|_| anyhow::anyhow!("TTS {} needs UTF-8 text input", name))?;
+            let ssml = match audio::base_type(&inputs[0].1) {
+                SSML_TYPE => Some(Ssml::parse(text).map_err(|e| e.context(format!("TTS {} input", name)))?),
+                _ => None,
+            };
+            // The node's engine, then each fallback in turn, all resolved before the stage starts.
+            let engines = tts_engines(ctx, plugin_base_dir, node, name, node.module(), opts)?;
+            runner.started(stage, text.as_bytes(), &inputs[0].1)?;
+            // Audio already streamed cannot be taken back for a fallback's.
+            let streamed = |engine: &TtsEngine<'_>| match &runner.stream {
+                Some(s) if s.stage == stage.index && s.started() => {
+                    anyhow::bail!("cannot fall back to {}: the output has started streaming", engine.name)
+                }
+                _ => Ok(()),
+            };
+            let (out, engine) = match &ssml {
+                Some(_) if node.is_dialogue() => anyhow::bail!("TTS {}: dialogue scripts are plain text, not SSML", name),
+                None if node.is_dialogue() => {
+                    (synthesize_dialogue(runner, ctx, plugin_base_dir, stage, node, &engines, text)?, name)
+                }
+                Some(doc) => {
+                    let parallelism = node.chunking.as_ref().map_or(1, |c| c.parallelism);
+                    with_fallbacks(runner, stage, &engines, |engine| {
+                        streamed(engine)?;
+                        synthesize_ssml(runner, stage, engine, doc, parallelism)
+                    })?
+                }
+                None => with_fallbacks(runner, stage, &engines, |engine| {
+                    streamed(engine)?;
+                    match &node.chunking {
+                        Some(chunking) => synthesize_chunked(runner, stage, engine, text, chunking),
+                        None => runner.run_chunk(stage, engine, 0, text, "text/plain"),
+                    }
+                })?,
+            };
+            runner.with_entry(stage, |entry| entry.engine = engine.to_string());
+            runner.finished(stage, &out.0, &out.1);
+            Ok(out)
+        }
+        NodeKind::Converter | NodeKind::PostProcessor => {
+            let (audio, audio_type) = &*inputs[0];
+            runner.started(stage, audio, audio_type)?;
+            let out = runner.run_retrying(
+                stage,
+                &stage_target(ctx, plugin_base_dir, name, node.module())?,
+                audio,
+                audio_type,
+                audio_type,
+                &opts,
+                &retry,
+            )?;
+            runner.finished(stage, &out.0, &out.1);
+            Ok(out)
+        }
+        NodeKind::Mix => {
+            let (first, first_type) = &*inputs[0];
+            runner.started(stage, first, first_type)?;
+            let inputs: Vec<&StageOutput> = inputs.iter().map(|i| &**i).collect();
+            let out = mix_audio(&inputs, node.mix.unwrap_or_default())
+                .map_err(|e| anyhow::anyhow!("mix {}: {}", node.name, e))?;
+            runner.with_entry(stage, |_| {});
+            runner.finished(stage, &out.0, &out.1);
+            Ok(out)
+        }
+        NodeKind::Source => {
+            let source = node.path.as_deref().unwrap_or_default();
+            // A source's input is the path it reads.
+            runner.started(stage, source.as_bytes(), "text/plain")?;
+            let path = ctx.paths.resolve_input(source)?;
+            let bytes = std::fs::read(&path).map_err(|e| anyhow::anyhow!("source {}: read {:?}: {}", node.name, path, e))?;
+            let out = (bytes, node.source_type());
+            runner.with_entry(stage, |_| {});
+            runner.finished(stage, &out.0, &out.1);
+            Ok(out)
         }
     }
+}
 
-    // Post-processors
-    if let Some(ref post) = orchestration.post_processors {
-        for p in post.iter().filter(|p| p.enabled) {
-            let plugin_dir = plugin_base_dir.join(&p.module);
-            let exec = plugin_executable(plugin_dir.to_str().unwrap(), None)
-                .ok_or_else(|| anyhow::anyhow!("no executable for post-processor {}", p.name))?;
-            let opts = options_from_toml(p.options.as_ref());
-            audio = run_subprocess_plugin(exec.to_str().unwrap(), &audio, &opts)?;
+/// Join (`concat`) or sum (`overlay`) decoded audio inputs. The result is WAV when the
+/// first input is, raw PCM otherwise.
+fn mix_audio(inputs: &[&StageOutput], mix: MixConfig) -> anyhow::Result<StageOutput> {
+    let decoded = |i: usize| {
+        audio::decode(&inputs[i].0, &inputs[i].1).map_err(|e| anyhow::anyhow!("input {}: {}", i + 1, e))
+    };
+    let mut acc = decoded(0)?;
+    for i in 1..inputs.len() {
+        let part = decoded(i)?;
+        match mix.mode {
+            MixMode::Concat => {
+                acc.append_silence(Duration::from_millis(u64::from(mix.silence_ms)));
+                acc.append(&part)
+            }
+            MixMode::Overlay => acc.overlay(&part),
         }
+        .map_err(|e| anyhow::anyhow!("input {}: {}", i + 1, e))?;
     }
+    Ok(match audio::base_type(&inputs[0].1) {
+        "audio/wav" | "audio/x-wav" | "audio/wave" => (acc.to_wav(), "audio/wav".to_string()),
+        _ => {
+            let content_type = acc.format.raw_mime();
+            (acc.data, content_type)
+        }
+    })
+}
 
-    Ok(audio)
+/// What resuming from a checkpoint must not change: the graph, apart from its input and
+/// output paths and debug settings, and every plugin it names.
+fn checkpoint_fingerprint(graph: &Graph, plugin_base_dir: &Path, ctx: &ExecutionContext) -> anyhow::Result<String> {
+    let mut graph = graph.clone();
+    graph.input.source.clear();
+    for sink in graph.output.iter_mut().chain(&mut graph.outputs) {
+        sink.path.clear();
+    }
+    graph.debug = None;
+    let mut plugins = Vec::new();
+    for (name, module) in graph.stage_modules() {
+        let hash = match ctx.registry.as_deref().and_then(|r| r.native(name)) {
+            Some(_) => "native".to_string(),
+            None => hash_plugin_dir(&ctx.paths.resolve_module(plugin_base_dir, module)?)?,
+        };
+        plugins.push((name.to_string(), hash));
+    }
+    Ok(run_fingerprint(&serde_json::to_string(&graph)?, &plugins))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Output bytes and content type of one stage invocation.
+type StageOutput = (Vec<u8>, String);
 
-    fn options_from_toml_test(v: Option<&toml::Value>) -> PluginOptions {
-        let mut opts = PluginOptions::new();
-        let Some(tbl) = v.and_then(|v| v.as_table()) else { return opts };
-        for (k, v) in tbl {
-            let s = match v {
-                toml::Value::String(s) => s.clone(),
-                toml::Value::Integer(i) => i.to_string(),
-                toml::Value::Float(f) => f.to_string(),
-                toml::Value::Boolean(b) => b.to_string(),
-                _ => continue,
-            };
-            opts.insert(k.clone(), s);
+/// An engine the TTS stage can synthesize with: `[tts]` itself or one of its fallbacks.
+struct TtsEngine<'a> {
+    name: &'a str,
+    target: StageTarget<'a>,
+    opts: PluginOptions,
+    retry: RetryPolicy,
+    /// SSML elements the engine reads; `None` if it only takes plain text.
+    ssml: Option<Vec<String>>,
+}
+
+impl<'a> TtsEngine<'a> {
+    fn new(name: &'a str, target: StageTarget<'a>, opts: PluginOptions, retry: RetryPolicy) -> Self {
+        let all = || ssml::ELEMENTS.iter().map(|e| e.to_string()).collect();
+        let ssml = match &target {
+            StageTarget::Native(NativePlugin::Tts(t)) => t.ssml_elements().map(|e| e.iter().map(|e| e.to_string()).collect()),
+            StageTarget::Native(_) => None,
+            StageTarget::Dir { dir, .. } => load_manifest(dir).and_then(|m| m.capabilities).and_then(|c| {
+                let takes_ssml = c.input.is_some_and(|i| i.iter().any(|t| t == SSML_TYPE));
+                takes_ssml.then(|| c.ssml_elements.unwrap_or_else(all))
+            }),
+        };
+        Self { name, target, opts, retry, ssml }
+    }
+}
+
+/// Engine `name` from `module` with `opts`, then each of `node`'s fallbacks with `opts`
+/// overlaid by its own options.
+fn tts_engines<'a>(
+    ctx: &'a ExecutionContext,
+    plugin_base_dir: &Path,
+    node: &'a Node,
+    name: &'a str,
+    module: &str,
+    opts: PluginOptions,
+) -> anyhow::Result<Vec<TtsEngine<'a>>> {
+    let mut engines = vec![TtsEngine::new(
+        name,
+        stage_target(ctx, plugin_base_dir, name, module)?,
+        opts.clone(),
+        node.retry.unwrap_or_default(),
+    )];
+    for f in node.fallbacks() {
+        let mut fallback_opts = opts.clone();
+        fallback_opts.extend(options_from_toml(f.options.as_ref()));
+        engines.push(TtsEngine::new(
+            &f.name,
+            stage_target(ctx, plugin_base_dir, &f.name, &f.module)?,
+            fallback_opts,
+            f.retry.or(node.retry).unwrap_or_default(),
+        ));
+    }
+    Ok(engines)
+}
+
+/// Run `synthesize` with each engine in turn until one succeeds, and return its output
+/// and the engine's name. A lone engine's error, or a cancellation, is returned as is.
+fn with_fallbacks<'e>(
+    runner: &StageRunner<'_>,
+    stage: &StageInfo,
+    engines: &'e [TtsEngine<'_>],
+    synthesize: impl Fn(&TtsEngine<'_>) -> anyhow::Result<StageOutput>,
+) -> anyhow::Result<(StageOutput, &'e str)> {
+    let mut failures = Vec::new();
+    for engine in engines {
+        match synthesize(engine) {
+            Ok(out) => return Ok((out, engine.name)),
+            Err(e) if engines.len() == 1 || is_cancelled(&e) => return Err(e),
+            Err(e) => {
+                runner.with_entry(stage, |entry| entry.retries += 1);
+                runner.warn(&format!("TTS engine {} failed: {:#}", engine.name, e));
+                failures.push(format!("{}: {:#}", engine.name, e));
+            }
         }
-        opts
     }
+    anyhow::bail!("all TTS engines failed: {}", failures.join("; "))
+}
 
-    #[test]
-    fn options_from_toml_parses_table() {
-        let t = toml::from_str::<toml::Value>(r#"[voice]
-foo = "bar"
-n = 1
-"#).unwrap();
-        let opts = options_from_toml_test(Some(&t));
-        assert_eq!(opts.get("voice"), None); // voice is a table, not a string
-        let t2 = toml::from_str::<toml::Value>(r#"voice = "en"
-rate = 1.5"#).unwrap();
-        let opts2 = options_from_toml_test(Some(&t2));
-        assert_eq!(opts2.get("voice"), Some(&"en".to_string()));
-        assert_eq!(opts2.get("rate"), Some(&"1.5".to_string()));
+/// Synthesize `parts` with at most `parallelism` in flight and return their audio in part
+/// order. Each part is the engines to try, in order, and its text; errors name the part
+/// as `label i/n`. When the stage feeds the output stream, each part is streamed once
+/// every part before it has been, after `gaps[i - 1]` of silence.
+fn synthesize_parts(
+    runner: &StageRunner<'_>,
+    stage: &StageInfo,
+    parts: &[(&[TtsEngine<'_>], &str)],
+    parallelism: usize,
+    label: &str,
+    gaps: &[Duration],
+) -> anyhow::Result<Vec<StageOutput>> {
+    let stream = runner.stream.as_ref().filter(|s| s.stage == stage.index);
+    // Parts finished, and how many of them have been streamed.
+    let results: Mutex<(Vec<Option<StageOutput>>, usize)> = Mutex::new((vec![None; parts.len()], 0));
+    let first_error: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let workers = parallelism.clamp(1, parts.len().max(1));
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= parts.len() || first_error.lock().unwrap().is_some() {
+                    break;
+                }
+                if let Err(e) = runner.check_cancelled() {
+                    first_error.lock().unwrap().get_or_insert(e);
+                    break;
+                }
+                let (engines, text) = parts[i];
+                let synthesize = |engine: &TtsEngine<'_>| runner.run_chunk(stage, engine, i, text, "text/plain");
+                match with_fallbacks(runner, stage, engines, synthesize) {
+                    Ok((out, _)) => {
+                        let mut results = results.lock().unwrap();
+                        let (outs, streamed) = &mut *results;
+                        outs[i] = Some(out);
+                        if let Some(stream) = stream {
+                            while let Some(Some(next)) = outs.get(*streamed) {
+                                let gap = streamed.checked_sub(1).and_then(|g| gaps.get(g)).copied().unwrap_or_default();
+                                if let Err(e) = stream.write_part(next, gap) {
+                                    first_error.lock().unwrap().get_or_insert(e.context("stream output"));
+                                    break;
+                                }
+                                *streamed += 1;
+                            }
+                        }
+                        drop(results);
+                        let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+                        if let Some(o) = runner.observer {
+                            o.chunk_finished(stage, done, parts.len());
+                        }
+                    }
+                    Err(e) => {
+                        first_error
+                            .lock()
+                            .unwrap()
+                            .get_or_insert(e.context(format!("{} {}/{}", label, i + 1, parts.len())));
+                    }
+                }
+            });
+        }
+    });
+    if let Some(e) = first_error.into_inner().unwrap() {
+        return Err(e);
+    }
+    Ok(results.into_inner().unwrap().0.into_iter().flatten().collect())
+}
+
+/// TTS stage for `[tts.chunking]`: segment the text, synthesize chunks with at most
+/// `parallelism` in flight, and stitch the audio back together in chunk order.
+fn synthesize_chunked(
+    runner: &StageRunner<'_>,
+    stage: &StageInfo,
+    engine: &TtsEngine<'_>,
+    text: &str,
+    chunking: &ChunkingConfig,
+) -> anyhow::Result<StageOutput> {
+    let chunks = segment(text, chunking.mode, chunking.max_chars);
+    if chunks.len() <= 1 {
+        let text = chunks.first().map(String::as_str).unwrap_or("");
+        return runner.run_chunk(stage, engine, 0, text, "text/plain");
+    }
+
+    let engines = std::slice::from_ref(engine);
+    let parts: Vec<(&[TtsEngine<'_>], &str)> = chunks.iter().map(|c| (engines, c.as_str())).collect();
+    let silence = Duration::from_millis(u64::from(chunking.silence_ms));
+    let gaps = vec![silence; parts.len() - 1];
+    let parts = synthesize_parts(runner, stage, &parts, chunking.parallelism, "chunk", &gaps)?;
+    let audio_type = parts[0].1.clone();
+    if let Some((_, other)) = parts.iter().find(|(_, t)| *t != audio_type) {
+        anyhow::bail!("TTS {} returned mixed output types across chunks: {} and {}", engine.name, audio_type, other);
+    }
+    let parts: Vec<Vec<u8>> = parts.into_iter().map(|(audio, _)| audio).collect();
+    let audio = audio::stitch(&parts, &audio_type, silence)
+        .map_err(|e| anyhow::anyhow!("stitch TTS {} chunks: {}", engine.name, e))?;
+    Ok((audio, audio_type))
+}
+
+/// TTS stage for SSML input. An engine taking SSML gets the document without the
+/// elements it does not support; any other engine gets the text between `<break>`s,
+/// synthesized with at most `parallelism` parts in flight and joined with each break as
+/// silence.
+fn synthesize_ssml(
+    runner: &StageRunner<'_>,
+    stage: &StageInfo,
+    engine: &TtsEngine<'_>,
+    doc: &Ssml,
+    parallelism: usize,
+) -> anyhow::Result<StageOutput> {
+    let tags = |elements: Vec<&str>| elements.iter().map(|e| format!("<{}>", e)).collect::<Vec<_>>().join(", ");
+    if let Some(supported) = &engine.ssml {
+        let unsupported = doc.unsupported(supported);
+        if unsupported.is_empty() {
+            return runner.run_chunk(stage, engine, 0, &doc.to_xml(), SSML_TYPE);
+        }
+        runner.warn(&format!("TTS {} does not support SSML {}; their text is kept", engine.name, tags(unsupported)));
+        let mut doc = doc.clone();
+        doc.retain(supported);
+        return runner.run_chunk(stage, engine, 0, &doc.to_xml(), SSML_TYPE);
+    }
+
+    let markup = doc.unsupported(&["break"]);
+    if !markup.is_empty() {
+        runner.warn(&format!("TTS {} takes plain text; SSML {} stripped", engine.name, tags(markup)));
+    }
+    let plain = doc.to_plain();
+    if plain.parts.len() <= 1 {
+        let text = plain.parts.first().map(String::as_str).unwrap_or("");
+        return runner.run_chunk(stage, engine, 0, text, "text/plain");
+    }
+    let engines = std::slice::from_ref(engine);
+    let parts: Vec<(&[TtsEngine<'_>], &str)> = plain.parts.iter().map(|p| (engines, p.as_str())).collect();
+    let parts = synthesize_parts(runner, stage, &parts, parallelism, "part", &plain.pauses)?;
+    let audio_type = parts[0].1.clone();
+    if let Some((_, other)) = parts.iter().find(|(_, t)| *t != audio_type) {
+        anyhow::bail!("TTS {} returned mixed output types across parts: {} and {}", engine.name, audio_type, other);
+    }
+    let parts: Vec<Vec<u8>> = parts.into_iter().map(|(audio, _)| audio).collect();
+    let audio = audio::stitch_with_gaps(&parts, &audio_type, &plain.pauses)
+        .map_err(|e| anyhow::anyhow!("join TTS {} parts at SSML breaks: {}", engine.name, e))?;
+    Ok((audio, audio_type))
+}
+
+/// TTS stage for `[speakers.*]`: split the script into lines, voice each with its
+/// speaker's engine and the node's fallbacks, and join them in order with `gap_ms` of
+/// silence. Narration uses `narrator`, the node's own engines.
+fn synthesize_dialogue(
+    runner: &StageRunner<'_>,
+    ctx: &ExecutionContext,
+    plugin_base_dir: &Path,
+    stage: &StageInfo,
+    node: &Node,
+    narrator: &[TtsEngine<'_>],
+    text: &str,
+) -> anyhow::Result<StageOutput> {
+    let speakers = node.speakers.as_ref().map(|s| s.iter()).into_iter().flatten();
+    let mut voices: HashMap<&str, Vec<TtsEngine<'_>>> = HashMap::new();
+    for (speaker, config) in speakers {
+        let (name, module) = config.engine(node.plugin_name(), node.module());
+        let mut opts = narrator[0].opts.clone();
+        opts.extend(speaker_options(config));
+        voices.insert(speaker, tts_engines(ctx, plugin_base_dir, node, name, module, opts)?);
+    }
+    let keys: Vec<&str> = voices.keys().copied().collect();
+    let lines = parse_script(text, &keys)?;
+    if lines.is_empty() {
+        let synthesize = |engine: &TtsEngine<'_>| runner.run_chunk(stage, engine, 0, "", "text/plain");
+        return with_fallbacks(runner, stage, narrator, synthesize).map(|(out, _)| out);
+    }
+
+    let parts: Vec<(&[TtsEngine<'_>], &str)> = lines
+        .iter()
+        .map(|l| {
+            let engines = l.speaker.as_deref().map_or(narrator, |s| voices[s].as_slice());
+            (engines, l.text.as_str())
+        })
+        .collect();
+    let dialogue = node.dialogue.unwrap_or_default();
+    let gap = Duration::from_millis(u64::from(dialogue.gap_ms));
+    let gaps = vec![gap; parts.len() - 1];
+    let parts = synthesize_parts(runner, stage, &parts, dialogue.parallelism, "line", &gaps)?;
+    // Lines from one kind of engine stitch as they are; mixed types are decoded and joined.
+    let audio_type = parts[0].1.clone();
+    if parts.iter().all(|(_, t)| *t == audio_type) {
+        let parts: Vec<Vec<u8>> = parts.into_iter().map(|(audio, _)| audio).collect();
+        let audio = audio::stitch(&parts, &audio_type, gap).map_err(|e| anyhow::anyhow!("stitch dialogue lines: {}", e))?;
+        return Ok((audio, audio_type));
     }
+    let parts: Vec<&StageOutput> = parts.iter().collect();
+    mix_audio(&parts, MixConfig { mode: MixMode::Concat, silence_ms: dialogue.gap_ms })
+        .map_err(|e| anyhow::anyhow!("join dialogue lines: {}", e))
 }
 
-fn options_from_toml(v: Option<&toml::Value>) -> PluginOptions {
+/// Options passed to the TTS plugin from `[tts]`: voice, rate and pitch, then its `options`.
+pub(crate) fn tts_options(tts: &TtsConfig) -> PluginOptions {
+    let mut opts = PluginOptions::new();
+    if let Some(v) = &tts.voice {
+        opts.insert("voice".into(), v.clone());
+    }
+    if let Some(r) = tts.rate {
+        opts.insert("rate".into(), r.to_string());
+    }
+    if let Some(p) = tts.pitch {
+        opts.insert("pitch".into(), p.to_string());
+    }
+    opts.extend(options_from_toml(tts.options.as_ref()));
+    opts
+}
+
+/// Options a `[speakers.*]` entry sets: voice, rate and pitch, then its `options`.
+fn speaker_options(speaker: &SpeakerConfig) -> PluginOptions {
+    let mut opts = PluginOptions::new();
+    if let Some(v) = &speaker.voice {
+        opts.insert("voice".into(), v.clone());
+    }
+    if let Some(r) = speaker.rate {
+        opts.insert("rate".into(), r.to_string());
+    }
+    if let Some(p) = speaker.pitch {
+        opts.insert("pitch".into(), p.to_string());
+    }
+    opts.extend(options_from_toml(speaker.options.as_ref()));
+    opts
+}
+
+pub(crate) fn options_from_toml(v: Option<&toml::Value>) -> PluginOptions {
     let mut opts = PluginOptions::new();
     let Some(tbl) = v.and_then(|v| v.as_table()) else { return opts };
     for (k, v) in tbl {
@@ -131,27 +1366,67 @@ fn options_from_toml(v: Option<&toml::Value>) -> PluginOptions {
 
 /// Run pipeline from discovered plugins (by type order: pre, tts, post/converter).
 pub fn run_pipeline_from_plugins(input_text: &str, plugins: &[crate::plugin::Plugin]) -> anyhow::Result<Vec<u8>> {
+    let run = |p: &crate::plugin::Plugin, input: &[u8]| -> anyhow::Result<Vec<u8>> {
+        match &p.backend {
+            PluginBackend::Native(native) => Ok(run_native_plugin(native, input, "", &p.options)?.0),
+            PluginBackend::Subprocess => {
+                let exec = plugin_executable(&p.path, p.manifest.as_ref())?
+                    .ok_or_else(|| anyhow::anyhow!("no executable for {}", p.path))?;
+                run_subprocess_plugin(exec.to_str().unwrap(), input, &p.options)
+            }
+        }
+    };
     let mut text = input_text.to_string();
     let mut audio: Vec<u8> = vec![];
 
     for p in plugins.iter().filter(|p| p.plugin_type == PluginType::Pre) {
-        let exec = plugin_executable(&p.path, p.manifest.as_ref())
-            .ok_or_else(|| anyhow::anyhow!("no executable for {}", p.path))?;
-        audio = run_subprocess_plugin(exec.to_str().unwrap(), text.as_bytes(), &p.options)?;
+        audio = run(p, text.as_bytes())?;
         text = String::from_utf8(audio.clone()).map_err(|_| anyhow::anyhow!("pre-plugin must return UTF-8 text"))?;
     }
 
     for p in plugins.iter().filter(|p| p.plugin_type == PluginType::Tts) {
-        let exec = plugin_executable(&p.path, p.manifest.as_ref())
-            .ok_or_else(|| anyhow::anyhow!("no executable for {}", p.path))?;
-        audio = run_subprocess_plugin(exec.to_str().unwrap(), text.as_bytes(), &p.options)?;
+        audio = run(p, text.as_bytes())?;
     }
 
     for p in plugins.iter().filter(|p| p.plugin_type == PluginType::Post || p.plugin_type == PluginType::Converter) {
-        let exec = plugin_executable(&p.path, p.manifest.as_ref())
-            .ok_or_else(|| anyhow::anyhow!("no executable for {}", p.path))?;
-        audio = run_subprocess_plugin(exec.to_str().unwrap(), &audio, &p.options)?;
+        audio = run(p, &audio)?;
     }
 
     Ok(audio)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options_from_toml_test(v: Option<&toml::Value>) -> PluginOptions {
+        let mut opts = PluginOptions::new();
+        let Some(tbl) = v.and_then(|v| v.as_table()) else { return opts };
+        for (k, v) in tbl {
+            let s = match v {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => continue,
+            };
+            opts.insert(k.clone(), s);
+        }
+        opts
+    }
+
+    #[test]
+    fn options_from_toml_parses_table() {
+        let t = toml::from_str::<toml::Value>(r#"[voice]
+foo = "bar"
+n = 1
+"#).unwrap();
+        let opts = options_from_toml_test(Some(&t));
+        assert_eq!(opts.get("voice"), None); // voice is a table, not a string
+        let t2 = toml::from_str::<toml::Value>(r#"voice = "en"
+rate = 1.5"#).unwrap();
+        let opts2 = options_from_toml_test(Some(&t2));
+        assert_eq!(opts2.get("voice"), Some(&"en".to_string()));
+        assert_eq!(opts2.get("rate"), Some(&"1.5".to_string()));
+    }
+}
@@ -0,0 +1,395 @@
+//! Dry-run planning: resolve every stage of an orchestration against the registry and
+//! report what would run, with which types and options, without starting any plugin.
+
+use crate::graph::{Graph, Node, NodeKind, INPUT_NODE};
+use crate::orchestration::{ChunkingConfig, Orchestration, RetryPolicy};
+use crate::paths::{PathError, PathPolicy};
+use crate::pipeline::{effective_options, negotiate_input, options_from_toml, plugin_executable};
+use crate::plugin::{NativePlugin, Plugin, PluginBackend, PluginOptions};
+use crate::registry::PluginRegistry;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// What a run of an orchestration would do.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionPlan {
+    pub name: String,
+    pub input: String,
+    /// Main chain's `[output]` path, if any.
+    pub output: Option<String>,
+    /// `[[outputs]]` branch name to path.
+    pub outputs: BTreeMap<String, String>,
+    /// Enabled stages in execution order.
+    pub stages: Vec<PlannedStage>,
+    /// Problems found while planning, such as stages with no matching plugin.
+    pub warnings: Vec<String>,
+}
+
+/// One stage as it would run.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedStage {
+    pub kind: String,
+    pub name: String,
+    pub module: String,
+    /// Graph node the stage runs; v1 stages get generated node names.
+    pub node: String,
+    /// Node outputs it reads, as `"node.port"`.
+    pub inputs: Vec<String>,
+    /// `[[outputs]]` branch; `None` for the main chain.
+    pub branch: Option<String>,
+    /// Registry plugin the stage resolved to; `None` if none matched.
+    pub plugin: Option<String>,
+    pub version: Option<String>,
+    /// `"subprocess"`, `"native"`, or `"built-in"` for mix and source nodes.
+    pub backend: &'static str,
+    pub executable: Option<PathBuf>,
+    /// Wire format and process lifecycle; `None` for native plugins.
+    pub transport: Option<&'static str>,
+    pub lifecycle: Option<&'static str>,
+    pub input_type: String,
+    pub output_type: String,
+    /// Manifest defaults merged with the stage's options.
+    pub options: BTreeMap<String, String>,
+    pub timeout_ms: Option<u64>,
+    /// Isolation the plugin runs under: `"none"` (plain child process) or `"in-process"`.
+    pub sandbox: &'static str,
+    /// Whether outputs may be served from the synthesis cache (deterministic subprocess plugins).
+    pub cacheable: bool,
+    pub chunking: Option<ChunkingConfig>,
+    pub retry: Option<RetryPolicy>,
+    /// TTS engines tried in order if this one fails.
+    pub fallbacks: Vec<String>,
+    /// Dialogue speakers and the TTS engine voicing each.
+    pub speakers: BTreeMap<String, String>,
+}
+
+/// Plan `orch` against `registry`. Stages resolve the way the executor resolves them:
+/// a native plugin registered under the stage name first, else the directory `module`
+/// resolves to under `plugin_base` through `paths`, matched to the discovered plugin in
+/// that directory. Modules escaping the plugin roots are reported in `warnings`.
+pub fn plan(orch: &Orchestration, registry: &PluginRegistry, plugin_base: &Path, paths: &PathPolicy) -> ExecutionPlan {
+    plan_graph(&Graph::from_orchestration(orch), registry, plugin_base, paths)
+}
+
+/// [`plan`] for an orchestration graph, with stages in execution order. Each node's input
+/// type is what its first input produces. A graph that fails its structural checks
+/// plans no stages and reports why in `warnings`.
+pub fn plan_graph(graph: &Graph, registry: &PluginRegistry, plugin_base: &Path, paths: &PathPolicy) -> ExecutionPlan {
+    let mut planner = Planner {
+        registry,
+        plugin_base,
+        paths,
+        current_type: "text/plain".to_string(),
+        stages: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let order = graph.topological_order().unwrap_or_else(|e| {
+        planner.warnings.push(e.to_string());
+        Vec::new()
+    });
+    let kind = graph.input.r#type.as_str();
+    if !matches!(kind, "text" | "ssml") && registry.reader(kind).is_none() && crate::reader::builtin(kind).is_none() {
+        planner.warnings.push(format!("input type {:?} has no reader", kind));
+    }
+    let mut types: HashMap<&str, String> = HashMap::from([(INPUT_NODE, graph.input.content_type().to_string())]);
+    for node in order {
+        planner.current_type = node.input_nodes().next().map(|n| types[n].clone()).unwrap_or_default();
+        match node.kind {
+            NodeKind::Mix | NodeKind::Source => planner.builtin(node),
+            kind => {
+                let default_output = match kind {
+                    NodeKind::Tts => "audio/raw".to_string(),
+                    _ => planner.current_type.clone(),
+                };
+                let options = options_from_toml(node.options.as_ref());
+                planner.stage(kind.label(), node.plugin_name(), node.module(), options, &default_output, node.chunking.clone());
+                for f in node.fallbacks() {
+                    match planner.resolve(&f.name, &f.module) {
+                        Ok(Some(_)) => {}
+                        Ok(None) => planner.warnings.push(format!("TTS fallback {}: no plugin found for module {:?}", f.name, f.module)),
+                        Err(e) => planner.warnings.push(format!("TTS fallback {}: {}", f.name, e)),
+                    }
+                    planner.last().fallbacks.push(f.name.clone());
+                }
+                for (speaker, config) in node.speakers.iter().flatten() {
+                    let (name, module) = config.engine(node.plugin_name(), node.module());
+                    if (name, module) != (node.plugin_name(), node.module()) {
+                        match planner.resolve(name, module) {
+                            Ok(Some(_)) => {}
+                            Ok(None) => planner
+                                .warnings
+                                .push(format!("speaker {}: no plugin found for TTS {} (module {:?})", speaker, name, module)),
+                            Err(e) => planner.warnings.push(format!("speaker {}: TTS {}: {}", speaker, name, e)),
+                        }
+                    }
+                    planner.last().speakers.insert(speaker.clone(), name.to_string());
+                }
+                planner.last().retry = node.retry;
+            }
+        }
+        let stage = planner.last();
+        stage.node = node.name.clone();
+        stage.inputs = node.inputs.clone();
+        stage.branch = node.branch.clone();
+        types.insert(&node.name, stage.output_type.clone());
+    }
+    ExecutionPlan {
+        name: graph.meta.name.clone(),
+        input: graph.input.source.clone(),
+        output: graph.output.as_ref().map(|o| o.path.clone()),
+        outputs: graph.outputs.iter().map(|o| (o.name.clone(), o.path.clone())).collect(),
+        stages: planner.stages,
+        warnings: planner.warnings,
+    }
+}
+
+struct Planner<'r> {
+    registry: &'r PluginRegistry,
+    plugin_base: &'r Path,
+    paths: &'r PathPolicy,
+    /// Content type flowing into the next stage.
+    current_type: String,
+    stages: Vec<PlannedStage>,
+    warnings: Vec<String>,
+}
+
+impl<'r> Planner<'r> {
+    /// The plugin a stage runs: a native plugin registered under `name`, else the
+    /// discovered plugin in the directory `module` resolves to. `None` if the directory is
+    /// missing or not in the registry; an error if it escapes the plugin roots.
+    fn resolve(&self, name: &str, module: &str) -> Result<Option<&'r Plugin>, PathError> {
+        if let Some(p) = self.registry.get(name).filter(|p| matches!(p.backend, PluginBackend::Native(_))) {
+            return Ok(Some(p));
+        }
+        let dir = match self.paths.resolve_module(self.plugin_base, module) {
+            Ok(dir) => dir,
+            Err(PathError::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(self
+            .registry
+            .all()
+            .into_iter()
+            .find(|p| !p.path.is_empty() && Path::new(&p.path).canonicalize().is_ok_and(|p| p == dir)))
+    }
+
+    fn last(&mut self) -> &mut PlannedStage {
+        self.stages.last_mut().expect("planned at least one stage")
+    }
+
+    /// A built-in mix or source node: runs in-process and is never cached.
+    fn builtin(&mut self, node: &Node) {
+        let (input_type, output_type) = match node.kind {
+            NodeKind::Source => (String::new(), node.source_type()),
+            _ => (self.current_type.clone(), self.current_type.clone()),
+        };
+        self.stages.push(PlannedStage {
+            kind: node.kind.label().to_string(),
+            name: node.name.clone(),
+            module: String::new(),
+            node: node.name.clone(),
+            inputs: Vec::new(),
+            branch: None,
+            plugin: None,
+            version: None,
+            backend: "built-in",
+            executable: None,
+            transport: None,
+            lifecycle: None,
+            input_type,
+            output_type,
+            options: BTreeMap::new(),
+            timeout_ms: None,
+            sandbox: "in-process",
+            cacheable: false,
+            chunking: None,
+            retry: None,
+            fallbacks: Vec::new(),
+            speakers: BTreeMap::new(),
+        });
+    }
+
+    fn stage(
+        &mut self,
+        kind: &str,
+        name: &str,
+        module: &str,
+        overrides: PluginOptions,
+        default_output: &str,
+        chunking: Option<ChunkingConfig>,
+    ) {
+        let input = self.current_type.clone();
+        let mut stage = PlannedStage {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            module: module.to_string(),
+            node: name.to_string(),
+            inputs: Vec::new(),
+            branch: None,
+            plugin: None,
+            version: None,
+            backend: PluginBackend::Subprocess.as_str(),
+            executable: None,
+            transport: None,
+            lifecycle: None,
+            input_type: input.clone(),
+            output_type: default_output.to_string(),
+            options: overrides.clone().into_iter().collect(),
+            timeout_ms: None,
+            sandbox: "none",
+            cacheable: true,
+            chunking,
+            retry: None,
+            fallbacks: Vec::new(),
+            speakers: BTreeMap::new(),
+        };
+        match self.resolve(name, module) {
+            Ok(None) => self.warnings.push(format!("{} {}: no plugin found for module {:?}", kind, name, module)),
+            Err(e) => self.warnings.push(format!("{} {}: {}", kind, name, e)),
+            Ok(Some(plugin)) => {
+                stage.plugin = Some(plugin.name.clone());
+                stage.backend = plugin.backend.as_str();
+                match &plugin.backend {
+                    PluginBackend::Native(native) => {
+                        stage.sandbox = "in-process";
+                        stage.cacheable = false;
+                        stage.output_type = match native {
+                            NativePlugin::Pre(_) => input.clone(),
+                            NativePlugin::Tts(t) => t.output_type().to_string(),
+                            NativePlugin::Post(p) | NativePlugin::Converter(p) => p.output_type(&input),
+                        };
+                    }
+                    PluginBackend::Subprocess => {
+                        let manifest = plugin.manifest.as_ref();
+                        let caps = manifest.and_then(|m| m.capabilities.as_ref());
+                        stage.input_type = negotiate_input(&input, caps.and_then(|c| c.input.as_ref()));
+                        if stage.input_type != input {
+                            self.warnings.push(format!(
+                                "{} {}: does not accept {}; it will be sent as {}",
+                                kind, name, input, stage.input_type
+                            ));
+                        }
+                        if let Some(out) = caps.and_then(|c| c.output.as_ref()).and_then(|o| o.first()) {
+                            stage.output_type = out.clone();
+                        }
+                        stage.version = manifest.map(|m| m.version.clone());
+                        match plugin_executable(&plugin.path, manifest) {
+                            Ok(Some(exec)) => stage.executable = Some(exec),
+                            Ok(None) => self.warnings.push(format!("{} {}: no executable in {}", kind, name, plugin.path)),
+                            Err(e) => self.warnings.push(format!("{} {}: {}", kind, name, e)),
+                        }
+                        stage.transport = manifest.map(|m| m.transport().as_str()).or(Some("env"));
+                        stage.lifecycle = manifest.map(|m| m.lifecycle().as_str()).or(Some("oneshot"));
+                        stage.options = effective_options(manifest, &overrides).into_iter().collect();
+                        stage.timeout_ms = manifest.and_then(|m| m.timeout_ms);
+                        stage.cacheable = manifest.is_none_or(|m| m.is_deterministic());
+                    }
+                }
+            }
+        }
+        self.current_type = stage.output_type.clone();
+        self.stages.push(stage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn plan_resolves_types_options_and_missing_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let tts = dir.path().join("plugins/my-tts");
+        fs::create_dir_all(&tts).unwrap();
+        fs::write(
+            tts.join("plugin.toml"),
+            r#"
+name = "my-tts"
+version = "1.2.0"
+type = "tts"
+transport = "framed"
+timeout_ms = 5000
+[capabilities]
+input = ["text/ssml", "text/plain"]
+output = ["audio/wav"]
+[options]
+voice = { type = "string", default = "en" }
+speed = { type = "number", default = 1.0 }
+"#,
+        )
+        .unwrap();
+        fs::write(tts.join("run.sh"), "#!/bin/sh\n").unwrap();
+        let registry = PluginRegistry::load_plugins(&dir.path().join("plugins")).unwrap();
+        let orch = Orchestration::from_toml(
+            r#"
+[meta]
+name = "book"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "narrator"
+module = "plugins/my-tts"
+voice = "de"
+[[post_processors]]
+name = "normalize"
+module = "plugins/normalize"
+[output]
+type = "file"
+path = "out.wav"
+"#,
+        )
+        .unwrap();
+        let plan = plan(&orch, &registry, dir.path(), &PathPolicy::default());
+        assert_eq!(plan.stages.len(), 2);
+        let tts = &plan.stages[0];
+        assert_eq!(tts.plugin.as_deref(), Some("my-tts"));
+        assert_eq!(tts.version.as_deref(), Some("1.2.0"));
+        assert_eq!(tts.transport, Some("framed"));
+        assert_eq!(tts.input_type, "text/plain");
+        assert_eq!(tts.output_type, "audio/wav");
+        assert_eq!(tts.options["voice"], "de");
+        assert_eq!(tts.options["speed"], "1");
+        assert_eq!(tts.timeout_ms, Some(5000));
+        assert!(tts.executable.as_ref().unwrap().ends_with("run.sh"));
+        let post = &plan.stages[1];
+        assert!(post.plugin.is_none());
+        assert_eq!(post.input_type, "audio/wav");
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.warnings[0].contains("normalize"));
+    }
+
+    #[test]
+    fn plan_resolves_modules_through_the_path_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        for d in ["project/plugins/sample-tts", "elsewhere/sample-tts"] {
+            let tts = dir.path().join(d);
+            fs::create_dir_all(&tts).unwrap();
+            fs::write(tts.join("plugin.toml"), "name = \"sample-tts\"\nversion = \"1.0.0\"\ntype = \"tts\"\n").unwrap();
+            fs::write(tts.join("run.sh"), "#!/bin/sh\n").unwrap();
+        }
+        let base = dir.path().join("project");
+        let registry = PluginRegistry::load_plugins(&base.join("plugins")).unwrap();
+        let orch = |module: &str| {
+            Orchestration::from_toml(&format!(
+                "[meta]\nname = \"b\"\nversion = \"0.1\"\nauthor = \"a\"\n[input]\ntype = \"text\"\nsource = \"in.txt\"\n\
+                 [tts]\nname = \"sample-tts\"\nmodule = \"{module}\"\n[output]\ntype = \"file\"\npath = \"out.wav\"\n"
+            ))
+            .unwrap()
+        };
+
+        let ok = plan(&orch("plugins/sample-tts"), &registry, &base, &PathPolicy::default());
+        assert_eq!(ok.stages[0].plugin.as_deref(), Some("sample-tts"));
+        assert!(ok.warnings.is_empty(), "{:?}", ok.warnings);
+
+        // Same directory name, but outside the plugin base: not the registry's plugin.
+        let escaped = plan(&orch("../elsewhere/sample-tts"), &registry, &base, &PathPolicy::default());
+        assert!(escaped.stages[0].plugin.is_none());
+        assert!(escaped.stages[0].executable.is_none());
+        assert!(escaped.warnings[0].contains("escapes the plugin roots"), "{:?}", escaped.warnings);
+    }
+}
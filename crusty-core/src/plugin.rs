@@ -2,6 +2,8 @@
 
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::Arc;
 
 pub type PluginOptions = HashMap<String, String>;
 
@@ -34,6 +36,63 @@ pub struct Plugin {
     pub path: String,
     pub options: PluginOptions,
     pub manifest: Option<PluginManifest>,
+    /// plugin.sig verification result from registry load.
+    pub signature: crate::trust::SignatureStatus,
+    /// How the executor runs it.
+    pub backend: PluginBackend,
+}
+
+/// Where a plugin's code runs.
+#[derive(Debug, Clone, Default)]
+pub enum PluginBackend {
+    /// Executable in `Plugin::path`, run as a child process.
+    #[default]
+    Subprocess,
+    /// Trait implementation registered in-process with the registry.
+    Native(NativePlugin),
+}
+
+impl PluginBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginBackend::Subprocess => "subprocess",
+            PluginBackend::Native(_) => "native",
+        }
+    }
+}
+
+/// A registered in-process implementation. Converters use [`PostProcessor`].
+#[derive(Clone)]
+pub enum NativePlugin {
+    Pre(Arc<dyn PreProcessor>),
+    Tts(Arc<dyn Tts>),
+    Post(Arc<dyn PostProcessor>),
+    Converter(Arc<dyn PostProcessor>),
+}
+
+impl NativePlugin {
+    pub fn plugin_type(&self) -> PluginType {
+        match self {
+            NativePlugin::Pre(_) => PluginType::Pre,
+            NativePlugin::Tts(_) => PluginType::Tts,
+            NativePlugin::Post(_) => PluginType::Post,
+            NativePlugin::Converter(_) => PluginType::Converter,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            NativePlugin::Pre(p) => p.name(),
+            NativePlugin::Tts(p) => p.name(),
+            NativePlugin::Post(p) | NativePlugin::Converter(p) => p.name(),
+        }
+    }
+}
+
+impl std::fmt::Debug for NativePlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativePlugin({} {:?})", self.plugin_type().as_str(), self.name())
+    }
 }
 
 /// Parsed plugin.toml (capabilities, options schema).
@@ -53,6 +112,218 @@ pub struct PluginManifest {
     pub capabilities: Option<ManifestCapabilities>,
     #[serde(default)]
     pub options: Option<toml::Value>,
+    /// Wire format: "env" (PLUGIN_INPUT + raw stdout, default) or "framed" (handshake + frames).
+    #[serde(default)]
+    pub transport: Option<String>,
+    /// Process lifecycle: "oneshot" (spawn per call, default) or "persistent" (pooled worker).
+    #[serde(default)]
+    pub lifecycle: Option<String>,
+    /// Pool settings for persistent workers.
+    #[serde(default)]
+    pub worker: Option<WorkerSettings>,
+    /// `false` when identical input and options can give different output; such
+    /// plugins are never served from the synthesis cache.
+    #[serde(default)]
+    pub deterministic: Option<bool>,
+    /// Kill a one-shot run (or a persistent worker's job) that takes longer than this.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl PluginManifest {
+    pub fn transport(&self) -> Transport {
+        match self.transport.as_deref() {
+            Some("framed") => Transport::Framed,
+            _ if self.lifecycle() == Lifecycle::Persistent => Transport::Framed,
+            _ => Transport::Env,
+        }
+    }
+
+    /// Option defaults from the `[options]` table: `key = value` or `key = { default = value }`.
+    pub fn default_options(&self) -> PluginOptions {
+        let mut options = PluginOptions::new();
+        let Some(tbl) = self.options.as_ref().and_then(|o| o.as_table()) else { return options };
+        for (k, v) in tbl {
+            let val = v.as_table().and_then(|t| t.get("default")).unwrap_or(v);
+            if let Some(s) = scalar_string(val) {
+                options.insert(k.clone(), s);
+            }
+        }
+        options
+    }
+
+    /// Typed schema of each `[options]` entry, by name.
+    pub fn option_specs(&self) -> Vec<OptionSpec> {
+        let Some(tbl) = self.options.as_ref().and_then(|o| o.as_table()) else { return Vec::new() };
+        tbl.iter().map(|(k, v)| OptionSpec::from_toml(k, v)).collect()
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic.unwrap_or(true)
+    }
+
+    pub fn lifecycle(&self) -> Lifecycle {
+        match self.lifecycle.as_deref() {
+            Some("persistent") => Lifecycle::Persistent,
+            _ => Lifecycle::Oneshot,
+        }
+    }
+}
+
+fn scalar_string(v: &toml::Value) -> Option<String> {
+    match v {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Value type of a plugin option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    String,
+    Float,
+    Integer,
+    Bool,
+    /// One of the spec's `choices`.
+    Enum,
+}
+
+impl OptionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OptionKind::String => "string",
+            OptionKind::Float => "float",
+            OptionKind::Integer => "integer",
+            OptionKind::Bool => "bool",
+            OptionKind::Enum => "enum",
+        }
+    }
+}
+
+/// One `[options]` entry: `key = { type, default, choices, description }`. Without a
+/// `type`, the kind follows the default's TOML type, or `enum` when there are choices; a
+/// bare `key = value` is its default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionSpec {
+    pub name: String,
+    pub kind: OptionKind,
+    pub default: Option<String>,
+    /// Allowed values, from `choices` (or `enum`); empty when any value of `kind` goes.
+    pub choices: Vec<String>,
+    pub description: Option<String>,
+}
+
+impl OptionSpec {
+    fn from_toml(name: &str, v: &toml::Value) -> Self {
+        let tbl = v.as_table();
+        let field = |key: &str| tbl.and_then(|t| t.get(key));
+        let default = match tbl {
+            Some(_) => field("default"),
+            None => Some(v),
+        };
+        let choices: Vec<String> = field("choices")
+            .or_else(|| field("enum"))
+            .and_then(|c| c.as_array())
+            .map(|c| c.iter().filter_map(scalar_string).collect())
+            .unwrap_or_default();
+        let kind = match field("type").and_then(|t| t.as_str()) {
+            Some("float" | "number") => OptionKind::Float,
+            Some("int" | "integer") => OptionKind::Integer,
+            Some("bool" | "boolean") => OptionKind::Bool,
+            Some("enum") => OptionKind::Enum,
+            Some(_) => OptionKind::String,
+            None if !choices.is_empty() => OptionKind::Enum,
+            None => match default {
+                Some(toml::Value::Float(_)) => OptionKind::Float,
+                Some(toml::Value::Integer(_)) => OptionKind::Integer,
+                Some(toml::Value::Boolean(_)) => OptionKind::Bool,
+                _ => OptionKind::String,
+            },
+        };
+        Self {
+            name: name.to_string(),
+            kind,
+            default: default.and_then(scalar_string),
+            choices,
+            description: field("description").and_then(|d| d.as_str()).map(str::to_string),
+        }
+    }
+
+    /// Check `value` against the spec, giving it as a TOML value of the spec's kind.
+    pub fn parse(&self, value: &str) -> anyhow::Result<toml::Value> {
+        if !self.choices.is_empty() && !self.choices.iter().any(|c| c == value) {
+            anyhow::bail!("option {}: {:?} is not one of {}", self.name, value, self.choices.join(", "));
+        }
+        match self.kind {
+            OptionKind::String | OptionKind::Enum => Ok(toml::Value::String(value.to_string())),
+            OptionKind::Float => value
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(toml::Value::Float)
+                .ok_or_else(|| anyhow::anyhow!("option {}: {:?} is not a number", self.name, value)),
+            OptionKind::Integer => value
+                .parse::<i64>()
+                .map(toml::Value::Integer)
+                .map_err(|_| anyhow::anyhow!("option {}: {:?} is not an integer", self.name, value)),
+            OptionKind::Bool => match value {
+                "true" => Ok(toml::Value::Boolean(true)),
+                "false" => Ok(toml::Value::Boolean(false)),
+                _ => anyhow::bail!("option {}: {:?} is not true or false", self.name, value),
+            },
+        }
+    }
+}
+
+/// How input/output bytes travel between core and the plugin process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// Input in PLUGIN_INPUT (and raw stdin), raw bytes on stdout.
+    Env,
+    /// Handshake frame + payload frames on stdin, frames on stdout.
+    Framed,
+}
+
+impl Transport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transport::Env => "env",
+            Transport::Framed => "framed",
+        }
+    }
+}
+
+/// Whether the plugin process lives for one call or serves many jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    Oneshot,
+    Persistent,
+}
+
+impl Lifecycle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Lifecycle::Oneshot => "oneshot",
+            Lifecycle::Persistent => "persistent",
+        }
+    }
+}
+
+/// `[worker]` table: per-plugin overrides for the persistent worker pool.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WorkerSettings {
+    /// Max warm processes for this plugin.
+    #[serde(default)]
+    pub pool_size: Option<usize>,
+    /// Recycle a worker after this many jobs.
+    #[serde(default)]
+    pub max_jobs: Option<u64>,
+    /// Shut down a worker idle for longer than this.
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -71,23 +342,44 @@ pub struct ManifestCapabilities {
     pub postprocessor: Option<bool>,
     #[serde(default)]
     pub output_formats: Option<Vec<String>>,
+    /// SSML elements understood when `input` lists `application/ssml+xml`; all of them
+    /// when unset.
+    #[serde(default)]
+    pub ssml_elements: Option<Vec<String>>,
 }
 
-// --- Optional in-process traits (for native Rust plugins) ---
+// --- In-process traits (native Rust plugins, registered with `PluginRegistry::register_*`) ---
+//
+// Output is written to `out` as it is produced rather than returned whole, and failures
+// are reported as errors. Implementations may be called from several threads at once.
 
-pub trait PreProcessor {
+pub trait PreProcessor: Send + Sync {
     fn name(&self) -> &str;
-    fn process(&self, input: &str, options: &PluginOptions) -> String;
+    /// Write the processed text to `out`.
+    fn process(&self, input: &str, options: &PluginOptions, out: &mut dyn Write) -> anyhow::Result<()>;
 }
 
-pub trait Tts {
+pub trait Tts: Send + Sync {
     fn name(&self) -> &str;
-    fn synthesize(&self, input: &str, options: &PluginOptions) -> Vec<u8>;
+    /// Content type of the audio written by [`Tts::synthesize`].
+    fn output_type(&self) -> &str {
+        "audio/raw"
+    }
+    /// SSML elements the engine reads from `application/ssml+xml` input; `None` (the
+    /// default) for engines that only take plain text.
+    fn ssml_elements(&self) -> Option<&[&str]> {
+        None
+    }
+    fn synthesize(&self, input: &str, options: &PluginOptions, out: &mut dyn Write) -> anyhow::Result<()>;
 }
 
-pub trait PostProcessor {
+pub trait PostProcessor: Send + Sync {
     fn name(&self) -> &str;
-    fn process(&self, input: &[u8], options: &PluginOptions) -> Vec<u8>;
+    /// Content type written for `input_type` input; unchanged by default.
+    fn output_type(&self, input_type: &str) -> String {
+        input_type.to_string()
+    }
+    fn process(&self, input: &mut dyn Read, options: &PluginOptions, out: &mut dyn Write) -> anyhow::Result<()>;
 }
 
 #[cfg(test)]
@@ -123,5 +415,58 @@ voice = { type = "string", default = "en" }
         assert_eq!(m.r#type.as_deref(), Some("tts"));
         assert!(m.capabilities.as_ref().unwrap().tts == Some(true));
         assert_eq!(m.capabilities.as_ref().unwrap().input.as_ref().unwrap()[0], "text/plain");
+        assert_eq!(m.transport(), Transport::Env);
+        assert_eq!(m.lifecycle(), Lifecycle::Oneshot);
+    }
+
+    #[test]
+    fn option_specs_type_and_check_values() {
+        let s = r#"
+name = "typed"
+version = "0.1.0"
+
+[options]
+voice = { type = "string", default = "en_us", description = "Voice id" }
+rate = { type = "float", default = "1.0" }
+style = { choices = ["calm", "bright"], default = "calm" }
+steps = 4
+"#;
+        let m: PluginManifest = toml::from_str(s).unwrap();
+        let specs = m.option_specs();
+        let spec = |name: &str| specs.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(spec("voice").description.as_deref(), Some("Voice id"));
+        assert_eq!(spec("rate").kind, OptionKind::Float);
+        assert_eq!(spec("rate").default.as_deref(), Some("1.0"));
+        assert_eq!(spec("style").kind, OptionKind::Enum);
+        assert_eq!(spec("steps").kind, OptionKind::Integer);
+        assert_eq!(spec("steps").default.as_deref(), Some("4"));
+
+        assert_eq!(spec("rate").parse("1.5").unwrap(), toml::Value::Float(1.5));
+        assert!(spec("rate").parse("fast").unwrap_err().to_string().contains("not a number"));
+        assert_eq!(spec("steps").parse("8").unwrap(), toml::Value::Integer(8));
+        assert!(spec("steps").parse("8.5").is_err());
+        assert_eq!(spec("style").parse("bright").unwrap(), toml::Value::String("bright".into()));
+        let err = spec("style").parse("loud").unwrap_err();
+        assert!(err.to_string().contains("not one of calm, bright"), "{err}");
+    }
+
+    #[test]
+    fn manifest_persistent_implies_framed() {
+        let s = r#"
+name = "neural"
+version = "0.1.0"
+lifecycle = "persistent"
+
+[worker]
+pool_size = 2
+max_jobs = 50
+"#;
+        let m: PluginManifest = toml::from_str(s).unwrap();
+        assert_eq!(m.lifecycle(), Lifecycle::Persistent);
+        assert_eq!(m.transport(), Transport::Framed);
+        let w = m.worker.unwrap();
+        assert_eq!(w.pool_size, Some(2));
+        assert_eq!(w.max_jobs, Some(50));
+        assert_eq!(w.idle_timeout_secs, None);
     }
 }
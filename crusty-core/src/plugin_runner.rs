@@ -1,10 +1,42 @@
 //! Subprocess plugin runner: handshake + framed I/O, and simple stdin/stdout fallback.
 
+use crate::cancel::{own_process_group, wait_cancellable, CancellationToken};
+use std::time::Duration;
 use crate::plugin::{PluginOptions, PluginType};
-use crate::protocol::{read_frame, write_frame, Handshake};
-use std::io::Write;
+use crate::protocol::{read_frame, write_frame, Handshake, ProgressFrame};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::{ChildStderr, Command, Stdio};
+
+/// Receives progress lines a plugin writes to stderr.
+pub(crate) type ProgressFn<'a> = &'a (dyn Fn(&ProgressFrame) + Sync);
+
+/// Optional hooks and limits for one plugin run.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct RunControl<'a> {
+    pub progress: Option<ProgressFn<'a>>,
+    pub cancel: Option<&'a CancellationToken>,
+    /// Kill the plugin's process group after this long.
+    pub timeout: Option<Duration>,
+}
+
+/// Pass plugin stderr through to ours, diverting progress lines to `progress`.
+fn forward_stderr(stderr: ChildStderr, progress: ProgressFn<'_>) {
+    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+        match ProgressFrame::parse_line(&line) {
+            Some(frame) => progress(&frame),
+            None => eprintln!("{}", line),
+        }
+    }
+}
+
+fn stderr_mode(progress: Option<ProgressFn<'_>>) -> Stdio {
+    if progress.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::inherit()
+    }
+}
 
 /// Run a plugin subprocess: send handshake then payload frames on stdin, read frames from stdout.
 /// `executable` is the plugin binary/script path; `input_bytes` is the first (and for v1 often only) payload.
@@ -14,37 +46,69 @@ pub fn run_subprocess_plugin_framed(
     input_bytes: &[u8],
     options: &PluginOptions,
 ) -> anyhow::Result<Vec<u8>> {
+    run_framed(executable, handshake, input_bytes, options, &RunControl::default())
+}
+
+/// Command for a one-shot plugin. With a cancellation token or timeout the plugin gets
+/// its own process group, so killing it also kills anything it spawned.
+fn plugin_command(executable: &str, input_bytes: &[u8], options: &PluginOptions, control: &RunControl<'_>) -> Command {
     let mut cmd = Command::new(executable);
     cmd.stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
+        .stderr(stderr_mode(control.progress))
         .env("PLUGIN_INPUT", std::str::from_utf8(input_bytes).unwrap_or(""));
     for (k, v) in options {
         cmd.env(format!("PLUGIN_OPT_{}", k.to_uppercase().replace('-', "_")), v);
     }
-    let mut child = cmd.spawn()?;
+    if control.cancel.is_some() || control.timeout.is_some() {
+        own_process_group(&mut cmd);
+    }
+    cmd
+}
 
+pub(crate) fn run_framed(
+    executable: &str,
+    handshake: &Handshake,
+    input_bytes: &[u8],
+    options: &PluginOptions,
+    control: &RunControl<'_>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut child = plugin_command(executable, input_bytes, options, control).spawn()?;
+    let stderr = child.stderr.take();
     let mut stdin = child.stdin.take().ok_or_else(|| anyhow::anyhow!("no stdin"))?;
+    let mut stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("no stdout"))?;
     let handshake_json = serde_json::to_string(handshake)?;
-    write_frame(&mut stdin, handshake_json.as_bytes())?;
-    if !input_bytes.is_empty() {
-        write_frame(&mut stdin, input_bytes)?;
-    }
-    drop(stdin);
 
-    let mut stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("no stdout"))?;
-    let mut out = Vec::new();
-    while let Some(chunk) = read_frame(&mut stdout)? {
-        if chunk.is_empty() {
-            break;
+    std::thread::scope(|s| {
+        if let (Some(stderr), Some(progress)) = (stderr, control.progress) {
+            s.spawn(move || forward_stderr(stderr, progress));
         }
-        out.extend_from_slice(&chunk);
-    }
-    let status = child.wait()?;
-    if !status.success() {
-        anyhow::bail!("plugin exited with {}", status);
-    }
-    Ok(out)
+        let writer = s.spawn(move || -> std::io::Result<()> {
+            write_frame(&mut stdin, handshake_json.as_bytes())?;
+            if !input_bytes.is_empty() {
+                write_frame(&mut stdin, input_bytes)?;
+            }
+            Ok(())
+        });
+        let reader = s.spawn(move || -> std::io::Result<Vec<u8>> {
+            let mut out = Vec::new();
+            while let Some(chunk) = read_frame(&mut stdout)? {
+                if chunk.is_empty() {
+                    break;
+                }
+                out.extend_from_slice(&chunk);
+            }
+            Ok(out)
+        });
+        let status = wait_cancellable(&mut child, control.cancel, control.timeout)?;
+        let out = reader.join().map_err(|_| anyhow::anyhow!("plugin reader panicked"))?;
+        let written = writer.join().map_err(|_| anyhow::anyhow!("plugin writer panicked"))?;
+        if !status.success() {
+            anyhow::bail!("plugin exited with {}", status);
+        }
+        written?;
+        Ok(out?)
+    })
 }
 
 /// Simple runner: write raw input to stdin, read full stdout. No framing.
@@ -54,28 +118,50 @@ pub fn run_subprocess_plugin(
     input_bytes: &[u8],
     options: &PluginOptions,
 ) -> anyhow::Result<Vec<u8>> {
-    let mut cmd = Command::new(executable);
-    cmd.stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .env("PLUGIN_INPUT", std::str::from_utf8(input_bytes).unwrap_or(""));
-    for (k, v) in options {
-        cmd.env(format!("PLUGIN_OPT_{}", k.to_uppercase().replace('-', "_")), v);
-    }
-    let mut child = cmd.spawn()?;
+    run_env(executable, input_bytes, options, &RunControl::default())
+}
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin.write_all(input_bytes)?;
-        stdin.flush()?;
-    }
-    let output = child.wait_with_output()?;
-    if !output.status.success() {
-        anyhow::bail!("plugin exited with {}", output.status);
-    }
-    Ok(output.stdout)
+pub(crate) fn run_env(
+    executable: &str,
+    input_bytes: &[u8],
+    options: &PluginOptions,
+    control: &RunControl<'_>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut child = plugin_command(executable, input_bytes, options, control).spawn()?;
+    let stderr = child.stderr.take();
+    let stdin = child.stdin.take();
+    let mut stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("no stdout"))?;
+
+    std::thread::scope(|s| {
+        if let (Some(stderr), Some(progress)) = (stderr, control.progress) {
+            s.spawn(move || forward_stderr(stderr, progress));
+        }
+        let writer = s.spawn(move || -> std::io::Result<()> {
+            let Some(mut stdin) = stdin else { return Ok(()) };
+            // Env-transport plugins may exit without reading stdin; that is not an error.
+            match stdin.write_all(input_bytes).and_then(|_| stdin.flush()) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e),
+                _ => Ok(()),
+            }
+        });
+        let reader = s.spawn(move || -> std::io::Result<Vec<u8>> {
+            let mut out = Vec::new();
+            stdout.read_to_end(&mut out)?;
+            Ok(out)
+        });
+        let status = wait_cancellable(&mut child, control.cancel, control.timeout)?;
+        let out = reader.join().map_err(|_| anyhow::anyhow!("plugin reader panicked"))?;
+        let written = writer.join().map_err(|_| anyhow::anyhow!("plugin writer panicked"))?;
+        if !status.success() {
+            anyhow::bail!("plugin exited with {}", status);
+        }
+        written?;
+        Ok(out?)
+    })
 }
 
 /// Verify a plugin by running a small sample; returns true if output is valid for the type.
+/// For the full conformance suite with a structured report, see [`crate::verify::verify_plugin_dir`].
 pub fn verify_plugin(plugin_path: &str, plugin_type: PluginType) -> bool {
     if !Path::new(plugin_path).exists() {
         eprintln!("Plugin not found: {}", plugin_path);
@@ -135,4 +221,39 @@ mod tests {
         let out = run_subprocess_plugin(script.to_str().unwrap(), b"env-content", &PluginOptions::new()).unwrap();
         assert_eq!(out, b"env-content");
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn progress_lines_are_diverted_from_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("run.sh");
+        fs::write(
+            &script,
+            "#!/bin/sh\necho '{\"type\":\"progress\",\"fraction\":0.5}' >&2\necho 'log line' >&2\nprintf done\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let seen = std::sync::Mutex::new(Vec::new());
+        let progress = |p: &ProgressFrame| seen.lock().unwrap().push(p.fraction);
+        let control = RunControl { progress: Some(&progress), ..Default::default() };
+        let out = run_env(script.to_str().unwrap(), b"", &PluginOptions::new(), &control).unwrap();
+        assert_eq!(out, b"done");
+        assert_eq!(*seen.lock().unwrap(), vec![Some(0.5)]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn timeout_kills_slow_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("run.sh");
+        fs::write(&script, "#!/bin/sh
+sleep 30
+").unwrap();
+        fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let control = RunControl { timeout: Some(Duration::from_millis(100)), ..Default::default() };
+        let started = std::time::Instant::now();
+        let err = run_env(script.to_str().unwrap(), b"", &PluginOptions::new(), &control).unwrap_err();
+        assert!(err.to_string().contains("timed out after 100 ms"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
 }
@@ -60,6 +60,32 @@ pub struct ErrorFrame {
     pub fatal: bool,
 }
 
+/// Progress report a plugin may write to stderr as one JSON line:
+/// `{"type": "progress", "fraction": 0.5, "message": "..."}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressFrame {
+    #[serde(rename = "type")]
+    pub typ: String,
+    /// Completed fraction of this invocation, 0.0 to 1.0.
+    #[serde(default)]
+    pub fraction: Option<f64>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl ProgressFrame {
+    /// Parse a stderr line; `None` unless it is a JSON object with `"type": "progress"`.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if !line.starts_with('{') {
+            return None;
+        }
+        serde_json::from_str::<ProgressFrame>(line)
+            .ok()
+            .filter(|p| p.typ == "progress")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,4 +132,13 @@ mod tests {
         let e2: ErrorFrame = serde_json::from_str(&j).unwrap();
         assert!(e2.fatal);
     }
+
+    #[test]
+    fn progress_frame_parse_line() {
+        let p = ProgressFrame::parse_line(r#"{"type":"progress","fraction":0.25,"message":"voice loaded"}"#).unwrap();
+        assert_eq!(p.fraction, Some(0.25));
+        assert_eq!(p.message.as_deref(), Some("voice loaded"));
+        assert!(ProgressFrame::parse_line(r#"{"type":"error","message":"x"}"#).is_none());
+        assert!(ProgressFrame::parse_line("plain log line").is_none());
+    }
 }
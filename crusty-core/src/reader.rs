@@ -0,0 +1,344 @@
+//! Input readers: turn the `[input]` source into the text the pipeline starts from.
+//!
+//! `[input] type` picks the reader. `text` and `ssml` pass the file through; `markdown`,
+//! `html` and `epub` extract its speakable text, skipping code blocks, navigation and
+//! scripts; other types use readers registered with
+//! [`PluginRegistry::register_reader`], which may also replace the built-in ones.
+//!
+//! Extracted documents are rendered as SSML — headings followed by a pause, emphasis
+//! kept, each chapter opened by `<mark name="chapter-N"/>` — or, with
+//! `[input] ssml = false`, as plain paragraphs separated by blank lines.
+
+mod epub;
+mod html;
+mod markdown;
+
+use crate::orchestration::Input;
+use crate::registry::PluginRegistry;
+use crate::ssml::{escape, Ssml, SSML_TYPE};
+use serde::Serialize;
+
+pub use epub::EpubReader;
+pub use html::HtmlReader;
+pub use markdown::MarkdownReader;
+
+/// Extracts a [`Document`] from an input file.
+pub trait InputReader: Send + Sync {
+    /// The `[input] type` it reads.
+    fn name(&self) -> &str;
+    fn read(&self, bytes: &[u8]) -> anyhow::Result<Document>;
+}
+
+impl std::fmt::Debug for dyn InputReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InputReader({:?})", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emphasis {
+    Moderate,
+    Strong,
+}
+
+/// A run of text within a heading or paragraph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub emphasis: Option<Emphasis>,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>, emphasis: Option<Emphasis>) -> Self {
+        Self { text: text.into(), emphasis }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// Start of a chapter, with its title.
+    Chapter(String),
+    Heading { level: u8, spans: Vec<Span> },
+    Paragraph(Vec<Span>),
+}
+
+/// Speakable content of an input, in reading order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+/// Text of `spans` without emphasis, whitespace collapsed.
+pub fn plain(spans: &[Span]) -> String {
+    normalize(spans).into_iter().map(|s| s.text).collect()
+}
+
+/// A chapter boundary in the pipeline input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Chapter {
+    pub title: String,
+    /// Byte offset in the input text where the chapter starts.
+    pub offset: usize,
+}
+
+/// The text a pipeline starts from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputText {
+    pub text: String,
+    pub content_type: &'static str,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Document {
+    /// A document opening a chapter at each top-level heading of `blocks`.
+    pub fn with_heading_chapters(blocks: Vec<Block>) -> Self {
+        let mut out = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            if let Block::Heading { level: 1, spans } = &block {
+                out.push(Block::Chapter(plain(spans)));
+            }
+            out.push(block);
+        }
+        Self { blocks: out }
+    }
+
+    /// Render as an SSML document.
+    pub fn to_ssml(&self) -> InputText {
+        let mut text = String::from("<speak>\n");
+        let mut chapters = Vec::new();
+        for block in &self.blocks {
+            match block {
+                Block::Chapter(title) => {
+                    chapters.push(Chapter {
+                        title: title.clone(),
+                        offset: text.len(),
+                    });
+                    text.push_str(&format!("<mark name=\"chapter-{}\"/>\n", chapters.len()));
+                }
+                Block::Heading { spans, .. } | Block::Paragraph(spans) => {
+                    let spans = normalize(spans);
+                    if spans.is_empty() {
+                        continue;
+                    }
+                    text.push_str("<p>");
+                    for span in &spans {
+                        let Some(emphasis) = span.emphasis else {
+                            text.push_str(&escape(&span.text, false));
+                            continue;
+                        };
+                        // Spaces at the edges stay outside the element.
+                        let inner = span.text.trim();
+                        if span.text.starts_with(' ') {
+                            text.push(' ');
+                        }
+                        text.push_str(match emphasis {
+                            Emphasis::Moderate => "<emphasis>",
+                            Emphasis::Strong => "<emphasis level=\"strong\">",
+                        });
+                        text.push_str(&escape(inner, false));
+                        text.push_str("</emphasis>");
+                        if span.text.ends_with(' ') {
+                            text.push(' ');
+                        }
+                    }
+                    text.push_str("</p>");
+                    if matches!(block, Block::Heading { .. }) {
+                        text.push_str("<break strength=\"strong\"/>");
+                    }
+                    text.push('\n');
+                }
+            }
+        }
+        text.push_str("</speak>\n");
+        InputText {
+            text,
+            content_type: SSML_TYPE,
+            chapters,
+        }
+    }
+
+    /// Render as plain text, one paragraph per heading or paragraph.
+    pub fn to_text(&self) -> InputText {
+        let mut text = String::new();
+        let mut chapters = Vec::new();
+        for block in &self.blocks {
+            match block {
+                Block::Chapter(title) => chapters.push(Chapter {
+                    title: title.clone(),
+                    offset: text.len(),
+                }),
+                Block::Heading { spans, .. } | Block::Paragraph(spans) => {
+                    let line = plain(spans);
+                    if line.is_empty() {
+                        continue;
+                    }
+                    text.push_str(&line);
+                    text.push_str("\n\n");
+                }
+            }
+        }
+        let len = text.trim_end().len();
+        text.truncate(len);
+        for chapter in &mut chapters {
+            chapter.offset = chapter.offset.min(len);
+        }
+        InputText {
+            text,
+            content_type: "text/plain",
+            chapters,
+        }
+    }
+}
+
+/// Collapse whitespace across `spans`, merging neighbours with the same emphasis and
+/// trimming the ends.
+fn normalize(spans: &[Span]) -> Vec<Span> {
+    let mut out: Vec<Span> = Vec::new();
+    for span in spans {
+        let mut text = String::with_capacity(span.text.len());
+        let mut space = out.last().is_none_or(|s: &Span| s.text.ends_with(' '));
+        for c in span.text.chars() {
+            if c.is_whitespace() {
+                if !space {
+                    text.push(' ');
+                }
+                space = true;
+            } else {
+                text.push(c);
+                space = false;
+            }
+        }
+        match out.last_mut() {
+            _ if text.is_empty() => {}
+            Some(last) if last.emphasis == span.emphasis => last.text.push_str(&text),
+            _ => out.push(Span::new(text, span.emphasis)),
+        }
+    }
+    if let Some(last) = out.last_mut() {
+        let len = last.text.trim_end().len();
+        last.text.truncate(len);
+    }
+    out.retain(|s| !s.text.is_empty());
+    out
+}
+
+/// The built-in reader for `kind`.
+pub fn builtin(kind: &str) -> Option<Box<dyn InputReader>> {
+    match kind {
+        "markdown" | "md" => Some(Box::new(MarkdownReader)),
+        "html" => Some(Box::new(HtmlReader)),
+        "epub" => Some(Box::new(EpubReader)),
+        _ => None,
+    }
+}
+
+/// Read `bytes` as `input` says, using readers from `registry` before the built-in ones.
+pub fn read_input(input: &Input, bytes: &[u8], registry: Option<&PluginRegistry>) -> anyhow::Result<InputText> {
+    let utf8 = || std::str::from_utf8(bytes).map_err(|e| anyhow::anyhow!("input is not UTF-8: {}", e));
+    let doc = match input.r#type.as_str() {
+        "text" => {
+            return Ok(InputText {
+                text: utf8()?.to_string(),
+                content_type: "text/plain",
+                chapters: Vec::new(),
+            })
+        }
+        "ssml" => {
+            let text = utf8()?;
+            Ssml::parse(text)?;
+            return Ok(InputText {
+                text: text.to_string(),
+                content_type: SSML_TYPE,
+                chapters: Vec::new(),
+            });
+        }
+        kind => match registry.and_then(|r| r.reader(kind)) {
+            Some(reader) => reader.read(bytes)?,
+            None => builtin(kind)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("unknown input type {:?} (expected text, ssml, markdown, html, epub or a registered reader)", kind)
+                })?
+                .read(bytes)?,
+        },
+    };
+    Ok(if input.content_type() == SSML_TYPE { doc.to_ssml() } else { doc.to_text() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(kind: &str, ssml: Option<bool>) -> Input {
+        Input {
+            r#type: kind.to_string(),
+            source: String::new(),
+            ssml,
+        }
+    }
+
+    #[test]
+    fn documents_render_as_ssml_or_plain_text_with_chapter_offsets() {
+        let doc = Document {
+            blocks: vec![
+                Block::Chapter("One".into()),
+                Block::Heading {
+                    level: 1,
+                    spans: vec![Span::new(" One ", None)],
+                },
+                Block::Paragraph(vec![
+                    Span::new("Fish  &\nchips ", None),
+                    Span::new(" now ", Some(Emphasis::Strong)),
+                    Span::new("please", None),
+                ]),
+                Block::Chapter("Two".into()),
+                Block::Paragraph(vec![Span::new("The end.", Some(Emphasis::Moderate))]),
+            ],
+        };
+        let ssml = doc.to_ssml();
+        assert_eq!(
+            ssml.text,
+            "<speak>\n<mark name=\"chapter-1\"/>\n<p>One</p><break strength=\"strong\"/>\n\
+             <p>Fish &amp; chips <emphasis level=\"strong\">now</emphasis> please</p>\n\
+             <mark name=\"chapter-2\"/>\n<p><emphasis>The end.</emphasis></p>\n</speak>\n"
+        );
+        Ssml::parse(&ssml.text).unwrap();
+        assert!(ssml.text[ssml.chapters[1].offset..].starts_with("<mark name=\"chapter-2\"/>"));
+
+        let text = doc.to_text();
+        assert_eq!(text.text, "One\n\nFish & chips now please\n\nThe end.");
+        let titles: Vec<_> = text.chapters.iter().map(|c| (c.title.as_str(), c.offset)).collect();
+        assert_eq!(titles, [("One", 0), ("Two", 30)]);
+        assert!(text.text[30..].starts_with("The end."));
+    }
+
+    #[test]
+    fn read_input_dispatches_on_type() {
+        let md = b"# Title\n\nHello *world*.";
+        let ssml = read_input(&input("markdown", None), md, None).unwrap();
+        assert_eq!(ssml.content_type, SSML_TYPE);
+        assert!(ssml.text.contains("<emphasis>world</emphasis>"), "{}", ssml.text);
+        let plain = read_input(&input("markdown", Some(false)), md, None).unwrap();
+        assert_eq!(plain.text, "Title\n\nHello world.");
+        assert_eq!(read_input(&input("text", None), md, None).unwrap().text, "# Title\n\nHello *world*.");
+        assert!(read_input(&input("ssml", None), b"<speak>unclosed", None).is_err());
+        let err = read_input(&input("docx", None), md, None).unwrap_err();
+        assert!(err.to_string().contains("unknown input type \"docx\""), "{err}");
+
+        struct Shout;
+        impl InputReader for Shout {
+            fn name(&self) -> &str {
+                "shout"
+            }
+            fn read(&self, bytes: &[u8]) -> anyhow::Result<Document> {
+                let text = String::from_utf8_lossy(bytes).to_uppercase();
+                Ok(Document {
+                    blocks: vec![Block::Paragraph(vec![Span::new(text, None)])],
+                })
+            }
+        }
+        let mut registry = PluginRegistry::new();
+        registry.register_reader(Shout);
+        let out = read_input(&input("shout", Some(false)), b"hey", Some(&registry)).unwrap();
+        assert_eq!(out.text, "HEY");
+    }
+}
@@ -0,0 +1,295 @@
+//! EPUB reader: the package's spine documents in reading order, one chapter each.
+//!
+//! The zip container is read directly: its central directory, stored and deflated
+//! entries. Zip64 and encrypted archives are not supported.
+
+use super::html::{blocks, title, tokenize};
+use super::{plain, Block, Document, InputReader};
+use std::collections::HashMap;
+
+pub struct EpubReader;
+
+impl InputReader for EpubReader {
+    fn name(&self) -> &str {
+        "epub"
+    }
+
+    fn read(&self, bytes: &[u8]) -> anyhow::Result<Document> {
+        let zip = Zip::open(bytes)?;
+        let container = tokenize(&zip.text("META-INF/container.xml")?);
+        let package = container
+            .iter()
+            .find(|t| t.is_open("rootfile"))
+            .and_then(|t| t.attr("full-path"))
+            .ok_or_else(|| anyhow::anyhow!("EPUB container.xml names no rootfile"))?
+            .to_string();
+        let opf = tokenize(&zip.text(&package)?);
+        let base = package.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+        // id -> (href, media-type, properties)
+        let manifest: HashMap<&str, (&str, &str, &str)> = opf
+            .iter()
+            .filter(|t| t.is_open("item"))
+            .filter_map(|t| {
+                Some((
+                    t.attr("id")?,
+                    (t.attr("href")?, t.attr("media-type").unwrap_or(""), t.attr("properties").unwrap_or("")),
+                ))
+            })
+            .collect();
+        let mut doc = Document::default();
+        for itemref in opf.iter().filter(|t| t.is_open("itemref")) {
+            if itemref.attr("linear") == Some("no") {
+                continue;
+            }
+            let Some(&(href, media_type, properties)) = itemref.attr("idref").and_then(|id| manifest.get(id)) else {
+                continue;
+            };
+            if properties.split_whitespace().any(|p| p == "nav") || !media_type.contains("html") {
+                continue;
+            }
+            let path = resolve(base, href);
+            let tokens = tokenize(&zip.text(&path)?);
+            let body = blocks(&tokens);
+            if body.is_empty() {
+                continue;
+            }
+            let heading = body.iter().find_map(|b| match b {
+                Block::Heading { spans, .. } => Some(plain(spans)),
+                _ => None,
+            });
+            let number = doc.blocks.iter().filter(|b| matches!(b, Block::Chapter(_))).count() + 1;
+            let name = heading.or_else(|| title(&tokens)).unwrap_or_else(|| format!("Chapter {}", number));
+            doc.blocks.push(Block::Chapter(name));
+            doc.blocks.extend(body);
+        }
+        if doc.blocks.is_empty() {
+            anyhow::bail!("EPUB spine has no readable documents");
+        }
+        Ok(doc)
+    }
+}
+
+/// Path of `href` (percent-encoded, relative to the package directory `base`) in the zip.
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href);
+    let mut decoded = Vec::with_capacity(href.len());
+    let bytes = href.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%').then(|| href.get(i + 1..i + 3)).flatten();
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    let href = String::from_utf8_lossy(&decoded);
+    let mut parts: Vec<&str> = base.split('/').filter(|p| !p.is_empty()).collect();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Entry of a zip central directory.
+struct Entry {
+    method: u16,
+    compressed: usize,
+    size: usize,
+    offset: usize,
+}
+
+struct Zip<'a> {
+    data: &'a [u8],
+    entries: HashMap<String, Entry>,
+}
+
+fn u16_at(data: &[u8], pos: usize) -> anyhow::Result<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow::anyhow!("EPUB zip is truncated"))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> anyhow::Result<usize> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| anyhow::anyhow!("EPUB zip is truncated"))
+}
+
+impl<'a> Zip<'a> {
+    fn open(data: &'a [u8]) -> anyhow::Result<Self> {
+        // End of central directory record: 22 bytes plus a comment of up to 64 KiB.
+        let floor = data.len().saturating_sub(22 + 0xffff);
+        let eocd = (floor..data.len().saturating_sub(21))
+            .rev()
+            .find(|&p| data[p..p + 4] == [0x50, 0x4b, 0x05, 0x06])
+            .ok_or_else(|| anyhow::anyhow!("not an EPUB: no zip directory found"))?;
+        let count = u16_at(data, eocd + 10)? as usize;
+        let mut pos = u32_at(data, eocd + 16)?;
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            if u32_at(data, pos)? != 0x0201_4b50 {
+                anyhow::bail!("EPUB zip directory is corrupt");
+            }
+            let name_len = u16_at(data, pos + 28)? as usize;
+            let extra_len = u16_at(data, pos + 30)? as usize;
+            let comment_len = u16_at(data, pos + 32)? as usize;
+            let name = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or_else(|| anyhow::anyhow!("EPUB zip is truncated"))?;
+            entries.insert(
+                String::from_utf8_lossy(name).into_owned(),
+                Entry {
+                    method: u16_at(data, pos + 10)?,
+                    compressed: u32_at(data, pos + 20)?,
+                    size: u32_at(data, pos + 24)?,
+                    offset: u32_at(data, pos + 42)?,
+                },
+            );
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { data, entries })
+    }
+
+    fn read(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let entry = self.entries.get(name).ok_or_else(|| anyhow::anyhow!("EPUB has no {}", name))?;
+        let data = self.data;
+        if u32_at(data, entry.offset)? != 0x0403_4b50 {
+            anyhow::bail!("EPUB zip entry {} is corrupt", name);
+        }
+        let start = entry.offset + 30 + u16_at(data, entry.offset + 26)? as usize + u16_at(data, entry.offset + 28)? as usize;
+        let raw = data
+            .get(start..start + entry.compressed)
+            .ok_or_else(|| anyhow::anyhow!("EPUB zip is truncated"))?;
+        match entry.method {
+            0 => Ok(raw.to_vec()),
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(raw, entry.size)
+                .map_err(|e| anyhow::anyhow!("EPUB zip entry {}: {:?}", name, e.status)),
+            m => anyhow::bail!("EPUB zip entry {} uses unsupported compression method {}", name, m),
+        }
+    }
+
+    fn text(&self, name: &str) -> anyhow::Result<String> {
+        Ok(String::from_utf8_lossy(&self.read(name)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A zip of `files`, deflating those marked so.
+    fn zip(files: &[(&str, &str, bool)]) -> Vec<u8> {
+        let (mut out, mut dir) = (Vec::new(), Vec::new());
+        for &(name, body, deflate) in files {
+            let data = if deflate {
+                miniz_oxide::deflate::compress_to_vec(body.as_bytes(), 6)
+            } else {
+                body.as_bytes().to_vec()
+            };
+            let method: u16 = if deflate { 8 } else { 0 };
+            let offset = out.len() as u32;
+            out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            out.extend_from_slice(&[20, 0, 0, 0]);
+            out.extend_from_slice(&method.to_le_bytes());
+            out.extend_from_slice(&[0; 8]);
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&[0, 0]);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&data);
+
+            dir.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            dir.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            dir.extend_from_slice(&method.to_le_bytes());
+            dir.extend_from_slice(&[0; 8]);
+            dir.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            dir.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            dir.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            dir.extend_from_slice(&[0; 12]);
+            dir.extend_from_slice(&offset.to_le_bytes());
+            dir.extend_from_slice(name.as_bytes());
+        }
+        let dir_offset = out.len() as u32;
+        out.extend_from_slice(&dir);
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(dir.len() as u32).to_le_bytes());
+        out.extend_from_slice(&dir_offset.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out
+    }
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+    const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="text/chapter%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="text/ch2.xhtml#start" media-type="application/xhtml+xml"/>
+    <item id="cover" href="cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="css" href="style.css" media-type="text/css"/>
+  </manifest>
+  <spine>
+    <itemref idref="nav"/>
+    <itemref idref="cover"/>
+    <itemref idref="c2"/>
+    <itemref idref="c1"/>
+    <itemref idref="css"/>
+  </spine>
+</package>"#;
+
+    #[test]
+    fn epub_reads_spine_documents_in_order_as_chapters() {
+        let epub = zip(&[
+            ("mimetype", "application/epub+zip", false),
+            ("META-INF/container.xml", CONTAINER, true),
+            ("OEBPS/content.opf", OPF, true),
+            ("OEBPS/nav.xhtml", "<html><body><nav><ol><li>Contents</li></ol></nav><p>Table</p></body></html>", true),
+            ("OEBPS/cover.xhtml", "<html><head><title>Cover</title></head><body><img src='c.png'/></body></html>", false),
+            ("OEBPS/text/ch2.xhtml", "<html><head><title>Second</title></head><body><p>It began.</p></body></html>", true),
+            ("OEBPS/text/chapter 1.xhtml", "<html><body><h2>The End</h2><p>It <em>ended</em>.</p></body></html>", true),
+        ]);
+        let doc = EpubReader.read(&epub).unwrap();
+        let chapters: Vec<&str> = doc
+            .blocks
+            .iter()
+            .filter_map(|b| match b {
+                Block::Chapter(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(chapters, ["Second", "The End"]);
+        assert_eq!(doc.to_text().text, "It began.\n\nThe End\n\nIt ended.");
+
+        assert!(EpubReader.read(b"plain text").unwrap_err().to_string().contains("not an EPUB"));
+        let missing = zip(&[("META-INF/container.xml", CONTAINER, false)]);
+        assert!(EpubReader.read(&missing).unwrap_err().to_string().contains("EPUB has no OEBPS/content.opf"));
+    }
+
+    #[test]
+    fn hrefs_resolve_against_the_package_directory() {
+        assert_eq!(resolve("OEBPS", "text/a%20b.xhtml#x"), "OEBPS/text/a b.xhtml");
+        assert_eq!(resolve("OEBPS/pkg", "../text/c.xhtml"), "OEBPS/text/c.xhtml");
+        assert_eq!(resolve("", "c.xhtml"), "c.xhtml");
+    }
+}
@@ -0,0 +1,374 @@
+//! HTML reader: block text with `<em>`/`<strong>` emphasis. Navigation, scripts, styles,
+//! `<pre>` code and hidden elements are skipped; each `<h1>` opens a chapter.
+//!
+//! The tokenizer is lenient, as real pages need: unclosed and mismatched tags are
+//! tolerated and unknown entities are kept as written.
+
+use super::{Block, Document, Emphasis, InputReader, Span};
+
+pub struct HtmlReader;
+
+impl InputReader for HtmlReader {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn read(&self, bytes: &[u8]) -> anyhow::Result<Document> {
+        Ok(Document::with_heading_chapters(blocks(&tokenize(&String::from_utf8_lossy(bytes)))))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Token {
+    Open {
+        /// Lowercased, namespace prefix removed.
+        name: String,
+        attrs: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    Close(String),
+    Text(String),
+}
+
+impl Token {
+    pub(super) fn attr(&self, key: &str) -> Option<&str> {
+        match self {
+            Token::Open { attrs, .. } => attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str()),
+            _ => None,
+        }
+    }
+
+    pub(super) fn is_open(&self, tag: &str) -> bool {
+        matches!(self, Token::Open { name, .. } if name == tag)
+    }
+}
+
+/// Elements whose content is not read.
+const SKIP: &[&str] = &[
+    "head", "script", "style", "nav", "noscript", "template", "svg", "math", "pre", "iframe", "object", "form",
+    "button", "select", "textarea",
+];
+/// Elements whose content is read as raw text.
+const RAW: &[&str] = &["script", "style"];
+/// Elements without content or a closing tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+/// Elements that end the paragraph being read.
+const BLOCKS: &[&str] = &[
+    "p", "div", "br", "hr", "li", "ul", "ol", "dl", "dt", "dd", "blockquote", "section", "article", "main", "aside",
+    "header", "footer", "figure", "figcaption", "table", "tr", "td", "th", "caption", "body", "address", "details",
+    "summary",
+];
+
+pub(super) fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < s.len() {
+        let rest = &s[pos..];
+        if let Some(r) = rest.strip_prefix("<!--") {
+            pos += 4 + r.find("-->").map_or(r.len(), |i| i + 3);
+        } else if let Some(r) = rest.strip_prefix("<![CDATA[") {
+            let end = r.find("]]>").unwrap_or(r.len());
+            tokens.push(Token::Text(r[..end].to_string()));
+            pos += 9 + (end + 3).min(r.len());
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            pos += rest.find('>').map_or(rest.len(), |i| i + 1);
+        } else if let Some(r) = rest.strip_prefix("</") {
+            let end = r.find('>').unwrap_or(r.len());
+            tokens.push(Token::Close(tag_name(&r[..end])));
+            pos += 2 + (end + 1).min(r.len());
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let (token, len) = open_tag(rest);
+            pos += len;
+            if let Token::Open { name, self_closing: false, .. } = &token {
+                if RAW.contains(&name.as_str()) {
+                    let close = format!("</{}", name);
+                    let r = &s[pos..];
+                    let end = r.to_ascii_lowercase().find(&close).unwrap_or(r.len());
+                    let name = name.clone();
+                    tokens.push(token);
+                    tokens.push(Token::Text(r[..end].to_string()));
+                    tokens.push(Token::Close(name));
+                    pos += end + r[end..].find('>').map_or(r.len() - end, |i| i + 1);
+                    continue;
+                }
+            }
+            tokens.push(token);
+        } else {
+            let end = rest.char_indices().skip(1).find(|&(_, c)| c == '<').map_or(rest.len(), |(i, _)| i);
+            tokens.push(Token::Text(unescape(&rest[..end])));
+            pos += end;
+        }
+    }
+    tokens
+}
+
+fn tag_name(raw: &str) -> String {
+    let name = raw.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+    let name = name.rsplit(':').next().unwrap_or(name);
+    name.to_ascii_lowercase()
+}
+
+/// Parse the open tag at the start of `s`, returning it and its length.
+fn open_tag(s: &str) -> (Token, usize) {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' && bytes[i] != b'/' {
+        i += 1;
+    }
+    let name = tag_name(&s[1..i]);
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => break,
+            Some(b'>') => {
+                i += 1;
+                break;
+            }
+            Some(b'/') => {
+                self_closing = true;
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !matches!(bytes[i], b'=' | b'>' | b'/') {
+            i += 1;
+        }
+        let key = s[start..i].to_ascii_lowercase();
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let mut value = String::new();
+        if bytes.get(i) == Some(&b'=') {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            let start = i;
+            match bytes.get(i) {
+                Some(&q @ (b'"' | b'\'')) => {
+                    let end = s[i + 1..].find(q as char).map_or(s.len(), |e| i + 1 + e);
+                    value = unescape(&s[start + 1..end]);
+                    i = (end + 1).min(s.len());
+                }
+                _ => {
+                    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                        i += 1;
+                    }
+                    value = unescape(&s[start..i]);
+                }
+            }
+        }
+        if key.is_empty() {
+            // Stray character; step over it.
+            i += 1;
+        } else {
+            self_closing = false;
+            attrs.push((key, value));
+        }
+    }
+    (Token::Open { name, attrs, self_closing }, i)
+}
+
+/// Decode character references, keeping unknown ones as written.
+pub(super) fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let decoded = rest[1..].find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end + 1];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" | "lsquo" | "rsquo" => Some('\''),
+                "ldquo" | "rdquo" => Some('"'),
+                "nbsp" => Some(' '),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "hellip" => Some('…'),
+                "copy" => Some('©'),
+                _ => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => entity.strip_prefix('#').and_then(|d| d.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            c.map(|c| (c, end + 2))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Text of the document's `<title>`, if any.
+pub(super) fn title(tokens: &[Token]) -> Option<String> {
+    let start = tokens.iter().position(|t| t.is_open("title"))?;
+    let text: String = tokens[start + 1..]
+        .iter()
+        .take_while(|t| matches!(t, Token::Text(_)))
+        .map(|t| match t {
+            Token::Text(s) => s.as_str(),
+            _ => "",
+        })
+        .collect();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+pub(super) fn blocks(tokens: &[Token]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
+    // Level of the heading being read.
+    let mut heading: Option<u8> = None;
+    let (mut em, mut strong) = (0usize, 0usize);
+    // Element being skipped, with its nesting depth.
+    let mut skip: Option<(&str, usize)> = None;
+    let flush = |spans: &mut Vec<Span>, heading: &mut Option<u8>, blocks: &mut Vec<Block>| {
+        let spans = std::mem::take(spans);
+        if spans.iter().any(|s| !s.text.trim().is_empty()) {
+            blocks.push(match heading.take() {
+                Some(level) => Block::Heading { level, spans },
+                None => Block::Paragraph(spans),
+            });
+        }
+        *heading = None;
+    };
+    for token in tokens {
+        if let Some((skipped, depth)) = &mut skip {
+            match token {
+                Token::Open { name, self_closing: false, .. } if name == *skipped => *depth += 1,
+                Token::Close(name) if name == *skipped => {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        skip = None;
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match token {
+            Token::Open { name, self_closing, .. } => {
+                let hidden = token.attr("hidden").is_some()
+                    || token.attr("aria-hidden") == Some("true")
+                    || token.attr("role") == Some("navigation");
+                if SKIP.contains(&name.as_str()) || hidden {
+                    if !self_closing && !VOID.contains(&name.as_str()) {
+                        skip = Some((name.as_str(), 1));
+                    }
+                    continue;
+                }
+                match name.as_str() {
+                    "em" | "i" | "cite" | "dfn" => em += 1,
+                    "strong" | "b" => strong += 1,
+                    h if heading_level(h).is_some() => {
+                        flush(&mut spans, &mut heading, &mut blocks);
+                        heading = heading_level(h);
+                    }
+                    n if BLOCKS.contains(&n) => flush(&mut spans, &mut heading, &mut blocks),
+                    _ => {}
+                }
+            }
+            Token::Close(name) => match name.as_str() {
+                "em" | "i" | "cite" | "dfn" => em = em.saturating_sub(1),
+                "strong" | "b" => strong = strong.saturating_sub(1),
+                n if heading_level(n).is_some() || BLOCKS.contains(&n) => flush(&mut spans, &mut heading, &mut blocks),
+                _ => {}
+            },
+            Token::Text(text) => {
+                let emphasis = if strong > 0 {
+                    Some(Emphasis::Strong)
+                } else if em > 0 {
+                    Some(Emphasis::Moderate)
+                } else {
+                    None
+                };
+                spans.push(Span::new(text.clone(), emphasis));
+            }
+        }
+    }
+    flush(&mut spans, &mut heading, &mut blocks);
+    blocks
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name.as_bytes() {
+        [b'h', d @ b'1'..=b'6'] => Some(d - b'0'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::plain;
+
+    #[test]
+    fn html_reads_body_text_and_skips_navigation_and_code() {
+        let page = r#"<!DOCTYPE html>
+<html><head><title>Ignored</title><style>p { color: red }</style></head>
+<body>
+<nav><a href="/">Home</a> | <a href="/docs">Docs</a></nav>
+<h1>Fish &amp; <em>Chips</em></h1>
+<p>Best served <strong>hot</strong>,<br>with salt&nbsp;&#x26; vinegar.
+<div hidden>secret</div>
+<pre><code>fn main() {}</code></pre>
+<script>if (a < b) document.write("<p>x</p>")</script>
+<ul><li>One<li>Two &unknown; done</ul>
+<img src="x.png" alt="A picture">
+</body></html>"#;
+        let doc = HtmlReader.read(page.as_bytes()).unwrap();
+        let text: Vec<String> = doc
+            .blocks
+            .iter()
+            .map(|b| match b {
+                Block::Chapter(title) => format!("chapter {}", title),
+                Block::Heading { level, spans } => format!("h{} {}", level, plain(spans)),
+                Block::Paragraph(spans) => plain(spans),
+            })
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "chapter Fish & Chips",
+                "h1 Fish & Chips",
+                "Best served hot,",
+                "with salt & vinegar.",
+                "One",
+                "Two &unknown; done"
+            ]
+        );
+        assert_eq!(
+            doc.blocks[2],
+            Block::Paragraph(vec![
+                Span::new("Best served ", None),
+                Span::new("hot", Some(Emphasis::Strong)),
+                Span::new(",", None)
+            ])
+        );
+        assert_eq!(title(&tokenize(page)).as_deref(), Some("Ignored"));
+    }
+}
@@ -0,0 +1,342 @@
+//! Markdown reader: CommonMark blocks and inline emphasis. Code blocks, images, link
+//! targets, inline HTML and front matter are dropped; each `#` heading opens a chapter.
+
+use super::{Block, Document, Emphasis, InputReader, Span};
+
+pub struct MarkdownReader;
+
+impl InputReader for MarkdownReader {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn read(&self, bytes: &[u8]) -> anyhow::Result<Document> {
+        let text = std::str::from_utf8(bytes).map_err(|e| anyhow::anyhow!("markdown is not UTF-8: {}", e))?;
+        Ok(Document::with_heading_chapters(blocks(text)))
+    }
+}
+
+fn blocks(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = Vec::new();
+    // Lines of the paragraph being collected.
+    let mut para: Vec<&str> = Vec::new();
+    let flush = |para: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !para.is_empty() {
+            blocks.push(Block::Paragraph(inline(&para.join("\n"))));
+            para.clear();
+        }
+    };
+
+    let mut i = 0;
+    if lines.first().is_some_and(|l| l.trim_end() == "---") {
+        if let Some(end) = lines[1..].iter().position(|l| matches!(l.trim_end(), "---" | "...")) {
+            i = end + 2;
+        }
+    }
+    // Open code fence: its character and length.
+    let mut fence: Option<(char, usize)> = None;
+    let mut comment = false;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if let Some((c, n)) = fence {
+            let t = line.trim();
+            if t.len() >= n && t.chars().all(|x| x == c) {
+                fence = None;
+            }
+            continue;
+        }
+        if comment {
+            comment = !line.contains("-->");
+            continue;
+        }
+        let indented = line.starts_with('\t') || line.starts_with("    ");
+        let mut t = line.trim();
+        while let Some(rest) = t.strip_prefix('>') {
+            t = rest.trim_start();
+        }
+        if t.is_empty() {
+            flush(&mut para, &mut blocks);
+            continue;
+        }
+        if indented && para.is_empty() {
+            // Indented code block.
+            continue;
+        }
+        let ticks = t.chars().take_while(|&c| c == '`').count();
+        let tildes = t.chars().take_while(|&c| c == '~').count();
+        if ticks >= 3 || tildes >= 3 {
+            flush(&mut para, &mut blocks);
+            fence = Some(if ticks >= 3 { ('`', ticks) } else { ('~', tildes) });
+            continue;
+        }
+        if t.starts_with("<!--") {
+            flush(&mut para, &mut blocks);
+            comment = !t.contains("-->");
+            continue;
+        }
+        let hashes = t.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&hashes) && t[hashes..].chars().next().is_none_or(char::is_whitespace) {
+            flush(&mut para, &mut blocks);
+            let title = t[hashes..].trim().trim_end_matches('#').trim_end();
+            blocks.push(Block::Heading {
+                level: hashes as u8,
+                spans: inline(title),
+            });
+            continue;
+        }
+        // Setext heading underline.
+        if !para.is_empty() && (t.chars().all(|c| c == '=') || t.chars().all(|c| c == '-')) {
+            let level = if t.starts_with('=') { 1 } else { 2 };
+            blocks.push(Block::Heading {
+                level,
+                spans: inline(&para.join("\n")),
+            });
+            para.clear();
+            continue;
+        }
+        let rule = t.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        if rule.len() >= 3 && ["-", "*", "_"].iter().any(|m| rule.chars().all(|c| c.to_string() == *m)) {
+            flush(&mut para, &mut blocks);
+            continue;
+        }
+        if t.starts_with('|') {
+            flush(&mut para, &mut blocks);
+            if !t.chars().all(|c| matches!(c, '|' | '-' | ':' | ' ')) {
+                let cells: Vec<&str> = t.split('|').map(str::trim).filter(|c| !c.is_empty()).collect();
+                blocks.push(Block::Paragraph(inline(&cells.join(", "))));
+            }
+            continue;
+        }
+        // Link reference definition.
+        if t.starts_with('[') && t.contains("]:") && !t.contains("](") {
+            continue;
+        }
+        if let Some(item) = list_item(t) {
+            flush(&mut para, &mut blocks);
+            para.push(item);
+            continue;
+        }
+        para.push(t);
+    }
+    flush(&mut para, &mut blocks);
+    blocks
+}
+
+/// Text of a list item line, its marker removed.
+fn list_item(t: &str) -> Option<&str> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = t.strip_prefix(marker) {
+            let rest = rest.trim_start();
+            // Task list checkbox.
+            let rest = ["[ ] ", "[x] ", "[X] "].iter().find_map(|b| rest.strip_prefix(b)).unwrap_or(rest);
+            return Some(rest);
+        }
+    }
+    let digits = t.chars().take_while(char::is_ascii_digit).count();
+    if (1..=9).contains(&digits) {
+        let rest = &t[digits..];
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some(rest.trim_start());
+        }
+    }
+    None
+}
+
+/// Split inline markdown into spans by emphasis.
+fn inline(text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let rest = |i: usize| chars[i..].iter().collect::<String>();
+    let mut spans = Vec::new();
+    let mut cur = String::new();
+    let (mut em, mut strong) = (false, false);
+    // Open `[` of links whose label is being read.
+    let mut links = 0;
+    let mut i = 0;
+    let flush = |cur: &mut String, spans: &mut Vec<Span>, em: bool, strong: bool| {
+        if !cur.is_empty() {
+            let emphasis = if strong {
+                Some(Emphasis::Strong)
+            } else if em {
+                Some(Emphasis::Moderate)
+            } else {
+                None
+            };
+            spans.push(Span::new(std::mem::take(cur), emphasis));
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(char::is_ascii_punctuation) => {
+                cur.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => {
+                let n = chars[i..].iter().take_while(|&&x| x == '`').count();
+                let ticks = "`".repeat(n);
+                let s = rest(i + n);
+                match s.find(&ticks) {
+                    Some(end) => {
+                        cur.push_str(s[..end].trim());
+                        i += n + s[..end].chars().count() + n;
+                    }
+                    None => {
+                        cur.push_str(&ticks);
+                        i += n;
+                    }
+                }
+            }
+            '!' if chars.get(i + 1) == Some(&'[') && rest(i).contains("](") => {
+                // Images are not read out: skip `![alt](target)`.
+                let s = rest(i);
+                let end = s.find("](").and_then(|j| s[j..].find(')').map(|k| j + k));
+                match end {
+                    Some(end) => i += s[..=end].chars().count(),
+                    None => {
+                        cur.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            '[' if rest(i + 1).contains(']') => {
+                links += 1;
+                i += 1;
+            }
+            ']' if links > 0 => {
+                links -= 1;
+                i += 1;
+                // Drop the `(target)` or `[reference]` that follows.
+                let close = match chars.get(i) {
+                    Some('(') => ')',
+                    Some('[') => ']',
+                    _ => continue,
+                };
+                if let Some(end) = rest(i).find(close) {
+                    i += rest(i)[..=end].chars().count();
+                }
+            }
+            '<' => {
+                // Inline HTML and autolinks.
+                let s = rest(i);
+                let tag = s.find('>').filter(|&end| {
+                    let inner = &s[1..end];
+                    !inner.is_empty() && !inner.starts_with(' ') && (inner.starts_with('/') || inner.starts_with('!') || inner.chars().next().is_some_and(char::is_alphabetic))
+                });
+                match tag {
+                    Some(end) => i += s[..=end].chars().count(),
+                    None => {
+                        cur.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            '*' | '_' => {
+                let n = chars[i..].iter().take_while(|&&x| x == c).count().min(3);
+                let prev = i.checked_sub(1).map(|p| chars[p]);
+                let next = chars.get(i + n).copied();
+                let intraword = c == '_' && prev.is_some_and(char::is_alphanumeric) && next.is_some_and(char::is_alphanumeric);
+                let open = match n {
+                    1 => em,
+                    2 => strong,
+                    _ => em && strong,
+                };
+                let delim: String = std::iter::repeat_n(c, n).collect();
+                let closes = open && prev.is_some_and(|p| !p.is_whitespace());
+                let opens = !open && next.is_some_and(|x| !x.is_whitespace()) && rest(i + n).contains(&delim);
+                if intraword || !(closes || opens) {
+                    cur.push_str(&delim);
+                } else {
+                    flush(&mut cur, &mut spans, em, strong);
+                    if n != 2 {
+                        em = !em;
+                    }
+                    if n != 1 {
+                        strong = !strong;
+                    }
+                }
+                i += n;
+            }
+            _ => {
+                cur.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut cur, &mut spans, em, strong);
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::plain;
+
+    const DOC: &str = r#"---
+title: Notes
+---
+# Getting *started*
+
+Install the **crusty** tool
+with [cargo](https://crates.io). ![logo](logo.png)
+
+```sh
+cargo install crusty
+```
+
+    indented code
+
+- first_item uses `snake_case`
+2. second <b>item</b>
+
+> Quoted __text__.
+
+| a | b |
+|---|---|
+| 1 | 2 |
+
+Part two
+========
+Done \*really\*.
+"#;
+
+    #[test]
+    fn markdown_keeps_prose_headings_and_emphasis() {
+        let doc = MarkdownReader.read(DOC.as_bytes()).unwrap();
+        let text: Vec<String> = doc
+            .blocks
+            .iter()
+            .map(|b| match b {
+                Block::Chapter(title) => format!("chapter {}", title),
+                Block::Heading { level, spans } => format!("h{} {}", level, plain(spans)),
+                Block::Paragraph(spans) => plain(spans),
+            })
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "chapter Getting started",
+                "h1 Getting started",
+                "Install the crusty tool with cargo.",
+                "first_item uses snake_case",
+                "second item",
+                "Quoted text.",
+                "a, b",
+                "1, 2",
+                "chapter Part two",
+                "h1 Part two",
+                "Done *really*.",
+            ]
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Heading {
+                level: 1,
+                spans: vec![Span::new("Getting ", None), Span::new("started", Some(Emphasis::Moderate))],
+            }
+        );
+        assert_eq!(inline("a **b** c")[1], Span::new("b", Some(Emphasis::Strong)));
+        assert_eq!(inline("2 * 3 * 4"), [Span::new("2 * 3 * 4", None)]);
+    }
+}
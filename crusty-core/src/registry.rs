@@ -1,8 +1,13 @@
 //! Plugin discovery: load plugin.toml from /plugins, build registry.
 
-use crate::plugin::{Plugin, PluginManifest, PluginOptions, PluginType};
+use crate::plugin::{
+    NativePlugin, Plugin, PluginBackend, PluginManifest, PluginOptions, PluginType, PostProcessor, PreProcessor, Tts,
+};
+use crate::reader::InputReader;
+use crate::trust::{verify_plugin_signature, SignaturePolicy, TrustConfig};
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 
 /// Registry of discovered plugins by type.
 #[derive(Debug, Default)]
@@ -13,6 +18,12 @@ pub struct PluginRegistry {
     pub converter: Vec<Plugin>,
     /// All by name for lookup.
     by_name: HashMap<String, Plugin>,
+    /// Plugins skipped by the signature policy, with the reason.
+    pub rejected: Vec<(String, String)>,
+    /// Non-fatal trust problems (policy `warn`).
+    pub warnings: Vec<String>,
+    /// Input readers by `[input] type`.
+    readers: HashMap<String, Arc<dyn InputReader>>,
 }
 
 impl PluginRegistry {
@@ -20,7 +31,13 @@ impl PluginRegistry {
         Self::default()
     }
 
+    /// Discover plugins with the default trust config (unsigned plugins allowed).
     pub fn load_plugins(plugin_dir: &Path) -> anyhow::Result<Self> {
+        Self::load_plugins_with_trust(plugin_dir, &TrustConfig::default())
+    }
+
+    /// Discover plugins, verifying each plugin.sig and applying the signature policy.
+    pub fn load_plugins_with_trust(plugin_dir: &Path, trust: &TrustConfig) -> anyhow::Result<Self> {
         let mut reg = PluginRegistry::new();
         for entry in std::fs::read_dir(plugin_dir)? {
             let entry = entry?;
@@ -57,19 +74,16 @@ impl PluginRegistry {
                 _ => PluginType::Pre,
             };
 
-            let mut options = PluginOptions::new();
-            if let Some(ref opts) = manifest.options {
-                if let Some(tbl) = opts.as_table() {
-                    for (k, v) in tbl {
-                        let val = v.as_table().and_then(|t| t.get("default")).unwrap_or(v);
-                        let s = match val {
-                            toml::Value::String(s) => s.clone(),
-                            toml::Value::Integer(i) => i.to_string(),
-                            toml::Value::Float(f) => f.to_string(),
-                            toml::Value::Boolean(b) => b.to_string(),
-                            _ => continue,
-                        };
-                        options.insert(k.clone(), s);
+            let options = manifest.default_options();
+
+            let signature = verify_plugin_signature(&entry.path(), &trust.keys);
+            if let Some(reason) = signature.problem() {
+                match trust.policy {
+                    SignaturePolicy::AllowUnsigned => {}
+                    SignaturePolicy::Warn => reg.warnings.push(format!("plugin {}: {}", manifest.name, reason)),
+                    SignaturePolicy::Require => {
+                        reg.rejected.push((manifest.name.clone(), reason));
+                        continue;
                     }
                 }
             }
@@ -81,18 +95,76 @@ impl PluginRegistry {
                 path: path.clone(),
                 options,
                 manifest: Some(manifest),
+                signature,
+                backend: PluginBackend::Subprocess,
             };
-            reg.by_name.insert(plugin.name.clone(), plugin.clone());
-            match plugin.plugin_type {
-                PluginType::Pre => reg.pre.push(plugin),
-                PluginType::Tts => reg.tts.push(plugin),
-                PluginType::Post => reg.post.push(plugin),
-                PluginType::Converter => reg.converter.push(plugin),
-            }
+            reg.insert(plugin);
         }
         Ok(reg)
     }
 
+    fn insert(&mut self, plugin: Plugin) {
+        // A later registration under the same name replaces the earlier one.
+        for other in [&mut self.pre, &mut self.tts, &mut self.post, &mut self.converter] {
+            other.retain(|p| p.name != plugin.name);
+        }
+        self.by_name.insert(plugin.name.clone(), plugin.clone());
+        match plugin.plugin_type {
+            PluginType::Pre => self.pre.push(plugin),
+            PluginType::Tts => self.tts.push(plugin),
+            PluginType::Post => self.post.push(plugin),
+            PluginType::Converter => self.converter.push(plugin),
+        }
+    }
+
+    /// Register an in-process plugin under its `name()`. Orchestration stages with that
+    /// name run it instead of a subprocess; a same-named discovered plugin is replaced.
+    pub fn register_native(&mut self, native: NativePlugin) {
+        self.insert(Plugin {
+            name: native.name().to_string(),
+            plugin_type: native.plugin_type(),
+            path: String::new(),
+            options: PluginOptions::new(),
+            manifest: None,
+            signature: Default::default(),
+            backend: PluginBackend::Native(native),
+        });
+    }
+
+    pub fn register_pre(&mut self, plugin: impl PreProcessor + 'static) {
+        self.register_native(NativePlugin::Pre(Arc::new(plugin)));
+    }
+
+    pub fn register_tts(&mut self, plugin: impl Tts + 'static) {
+        self.register_native(NativePlugin::Tts(Arc::new(plugin)));
+    }
+
+    pub fn register_post(&mut self, plugin: impl PostProcessor + 'static) {
+        self.register_native(NativePlugin::Post(Arc::new(plugin)));
+    }
+
+    pub fn register_converter(&mut self, plugin: impl PostProcessor + 'static) {
+        self.register_native(NativePlugin::Converter(Arc::new(plugin)));
+    }
+
+    /// Register an input reader for `[input] type = "<name()>"`, replacing a built-in
+    /// reader of that name.
+    pub fn register_reader(&mut self, reader: impl InputReader + 'static) {
+        self.readers.insert(reader.name().to_string(), Arc::new(reader));
+    }
+
+    pub fn reader(&self, kind: &str) -> Option<&dyn InputReader> {
+        self.readers.get(kind).map(|r| r.as_ref())
+    }
+
+    /// The native implementation registered as `name`, if any.
+    pub fn native(&self, name: &str) -> Option<&NativePlugin> {
+        match &self.by_name.get(name)?.backend {
+            PluginBackend::Native(n) => Some(n),
+            PluginBackend::Subprocess => None,
+        }
+    }
+
     pub fn get(&self, name: &str) -> Option<&Plugin> {
         self.by_name.get(name)
     }
@@ -153,6 +225,58 @@ output = ["audio/wav"]
         assert!(reg.all().is_empty());
     }
 
+    #[test]
+    fn require_policy_rejects_unsigned_and_keeps_signed() {
+        use crate::trust::{public_key_hex, sign_plugin_dir, TrustedKey, TrustedKeys};
+        let (_guard, plugin_path) = make_temp_plugin_dir();
+        let parent = plugin_path.parent().unwrap();
+        let other = parent.join("unsigned-tts");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join("plugin.toml"), "name = \"unsigned-tts\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&[9; 32]);
+        sign_plugin_dir(&plugin_path, &key).unwrap();
+        let trust = TrustConfig {
+            policy: SignaturePolicy::Require,
+            keys: TrustedKeys {
+                keys: vec![TrustedKey { name: "dev".into(), public_key: public_key_hex(&key) }],
+            },
+        };
+        let reg = PluginRegistry::load_plugins_with_trust(parent, &trust).unwrap();
+        assert!(reg.get("my-tts").unwrap().signature.is_verified());
+        assert!(reg.get("unsigned-tts").is_none());
+        assert_eq!(reg.rejected, vec![("unsigned-tts".to_string(), "unsigned".to_string())]);
+
+        let warn = TrustConfig { policy: SignaturePolicy::Warn, ..trust };
+        let reg = PluginRegistry::load_plugins_with_trust(parent, &warn).unwrap();
+        assert_eq!(reg.all().len(), 2);
+        assert_eq!(reg.warnings.len(), 1);
+    }
+
+    struct Upper;
+
+    impl PreProcessor for Upper {
+        fn name(&self) -> &str {
+            "my-tts"
+        }
+        fn process(&self, input: &str, _: &PluginOptions, out: &mut dyn std::io::Write) -> anyhow::Result<()> {
+            Ok(out.write_all(input.to_uppercase().as_bytes())?)
+        }
+    }
+
+    #[test]
+    fn native_registration_replaces_discovered_plugin() {
+        let (_guard, plugin_path) = make_temp_plugin_dir();
+        let mut reg = PluginRegistry::load_plugins(plugin_path.parent().unwrap()).unwrap();
+        assert!(reg.native("my-tts").is_none());
+        reg.register_pre(Upper);
+        let native = reg.native("my-tts").unwrap();
+        assert_eq!(native.plugin_type(), PluginType::Pre);
+        assert_eq!(reg.get("my-tts").unwrap().backend.as_str(), "native");
+        assert!(reg.tts.is_empty());
+        assert_eq!(reg.pre.len(), 1);
+    }
+
     #[test]
     fn pipeline_order_filters() {
         let (_guard, plugin_path) = make_temp_plugin_dir();
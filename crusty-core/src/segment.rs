@@ -0,0 +1,186 @@
+//! Text segmentation: split long input into sentence or paragraph chunks under a
+//! character budget, so each TTS call stays within engine limits.
+
+use crate::orchestration::ChunkMode;
+
+/// Split `text` into chunks of at most `max_chars` characters, packing whole sentences
+/// (or paragraphs) greedily. Units longer than the budget are split at word boundaries,
+/// and words longer than the budget at character boundaries. Whitespace-only input
+/// yields no chunks.
+pub fn segment(text: &str, mode: ChunkMode, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for paragraph in paragraphs(text) {
+        let units = match mode {
+            ChunkMode::Paragraph => vec![paragraph.to_string()],
+            ChunkMode::Sentence => sentences(paragraph),
+        };
+        let mut first_in_paragraph = true;
+        for unit in units.iter().flat_map(|u| split_long(u, max_chars)) {
+            let sep = if current.is_empty() {
+                ""
+            } else if first_in_paragraph {
+                "\n\n"
+            } else {
+                " "
+            };
+            let unit_len = unit.chars().count();
+            if current_len + sep.len() + unit_len > max_chars && !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+            } else {
+                current.push_str(sep);
+                current_len += sep.len();
+            }
+            current.push_str(&unit);
+            current_len += unit_len;
+            first_in_paragraph = false;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Non-empty paragraphs separated by blank lines, trimmed.
+fn paragraphs(text: &str) -> impl Iterator<Item = &str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut blank_run = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            if !blank_run {
+                out.push(&text[start..offset]);
+                blank_run = true;
+            }
+        } else if blank_run {
+            start = offset;
+            blank_run = false;
+        }
+        offset += line.len();
+    }
+    if !blank_run {
+        out.push(&text[start..]);
+    }
+    out.into_iter().map(str::trim).filter(|p| !p.is_empty())
+}
+
+/// Sentences ending in `.`, `!`, `?` or `…` (plus closing quotes/brackets) followed by
+/// whitespace. Line breaks inside a sentence are folded to spaces.
+fn sentences(paragraph: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    let mut chars = paragraph.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(if c == '\n' || c == '\r' { ' ' } else { c });
+        if matches!(c, '.' | '!' | '?' | '…') {
+            while let Some(&close) = chars.peek() {
+                if matches!(close, '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '”' | '’') {
+                    current.push(close);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if chars.peek().is_none_or(|n| n.is_whitespace()) {
+                push_trimmed(&mut out, &mut current);
+            }
+        }
+    }
+    push_trimmed(&mut out, &mut current);
+    out
+}
+
+fn push_trimmed(out: &mut Vec<String>, current: &mut String) {
+    let s = current.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !s.is_empty() {
+        out.push(s);
+    }
+    current.clear();
+}
+
+/// Split a unit over the budget at word boundaries (hard-splitting oversized words).
+fn split_long(unit: &str, max_chars: usize) -> Vec<String> {
+    if unit.chars().count() <= max_chars {
+        return vec![unit.to_string()];
+    }
+    let mut out = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for word in unit.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > max_chars {
+            if !current.is_empty() {
+                out.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            out.push(word.drain(..max_chars).collect());
+        }
+        if word.is_empty() {
+            continue;
+        }
+        let sep = usize::from(!current.is_empty());
+        if current_len + sep + word.len() > max_chars {
+            out.push(std::mem::take(&mut current));
+            current_len = 0;
+        } else if sep == 1 {
+            current.push(' ');
+            current_len += 1;
+        }
+        current_len += word.len();
+        current.extend(word);
+    }
+    if !current.is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_sentences_under_budget() {
+        let text = "One two. Three four! Five six? Seven.";
+        let chunks = segment(text, ChunkMode::Sentence, 20);
+        assert_eq!(chunks, vec!["One two. Three four!", "Five six? Seven."]);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 20));
+        assert_eq!(segment(text, ChunkMode::Sentence, 1000), vec![text]);
+    }
+
+    #[test]
+    fn sentence_boundaries_need_following_whitespace() {
+        let chunks = segment("Version 1.2 is out. \"Really?\" she said.", ChunkMode::Sentence, 20);
+        assert_eq!(chunks, vec!["Version 1.2 is out.", "\"Really?\" she said."]);
+    }
+
+    #[test]
+    fn paragraph_mode_keeps_paragraphs_whole() {
+        let text = "First para\nline two.\n\n\n  Second para.  \n\nThird.";
+        assert_eq!(
+            segment(text, ChunkMode::Paragraph, 30),
+            vec!["First para\nline two.", "Second para.\n\nThird."]
+        );
+        assert_eq!(segment(text, ChunkMode::Paragraph, 12).len(), 4);
+    }
+
+    #[test]
+    fn oversized_units_split_at_words_then_chars() {
+        let chunks = segment("alpha beta gamma delta", ChunkMode::Sentence, 11);
+        assert_eq!(chunks, vec!["alpha beta", "gamma delta"]);
+        let chunks = segment("abcdefghij", ChunkMode::Sentence, 4);
+        assert_eq!(chunks, vec!["abcd", "efgh", "ij"]);
+        let chunks = segment("ééééé", ChunkMode::Sentence, 2);
+        assert_eq!(chunks, vec!["éé", "éé", "é"]);
+    }
+
+    #[test]
+    fn whitespace_only_yields_nothing() {
+        assert!(segment(" \n\n \t", ChunkMode::Sentence, 10).is_empty());
+    }
+}
@@ -0,0 +1,495 @@
+//! SSML input (`[input] type = "ssml"`): parsing, and downgrading for engines that take
+//! fewer elements or only plain text.
+//!
+//! The parser covers the XML SSML documents use: a `<speak>` root, elements, attributes,
+//! character references, comments and CDATA. Namespaces are not interpreted.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Content type SSML is negotiated as.
+pub const SSML_TYPE: &str = "application/ssml+xml";
+
+/// SSML 1.1 elements: what an engine taking SSML supports unless its manifest lists
+/// `ssml_elements`.
+pub const ELEMENTS: &[&str] = &[
+    "speak", "p", "s", "break", "prosody", "emphasis", "say-as", "sub", "phoneme", "voice", "audio", "mark", "lang",
+    "lexicon", "lookup", "meta", "metadata", "desc", "token", "w",
+];
+
+/// Longest pause a `<break>` (or a run of adjacent ones) makes; longer times are clamped,
+/// as SSML engines do.
+pub const MAX_BREAK: Duration = Duration::from_secs(10);
+
+/// Deepest element nesting the parser accepts.
+const MAX_DEPTH: usize = 256;
+
+/// Elements that carry no spoken text of their own.
+const SILENT: &[&str] = &["break", "mark", "meta", "metadata", "lexicon", "desc"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SsmlNode {
+    Text(String),
+    Element(Element),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<SsmlNode>,
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// A parsed SSML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ssml {
+    /// The `<speak>` element.
+    pub root: Element,
+}
+
+/// An SSML document reduced to plain text: `parts` to synthesize, with `pauses[i]` of
+/// silence between `parts[i]` and `parts[i + 1]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plain {
+    pub parts: Vec<String>,
+    pub pauses: Vec<Duration>,
+}
+
+impl Ssml {
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut p = Parser { s, pos: 0 };
+        p.skip_misc()?;
+        if !p.rest().starts_with('<') {
+            return Err(p.error("expected <speak>"));
+        }
+        let root = p.element(0)?;
+        p.skip_misc()?;
+        if p.pos < s.len() {
+            return Err(p.error("content after the root element"));
+        }
+        if root.name != "speak" {
+            anyhow::bail!("SSML root element must be <speak>, not <{}>", root.name);
+        }
+        Ok(Self { root })
+    }
+
+    /// Names of the elements used, `speak` included.
+    pub fn elements(&self) -> BTreeSet<&str> {
+        fn walk<'a>(e: &'a Element, out: &mut BTreeSet<&'a str>) {
+            out.insert(&e.name);
+            for c in &e.children {
+                if let SsmlNode::Element(c) = c {
+                    walk(c, out);
+                }
+            }
+        }
+        let mut out = BTreeSet::new();
+        walk(&self.root, &mut out);
+        out
+    }
+
+    /// Elements used that are not in `supported`, `speak` aside.
+    pub fn unsupported<S: AsRef<str>>(&self, supported: &[S]) -> Vec<&str> {
+        self.elements()
+            .into_iter()
+            .filter(|e| *e != "speak" && !supported.iter().any(|s| s.as_ref() == *e))
+            .collect()
+    }
+
+    /// Remove elements not in `supported`: silent ones are dropped, `<sub>` becomes its
+    /// alias, and the rest are replaced by their content.
+    pub fn retain<S: AsRef<str>>(&mut self, supported: &[S]) {
+        fn strip<S: AsRef<str>>(nodes: Vec<SsmlNode>, supported: &[S]) -> Vec<SsmlNode> {
+            let mut out = Vec::new();
+            for node in nodes {
+                match node {
+                    SsmlNode::Element(mut e) if supported.iter().any(|s| s.as_ref() == e.name) => {
+                        e.children = strip(e.children, supported);
+                        out.push(SsmlNode::Element(e));
+                    }
+                    SsmlNode::Element(e) if SILENT.contains(&e.name.as_str()) => {}
+                    SsmlNode::Element(e) if e.name == "sub" && e.attr("alias").is_some() => {
+                        out.push(SsmlNode::Text(e.attr("alias").unwrap_or_default().to_string()));
+                    }
+                    SsmlNode::Element(e) => out.extend(strip(e.children, supported)),
+                    text => out.push(text),
+                }
+            }
+            out
+        }
+        let children = std::mem::take(&mut self.root.children);
+        self.root.children = strip(children, supported);
+    }
+
+    pub fn to_xml(&self) -> String {
+        fn write(e: &Element, out: &mut String) {
+            out.push('<');
+            out.push_str(&e.name);
+            for (k, v) in &e.attrs {
+                out.push_str(&format!(" {}=\"{}\"", k, escape(v, true)));
+            }
+            if e.children.is_empty() {
+                out.push_str("/>");
+                return;
+            }
+            out.push('>');
+            for c in &e.children {
+                match c {
+                    SsmlNode::Text(t) => out.push_str(&escape(t, false)),
+                    SsmlNode::Element(c) => write(c, out),
+                }
+            }
+            out.push_str(&format!("</{}>", e.name));
+        }
+        let mut out = String::new();
+        write(&self.root, &mut out);
+        out
+    }
+
+    /// The spoken text, split at `<break>`s. Whitespace is collapsed, `<sub>` reads as its
+    /// alias, and silent elements are skipped. Pauses before the first or after the last
+    /// part are dropped; adjacent breaks add up, to at most [`MAX_BREAK`].
+    pub fn to_plain(&self) -> Plain {
+        fn walk(e: &Element, text: &mut String, plain: &mut Plain) {
+            if e.name == "break" {
+                let pause = break_duration(e);
+                if pause.is_zero() {
+                    return;
+                }
+                let part = collapse(text);
+                text.clear();
+                match (part.is_empty(), plain.parts.is_empty()) {
+                    (false, _) => {
+                        plain.parts.push(part);
+                        plain.pauses.push(pause);
+                    }
+                    // Breaks before any text have nothing to follow.
+                    (true, true) => {}
+                    (true, false) => {
+                        let last = plain.pauses.last_mut().unwrap();
+                        *last = (*last + pause).min(MAX_BREAK);
+                    }
+                }
+                return;
+            }
+            if SILENT.contains(&e.name.as_str()) {
+                return;
+            }
+            if let ("sub", Some(alias)) = (e.name.as_str(), e.attr("alias")) {
+                text.push_str(alias);
+                return;
+            }
+            // Sentences and paragraphs are separate even without whitespace between them.
+            let block = matches!(e.name.as_str(), "p" | "s");
+            if block {
+                text.push(' ');
+            }
+            for c in &e.children {
+                match c {
+                    SsmlNode::Text(t) => text.push_str(t),
+                    SsmlNode::Element(c) => walk(c, text, plain),
+                }
+            }
+            if block {
+                text.push(' ');
+            }
+        }
+        let mut plain = Plain::default();
+        let mut text = String::new();
+        walk(&self.root, &mut text, &mut plain);
+        let last = collapse(&text);
+        if last.is_empty() {
+            // Pauses are only kept between parts.
+            plain.pauses.pop();
+        } else {
+            plain.parts.push(last);
+        }
+        plain
+    }
+}
+
+/// `<break>` length: its `time` (`"500ms"`, `"1.5s"`, clamped to [`MAX_BREAK`]), else its
+/// `strength`.
+fn break_duration(e: &Element) -> Duration {
+    if let Some(time) = e.attr("time").map(str::trim) {
+        let parsed = match time.strip_suffix("ms") {
+            Some(ms) => ms.trim().parse::<f64>().ok().map(|ms| ms / 1000.0),
+            None => time.strip_suffix('s').and_then(|s| s.trim().parse::<f64>().ok()),
+        };
+        if let Some(secs) = parsed.filter(|s| s.is_finite() && *s >= 0.0) {
+            return Duration::from_secs_f64(secs.min(MAX_BREAK.as_secs_f64()));
+        }
+    }
+    let ms = match e.attr("strength").unwrap_or("medium") {
+        "none" => 0,
+        "x-weak" => 100,
+        "weak" => 250,
+        "strong" => 750,
+        "x-strong" => 1000,
+        _ => 500,
+    };
+    Duration::from_millis(ms)
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn escape(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.s[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        let line = self.s[..self.pos].matches('\n').count() + 1;
+        anyhow::anyhow!("invalid SSML at line {}: {}", line, message)
+    }
+
+    fn skip_ws(&mut self) {
+        self.pos = self.s.len() - self.rest().trim_start().len();
+    }
+
+    /// Skip `what` and everything up to and including `end`.
+    fn skip_past(&mut self, end: &str, what: &str) -> anyhow::Result<()> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.pos += i + end.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("unterminated {}", what))),
+        }
+    }
+
+    /// Whitespace, comments, the XML declaration and a doctype outside the root.
+    fn skip_misc(&mut self) -> anyhow::Result<()> {
+        loop {
+            self.skip_ws();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>", "processing instruction")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->", "comment")?;
+            } else if rest.starts_with("<!DOCTYPE") {
+                self.skip_past(">", "doctype")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> anyhow::Result<String> {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(name)
+    }
+
+    /// An element nested `depth` elements deep, with the parser at its `<`.
+    fn element(&mut self, depth: usize) -> anyhow::Result<Element> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error(&format!("elements nest deeper than {} levels", MAX_DEPTH)));
+        }
+        self.pos += 1;
+        let name = self.name()?;
+        let mut attrs = Vec::new();
+        loop {
+            self.skip_ws();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok(Element { name, attrs, children: Vec::new() });
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?;
+            self.skip_ws();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(&format!("attribute {} needs a value", key)));
+            }
+            self.pos += 1;
+            self.skip_ws();
+            let quote = match self.rest().chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error(&format!("attribute {} value must be quoted", key))),
+            };
+            self.pos += 1;
+            let end = self.rest().find(quote).ok_or_else(|| self.error("unterminated attribute value"))?;
+            let value = self.unescape(&self.rest()[..end])?;
+            self.pos += end + 1;
+            if attrs.iter().any(|(k, _)| *k == key) {
+                return Err(self.error(&format!("duplicate attribute {}", key)));
+            }
+            attrs.push((key, value));
+        }
+
+        let mut children = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(self.error(&format!("<{}> is not closed", name)));
+            }
+            if rest.starts_with("</") {
+                self.pos += 2;
+                let close = self.name()?;
+                self.skip_ws();
+                if close != name || !self.rest().starts_with('>') {
+                    return Err(self.error(&format!("expected </{}>", name)));
+                }
+                self.pos += 1;
+                return Ok(Element { name, attrs, children });
+            }
+            if rest.starts_with("<!--") {
+                self.skip_past("-->", "comment")?;
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").ok_or_else(|| self.error("unterminated CDATA"))?;
+                children.push(SsmlNode::Text(cdata[..end].to_string()));
+                self.pos += "<![CDATA[".len() + end + 3;
+            } else if rest.starts_with('<') {
+                children.push(SsmlNode::Element(self.element(depth + 1)?));
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let text = self.unescape(&rest[..end])?;
+                self.pos += end;
+                children.push(SsmlNode::Text(text));
+            }
+        }
+    }
+
+    fn unescape(&self, raw: &str) -> anyhow::Result<String> {
+        let mut out = String::with_capacity(raw.len());
+        let mut rest = raw;
+        while let Some(i) = rest.find('&') {
+            out.push_str(&rest[..i]);
+            let end = rest[i..].find(';').ok_or_else(|| self.error("unterminated character reference"))?;
+            let entity = &rest[i + 1..i + end];
+            let c = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => entity.strip_prefix('#').and_then(|d| d.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)
+                        .ok_or_else(|| self.error(&format!("unknown character reference &{};", entity)))?
+                }
+            };
+            out.push(c);
+            rest = &rest[i + end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"<?xml version="1.0"?>
+<!-- greeting -->
+<speak version="1.1">
+  <p>Hello <emphasis level="strong">there</emphasis>, <sub alias="Doctor">Dr.</sub> Smith &amp; co.</p>
+  <break time="750ms"/><break strength="weak"/>
+  <prosody rate="slow">Take a <![CDATA[<deep>]]> breath.<mark name="m1"/></prosody>
+  <break/>
+</speak>"#;
+
+    #[test]
+    fn parse_lists_elements_and_reduces_to_plain_text() {
+        let doc = Ssml::parse(DOC).unwrap();
+        assert_eq!(
+            doc.elements().into_iter().collect::<Vec<_>>(),
+            ["break", "emphasis", "mark", "p", "prosody", "speak", "sub"]
+        );
+        assert_eq!(doc.unsupported(&["break", "p", "sub"]), ["emphasis", "mark", "prosody"]);
+        assert_eq!(
+            doc.to_plain(),
+            Plain {
+                parts: vec!["Hello there, Doctor Smith & co.".to_string(), "Take a <deep> breath.".to_string()],
+                pauses: vec![Duration::from_millis(1000)],
+            }
+        );
+    }
+
+    #[test]
+    fn retain_unwraps_unsupported_elements() {
+        let mut doc = Ssml::parse(DOC).unwrap();
+        doc.retain(&["break", "p"]);
+        let xml = doc.to_xml();
+        assert!(xml.starts_with(r#"<speak version="1.1">"#), "{xml}");
+        assert!(xml.contains("<p>Hello there, Doctor Smith &amp; co.</p>"), "{xml}");
+        assert!(xml.contains("Take a &lt;deep&gt; breath."), "{xml}");
+        assert!(!xml.contains("prosody") && !xml.contains("mark"), "{xml}");
+        assert_eq!(Ssml::parse(&xml).unwrap().elements().into_iter().collect::<Vec<_>>(), ["break", "p", "speak"]);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_documents() {
+        for (doc, message) in [
+            ("<speak>Hi", "<speak> is not closed"),
+            ("<speak><p>Hi</s></speak>", "expected </p>"),
+            ("<voice>Hi</voice>", "must be <speak>"),
+            ("<speak a=1>Hi</speak>", "must be quoted"),
+            ("<speak>&nbsp;</speak>", "&nbsp;"),
+            ("<speak/>trailing", "after the root"),
+        ] {
+            let err = Ssml::parse(doc).unwrap_err().to_string();
+            assert!(err.contains(message), "{doc}: {err}");
+        }
+    }
+
+    #[test]
+    fn break_times_are_clamped() {
+        for time in ["1e20s", "100000s", "99999999ms"] {
+            let doc = Ssml::parse(&format!(r#"<speak>a<break time="{time}"/>b</speak>"#)).unwrap();
+            assert_eq!(doc.to_plain().pauses, [MAX_BREAK], "{time}");
+        }
+        let doc = Ssml::parse(r#"<speak>a<break time="8s"/><break time="8s"/>b</speak>"#).unwrap();
+        assert_eq!(doc.to_plain().pauses, [MAX_BREAK]);
+    }
+
+    #[test]
+    fn parse_limits_nesting_depth() {
+        let nested = |depth: usize| format!("<speak>{}Hi{}</speak>", "<p>".repeat(depth), "</p>".repeat(depth));
+        assert!(Ssml::parse(&nested(MAX_DEPTH - 1)).is_ok());
+        let err = Ssml::parse(&nested(200_000)).unwrap_err().to_string();
+        assert!(err.contains("invalid SSML") && err.contains("deeper than 256"), "{err}");
+    }
+}
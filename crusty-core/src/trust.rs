@@ -0,0 +1,244 @@
+//! Plugin trust: detached ed25519 signatures (`plugin.sig`) over the plugin directory
+//! hash, a trusted-keys file, and the policy the registry enforces at load time and the
+//! executor before each plugin runs.
+
+use crate::lock::{from_hex, hash_plugin_dir, to_hex};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Detached signature file inside a plugin directory (excluded from the directory hash).
+pub const SIGNATURE_FILE: &str = "plugin.sig";
+
+/// What `PluginRegistry::load_plugins_with_trust` and the executor do with plugins that
+/// are not verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignaturePolicy {
+    /// Load everything; status is still reported.
+    #[default]
+    AllowUnsigned,
+    /// Load and run everything, warning about each plugin that is not verified.
+    Warn,
+    /// Only load and run plugins signed by a trusted key.
+    Require,
+}
+
+impl std::str::FromStr for SignaturePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "allow" | "allow-unsigned" => Ok(SignaturePolicy::AllowUnsigned),
+            "warn" => Ok(SignaturePolicy::Warn),
+            "require" => Ok(SignaturePolicy::Require),
+            _ => anyhow::bail!("unknown signature policy {:?} (expected allow, warn or require)", s),
+        }
+    }
+}
+
+/// Verification outcome for one plugin directory.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum SignatureStatus {
+    /// No plugin.sig.
+    #[default]
+    Unsigned,
+    /// Valid signature by a trusted key.
+    Verified { key: String },
+    /// Valid signature, but the key is not in the trusted set.
+    Untrusted { key: String },
+    /// plugin.sig is malformed or does not match the directory contents.
+    Invalid { reason: String },
+}
+
+impl SignatureStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureStatus::Unsigned => "unsigned",
+            SignatureStatus::Verified { .. } => "verified",
+            SignatureStatus::Untrusted { .. } => "untrusted",
+            SignatureStatus::Invalid { .. } => "invalid",
+        }
+    }
+
+    pub fn is_verified(&self) -> bool {
+        matches!(self, SignatureStatus::Verified { .. })
+    }
+
+    /// Why the plugin is not verified, or `None` when it is.
+    pub fn problem(&self) -> Option<String> {
+        match self {
+            SignatureStatus::Verified { .. } => None,
+            SignatureStatus::Invalid { reason } => Some(format!("invalid signature: {}", reason)),
+            other => Some(other.as_str().to_string()),
+        }
+    }
+}
+
+/// plugin.sig contents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginSignature {
+    /// Hex ed25519 public key of the signer.
+    pub key: String,
+    /// Hex signature over the `sha256:<hex>` directory hash string.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedKey {
+    pub name: String,
+    /// Hex ed25519 public key.
+    pub public_key: String,
+}
+
+/// Trusted-keys file: `[[key]] name = "...", public_key = "<hex>"`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustedKeys {
+    #[serde(default, rename = "key")]
+    pub keys: Vec<TrustedKey>,
+}
+
+impl TrustedKeys {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let s = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("read {:?}: {}", path, e))?;
+        let keys: TrustedKeys = toml::from_str(&s)?;
+        for k in &keys.keys {
+            parse_public_key(&k.public_key).map_err(|e| anyhow::anyhow!("trusted key {}: {}", k.name, e))?;
+        }
+        Ok(keys)
+    }
+
+    /// Name of the trusted key with this hex public key, if any.
+    pub fn find(&self, public_key: &str) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|k| k.public_key.eq_ignore_ascii_case(public_key.trim()))
+            .map(|k| k.name.as_str())
+    }
+}
+
+/// Policy plus trusted keys, passed to registry loading and through `ExecutionContext`.
+#[derive(Debug, Clone, Default)]
+pub struct TrustConfig {
+    pub policy: SignaturePolicy,
+    pub keys: TrustedKeys,
+}
+
+fn parse_public_key(hex: &str) -> anyhow::Result<VerifyingKey> {
+    let bytes: [u8; 32] = from_hex(hex)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("public key must be 32 hex-encoded bytes"))?;
+    Ok(VerifyingKey::from_bytes(&bytes)?)
+}
+
+/// Parse a signing key file: 32-byte hex seed.
+pub fn parse_signing_key(hex: &str) -> anyhow::Result<SigningKey> {
+    let bytes: [u8; 32] = from_hex(hex)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("signing key must be 32 hex-encoded bytes"))?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Hex public key for a signing key (what goes into a trusted-keys file).
+pub fn public_key_hex(key: &SigningKey) -> String {
+    to_hex(key.verifying_key().as_bytes())
+}
+
+/// Sign the directory hash and write plugin.sig. Returns the signed hash.
+pub fn sign_plugin_dir(dir: &Path, key: &SigningKey) -> anyhow::Result<String> {
+    let hash = hash_plugin_dir(dir)?;
+    let sig = PluginSignature {
+        key: public_key_hex(key),
+        signature: to_hex(&key.sign(hash.as_bytes()).to_bytes()),
+    };
+    std::fs::write(dir.join(SIGNATURE_FILE), toml::to_string_pretty(&sig)?)?;
+    Ok(hash)
+}
+
+/// Check plugin.sig against the current directory contents and the trusted keys.
+pub fn verify_plugin_signature(dir: &Path, trusted: &TrustedKeys) -> SignatureStatus {
+    let invalid = |reason: String| SignatureStatus::Invalid { reason };
+    let Ok(contents) = std::fs::read_to_string(dir.join(SIGNATURE_FILE)) else {
+        return SignatureStatus::Unsigned;
+    };
+    let sig: PluginSignature = match toml::from_str(&contents) {
+        Ok(s) => s,
+        Err(e) => return invalid(format!("parse {}: {}", SIGNATURE_FILE, e)),
+    };
+    let key = match parse_public_key(&sig.key) {
+        Ok(k) => k,
+        Err(e) => return invalid(e.to_string()),
+    };
+    let Some(sig_bytes) = from_hex(&sig.signature).and_then(|b| <[u8; 64]>::try_from(b).ok()) else {
+        return invalid("signature must be 64 hex-encoded bytes".into());
+    };
+    let hash = match hash_plugin_dir(dir) {
+        Ok(h) => h,
+        Err(e) => return invalid(format!("hash plugin dir: {}", e)),
+    };
+    if key.verify_strict(hash.as_bytes(), &Signature::from_bytes(&sig_bytes)).is_err() {
+        return invalid("signature does not match plugin contents".into());
+    }
+    match trusted.find(&sig.key) {
+        Some(name) => SignatureStatus::Verified { key: name.to_string() },
+        None => SignatureStatus::Untrusted { key: sig.key.to_lowercase() },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn plugin_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("plugin.toml"), "name = \"p\"\nversion = \"0.1\"\n").unwrap();
+        fs::write(dir.path().join("run.sh"), "#!/bin/sh\ncat\n").unwrap();
+        dir
+    }
+
+    fn trusting(k: &SigningKey) -> TrustedKeys {
+        TrustedKeys {
+            keys: vec![TrustedKey { name: "release".into(), public_key: public_key_hex(k) }],
+        }
+    }
+
+    #[test]
+    fn sign_then_verify_with_trusted_key() {
+        let dir = plugin_dir();
+        let k = key(7);
+        assert_eq!(verify_plugin_signature(dir.path(), &trusting(&k)), SignatureStatus::Unsigned);
+        sign_plugin_dir(dir.path(), &k).unwrap();
+        assert_eq!(
+            verify_plugin_signature(dir.path(), &trusting(&k)),
+            SignatureStatus::Verified { key: "release".into() }
+        );
+        assert_eq!(verify_plugin_signature(dir.path(), &trusting(&key(8))).as_str(), "untrusted");
+    }
+
+    #[test]
+    fn tampering_invalidates_signature() {
+        let dir = plugin_dir();
+        let k = key(1);
+        sign_plugin_dir(dir.path(), &k).unwrap();
+        fs::write(dir.path().join("run.sh"), "#!/bin/sh\necho evil\n").unwrap();
+        assert_eq!(verify_plugin_signature(dir.path(), &trusting(&k)).as_str(), "invalid");
+    }
+
+    #[test]
+    fn policy_and_keys_parse() {
+        assert_eq!("require".parse::<SignaturePolicy>().unwrap(), SignaturePolicy::Require);
+        assert!("sometimes".parse::<SignaturePolicy>().is_err());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trusted_keys.toml");
+        fs::write(&path, format!("[[key]]\nname = \"ci\"\npublic_key = \"{}\"\n", public_key_hex(&key(3)))).unwrap();
+        let keys = TrustedKeys::load(&path).unwrap();
+        assert_eq!(keys.find(&public_key_hex(&key(3))), Some("ci"));
+        fs::write(&path, "[[key]]\nname = \"bad\"\npublic_key = \"abcd\"\n").unwrap();
+        assert!(TrustedKeys::load(&path).is_err());
+    }
+}
@@ -1,13 +1,211 @@
 //! Orchestration validation: type intersection rule (Output(A) ∩ Input(B) ≠ ∅).
 
+use crate::graph::{Graph, Node, NodeKind, INPUT_NODE};
 use crate::orchestration::Orchestration;
 use crate::plugin::ManifestCapabilities;
+use crate::ssml::{self, Ssml, SSML_TYPE};
+use std::collections::HashMap;
 use std::path::Path;
 
 fn types_intersect(a: &[String], b: &[String]) -> bool {
     a.iter().any(|t| b.contains(t))
 }
 
+
+fn load_manifest_capabilities(plugin_base: &Path, module: &str) -> Option<ManifestCapabilities> {
+    let toml_path = plugin_base.join(module).join("plugin.toml");
+    let s = std::fs::read_to_string(toml_path).ok()?;
+    let manifest: crate::plugin::PluginManifest = toml::from_str(&s).ok()?;
+    manifest.capabilities
+}
+
+/// Declared `(input, output)` types of the plugin in `module`.
+fn declared(plugin_base: &Path, module: &str) -> (Option<Vec<String>>, Option<Vec<String>>) {
+    let cap = load_manifest_capabilities(plugin_base, module);
+    (
+        cap.as_ref().and_then(|c| c.input.clone()),
+        cap.as_ref().and_then(|c| c.output.clone()),
+    )
+}
+
+/// Default output type for pipeline start (input is text).
+const INPUT_TEXT: &[&str] = &["text/plain", "text"];
+
+fn input_text() -> Vec<String> {
+    INPUT_TEXT.iter().map(|s| s.to_string()).collect()
+}
+
+/// Types of the `[input]` text: SSML for SSML input and documents read as SSML, plain
+/// text otherwise.
+fn graph_input(graph: &Graph) -> Vec<String> {
+    match graph.input.content_type() {
+        SSML_TYPE => vec![SSML_TYPE.to_string()],
+        _ => input_text(),
+    }
+}
+
+/// Whether a TTS engine declaring `input` can take `text`. SSML reaches engines that only
+/// take plain text downgraded, so it fits them too.
+fn tts_accepts(text: &[String], input: &[String]) -> bool {
+    types_intersect(text, input) || (text.iter().any(|t| t == SSML_TYPE) && types_intersect(&input_text(), input))
+}
+
+/// Validate that for each adjacent pair of stages, output types of stage N
+/// intersect input types of stage N+1. If a manifest does not declare
+/// input/output, that link is skipped (no type check).
+pub fn validate_orchestration_types(
+    orch: &Orchestration,
+    plugin_base: &Path,
+) -> anyhow::Result<()> {
+    validate_graph_types(&Graph::from_orchestration(orch), plugin_base)
+}
+
+/// Validate every edge of `graph`: the types a node produces must intersect the input
+/// types its consumer declares, and the inputs of a mix node must match each other.
+/// Undeclared types are not checked; a plugin without declared outputs is assumed to
+/// produce `audio/raw` or `audio/wav` (TTS), `audio/raw` (converters), or whatever it
+/// was given (pre- and post-processors).
+pub fn validate_graph_types(graph: &Graph, plugin_base: &Path) -> anyhow::Result<()> {
+    let mut produced: HashMap<&str, Vec<String>> = HashMap::from([(INPUT_NODE, graph_input(graph))]);
+    for node in graph.topological_order()? {
+        let prefix = node.branch.as_ref().map(|b| format!("output {}: ", b)).unwrap_or_default();
+        let inputs: Vec<Vec<String>> = node.input_nodes().map(|n| produced[n].clone()).collect();
+        let output = match node.kind {
+            NodeKind::Source => vec![node.source_type()],
+            NodeKind::Mix => {
+                for (i, other) in inputs.iter().enumerate().skip(1) {
+                    if !types_intersect(other, &inputs[0]) {
+                        anyhow::bail!(
+                            "{}mix {}: input {} {:?} does not match input {} {:?}",
+                            prefix,
+                            node.name,
+                            node.inputs[i],
+                            other,
+                            node.inputs[0],
+                            inputs[0]
+                        );
+                    }
+                }
+                inputs[0].clone()
+            }
+            kind => {
+                let prev = &inputs[0];
+                let (input, output) = declared(plugin_base, node.module());
+                if let Some(ref inp) = input {
+                    let fits = match kind {
+                        NodeKind::Tts => tts_accepts(prev, inp),
+                        _ => types_intersect(prev, inp),
+                    };
+                    if !fits {
+                        anyhow::bail!(
+                            "{}{} {}: pipeline output {:?} does not match input {:?}",
+                            prefix,
+                            kind.label(),
+                            node.plugin_name(),
+                            prev,
+                            inp
+                        );
+                    }
+                }
+                output.unwrap_or_else(|| match kind {
+                    NodeKind::Tts => vec!["audio/raw".to_string(), "audio/wav".to_string()],
+                    NodeKind::Converter => vec!["audio/raw".to_string()],
+                    _ => prev.clone(),
+                })
+            }
+        };
+        produced.insert(&node.name, output);
+    }
+    validate_graph_fallbacks(graph, plugin_base)
+}
+
+/// Check each enabled `[[tts.fallbacks]]` engine against the TTS stage's neighbours: it
+/// must accept the text the stage receives, and the stage after TTS must accept its
+/// output. Undeclared types are not checked.
+pub fn validate_tts_fallbacks(orch: &Orchestration, plugin_base: &Path) -> anyhow::Result<()> {
+    validate_graph_fallbacks(&Graph::from_orchestration(orch), plugin_base)
+}
+
+/// [`validate_tts_fallbacks`] for every TTS node of a graph, speaker engines included;
+/// the nodes reading a TTS node's output are its neighbours.
+pub fn validate_graph_fallbacks(graph: &Graph, plugin_base: &Path) -> anyhow::Result<()> {
+    let produced = |name: &str| -> Vec<String> {
+        match graph.node(name) {
+            Some(n) if n.kind == NodeKind::Source => vec![n.source_type()],
+            Some(n) if n.kind.is_plugin() => declared(plugin_base, n.module()).1.unwrap_or_else(input_text),
+            _ => graph_input(graph),
+        }
+    };
+    for tts in graph.nodes.iter().filter(|n| n.kind == NodeKind::Tts) {
+        let text_in = tts.input_nodes().next().map(produced).unwrap_or_else(|| graph_input(graph));
+        let next: Vec<&Node> = graph
+            .nodes
+            .iter()
+            .filter(|n| n.kind.is_plugin() && n.input_nodes().any(|i| i == tts.name))
+            .collect();
+        // Fallbacks and speaker engines stand in for the node's own engine.
+        let engines = tts
+            .fallbacks()
+            .map(|f| (format!("TTS fallback {}", f.name), f.module.as_str()))
+            .chain(tts.speaker_engines().into_iter().map(|(name, module)| (format!("speaker TTS {}", name), module)));
+        for (label, module) in engines {
+            let (input, output) = declared(plugin_base, module);
+            if let Some(ref inp) = input {
+                if !tts_accepts(&text_in, inp) {
+                    anyhow::bail!("{}: pipeline output {:?} does not match input {:?}", label, text_in, inp);
+                }
+            }
+            let Some(out) = output else { continue };
+            for n in &next {
+                if let Some(next_in) = declared(plugin_base, n.module()).0 {
+                    if !types_intersect(&out, &next_in) {
+                        anyhow::bail!(
+                            "{}: output {:?} does not match {} input {:?}",
+                            label,
+                            out,
+                            n.plugin_name(),
+                            next_in
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// SSML elements in `doc` that a TTS node or fallback of `graph` will not receive: those
+/// missing from an engine's `ssml_elements`, or all markup (`<break>` aside, which becomes
+/// silence) for engines that only take plain text. One message per engine.
+pub fn validate_ssml(graph: &Graph, doc: &Ssml, plugin_base: &Path) -> Vec<String> {
+    let mut issues = Vec::new();
+    let tags = |elements: Vec<&str>| elements.iter().map(|e| format!("<{}>", e)).collect::<Vec<_>>().join(", ");
+    for tts in graph.nodes.iter().filter(|n| n.kind == NodeKind::Tts) {
+        let engines = std::iter::once((tts.plugin_name(), tts.module()))
+            .chain(tts.fallbacks().map(|f| (f.name.as_str(), f.module.as_str())));
+        for (name, module) in engines {
+            let caps = load_manifest_capabilities(plugin_base, module);
+            let takes_ssml =
+                caps.as_ref().and_then(|c| c.input.as_ref()).is_some_and(|i| i.iter().any(|t| t == SSML_TYPE));
+            if takes_ssml {
+                let supported = caps
+                    .and_then(|c| c.ssml_elements)
+                    .unwrap_or_else(|| ssml::ELEMENTS.iter().map(|e| e.to_string()).collect());
+                let unsupported = doc.unsupported(&supported);
+                if !unsupported.is_empty() {
+                    issues.push(format!("TTS {}: does not declare SSML {}; their text is kept", name, tags(unsupported)));
+                }
+            } else {
+                let markup = doc.unsupported(&["break"]);
+                if !markup.is_empty() {
+                    issues.push(format!("TTS {}: takes plain text; SSML {} will be stripped", name, tags(markup)));
+                }
+            }
+        }
+    }
+    issues
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,100 +300,214 @@ path = "out.bin"
         let err = validate_orchestration_types(&orch, base).unwrap_err();
         assert!(err.to_string().contains("does not match input"));
     }
-}
 
-fn load_manifest_capabilities(plugin_base: &Path, module: &str) -> Option<ManifestCapabilities> {
-    let toml_path = plugin_base.join(module).join("plugin.toml");
-    let s = std::fs::read_to_string(toml_path).ok()?;
-    let manifest: crate::plugin::PluginManifest = toml::from_str(&s).ok()?;
-    manifest.capabilities
-}
-
-/// Default output type for pipeline start (input is text).
-const INPUT_TEXT: &[&str] = &["text/plain", "text"];
-
-/// Validate that for each adjacent pair of stages, output types of stage N
-/// intersect input types of stage N+1. If a manifest does not declare
-/// input/output, that link is skipped (no type check).
-pub fn validate_orchestration_types(
-    orch: &Orchestration,
-    plugin_base: &Path,
-) -> anyhow::Result<()> {
-    let mut prev_output: Vec<String> = INPUT_TEXT.iter().map(|s| s.to_string()).collect();
-
-    // Pre-processors: input text → output text
-    if let Some(ref pre) = orch.pre_processors {
-        for p in pre.iter().filter(|p| p.enabled) {
-            let cap = load_manifest_capabilities(plugin_base, &p.module);
-            let input = cap.as_ref().and_then(|c| c.input.as_ref()).map(|v| v.clone());
-            let output = cap.as_ref().and_then(|c| c.output.as_ref()).map(|v| v.clone());
-            if let Some(ref inp) = input {
-                if !types_intersect(&prev_output, inp) {
-                    anyhow::bail!(
-                        "pre-processor {}: pipeline output {:?} does not match input {:?}",
-                        p.name,
-                        prev_output,
-                        inp
-                    );
-                }
-            }
-            prev_output = output.unwrap_or_else(|| vec!["text/plain".to_string()]);
-        }
+    #[test]
+    fn validate_rejects_fallback_the_next_stage_cannot_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        let plugin = |name: &str, input: &str, output: &str| {
+            fs::create_dir_all(base.join(name)).unwrap();
+            fs::write(
+                base.join(name).join("plugin.toml"),
+                format!("name = \"{name}\"\nversion = \"0.1\"\n[capabilities]\ninput = [{input}]\noutput = [{output}]\n"),
+            )
+            .unwrap();
+        };
+        plugin("tts", "\"text/plain\"", "\"audio/wav\"");
+        plugin("backup", "\"text/plain\"", "\"audio/mpeg\"");
+        plugin("norm", "\"audio/wav\"", "\"audio/wav\"");
+        let orch_toml = |fallback_enabled: bool| {
+            format!(
+                r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "tts"
+module = "tts"
+[[tts.fallbacks]]
+name = "backup"
+module = "backup"
+enabled = {fallback_enabled}
+[[post_processors]]
+name = "norm"
+module = "norm"
+[output]
+type = "file"
+path = "out.bin"
+"#
+            )
+        };
+        let orch = Orchestration::from_toml(&orch_toml(true)).unwrap();
+        let err = validate_orchestration_types(&orch, base).unwrap_err();
+        assert!(err.to_string().contains("TTS fallback backup"), "{err}");
+        let orch = Orchestration::from_toml(&orch_toml(false)).unwrap();
+        assert!(validate_orchestration_types(&orch, base).is_ok());
     }
 
-    // TTS: text → audio
-    let cap = load_manifest_capabilities(plugin_base, &orch.tts.module);
-    let tts_input = cap.as_ref().and_then(|c| c.input.as_ref()).map(|v| v.clone());
-    let tts_output = cap.as_ref().and_then(|c| c.output.as_ref()).map(|v| v.clone());
-    if let Some(ref inp) = tts_input {
-        if !types_intersect(&prev_output, inp) {
-            anyhow::bail!(
-                "TTS {}: pipeline output {:?} does not match input {:?}",
-                orch.tts.name,
-                prev_output,
-                inp
-            );
+    #[test]
+    fn validate_checks_each_output_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        for (name, input, output) in [("tts", "text/plain", "audio/wav"), ("lame", "audio/wav", "audio/mpeg"), ("tag", "audio/mpeg", "audio/mpeg")] {
+            fs::create_dir_all(base.join(name)).unwrap();
+            fs::write(
+                base.join(name).join("plugin.toml"),
+                format!("name = \"{name}\"\nversion = \"0.1\"\n[capabilities]\ninput = [\"{input}\"]\noutput = [\"{output}\"]\n"),
+            )
+            .unwrap();
         }
+        let orch = |branch_post: &str| {
+            Orchestration::from_toml(&format!(
+                r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "tts"
+module = "tts"
+[[outputs]]
+name = "mp3"
+path = "out.mp3"
+[[outputs.converters]]
+name = "lame"
+module = "lame"
+[[outputs]]
+name = "wav"
+path = "out.wav"
+[[outputs.post_processors]]
+name = "{branch_post}"
+module = "{branch_post}"
+"#
+            ))
+            .unwrap()
+        };
+        // `tag` reads MP3, which only the mp3 branch produces.
+        assert!(validate_orchestration_types(&orch("lame"), base).is_ok());
+        let err = validate_orchestration_types(&orch("tag"), base).unwrap_err();
+        assert!(err.to_string().starts_with("output wav: post-processor tag"), "{err}");
     }
-    prev_output = tts_output.unwrap_or_else(|| vec!["audio/raw".to_string(), "audio/wav".to_string()]);
-
-    // Converters
-    if let Some(ref conv) = orch.audio_converters {
-        for node in conv.iter().filter(|c| c.enabled) {
-            let cap = load_manifest_capabilities(plugin_base, &node.module);
-            let input = cap.as_ref().and_then(|c| c.input.as_ref()).map(|v| v.clone());
-            let output = cap.as_ref().and_then(|c| c.output.as_ref()).map(|v| v.clone());
-            if let Some(ref inp) = input {
-                if !types_intersect(&prev_output, inp) {
-                    anyhow::bail!(
-                        "converter {}: pipeline output {:?} does not match input {:?}",
-                        node.name,
-                        prev_output,
-                        inp
-                    );
-                }
-            }
-            prev_output = output.unwrap_or_else(|| vec!["audio/raw".to_string()]);
+
+    #[test]
+    fn validate_graph_checks_every_edge_and_mix_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        for (name, input, output) in [("wav-tts", "text/plain", "audio/wav"), ("mp3-tts", "text/plain", "audio/mpeg"), ("lame", "audio/wav", "audio/mpeg")] {
+            fs::create_dir_all(base.join(name)).unwrap();
+            fs::write(
+                base.join(name).join("plugin.toml"),
+                format!("name = \"{name}\"\nversion = \"0.1\"\n[capabilities]\ninput = [\"{input}\"]\noutput = [\"{output}\"]\n"),
+            )
+            .unwrap();
         }
+        let graph = |second: &str, encode_from: &str| {
+            Graph::from_toml(&format!(
+                r#"
+version = 2
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[[nodes]]
+name = "a"
+kind = "tts"
+module = "wav-tts"
+inputs = ["input.text"]
+[[nodes]]
+name = "b"
+kind = "tts"
+module = "{second}"
+inputs = ["input.text"]
+[[nodes]]
+name = "both"
+kind = "mix"
+inputs = ["a", "b"]
+[[nodes]]
+name = "encode"
+kind = "converter"
+module = "lame"
+inputs = ["{encode_from}"]
+[output]
+from = "encode"
+path = "out.mp3"
+"#
+            ))
+            .unwrap()
+        };
+        assert!(validate_graph_types(&graph("wav-tts", "both"), base).is_ok());
+        let err = validate_graph_types(&graph("mp3-tts", "a"), base).unwrap_err();
+        assert!(err.to_string().starts_with("mix both: input b [\"audio/mpeg\"] does not match input a"), "{err}");
+        // Without the mix, `encode` reads the MP3 voice directly.
+        let mut g = graph("mp3-tts", "b.out");
+        g.nodes.remove(2);
+        let err = validate_graph_types(&g, base).unwrap_err();
+        assert!(err.to_string().starts_with("converter encode: pipeline output [\"audio/mpeg\"]"), "{err}");
     }
 
-    // Post-processors
-    if let Some(ref post) = orch.post_processors {
-        for p in post.iter().filter(|p| p.enabled) {
-            let cap = load_manifest_capabilities(plugin_base, &p.module);
-            let input = cap.as_ref().and_then(|c| c.input.as_ref()).map(|v| v.clone());
-            if let Some(ref inp) = input {
-                if !types_intersect(&prev_output, inp) {
-                    anyhow::bail!(
-                        "post-processor {}: pipeline output {:?} does not match input {:?}",
-                        p.name,
-                        prev_output,
-                        inp
-                    );
-                }
-            }
+    #[test]
+    fn validate_ssml_input_and_elements_per_engine() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        let plugins = [
+            ("neural", "[\"text/plain\", \"application/ssml+xml\"]\nssml_elements = [\"break\", \"prosody\"]"),
+            ("basic", "[\"text/plain\"]"),
+            ("lower", "[\"text/plain\"]\noutput = [\"text/plain\"]"),
+        ];
+        for (name, input) in plugins {
+            fs::create_dir_all(base.join(name)).unwrap();
+            fs::write(
+                base.join(name).join("plugin.toml"),
+                format!("name = \"{name}\"\nversion = \"0.1\"\n[capabilities]\ninput = {input}\n"),
+            )
+            .unwrap();
         }
-    }
+        let orch = |pre: &str| {
+            Graph::from_toml(&format!(
+                r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "ssml"
+source = "in.xml"
+{pre}
+[tts]
+name = "neural"
+module = "neural"
+[[tts.fallbacks]]
+name = "basic"
+module = "basic"
+[output]
+type = "file"
+path = "out.wav"
+"#
+            ))
+            .unwrap()
+        };
+        // TTS engines take SSML, downgraded if need be; a plain-text pre-processor does not.
+        assert!(validate_graph_types(&orch(""), base).is_ok());
+        let err = validate_graph_types(&orch("[[pre_processors]]\nname = \"lower\"\nmodule = \"lower\""), base).unwrap_err();
+        assert!(err.to_string().contains("pipeline output [\"application/ssml+xml\"]"), "{err}");
 
-    Ok(())
+        let doc = Ssml::parse("<speak><prosody rate=\"slow\">Hi</prosody><break/><emphasis>there</emphasis></speak>").unwrap();
+        assert_eq!(
+            validate_ssml(&orch(""), &doc, base),
+            [
+                "TTS neural: does not declare SSML <emphasis>; their text is kept",
+                "TTS basic: takes plain text; SSML <emphasis>, <prosody> will be stripped",
+            ]
+        );
+    }
 }
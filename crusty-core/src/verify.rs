@@ -0,0 +1,665 @@
+//! Plugin conformance suite: checks a plugin directory against its own manifest and
+//! returns a structured report instead of printing. Intended for plugin authors' CI.
+
+use crate::audio::{Audio, DEFAULT_RAW_FORMAT};
+use crate::pipeline::plugin_executable;
+use crate::plugin::{Lifecycle, PluginManifest, PluginOptions, Transport};
+use crate::protocol::{read_frame, write_frame, ErrorFrame, Handshake, PROTOCOL_VERSION};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// Per-invocation limit; a plugin that exceeds it fails the check that ran it.
+const RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+const VALID_TYPES: &[&str] = &["pre", "preprocessor", "tts", "synth", "post", "postprocessor", "converter", "encode"];
+const OPTION_TYPES: &[&str] = &["string", "float", "number", "int", "integer", "bool", "boolean", "enum"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+            CheckStatus::Skip => "skip",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+/// Outcome of [`verify_plugin_dir`]: one entry per check, in run order.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    pub plugin: String,
+    pub path: String,
+    pub checks: Vec<CheckResult>,
+}
+
+impl VerificationReport {
+    /// True when no check failed (warnings and skips are allowed).
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+
+    pub fn check(&self, name: &str) -> Option<&CheckResult> {
+        self.checks.iter().find(|c| c.name == name)
+    }
+
+    fn push(&mut self, name: &str, status: CheckStatus, message: impl Into<String>) {
+        self.checks.push(CheckResult {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        });
+    }
+}
+
+/// Result of one raw plugin invocation.
+struct Invocation {
+    status: Option<ExitStatus>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    timed_out: bool,
+}
+
+impl Invocation {
+    fn succeeded(&self) -> bool {
+        self.status.is_some_and(|s| s.success())
+    }
+
+    fn describe_failure(&self) -> String {
+        if self.timed_out {
+            return format!("timed out after {:?}", RUN_TIMEOUT);
+        }
+        match self.status {
+            Some(s) => format!("exited with {}", s),
+            None => "could not be run".to_string(),
+        }
+    }
+}
+
+/// Everything needed to invoke the plugin the way the executor would.
+struct Target<'a> {
+    executable: &'a Path,
+    manifest: &'a PluginManifest,
+    input_type: String,
+    output_type: String,
+}
+
+impl Target<'_> {
+    fn transport(&self) -> Transport {
+        self.manifest.transport()
+    }
+
+    fn invoke(&self, input: &[u8], opts: &PluginOptions) -> Invocation {
+        let framed = self.transport() == Transport::Framed;
+        let persistent = self.manifest.lifecycle() == Lifecycle::Persistent;
+        let mut cmd = Command::new(self.executable);
+        cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        if persistent {
+            cmd.env("PLUGIN_LIFECYCLE", "persistent");
+        } else {
+            cmd.env("PLUGIN_INPUT", std::str::from_utf8(input).unwrap_or(""));
+            for (k, v) in opts {
+                cmd.env(format!("PLUGIN_OPT_{}", k.to_uppercase().replace('-', "_")), v);
+            }
+        }
+        let Ok(mut child) = cmd.spawn() else {
+            return Invocation { status: None, stdout: Vec::new(), stderr: Vec::new(), timed_out: false };
+        };
+
+        let mut stdin_bytes = Vec::new();
+        if framed {
+            let config = serde_json::Value::Object(
+                opts.iter()
+                    .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+                    .collect(),
+            );
+            let handshake = Handshake::new(&self.input_type, &self.output_type, config);
+            let _ = write_frame(&mut stdin_bytes, &serde_json::to_vec(&handshake).unwrap_or_default());
+            if !input.is_empty() {
+                let _ = write_frame(&mut stdin_bytes, input);
+            }
+            if persistent {
+                let _ = write_frame(&mut stdin_bytes, &[]);
+            }
+        } else {
+            stdin_bytes.extend_from_slice(input);
+        }
+
+        let mut stdin = child.stdin.take();
+        let mut stdout = child.stdout.take();
+        let mut stderr = child.stderr.take();
+        let writer = std::thread::spawn(move || {
+            if let Some(ref mut w) = stdin {
+                let _ = w.write_all(&stdin_bytes);
+            }
+        });
+        let out_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(ref mut r) = stdout {
+                let _ = r.read_to_end(&mut buf);
+            }
+            buf
+        });
+        let err_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(ref mut r) = stderr {
+                let _ = r.read_to_end(&mut buf);
+            }
+            buf
+        });
+
+        let deadline = Instant::now() + RUN_TIMEOUT;
+        let mut timed_out = false;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(s)) => break Some(s),
+                Ok(None) if Instant::now() >= deadline => {
+                    timed_out = true;
+                    let _ = child.kill();
+                    break child.wait().ok();
+                }
+                Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+                Err(_) => break None,
+            }
+        };
+        let _ = writer.join();
+        Invocation {
+            status,
+            stdout: out_reader.join().unwrap_or_default(),
+            stderr: err_reader.join().unwrap_or_default(),
+            timed_out,
+        }
+    }
+
+    /// Payload bytes from a run: raw stdout for env plugins, concatenated frames for framed ones.
+    fn payload(&self, inv: &Invocation) -> Result<Vec<u8>, String> {
+        if self.transport() == Transport::Env {
+            return Ok(inv.stdout.clone());
+        }
+        decode_frames(&inv.stdout).map(|frames| frames.concat())
+    }
+
+    /// An error frame in the output (framed) or on stderr (env), if the plugin sent one.
+    fn error_frame(&self, inv: &Invocation) -> Option<ErrorFrame> {
+        let from_frames = decode_frames(&inv.stdout)
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|f| serde_json::from_slice::<ErrorFrame>(&f).ok());
+        let from_stderr = String::from_utf8_lossy(&inv.stderr)
+            .lines()
+            .filter_map(|l| serde_json::from_str::<ErrorFrame>(l.trim()).ok())
+            .collect::<Vec<_>>();
+        from_frames.chain(from_stderr).find(|e| e.typ == "error")
+    }
+}
+
+/// Split a framed stream into payloads, stopping at EOS. Errors on a truncated frame.
+fn decode_frames(bytes: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut cur = std::io::Cursor::new(bytes);
+    let mut frames = Vec::new();
+    loop {
+        let pos = cur.position() as usize;
+        if pos == bytes.len() {
+            return Ok(frames);
+        }
+        match read_frame(&mut cur) {
+            Ok(Some(f)) if f.is_empty() => return Ok(frames),
+            Ok(Some(f)) => frames.push(f),
+            Ok(None) | Err(_) => return Err(format!("truncated frame at byte {}", pos)),
+        }
+    }
+}
+
+/// 100 ms of 16-bit mono silence as WAV, used as sample audio input.
+fn sample_wav() -> Vec<u8> {
+    Audio::silence(DEFAULT_RAW_FORMAT, Duration::from_millis(100)).to_wav()
+}
+
+fn sample_input(plugin_type: &str, input_type: &str) -> Vec<u8> {
+    match plugin_type {
+        "pre" | "preprocessor" => b"Test input for pre-processor.".to_vec(),
+        "tts" | "synth" => b"Hello, world!".to_vec(),
+        _ if input_type == "audio/wav" => sample_wav(),
+        _ => vec![0u8; 4410],
+    }
+}
+
+/// A value that is invalid for the declared option type, if one can be constructed.
+fn invalid_option_value(spec: &toml::Value) -> Option<String> {
+    let tbl = spec.as_table()?;
+    if tbl.contains_key("choices") || tbl.contains_key("enum") {
+        return Some("__not_a_valid_choice__".into());
+    }
+    match tbl.get("type").and_then(|t| t.as_str())? {
+        "float" | "number" | "int" | "integer" => Some("not-a-number".into()),
+        "bool" | "boolean" => Some("maybe".into()),
+        _ => None,
+    }
+}
+
+fn check_manifest(report: &mut VerificationReport, raw: &toml::Value, manifest: &PluginManifest) {
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+    match raw.get("type").and_then(|t| t.as_str()) {
+        Some(t) if VALID_TYPES.contains(&t) => {}
+        Some(t) => problems.push(format!("unknown type {:?}", t)),
+        None => warnings.push("no `type`; role is inferred from capabilities".to_string()),
+    }
+    match manifest.api_version.as_deref() {
+        Some(v) if v.split('.').take(2).eq(PROTOCOL_VERSION.split('.').take(2)) => {}
+        Some(v) => problems.push(format!("protocol_version {} incompatible with core {}", v, PROTOCOL_VERSION)),
+        None => warnings.push("no protocol_version".to_string()),
+    }
+    if let Some(t) = manifest.transport.as_deref() {
+        if t != "env" && t != "framed" {
+            problems.push(format!("unknown transport {:?}", t));
+        }
+    }
+    if let Some(l) = manifest.lifecycle.as_deref() {
+        if l != "oneshot" && l != "persistent" {
+            problems.push(format!("unknown lifecycle {:?}", l));
+        }
+    }
+    match manifest.capabilities.as_ref() {
+        Some(c) if c.input.is_some() && c.output.is_some() => {}
+        _ => warnings.push("capabilities.input/output not declared; type checks are skipped".to_string()),
+    }
+    if let Some(opts) = manifest.options.as_ref().and_then(|o| o.as_table()) {
+        for (name, spec) in opts {
+            let Some(spec) = spec.as_table() else {
+                warnings.push(format!("option {} has no schema table", name));
+                continue;
+            };
+            match spec.get("type").and_then(|t| t.as_str()) {
+                Some(t) if OPTION_TYPES.contains(&t) => {}
+                Some(t) => problems.push(format!("option {}: unknown type {:?}", name, t)),
+                None => problems.push(format!("option {}: missing type", name)),
+            }
+        }
+    }
+    let (status, msg) = if !problems.is_empty() {
+        (CheckStatus::Fail, problems.join("; "))
+    } else if !warnings.is_empty() {
+        (CheckStatus::Warn, warnings.join("; "))
+    } else {
+        (CheckStatus::Pass, "manifest is complete".to_string())
+    };
+    report.push("manifest", status, msg);
+}
+
+/// Run the conformance suite against the plugin in `plugin_dir`.
+pub fn verify_plugin_dir(plugin_dir: &Path) -> VerificationReport {
+    let mut report = VerificationReport {
+        plugin: plugin_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        path: plugin_dir.display().to_string(),
+        checks: Vec::new(),
+    };
+
+    let contents = match std::fs::read_to_string(plugin_dir.join("plugin.toml")) {
+        Ok(c) => c,
+        Err(e) => {
+            report.push("manifest", CheckStatus::Fail, format!("read plugin.toml: {}", e));
+            return report;
+        }
+    };
+    let (raw, manifest) = match (toml::from_str::<toml::Value>(&contents), toml::from_str::<PluginManifest>(&contents)) {
+        (Ok(raw), Ok(m)) => (raw, m),
+        (_, Err(e)) | (Err(e), _) => {
+            report.push("manifest", CheckStatus::Fail, format!("parse plugin.toml: {}", e));
+            return report;
+        }
+    };
+    report.plugin = manifest.name.clone();
+    check_manifest(&mut report, &raw, &manifest);
+
+    let executable = match plugin_executable(&plugin_dir.to_string_lossy(), Some(&manifest)) {
+        Ok(Some(executable)) => executable,
+        Ok(None) => {
+            report.push("executable", CheckStatus::Fail, "no entrypoint, run.sh or run.py found");
+            return report;
+        }
+        Err(e) => {
+            report.push("executable", CheckStatus::Fail, e.to_string());
+            return report;
+        }
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&executable).map(|m| m.permissions().mode()).unwrap_or(0);
+        if mode & 0o111 == 0 {
+            report.push("executable", CheckStatus::Fail, format!("{} is not executable", executable.display()));
+            return report;
+        }
+    }
+    report.push("executable", CheckStatus::Pass, executable.display().to_string());
+
+    let caps = manifest.capabilities.clone().unwrap_or_default();
+    let plugin_type = raw.get("type").and_then(|t| t.as_str()).unwrap_or("pre").to_string();
+    let default_in = if matches!(plugin_type.as_str(), "pre" | "preprocessor" | "tts" | "synth") {
+        "text/plain"
+    } else {
+        "audio/wav"
+    };
+    let target = Target {
+        executable: &executable,
+        manifest: &manifest,
+        input_type: caps.input.as_ref().and_then(|i| i.first().cloned()).unwrap_or_else(|| default_in.to_string()),
+        output_type: caps.output.as_ref().and_then(|o| o.first().cloned()).unwrap_or_else(|| "audio/raw".to_string()),
+    };
+    let opts = PluginOptions::new();
+    let sample = sample_input(&plugin_type, &target.input_type);
+
+    // Handshake: framed plugins must answer with well-formed, terminated frames.
+    let first = target.invoke(&sample, &opts);
+    if target.transport() == Transport::Framed {
+        match decode_frames(&first.stdout) {
+            Ok(_) if first.succeeded() => report.push("handshake", CheckStatus::Pass, "frames well-formed"),
+            Ok(_) => report.push("handshake", CheckStatus::Fail, first.describe_failure()),
+            Err(e) => report.push("handshake", CheckStatus::Fail, e),
+        }
+    } else {
+        report.push("handshake", CheckStatus::Skip, "env transport has no handshake");
+    }
+
+    // Sample run and declared output type.
+    let first_payload = if first.succeeded() { target.payload(&first).ok() } else { None };
+    match &first_payload {
+        Some(out) if !out.is_empty() => {
+            report.push("sample_run", CheckStatus::Pass, format!("{} bytes", out.len()))
+        }
+        Some(_) => report.push("sample_run", CheckStatus::Fail, "empty output"),
+        None => report.push("sample_run", CheckStatus::Fail, first.describe_failure()),
+    }
+    match (&first_payload, target.output_type.as_str()) {
+        (None, _) => report.push("output_type", CheckStatus::Skip, "no output to inspect"),
+        (Some(out), "audio/wav") => match Audio::parse_wav(out) {
+            Ok(audio) => report.push(
+                "output_type",
+                CheckStatus::Pass,
+                format!(
+                    "valid WAV: {} Hz, {} ch, {}, {:.2} s",
+                    audio.format.sample_rate,
+                    audio.format.channels,
+                    audio.format.sample_format.as_str(),
+                    audio.duration().as_secs_f64()
+                ),
+            ),
+            Err(e) => report.push("output_type", CheckStatus::Fail, format!("declared audio/wav: {}", e)),
+        },
+        (Some(out), t) if t.starts_with("text/") => match std::str::from_utf8(out) {
+            Ok(_) => report.push("output_type", CheckStatus::Pass, "valid UTF-8"),
+            Err(e) => report.push("output_type", CheckStatus::Fail, format!("declared {}: {}", t, e)),
+        },
+        (Some(_), t) => report.push("output_type", CheckStatus::Skip, format!("no structural check for {}", t)),
+    }
+
+    // Edge-case inputs for text-consuming plugins.
+    if target.input_type.starts_with("text") {
+        let empty = target.invoke(b"", &opts);
+        if empty.succeeded() {
+            report.push("empty_input", CheckStatus::Pass, "accepted");
+        } else if empty.timed_out || empty.status.is_none_or(|s| s.code().is_none()) {
+            report.push("empty_input", CheckStatus::Fail, empty.describe_failure());
+        } else if target.error_frame(&empty).is_some() {
+            report.push("empty_input", CheckStatus::Pass, "rejected with error frame");
+        } else {
+            report.push("empty_input", CheckStatus::Warn, format!("{} without an error frame", empty.describe_failure()));
+        }
+
+        let unicode = "Grüße — naïve café, 你好, 👋🏽";
+        let inv = target.invoke(unicode.as_bytes(), &opts);
+        match (inv.succeeded(), target.payload(&inv)) {
+            (true, Ok(out)) if target.output_type.starts_with("text/") && std::str::from_utf8(&out).is_err() => {
+                report.push("unicode_input", CheckStatus::Fail, "output is not valid UTF-8")
+            }
+            (true, Ok(_)) => report.push("unicode_input", CheckStatus::Pass, "accepted"),
+            (true, Err(e)) => report.push("unicode_input", CheckStatus::Fail, e),
+            (false, _) => report.push("unicode_input", CheckStatus::Fail, inv.describe_failure()),
+        }
+    } else {
+        report.push("empty_input", CheckStatus::Skip, "plugin does not take text");
+        report.push("unicode_input", CheckStatus::Skip, "plugin does not take text");
+    }
+
+    // Bad options: either tolerate them or fail cleanly with an error frame.
+    let bad: PluginOptions = manifest
+        .options
+        .as_ref()
+        .and_then(|o| o.as_table())
+        .map(|t| t.iter().filter_map(|(k, v)| invalid_option_value(v).map(|bad| (k.clone(), bad))).collect())
+        .unwrap_or_default();
+    if bad.is_empty() {
+        report.push("bad_options", CheckStatus::Skip, "no typed options to violate");
+    } else {
+        let inv = target.invoke(&sample, &bad);
+        if inv.timed_out {
+            report.push("bad_options", CheckStatus::Fail, inv.describe_failure());
+        } else if inv.succeeded() {
+            report.push("bad_options", CheckStatus::Warn, "invalid option values were accepted silently");
+        } else if let Some(e) = target.error_frame(&inv) {
+            report.push("bad_options", CheckStatus::Pass, format!("rejected: {}", e.message));
+        } else {
+            report.push("bad_options", CheckStatus::Fail, format!("{} without an error frame", inv.describe_failure()));
+        }
+    }
+
+    // Determinism: same input and options should give the same bytes.
+    match &first_payload {
+        Some(out) => {
+            let again = target.invoke(&sample, &opts);
+            match target.payload(&again) {
+                Ok(o) if again.succeeded() && &o == out => {
+                    report.push("deterministic", CheckStatus::Pass, "identical output on rerun")
+                }
+                Ok(_) if again.succeeded() && !target.manifest.is_deterministic() => {
+                    report.push("deterministic", CheckStatus::Pass, "output varies; declared deterministic = false")
+                }
+                Ok(_) if again.succeeded() => report.push(
+                    "deterministic",
+                    CheckStatus::Warn,
+                    "output differs between identical runs; declare deterministic = false to bypass the cache",
+                ),
+                _ => report.push("deterministic", CheckStatus::Fail, "rerun failed"),
+            }
+        }
+        None => report.push("deterministic", CheckStatus::Skip, "sample run failed"),
+    }
+
+    check_fixture(&mut report, &target, plugin_dir);
+    report
+}
+
+/// `fixtures/<stem>.*` in the plugin directory.
+fn fixture_file(plugin_dir: &Path, stem: &str) -> Option<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(plugin_dir.join("fixtures"))
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.file_stem().is_some_and(|s| s == stem))
+        .collect();
+    files.sort();
+    files.into_iter().next()
+}
+
+/// The plugin's own test case: `fixtures/input.*` run with the default options, and
+/// compared with `fixtures/expected.*` when there is one.
+fn check_fixture(report: &mut VerificationReport, target: &Target<'_>, plugin_dir: &Path) {
+    let Some(input_path) = fixture_file(plugin_dir, "input") else {
+        report.push("fixture", CheckStatus::Skip, "no fixtures/input.*");
+        return;
+    };
+    let shown = |p: &Path| Path::new("fixtures").join(p.file_name().unwrap_or_default()).display().to_string();
+    let input = match std::fs::read(&input_path) {
+        Ok(input) => input,
+        Err(e) => return report.push("fixture", CheckStatus::Fail, format!("read {}: {}", shown(&input_path), e)),
+    };
+    let inv = target.invoke(&input, &target.manifest.default_options());
+    let out = match target.payload(&inv) {
+        Ok(out) if inv.succeeded() => out,
+        Ok(_) => return report.push("fixture", CheckStatus::Fail, format!("{}: {}", shown(&input_path), inv.describe_failure())),
+        Err(e) => return report.push("fixture", CheckStatus::Fail, e),
+    };
+    let Some(expected_path) = fixture_file(plugin_dir, "expected") else {
+        return report.push("fixture", CheckStatus::Pass, format!("{} bytes from {}", out.len(), shown(&input_path)));
+    };
+    match std::fs::read(&expected_path) {
+        Ok(expected) if expected == out => {
+            report.push("fixture", CheckStatus::Pass, format!("output matches {}", shown(&expected_path)))
+        }
+        Ok(expected) => report.push(
+            "fixture",
+            CheckStatus::Fail,
+            format!("output ({} bytes) differs from {} ({} bytes)", out.len(), shown(&expected_path), expected.len()),
+        ),
+        Err(e) => report.push("fixture", CheckStatus::Fail, format!("read {}: {}", shown(&expected_path), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn plugin(dir: &Path, manifest: &str, script: &str) {
+        fs::write(dir.join("plugin.toml"), manifest).unwrap();
+        let run = dir.join("run.sh");
+        fs::write(&run, script).unwrap();
+        fs::set_permissions(&run, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn sample_wav_parses_and_truncation_is_detected() {
+        assert_eq!(Audio::parse_wav(&sample_wav()).unwrap().frames(), 2205);
+        let mut truncated = sample_wav();
+        truncated.truncate(60);
+        assert!(Audio::parse_wav(&truncated).is_err());
+    }
+
+    #[test]
+    fn decode_frames_detects_truncation() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"abc").unwrap();
+        assert_eq!(decode_frames(&buf).unwrap(), vec![b"abc".to_vec()]);
+        buf.extend_from_slice(&[9, 0, 0, 0, b'x']);
+        assert!(decode_frames(&buf).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn echo_tts_passes_suite() {
+        let dir = tempfile::tempdir().unwrap();
+        plugin(
+            dir.path(),
+            r#"
+name = "echo"
+version = "0.1.0"
+protocol_version = "0.1"
+type = "tts"
+[capabilities]
+input = ["text/plain"]
+output = ["audio/raw"]
+"#,
+            "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n",
+        );
+        let report = verify_plugin_dir(dir.path());
+        assert!(report.passed(), "{:?}", report);
+        assert_eq!(report.plugin, "echo");
+        assert_eq!(report.check("manifest").unwrap().status, CheckStatus::Pass);
+        assert_eq!(report.check("handshake").unwrap().status, CheckStatus::Skip);
+        assert_eq!(report.check("deterministic").unwrap().status, CheckStatus::Pass);
+        assert_eq!(report.check("fixture").unwrap().status, CheckStatus::Skip);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn fixture_output_is_compared_with_expected() {
+        let dir = tempfile::tempdir().unwrap();
+        plugin(
+            dir.path(),
+            r#"
+name = "upper"
+version = "0.1.0"
+protocol_version = "0.1"
+type = "pre"
+[capabilities]
+input = ["text/plain"]
+output = ["text/plain"]
+[options]
+case = { type = "string", default = "upper" }
+"#,
+            "#!/bin/sh
+[ \"$PLUGIN_OPT_CASE\" = upper ] || exit 1
+printf '%s' \"$PLUGIN_INPUT\" | tr a-z A-Z
+",
+        );
+        fs::create_dir(dir.path().join("fixtures")).unwrap();
+        fs::write(dir.path().join("fixtures/input.txt"), "hello").unwrap();
+        let report = verify_plugin_dir(dir.path());
+        assert_eq!(report.check("fixture").unwrap().status, CheckStatus::Pass, "{:?}", report);
+
+        fs::write(dir.path().join("fixtures/expected.txt"), "HELLO").unwrap();
+        let report = verify_plugin_dir(dir.path());
+        let fixture = report.check("fixture").unwrap();
+        assert_eq!(fixture.status, CheckStatus::Pass);
+        assert_eq!(fixture.message, "output matches fixtures/expected.txt");
+
+        fs::write(dir.path().join("fixtures/expected.txt"), "hello").unwrap();
+        let report = verify_plugin_dir(dir.path());
+        assert!(!report.passed());
+        assert!(report.check("fixture").unwrap().message.contains("differs from fixtures/expected.txt"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn declared_wav_that_is_not_wav_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        plugin(
+            dir.path(),
+            r#"
+name = "liar"
+version = "0.1.0"
+protocol_version = "0.1"
+type = "tts"
+[capabilities]
+input = ["text/plain"]
+output = ["audio/wav"]
+[options]
+rate = { type = "float", default = 1.0 }
+"#,
+            "#!/bin/sh\ncase \"$PLUGIN_OPT_RATE\" in not-a-number) exit 2;; esac\nprintf 'RIFFjunk'\n",
+        );
+        let report = verify_plugin_dir(dir.path());
+        assert!(!report.passed());
+        assert_eq!(report.check("output_type").unwrap().status, CheckStatus::Fail);
+        assert_eq!(report.check("bad_options").unwrap().status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn missing_manifest_fails_early() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = verify_plugin_dir(dir.path());
+        assert!(!report.passed());
+        assert_eq!(report.checks.len(), 1);
+    }
+}
@@ -0,0 +1,457 @@
+//! Persistent plugin workers: a pool of warm subprocesses serving framed jobs.
+//!
+//! Per job, core writes a handshake frame, the payload frames and a zero-length EOS frame;
+//! the worker answers with output frames terminated by its own EOS frame, then waits for
+//! the next handshake. Closing stdin asks the worker to exit.
+
+use crate::cancel::{kill_process_group, own_process_group, CancellationToken, Cancelled, POLL_INTERVAL};
+use crate::plugin::WorkerSettings;
+use crate::protocol::{read_frame, write_frame, Handshake};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Pool-wide defaults; a plugin's `[worker]` table overrides them.
+#[derive(Debug, Clone)]
+pub struct WorkerPoolConfig {
+    /// Max warm processes per plugin.
+    pub pool_size: usize,
+    /// Recycle a worker after this many jobs (`None` = never).
+    pub max_jobs: Option<u64>,
+    /// Shut down workers idle for longer than this.
+    pub idle_timeout: Duration,
+}
+
+impl Default for WorkerPoolConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: 1,
+            max_jobs: None,
+            idle_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    jobs: u64,
+    last_used: Instant,
+    idle_timeout: Duration,
+}
+
+impl Worker {
+    fn spawn(executable: &Path, idle_timeout: Duration) -> anyhow::Result<Self> {
+        let mut cmd = Command::new(executable);
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .env("PLUGIN_LIFECYCLE", "persistent");
+        // Own process group, so cancelling a job can kill the worker and its children.
+        own_process_group(&mut cmd);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("spawn worker {:?}: {}", executable, e))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow::anyhow!("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("no stdout"))?;
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+            jobs: 0,
+            last_used: Instant::now(),
+            idle_timeout,
+        })
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// One request/response exchange. Input is written from a second thread so a worker
+    /// that streams output before draining stdin cannot deadlock us. Cancelling, or
+    /// passing `timeout`, kills the worker's process group, which ends the exchange with
+    /// an error; `timed_out` records which.
+    fn request(
+        &mut self,
+        handshake: &Handshake,
+        input: &[u8],
+        cancel: Option<&CancellationToken>,
+        timeout: Option<Duration>,
+        timed_out: &AtomicBool,
+    ) -> anyhow::Result<Vec<u8>> {
+        let handshake_json = serde_json::to_vec(handshake)?;
+        let pid = self.child.id();
+        let Worker { stdin, stdout, .. } = self;
+        let finished = AtomicBool::new(false);
+        let deadline = timeout.map(|t| Instant::now() + t);
+        std::thread::scope(|s| {
+            if cancel.is_some() || deadline.is_some() {
+                let finished = &finished;
+                s.spawn(move || {
+                    while !finished.load(Ordering::SeqCst) {
+                        let expired = deadline.is_some_and(|d| Instant::now() >= d);
+                        if expired || cancel.is_some_and(CancellationToken::is_cancelled) {
+                            timed_out.store(expired, Ordering::SeqCst);
+                            kill_process_group(pid);
+                            return;
+                        }
+                        std::thread::sleep(POLL_INTERVAL);
+                    }
+                });
+            }
+            let writer = s.spawn(move || -> std::io::Result<()> {
+                write_frame(&mut *stdin, &handshake_json)?;
+                if !input.is_empty() {
+                    write_frame(&mut *stdin, input)?;
+                }
+                write_frame(&mut *stdin, &[])
+            });
+            let mut out = Vec::new();
+            let read_result = loop {
+                match read_frame(&mut *stdout) {
+                    Ok(Some(chunk)) if chunk.is_empty() => break Ok(()),
+                    Ok(Some(chunk)) => out.extend_from_slice(&chunk),
+                    Ok(None) => break Err(anyhow::anyhow!("worker exited mid-job")),
+                    Err(e) => break Err(e.into()),
+                }
+            };
+            finished.store(true, Ordering::SeqCst);
+            let write_result = writer.join().map_err(|_| anyhow::anyhow!("worker writer panicked"))?;
+            read_result?;
+            write_result?;
+            Ok(out)
+        })
+    }
+
+    fn shutdown(self) {
+        let Worker { mut child, stdin, .. } = self;
+        drop(stdin);
+        // Give a well-behaved worker a moment to exit on EOF before killing it.
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[derive(Default)]
+struct PoolInner {
+    idle: HashMap<String, Vec<Worker>>,
+    /// Live workers (idle + checked out) per executable.
+    live: HashMap<String, usize>,
+}
+
+/// Warm workers keyed by executable path.
+pub struct WorkerPool {
+    config: WorkerPoolConfig,
+    inner: Mutex<PoolInner>,
+    returned: Condvar,
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self::new(WorkerPoolConfig::default())
+    }
+}
+
+impl WorkerPool {
+    pub fn new(config: WorkerPoolConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(PoolInner::default()),
+            returned: Condvar::new(),
+        }
+    }
+
+    /// Run one job on a warm worker for `executable`, spawning one if the pool has room.
+    /// A worker that crashes mid-job is discarded and the error returned; the next job
+    /// gets a fresh process.
+    pub fn run(
+        &self,
+        executable: &Path,
+        settings: Option<&WorkerSettings>,
+        handshake: &Handshake,
+        input: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        self.run_cancellable(executable, settings, handshake, input, None, None)
+    }
+
+    /// Like [`WorkerPool::run`]; cancelling `cancel` kills the worker mid-job and returns
+    /// [`Cancelled`], and a job running past `timeout` is killed the same way.
+    pub fn run_cancellable(
+        &self,
+        executable: &Path,
+        settings: Option<&WorkerSettings>,
+        handshake: &Handshake,
+        input: &[u8],
+        cancel: Option<&CancellationToken>,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Vec<u8>> {
+        if let Some(cancel) = cancel {
+            cancel.check()?;
+        }
+        let key = executable.to_string_lossy().to_string();
+        let max_jobs = settings.and_then(|s| s.max_jobs).or(self.config.max_jobs);
+        let mut worker = self.checkout(&key, executable, settings)?;
+        let timed_out = AtomicBool::new(false);
+        match worker.request(handshake, input, cancel, timeout, &timed_out) {
+            Ok(out) => {
+                worker.jobs += 1;
+                worker.last_used = Instant::now();
+                if max_jobs.is_some_and(|max| worker.jobs >= max) {
+                    self.retire(&key, worker, false);
+                } else {
+                    let mut g = self.inner.lock().unwrap();
+                    g.idle.entry(key).or_default().push(worker);
+                    drop(g);
+                    self.returned.notify_all();
+                }
+                Ok(out)
+            }
+            Err(e) => {
+                self.retire(&key, worker, true);
+                if timed_out.load(Ordering::SeqCst) {
+                    anyhow::bail!("persistent worker {}: timed out after {} ms", key, timeout.unwrap_or_default().as_millis());
+                }
+                if cancel.is_some_and(CancellationToken::is_cancelled) {
+                    return Err(Cancelled.into());
+                }
+                Err(anyhow::anyhow!("persistent worker {}: {}", key, e))
+            }
+        }
+    }
+
+    fn checkout(&self, key: &str, executable: &Path, settings: Option<&WorkerSettings>) -> anyhow::Result<Worker> {
+        let pool_size = settings
+            .and_then(|s| s.pool_size)
+            .unwrap_or(self.config.pool_size)
+            .max(1);
+        let idle_timeout = settings
+            .and_then(|s| s.idle_timeout_secs)
+            .map(Duration::from_secs)
+            .unwrap_or(self.config.idle_timeout);
+        let mut g = self.inner.lock().unwrap();
+        loop {
+            while let Some(mut w) = g.idle.get_mut(key).and_then(|v| v.pop()) {
+                if w.is_alive() {
+                    return Ok(w);
+                }
+                // Died while idle: drop it and free its slot.
+                *g.live.entry(key.to_string()).or_default() -= 1;
+                w.kill();
+            }
+            let live = g.live.get(key).copied().unwrap_or(0);
+            if live < pool_size {
+                *g.live.entry(key.to_string()).or_default() += 1;
+                drop(g);
+                return Worker::spawn(executable, idle_timeout).inspect_err(|_| {
+                    *self.inner.lock().unwrap().live.entry(key.to_string()).or_default() -= 1;
+                    self.returned.notify_all();
+                });
+            }
+            g = self.returned.wait(g).unwrap();
+        }
+    }
+
+    fn retire(&self, key: &str, worker: Worker, crashed: bool) {
+        {
+            let mut g = self.inner.lock().unwrap();
+            if let Some(n) = g.live.get_mut(key) {
+                *n = n.saturating_sub(1);
+            }
+        }
+        self.returned.notify_all();
+        if crashed {
+            worker.kill();
+        } else {
+            worker.shutdown();
+        }
+    }
+
+    /// Shut down workers idle past their timeout. Call periodically (the daemon does).
+    pub fn reap_idle(&self) -> usize {
+        let expired: Vec<Worker> = {
+            let mut g = self.inner.lock().unwrap();
+            let mut expired = Vec::new();
+            let PoolInner { idle, live } = &mut *g;
+            for (key, workers) in idle.iter_mut() {
+                let (stale, fresh): (Vec<Worker>, Vec<Worker>) = workers
+                    .drain(..)
+                    .partition(|w| w.last_used.elapsed() >= w.idle_timeout);
+                *workers = fresh;
+                if let Some(n) = live.get_mut(key) {
+                    *n = n.saturating_sub(stale.len());
+                }
+                expired.extend(stale);
+            }
+            expired
+        };
+        let n = expired.len();
+        for w in expired {
+            w.shutdown();
+        }
+        n
+    }
+
+    /// Number of idle warm workers across all plugins.
+    pub fn idle_count(&self) -> usize {
+        self.inner.lock().unwrap().idle.values().map(Vec::len).sum()
+    }
+
+    /// Shut down every idle worker.
+    pub fn shutdown(&self) {
+        let all: Vec<Worker> = {
+            let mut g = self.inner.lock().unwrap();
+            let PoolInner { idle, live } = &mut *g;
+            let mut all = Vec::new();
+            for (key, workers) in idle.iter_mut() {
+                if let Some(n) = live.get_mut(key) {
+                    *n = n.saturating_sub(workers.len());
+                }
+                all.append(workers);
+            }
+            all
+        };
+        for w in all {
+            w.shutdown();
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Shell worker speaking the persistent protocol; `reply` is evaluated per job
+    /// with the concatenated payload in `$body`.
+    const WORKER_SH: &str = r#"#!/bin/sh
+read_len() { dd bs=1 count=4 2>/dev/null | od -An -tu4 | tr -d ' \n'; }
+write_len() {
+    n=$1
+    printf "$(printf '\\%03o\\%03o\\%03o\\%03o' $((n & 255)) $((n >> 8 & 255)) $((n >> 16 & 255)) $((n >> 24 & 255)))"
+}
+while :; do
+    len=$(read_len)
+    [ -z "$len" ] && exit 0
+    dd bs=1 count="$len" of=/dev/null 2>/dev/null
+    body=""
+    while :; do
+        len=$(read_len)
+        [ -z "$len" ] && exit 0
+        [ "$len" -eq 0 ] && break
+        body="$body$(dd bs=1 count="$len" 2>/dev/null)"
+    done
+    REPLY_LINE
+    write_len ${#out}
+    printf '%s' "$out"
+    write_len 0
+done
+"#;
+
+    fn worker_script(dir: &Path, reply: &str) -> std::path::PathBuf {
+        let script = dir.join("run.sh");
+        fs::write(&script, WORKER_SH.replace("REPLY_LINE", reply)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    fn handshake() -> Handshake {
+        Handshake::new("text/plain", "audio/raw", serde_json::json!({}))
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn persistent_worker_serves_successive_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = worker_script(dir.path(), r#"out="$body""#);
+        let pool = WorkerPool::default();
+        assert_eq!(pool.run(&script, None, &handshake(), b"first").unwrap(), b"first");
+        assert_eq!(pool.run(&script, None, &handshake(), b"second").unwrap(), b"second");
+        assert_eq!(pool.idle_count(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn worker_is_reused_then_recycled_after_max_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = worker_script(dir.path(), r#"out="$$""#);
+        let pool = WorkerPool::default();
+        let settings = WorkerSettings { max_jobs: Some(2), ..Default::default() };
+        let a = pool.run(&script, Some(&settings), &handshake(), b"x").unwrap();
+        let b = pool.run(&script, Some(&settings), &handshake(), b"x").unwrap();
+        let c = pool.run(&script, Some(&settings), &handshake(), b"x").unwrap();
+        assert_eq!(a, b, "second job should hit the warm worker");
+        assert_ne!(b, c, "worker should be recycled after max_jobs");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn crashed_worker_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        // Crash on "boom", echo otherwise.
+        let script = worker_script(dir.path(), r#"[ "$body" = "boom" ] && exit 3; out="$body""#);
+        let pool = WorkerPool::default();
+        assert!(pool.run(&script, None, &handshake(), b"boom").is_err());
+        assert_eq!(pool.run(&script, None, &handshake(), b"ok").unwrap(), b"ok");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn cancelling_a_job_kills_the_worker() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = worker_script(dir.path(), r#"[ "$body" = "slow" ] && sleep 30; out="$body""#);
+        let pool = WorkerPool::default();
+        let cancel = CancellationToken::new();
+        let started = Instant::now();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(200));
+                cancel.cancel();
+            });
+            let err = pool
+                .run_cancellable(&script, None, &handshake(), b"slow", Some(&cancel), None)
+                .unwrap_err();
+            assert!(crate::cancel::is_cancelled(&err));
+        });
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(pool.idle_count(), 0);
+        assert_eq!(pool.run(&script, None, &handshake(), b"ok").unwrap(), b"ok");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reap_idle_shuts_down_expired_workers() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = worker_script(dir.path(), r#"out="$body""#);
+        let pool = WorkerPool::new(WorkerPoolConfig {
+            idle_timeout: Duration::ZERO,
+            ..Default::default()
+        });
+        pool.run(&script, None, &handshake(), b"x").unwrap();
+        assert_eq!(pool.reap_idle(), 1);
+        assert_eq!(pool.idle_count(), 0);
+    }
+}
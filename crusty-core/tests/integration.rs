@@ -104,3 +104,1272 @@ path = "out.bin"
     let orch = Orchestration::from_toml(&orch_toml).unwrap();
     assert!(validate_orchestration_types(&orch, base).is_ok());
 }
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_with_persistent_tts_worker() {
+    use crusty_core::{execute_pipeline_with, ExecutionContext, WorkerPool};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "Hello worker").unwrap();
+
+    let tts_dir = base.join("plugins").join("tts-worker");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(
+        tts_dir.join("plugin.toml"),
+        r#"
+name = "tts-worker"
+version = "0.1"
+type = "tts"
+lifecycle = "persistent"
+[capabilities]
+input = ["text/plain"]
+output = ["audio/raw"]
+"#,
+    )
+    .unwrap();
+    // Framed echo loop: handshake, payload frames until EOS, reply + EOS.
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(
+        &run_sh,
+        r#"#!/bin/sh
+read_len() { dd bs=1 count=4 2>/dev/null | od -An -tu4 | tr -d ' \n'; }
+write_len() {
+    n=$1
+    printf "$(printf '\\%03o\\%03o\\%03o\\%03o' $((n & 255)) $((n >> 8 & 255)) $((n >> 16 & 255)) $((n >> 24 & 255)))"
+}
+while :; do
+    len=$(read_len)
+    [ -z "$len" ] && exit 0
+    dd bs=1 count="$len" of=/dev/null 2>/dev/null
+    body=""
+    while :; do
+        len=$(read_len)
+        [ -z "$len" ] && exit 0
+        [ "$len" -eq 0 ] && break
+        body="$body$(dd bs=1 count="$len" 2>/dev/null)"
+    done
+    write_len ${#body}
+    printf '%s' "$body"
+    write_len 0
+done
+"#,
+    )
+    .unwrap();
+    fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let orch_toml = format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts-worker"
+module = "plugins/tts-worker"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    );
+    let orch = Orchestration::from_toml(&orch_toml).unwrap();
+    let pool = Arc::new(WorkerPool::default());
+    let ctx = ExecutionContext { workers: Some(Arc::clone(&pool)), ..Default::default() };
+    assert_eq!(execute_pipeline_with(&orch, base, &ctx).unwrap(), b"Hello worker");
+    assert_eq!(execute_pipeline_with(&orch, base, &ctx).unwrap(), b"Hello worker");
+    assert_eq!(pool.idle_count(), 1, "worker should stay warm between runs");
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_refuses_tampered_plugin_when_locked() {
+    use crusty_core::{execute_pipeline_with, ExecutionContext, IntegrityMode, LockCheck, Lockfile};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "Hi").unwrap();
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(tts_dir.join("plugin.toml"), "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(&run_sh, "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+    fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+    let ctx = ExecutionContext {
+        lock: Some(Arc::new(LockCheck {
+            lock: Lockfile::generate(&orch, base).unwrap(),
+            mode: IntegrityMode::Enforce,
+        })),
+        ..Default::default()
+    };
+    assert_eq!(execute_pipeline_with(&orch, base, &ctx).unwrap(), b"Hi");
+
+    fs::write(&run_sh, "#!/bin/sh\nprintf 'pwned'\n").unwrap();
+    let err = execute_pipeline_with(&orch, base, &ctx).unwrap_err();
+    assert!(err.to_string().contains("integrity check failed"), "{}", err);
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_requires_trusted_signatures_under_require() {
+    use crusty_core::trust::{parse_signing_key, public_key_hex, sign_plugin_dir, TrustedKey};
+    use crusty_core::{execute_pipeline_with, ExecutionContext, SignaturePolicy, TrustConfig, TrustedKeys};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "Hi").unwrap();
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(tts_dir.join("plugin.toml"), "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(&run_sh, "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+    fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+    let key = parse_signing_key(&"07".repeat(32)).unwrap();
+    let ctx = ExecutionContext {
+        trust: Some(Arc::new(TrustConfig {
+            policy: SignaturePolicy::Require,
+            keys: TrustedKeys {
+                keys: vec![TrustedKey { name: "release".into(), public_key: public_key_hex(&key) }],
+            },
+        })),
+        ..Default::default()
+    };
+    let err = execute_pipeline_with(&orch, base, &ctx).unwrap_err();
+    assert!(err.to_string().contains("signature check failed: plugin tts-stub: unsigned"), "{}", err);
+
+    sign_plugin_dir(&tts_dir, &key).unwrap();
+    assert_eq!(execute_pipeline_with(&orch, base, &ctx).unwrap(), b"Hi");
+
+    fs::write(&run_sh, "#!/bin/sh\nprintf 'pwned'\n").unwrap();
+    let err = execute_pipeline_with(&orch, base, &ctx).unwrap_err();
+    assert!(err.to_string().contains("invalid signature"), "{}", err);
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_refuses_module_outside_plugin_base() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("plugins");
+    let outside = dir.path().join("outside");
+    fs::create_dir_all(&base).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(dir.path().join("input.txt"), "Hello").unwrap();
+    let marker = dir.path().join("ran");
+    let run_sh = outside.join("run.sh");
+    fs::write(&run_sh, format!("#!/bin/sh\ntouch {}\n", marker.display())).unwrap();
+    fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "evil"
+module = "../outside"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        dir.path().join("input.txt").display()
+    ))
+    .unwrap();
+
+    let err = execute_pipeline(&orch, &base).unwrap_err();
+    let path_err = err.downcast_ref::<crusty_core::PathError>().expect("typed path error");
+    assert_eq!(path_err.kind(), "module_escapes_root");
+    assert!(!marker.exists(), "escaped plugin must not run");
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_refuses_entrypoint_outside_plugin_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("plugins");
+    let tts_dir = base.join("tts");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(dir.path().join("input.txt"), "Hello").unwrap();
+    let marker = dir.path().join("ran");
+    let outside = dir.path().join("outside.sh");
+    fs::write(&outside, format!("#!/bin/sh\ntouch {}\n", marker.display())).unwrap();
+    fs::set_permissions(&outside, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts"
+module = "tts"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        dir.path().join("input.txt").display()
+    ))
+    .unwrap();
+
+    for entrypoint in ["../../outside.sh".to_string(), outside.display().to_string()] {
+        fs::write(
+            tts_dir.join("plugin.toml"),
+            format!("name = \"tts\"\nversion = \"0.1\"\ntype = \"tts\"\nentrypoint = {:?}\n", entrypoint),
+        )
+        .unwrap();
+        let err = execute_pipeline(&orch, &base).unwrap_err();
+        let path_err = err.downcast_ref::<crusty_core::PathError>().expect("typed path error");
+        assert_eq!(path_err.kind(), "entrypoint_escapes_plugin", "{}", entrypoint);
+    }
+    assert!(!marker.exists(), "escaped entrypoint must not run");
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_chunks_long_text_in_order() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "First one. Second two.\n\nThird three. Fourth.").unwrap();
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(tts_dir.join("plugin.toml"), "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+    // Earlier chunks finish last, so ordering must come from reassembly, not completion.
+    // Input is echoed twice so every chunk is a whole number of 16-bit samples.
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(
+        &run_sh,
+        "#!/bin/sh\ncase \"$PLUGIN_INPUT\" in First*) sleep 0.3;; Second*) sleep 0.2;; esac\nprintf '<%s>' \"$PLUGIN_INPUT$PLUGIN_INPUT\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[tts.chunking]
+mode = "sentence"
+max_chars = 14
+parallelism = 4
+silence_ms = 1
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+
+    let out = execute_pipeline(&orch, base).unwrap();
+    // 1 ms of 16-bit mono silence at 22050 Hz between chunks.
+    let gap = [0u8; 44];
+    let expected: Vec<u8> = ["First one.", "Second two.", "Third three.", "Fourth."]
+        .iter()
+        .map(|c| format!("<{c}{c}>").into_bytes())
+        .collect::<Vec<_>>()
+        .join(&gap[..]);
+    assert_eq!(out, expected);
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_serves_repeat_chunks_from_cache() {
+    use crusty_core::{execute_pipeline_report, CacheUse, ExecutionContext, SynthesisCache};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    let calls = base.join("calls.log");
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(tts_dir.join("plugin.toml"), "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(
+        &run_sh,
+        format!("#!/bin/sh\necho x >> {}\nprintf '%s' \"$PLUGIN_INPUT$PLUGIN_INPUT\"\n", calls.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755)).unwrap();
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[tts.chunking]
+max_chars = 12
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+    let ctx = ExecutionContext {
+        cache: Some(Arc::new(SynthesisCache::open(&base.join("cache"), 1 << 20).unwrap())),
+        ..Default::default()
+    };
+    let invocations = || fs::read_to_string(&calls).unwrap_or_default().lines().count();
+
+    fs::write(base.join("input.txt"), "Alpha one. Beta two. Gamma three.").unwrap();
+    let first = execute_pipeline_report(&orch, base, &ctx).unwrap();
+    assert_eq!((first.report.cache_hits(), first.report.cache_misses()), (0, 3));
+    assert_eq!(invocations(), 3);
+
+    let second = execute_pipeline_report(&orch, base, &ctx).unwrap();
+    assert_eq!(second.audio, first.audio);
+    assert_eq!((second.report.cache_hits(), second.report.cache_misses()), (3, 0));
+    assert_eq!(invocations(), 3, "cached chunks do not run the plugin");
+
+    // Only the edited sentence is re-rendered.
+    fs::write(base.join("input.txt"), "Alpha one. Beta 2. Gamma three.").unwrap();
+    let third = execute_pipeline_report(&orch, base, &ctx).unwrap();
+    assert_eq!((third.report.cache_hits(), third.report.cache_misses()), (2, 1));
+
+    // Non-deterministic plugins always run.
+    fs::write(
+        tts_dir.join("plugin.toml"),
+        "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\ndeterministic = false\n",
+    )
+    .unwrap();
+    let before = invocations();
+    let fourth = execute_pipeline_report(&orch, base, &ctx).unwrap();
+    assert_eq!(fourth.report.stages[0].cache, CacheUse::Bypass);
+    assert_eq!(invocations(), before + 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_reports_progress_to_observer() {
+    use crusty_core::{execute_pipeline_with, ExecutionContext, PipelineObserver, ProgressFrame, StageInfo};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl PipelineObserver for Recorder {
+        fn stage_started(&self, stage: &StageInfo, bytes_in: usize) {
+            self.0.lock().unwrap().push(format!("start {} {}/{} in={}", stage.name, stage.index, stage.total, bytes_in));
+        }
+        fn stage_finished(&self, stage: &StageInfo, bytes_out: usize) {
+            self.0.lock().unwrap().push(format!("end {} out={}", stage.name, bytes_out));
+        }
+        fn chunk_finished(&self, _stage: &StageInfo, done: usize, total: usize) {
+            self.0.lock().unwrap().push(format!("chunk {}/{}", done, total));
+        }
+        fn plugin_progress(&self, stage: &StageInfo, progress: &ProgressFrame) {
+            self.0.lock().unwrap().push(format!("progress {} {:?}", stage.name, progress.fraction));
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "One. Two.").unwrap();
+    for (name, script) in [
+        ("pre", "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n"),
+        ("tts", "#!/bin/sh\necho '{\"type\":\"progress\",\"fraction\":1.0}' >&2\nprintf '%s' \"$PLUGIN_INPUT$PLUGIN_INPUT\"\n"),
+    ] {
+        let plugin = base.join("plugins").join(name);
+        fs::create_dir_all(&plugin).unwrap();
+        fs::write(plugin.join("plugin.toml"), format!("name = \"{}\"\nversion = \"0.1\"\n", name)).unwrap();
+        fs::write(plugin.join("run.sh"), script).unwrap();
+        fs::set_permissions(plugin.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[[pre_processors]]
+name = "pre"
+module = "plugins/pre"
+[tts]
+name = "tts"
+module = "plugins/tts"
+[tts.chunking]
+max_chars = 4
+parallelism = 1
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+    let recorder = Arc::new(Recorder::default());
+    let ctx = ExecutionContext {
+        observer: Some(recorder.clone()),
+        ..Default::default()
+    };
+    execute_pipeline_with(&orch, base, &ctx).unwrap();
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec![
+            "start pre 0/2 in=9",
+            "end pre out=9",
+            "start tts 1/2 in=9",
+            "progress tts Some(1.0)",
+            "chunk 1/2",
+            "progress tts Some(1.0)",
+            "chunk 2/2",
+            "end tts out=16",
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn cancelling_a_pipeline_kills_the_stage_and_skips_later_stages() {
+    use crusty_core::{execute_pipeline_with, is_cancelled, CancellationToken, ExecutionContext};
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "hello").unwrap();
+    let marker = |name: &str| base.join(name);
+    for (name, script) in [
+        // The TTS backgrounds a child that would leave a mark if it outlived the stage.
+        (
+            "tts",
+            format!("#!/bin/sh\n(sleep 1; touch {:?}) &\nsleep 30\n", marker("child-survived")),
+        ),
+        ("post", format!("#!/bin/sh\ntouch {:?}\ncat\n", marker("post-ran"))),
+    ] {
+        let plugin = base.join("plugins").join(name);
+        fs::create_dir_all(&plugin).unwrap();
+        fs::write(plugin.join("run.sh"), script).unwrap();
+        fs::set_permissions(plugin.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts"
+module = "plugins/tts"
+[[post_processors]]
+name = "post"
+module = "plugins/post"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+    let cancel = CancellationToken::new();
+    let ctx = ExecutionContext {
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let err = std::thread::scope(|s| {
+        s.spawn(|| {
+            std::thread::sleep(Duration::from_millis(300));
+            cancel.cancel();
+        });
+        execute_pipeline_with(&orch, base, &ctx).unwrap_err()
+    });
+    assert!(is_cancelled(&err), "unexpected error: {err:#}");
+    assert!(started.elapsed() < Duration::from_secs(10));
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(!marker("child-survived").exists(), "plugin's process group should be killed");
+    assert!(!marker("post-ran").exists(), "later stages should not start");
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_runs_registered_native_plugins() {
+    use crusty_core::{
+        execute_pipeline_with, ExecutionContext, PluginOptions, PluginRegistry, PostProcessor, PreProcessor, Tts,
+    };
+    use std::io::{Read, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    struct Trim;
+    impl PreProcessor for Trim {
+        fn name(&self) -> &str {
+            "trim"
+        }
+        fn process(&self, input: &str, _: &PluginOptions, out: &mut dyn Write) -> anyhow::Result<()> {
+            Ok(out.write_all(input.trim().as_bytes())?)
+        }
+    }
+
+    /// "Synthesizes" the text reversed, or fails on request.
+    struct Backwards;
+    impl Tts for Backwards {
+        fn name(&self) -> &str {
+            "backwards"
+        }
+        fn synthesize(&self, input: &str, options: &PluginOptions, out: &mut dyn Write) -> anyhow::Result<()> {
+            if options.get("voice").is_some_and(|v| v == "broken") {
+                anyhow::bail!("voice not installed");
+            }
+            Ok(out.write_all(input.chars().rev().collect::<String>().as_bytes())?)
+        }
+    }
+
+    struct Frame;
+    impl PostProcessor for Frame {
+        fn name(&self) -> &str {
+            "frame"
+        }
+        fn output_type(&self, _: &str) -> String {
+            "application/x-framed".into()
+        }
+        fn process(&self, input: &mut dyn Read, _: &PluginOptions, out: &mut dyn Write) -> anyhow::Result<()> {
+            let mut buf = Vec::new();
+            input.read_to_end(&mut buf)?;
+            write!(out, "[{}]", String::from_utf8_lossy(&buf))?;
+            Ok(())
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "  abc  ").unwrap();
+    // A subprocess stage between native ones.
+    let conv = base.join("plugins").join("dup");
+    fs::create_dir_all(&conv).unwrap();
+    fs::write(conv.join("run.sh"), "#!/bin/sh\ncat; printf '+'\n").unwrap();
+    fs::set_permissions(conv.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let orch_toml = |voice: &str| {
+        format!(
+            r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[[pre_processors]]
+name = "trim"
+module = "trim"
+[tts]
+name = "backwards"
+module = "backwards"
+voice = "{}"
+[[audio_converters]]
+name = "dup"
+module = "plugins/dup"
+[[post_processors]]
+name = "frame"
+module = "frame"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+            base.join("input.txt").display(),
+            voice
+        )
+    };
+    let mut registry = PluginRegistry::new();
+    registry.register_pre(Trim);
+    registry.register_tts(Backwards);
+    registry.register_post(Frame);
+    let ctx = ExecutionContext {
+        registry: Some(Arc::new(registry)),
+        ..Default::default()
+    };
+    let orch = Orchestration::from_toml(&orch_toml("en")).unwrap();
+    let out = crusty_core::execute_pipeline_report(&orch, base, &ctx).unwrap();
+    assert_eq!(out.audio, b"[cba+]");
+    assert_eq!(out.audio_type, "application/x-framed");
+
+    let orch = Orchestration::from_toml(&orch_toml("broken")).unwrap();
+    let err = execute_pipeline_with(&orch, base, &ctx).unwrap_err();
+    assert!(format!("{err:#}").contains("voice not installed"), "{err:#}");
+
+    // A native plugin referenced from the wrong stage kind is refused.
+    let orch = Orchestration::from_toml(&orch_toml("en").replace("name = \"backwards\"", "name = \"trim\"")).unwrap();
+    let err = execute_pipeline_with(&orch, base, &ctx).unwrap_err();
+    assert!(err.to_string().contains("cannot run as TTS"), "{err}");
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_keeps_intermediates_up_to_a_failing_stage() {
+    use crusty_core::{execute_pipeline_with, ArtifactManifest, ExecutionContext};
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "hello").unwrap();
+    let plugin = |name: &str, script: &str| {
+        let p = base.join("plugins").join(name);
+        fs::create_dir_all(&p).unwrap();
+        fs::write(p.join("run.sh"), script).unwrap();
+        fs::set_permissions(p.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    plugin("upper", "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\" | tr a-z A-Z\n");
+    plugin("tts", "#!/bin/sh\nprintf 'PCM:%s' \"$PLUGIN_INPUT\"\n");
+    plugin("broken", "#!/bin/sh\necho 'clipping' >&2\nexit 3\n");
+    let orch_toml = |post: &str| {
+        format!(
+            r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[[pre_processors]]
+name = "upper"
+module = "plugins/upper"
+[tts]
+name = "tts"
+module = "plugins/tts"
+[[post_processors]]
+name = "post"
+module = "plugins/{}"
+[output]
+type = "file"
+path = "out.bin"
+[debug]
+keep_intermediates = "{}"
+"#,
+            base.join("input.txt").display(),
+            post,
+            base.join("debug").display()
+        )
+    };
+
+    // Only the failing stage's output is missing from the manifest.
+    let orch = Orchestration::from_toml(&orch_toml("broken")).unwrap();
+    assert!(execute_pipeline_with(&orch, base, &ExecutionContext::default()).is_err());
+    let manifest = ArtifactManifest::load(&base.join("debug")).unwrap();
+    let files: Vec<_> = manifest
+        .stages
+        .iter()
+        .map(|s| (s.input.as_ref().map(|a| a.file.as_str()), s.output.as_ref().map(|a| a.file.as_str())))
+        .collect();
+    assert_eq!(
+        files,
+        vec![
+            (Some("00-pre-processor-upper.input.txt"), Some("00-pre-processor-upper.output.txt")),
+            (Some("01-tts-tts.input.txt"), Some("01-tts-tts.output.pcm")),
+            (Some("02-post-processor-post.input.pcm"), None),
+        ]
+    );
+    assert_eq!(fs::read(base.join("debug/01-tts-tts.input.txt")).unwrap(), b"HELLO");
+    assert_eq!(fs::read(base.join("debug/02-post-processor-post.input.pcm")).unwrap(), b"PCM:HELLO");
+
+    // The context's directory takes precedence over the orchestration's.
+    let ctx = ExecutionContext {
+        keep_intermediates: Some(base.join("ctx-debug")),
+        ..Default::default()
+    };
+    let orch = Orchestration::from_toml(&orch_toml("upper")).unwrap();
+    execute_pipeline_with(&orch, base, &ctx).unwrap();
+    let manifest = ArtifactManifest::load(&base.join("ctx-debug")).unwrap();
+    assert_eq!(manifest.stages.len(), 3);
+    assert_eq!(manifest.stages[2].output.as_ref().unwrap().bytes, 9);
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_retries_and_falls_back_to_other_tts_engines() {
+    use crusty_core::execute_pipeline_report;
+    use crusty_core::ExecutionContext;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "hi").unwrap();
+    let plugin = |name: &str, script: &str| {
+        let p = base.join("plugins").join(name);
+        fs::create_dir_all(&p).unwrap();
+        fs::write(p.join("run.sh"), script).unwrap();
+        fs::set_permissions(p.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    // Fails on its first call only, counting calls in a file next to it.
+    plugin(
+        "flaky",
+        "#!/bin/sh\nd=$(dirname \"$0\")\necho x >> \"$d/calls\"\n[ $(wc -l < \"$d/calls\") -gt 1 ] || exit 1\nprintf 'flaky:%s' \"$PLUGIN_INPUT\"\n",
+    );
+    plugin("down", "#!/bin/sh\necho 'service unavailable' >&2\nexit 1\n");
+    plugin("backup", "#!/bin/sh\nprintf '%s:%s' \"$PLUGIN_OPT_VOICE\" \"$PLUGIN_INPUT\"\n");
+    let orch_toml = |tts: &str, retry: u32, fallbacks: &[&str]| {
+        let mut s = format!(
+            r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "{tts}"
+module = "plugins/{tts}"
+voice = "en"
+retry = {{ attempts = {retry}, backoff_ms = 1 }}
+"#,
+            base.join("input.txt").display()
+        );
+        for f in fallbacks {
+            s += &format!("[[tts.fallbacks]]\nname = \"{f}\"\nmodule = \"plugins/{f}\"\noptions = {{ voice = \"de\" }}\n");
+        }
+        s + "[output]\ntype = \"file\"\npath = \"out.bin\"\n"
+    };
+    let run = |toml: String| execute_pipeline_report(&Orchestration::from_toml(&toml).unwrap(), base, &ExecutionContext::default());
+
+    // A transient failure is retried on the same engine.
+    let out = run(orch_toml("flaky", 2, &["backup"])).unwrap();
+    assert_eq!(out.audio, b"flaky:hi");
+    assert_eq!(out.report.stages[0].engine, "flaky");
+    assert_eq!(out.report.stages[0].retries, 1);
+
+    // A persistent failure moves on to the fallback, which gets its own options.
+    let out = run(orch_toml("down", 2, &["backup"])).unwrap();
+    assert_eq!(out.audio, b"de:hi");
+    assert_eq!(out.report.stages[0].name, "down");
+    assert_eq!(out.report.stages[0].engine, "backup");
+    assert_eq!(out.report.stages[0].retries, 2);
+
+    let err = run(orch_toml("down", 1, &["down"])).unwrap_err();
+    assert!(err.to_string().starts_with("all TTS engines failed"), "{err}");
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_resumes_from_first_incomplete_chunk() {
+    use crusty_core::{execute_pipeline_report, ExecutionContext};
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "One. Two. Three.").unwrap();
+    let tts = base.join("plugins").join("tts");
+    fs::create_dir_all(&tts).unwrap();
+    // Logs each call; fails on "Three." while the `broken` marker exists.
+    let script = format!(
+        "#!/bin/sh\necho \"$PLUGIN_INPUT\" >> {log}\ncase \"$PLUGIN_INPUT\" in *Three*) [ -e {broken} ] && exit 1;; esac\nprintf '[%s]' \"$PLUGIN_INPUT\"\n",
+        log = base.join("calls").display(),
+        broken = base.join("broken").display()
+    );
+    fs::write(tts.join("run.sh"), &script).unwrap();
+    fs::set_permissions(tts.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(base.join("broken"), "").unwrap();
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "book"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts"
+module = "plugins/tts"
+[tts.chunking]
+max_chars = 6
+parallelism = 1
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+    let ctx = |resume| ExecutionContext {
+        work_dir: Some(base.join("work")),
+        resume,
+        ..Default::default()
+    };
+    let calls = || fs::read_to_string(base.join("calls")).unwrap().lines().count();
+
+    assert!(execute_pipeline_report(&orch, base, &ctx(false)).is_err());
+    assert_eq!(calls(), 3);
+    fs::remove_file(base.join("broken")).unwrap();
+
+    let out = execute_pipeline_report(&orch, base, &ctx(true)).unwrap();
+    assert_eq!(out.audio, b"[One.][Two.][Three.]");
+    assert_eq!(out.report.stages[0].resumed, 2);
+    assert_eq!(calls(), 4, "only the failed chunk is synthesized again");
+
+    // A changed plugin invalidates the checkpoint.
+    fs::write(tts.join("run.sh"), format!("{}# v2\n", script)).unwrap();
+    let err = execute_pipeline_report(&orch, base, &ctx(true)).unwrap_err();
+    assert!(err.to_string().contains("changed since the checkpoint"), "{err}");
+    assert!(execute_pipeline_report(&orch, base, &ctx(false)).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_fans_tts_output_out_to_branches() {
+    use crusty_core::{execute_pipeline_report, ExecutionContext};
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "hi").unwrap();
+    let plugin = |name: &str, script: &str| {
+        let p = base.join("plugins").join(name);
+        fs::create_dir_all(&p).unwrap();
+        fs::write(p.join("run.sh"), script).unwrap();
+        fs::set_permissions(p.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    plugin("tts", &format!("#!/bin/sh\necho x >> {}\nprintf 'pcm-%s' \"$PLUGIN_INPUT\"\n", base.join("calls").display()));
+    plugin("upper", "#!/bin/sh\ntr a-z A-Z\n");
+    plugin("frame", "#!/bin/sh\nprintf '<'; cat; printf '>'\n");
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts"
+module = "plugins/tts"
+[[outputs]]
+name = "master"
+path = "master.pcm"
+[[outputs]]
+name = "loud"
+path = "loud.pcm"
+[[outputs.converters]]
+name = "upper"
+module = "plugins/upper"
+[[outputs.post_processors]]
+name = "frame"
+module = "plugins/frame"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+    let out = execute_pipeline_report(&orch, base, &ExecutionContext::default()).unwrap();
+    assert_eq!(fs::read_to_string(base.join("calls")).unwrap().lines().count(), 1, "TTS runs once");
+    assert_eq!(out.audio, b"pcm-hi");
+    let outputs: Vec<_> = out.outputs.iter().map(|o| (o.name.as_str(), o.path.as_str(), o.audio.as_slice())).collect();
+    assert_eq!(outputs, vec![("master", "master.pcm", &b"pcm-hi"[..]), ("loud", "loud.pcm", &b"<PCM-HI>"[..])]);
+    let stages: Vec<_> = out.report.stages.iter().map(|s| (s.name.as_str(), s.branch.as_deref())).collect();
+    assert_eq!(stages, vec![("tts", None), ("upper", Some("loud")), ("frame", Some("loud"))]);
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_graph_runs_independent_voices_in_parallel_and_mixes_them() {
+    use crusty_core::{execute_graph_report, validate_graph_types, ExecutionContext, Graph};
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "hi").unwrap();
+    fs::write(base.join("bed.raw"), [7u8, 0]).unwrap();
+    let plugin = |name: &str, script: &str| {
+        let p = base.join("plugins").join(name);
+        fs::create_dir_all(&p).unwrap();
+        fs::write(p.join("run.sh"), script).unwrap();
+        fs::set_permissions(p.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    // alice only finishes once bob has started, so the run deadlocks (and alice gives up)
+    // unless the two voices run concurrently.
+    let marker = base.join("bob.started");
+    plugin(
+        "alice",
+        &format!(
+            "#!/bin/sh\nfor i in $(seq 50); do [ -f {m} ] && break; sleep 0.1; done\n[ -f {m} ] || exit 1\nprintf '\\001\\000\\002\\000'\n",
+            m = marker.display()
+        ),
+    );
+    plugin("bob", &format!("#!/bin/sh\ntouch {}\nprintf '\\012\\000'\n", marker.display()));
+    let graph = Graph::from_toml(&format!(
+        r#"
+version = 2
+[meta]
+name = "duet"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{input}"
+[[nodes]]
+name = "alice"
+kind = "tts"
+module = "plugins/alice"
+inputs = ["input.text"]
+[[nodes]]
+name = "bob"
+kind = "tts"
+module = "plugins/bob"
+inputs = ["input"]
+[[nodes]]
+name = "duet"
+kind = "mix"
+inputs = ["alice.out", "bob.out"]
+mix = {{ mode = "overlay" }}
+[[nodes]]
+name = "bed"
+kind = "source"
+path = "{bed}"
+content_type = "audio/raw"
+[[nodes]]
+name = "final"
+kind = "mix"
+inputs = ["duet", "bed"]
+[output]
+from = "final.out"
+path = "out.pcm"
+[[outputs]]
+name = "alice-only"
+from = "alice"
+path = "alice.pcm"
+"#,
+        input = base.join("input.txt").display(),
+        bed = base.join("bed.raw").display(),
+    ))
+    .unwrap();
+    assert!(validate_graph_types(&graph, base).is_ok());
+
+    let out = execute_graph_report(&graph, base, &ExecutionContext::default()).unwrap();
+    assert_eq!(out.audio, [11, 0, 2, 0, 7, 0]);
+    assert_eq!(out.audio_type, "audio/raw;rate=22050;channels=1;format=s16le");
+    assert_eq!(out.outputs[0].name, "alice-only");
+    assert_eq!(out.outputs[0].audio, [1, 0, 2, 0]);
+    let stages: Vec<_> = out.report.stages.iter().map(|s| (s.index, s.kind.as_str(), s.name.as_str())).collect();
+    assert_eq!(
+        stages,
+        [(0, "TTS", "alice"), (1, "TTS", "bob"), (2, "mix", "duet"), (3, "source", "bed"), (4, "mix", "final")]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_voices_each_dialogue_speaker_with_its_engine() {
+    use crusty_core::{execute_pipeline_report, plan, ExecutionContext};
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.txt"), "Welcome.\n\nALICE: Hi Bob.\nBOB: Hi Alice.\nHow are you?\n").unwrap();
+    let plugin = |name: &str, script: &str| {
+        let p = base.join("plugins").join(name);
+        fs::create_dir_all(&p).unwrap();
+        fs::write(p.join("run.sh"), script).unwrap();
+        fs::set_permissions(p.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    // Text is echoed twice so every line is a whole number of 16-bit samples.
+    plugin("tts", "#!/bin/sh\nprintf '[%s/%s%s/%s]' \"$PLUGIN_OPT_VOICE\" \"$PLUGIN_INPUT\" \"$PLUGIN_OPT_VOICE\" \"$PLUGIN_INPUT\"\n");
+    plugin("piper", "#!/bin/sh\nprintf '{%s/%s%s/%s}' \"$PLUGIN_OPT_VOICE\" \"$PLUGIN_INPUT\" \"$PLUGIN_OPT_VOICE\" \"$PLUGIN_INPUT\"\n");
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "{}"
+[tts]
+name = "tts"
+module = "plugins/tts"
+voice = "narr"
+[speakers.alice]
+voice = "f1"
+[speakers.bob]
+tts = "piper"
+module = "plugins/piper"
+voice = "m2"
+[dialogue]
+gap_ms = 1
+parallelism = 3
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("input.txt").display()
+    ))
+    .unwrap();
+
+    let registry = PluginRegistry::load_plugins(&base.join("plugins")).unwrap();
+    let stage = &plan(&orch, &registry, base, &crusty_core::PathPolicy::default()).stages[0];
+    let speakers: Vec<_> = stage.speakers.iter().map(|(s, e)| (s.as_str(), e.as_str())).collect();
+    assert_eq!(speakers, vec![("alice", "tts"), ("bob", "piper")]);
+
+    let out = execute_pipeline_report(&orch, base, &ExecutionContext::default()).unwrap();
+    // 1 ms of 16-bit mono silence at 22050 Hz between lines.
+    let gap = [0u8; 44];
+    let expected = [
+        "[narr/Welcome.narr/Welcome.]",
+        "[f1/Hi Bob.f1/Hi Bob.]",
+        "{m2/Hi Alice. How are you?m2/Hi Alice. How are you?}",
+    ]
+    .map(|l| l.as_bytes().to_vec())
+    .join(&gap[..]);
+    assert_eq!(out.audio, expected);
+    assert_eq!(out.report.stages.len(), 1);
+    assert_eq!(out.report.stages[0].engine, "tts");
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_sends_ssml_or_downgrades_it_per_engine() {
+    use crusty_core::{execute_pipeline_report, ExecutionContext};
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(base.join("input.xml"), "<speak>Hello <emphasis>world</emphasis>.<break time=\"1ms\"/>Bye.</speak>").unwrap();
+    let plugin = |name: &str, manifest: Option<&str>, script: &str| {
+        let p = base.join("plugins").join(name);
+        fs::create_dir_all(&p).unwrap();
+        if let Some(m) = manifest {
+            fs::write(p.join("plugin.toml"), m).unwrap();
+        }
+        fs::write(p.join("run.sh"), script).unwrap();
+        fs::set_permissions(p.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    // Text is echoed twice so every part is a whole number of 16-bit samples.
+    plugin("basic", None, "#!/bin/sh\nprintf '[%s%s]' \"$PLUGIN_INPUT\" \"$PLUGIN_INPUT\"\n");
+    plugin(
+        "neural",
+        Some("name = \"neural\"\nversion = \"0.1\"\n[capabilities]\ninput = [\"text/plain\", \"application/ssml+xml\"]\nssml_elements = [\"break\"]\n"),
+        "#!/bin/sh\nprintf '%s%s' \"$PLUGIN_INPUT\" \"$PLUGIN_INPUT\"\n",
+    );
+    let run = |tts: &str| {
+        let orch = Orchestration::from_toml(&format!(
+            r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "ssml"
+source = "{}"
+[tts]
+name = "{tts}"
+module = "plugins/{tts}"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+            base.join("input.xml").display()
+        ))
+        .unwrap();
+        execute_pipeline_report(&orch, base, &ExecutionContext::default()).unwrap().audio
+    };
+
+    // Plain-text engines get the text between breaks, joined with the break as silence:
+    // 1 ms of 16-bit mono at 22050 Hz.
+    let expected = [&b"[Hello world.Hello world.]"[..], &[0u8; 44], b"[Bye.Bye.]"].concat();
+    assert_eq!(run("basic"), expected);
+    // SSML engines get the document without the elements they do not declare.
+    let xml = "<speak>Hello world.<break time=\"1ms\"/>Bye.</speak>";
+    assert_eq!(String::from_utf8(run("neural")).unwrap(), format!("{xml}{xml}"));
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_reads_markdown_input_and_reports_chapters() {
+    use crusty_core::{execute_pipeline_report, ExecutionContext};
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    fs::write(
+        base.join("book.md"),
+        "# One\n\nHello *world*.\n\n```\nlet x = 1;\n```\n\n# Two\n\n- Bye.\n",
+    )
+    .unwrap();
+    let p = base.join("plugins").join("echo");
+    fs::create_dir_all(&p).unwrap();
+    fs::write(p.join("run.sh"), "#!/bin/sh\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+    fs::set_permissions(p.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    let orch = Orchestration::from_toml(&format!(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "markdown"
+source = "{}"
+ssml = false
+[tts]
+name = "echo"
+module = "plugins/echo"
+[output]
+type = "file"
+path = "out.bin"
+"#,
+        base.join("book.md").display()
+    ))
+    .unwrap();
+    let output = execute_pipeline_report(&orch, base, &ExecutionContext::default()).unwrap();
+    let text = String::from_utf8(output.audio).unwrap();
+    assert_eq!(text, "One\n\nHello world.\n\nTwo\n\nBye.");
+    let chapters: Vec<(&str, usize)> = output.report.chapters.iter().map(|c| (c.title.as_str(), c.offset)).collect();
+    assert_eq!(chapters, [("One", 0), ("Two", 19)]);
+}
+
+#[cfg(unix)]
+#[test]
+fn execute_pipeline_streams_chunks_in_order_from_given_input() {
+    use crusty_core::{execute_pipeline_report, ExecutionContext};
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::{Arc, Mutex};
+
+    /// Records each write separately.
+    struct Writes(Arc<Mutex<Vec<Vec<u8>>>>);
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().push(buf.to_vec());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path();
+    let tts_dir = base.join("plugins").join("tts-stub");
+    fs::create_dir_all(&tts_dir).unwrap();
+    fs::write(tts_dir.join("plugin.toml"), "name = \"tts-stub\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+    let run_sh = tts_dir.join("run.sh");
+    fs::write(
+        &run_sh,
+        "#!/bin/sh\ncase \"$PLUGIN_INPUT\" in First*) sleep 0.3;; esac\nprintf '<%s>' \"$PLUGIN_INPUT$PLUGIN_INPUT\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755)).unwrap();
+    // The source does not exist: the input comes from the context.
+    let orch = Orchestration::from_toml(
+        r#"
+[meta]
+name = "test"
+version = "0.1"
+author = "test"
+[input]
+type = "text"
+source = "-"
+[tts]
+name = "tts-stub"
+module = "plugins/tts-stub"
+[tts.chunking]
+mode = "sentence"
+max_chars = 14
+parallelism = 3
+silence_ms = 1
+[output]
+type = "file"
+path = "out.bin"
+"#,
+    )
+    .unwrap();
+
+    let writes = Arc::new(Mutex::new(Vec::new()));
+    let ctx = ExecutionContext {
+        input: Some(b"First one. Second two. Third.".as_slice().into()),
+        stream: Some(Arc::new(Mutex::new(Writes(Arc::clone(&writes))))),
+        ..Default::default()
+    };
+    let output = execute_pipeline_report(&orch, base, &ctx).unwrap();
+    let writes = writes.lock().unwrap();
+    // The first chunk finishes last, yet is written first; each later one follows its gap.
+    let gap = [0u8; 44];
+    assert_eq!(writes.len(), 3);
+    assert_eq!(writes[0], b"<First one.First one.>");
+    assert_eq!(writes[1], [&gap[..], b"<Second two.Second two.>"].concat());
+    assert_eq!(writes.concat(), output.audio);
+}
@@ -7,10 +7,14 @@ use axum::{
     extract::{Path, State},
     http::{header, StatusCode},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
-use crusty_core::{execute_pipeline, validate_orchestration_types, Orchestration};
+use crusty_core::{
+    execute_graph_report, read_input, resolve_plugin_executable, validate_graph_types, validate_ssml, ArtifactManifest, Batch, CancellationToken,
+    ExecutionContext, Graph, NodeKind, PathError, SignaturePolicy, Ssml, SSML_TYPE,
+};
+use crusty_core::trust::verify_plugin_signature;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 
@@ -20,9 +24,20 @@ pub fn build_app(state: state::AppState) -> Router {
         .route("/plugins", get(list_plugins))
         .route("/plugins/:id", get(get_plugin))
         .route("/pipeline/validate", post(validate_pipeline))
+        .route("/pipeline/plan", post(plan_pipeline))
         .route("/pipeline/run", post(run_pipeline))
+        .route("/pipeline/batch", post(run_batch))
+        .route("/batches/:id", get(batch_status).delete(cancel_batch))
         .route("/jobs/:id/status", get(job_status))
         .route("/jobs/:id/stream", get(job_stream))
+        .route("/jobs/:id/outputs", get(job_outputs))
+        .route("/jobs/:id/outputs/:name", get(job_output_file))
+        .route("/jobs/:id/events", get(job_events))
+        .route("/jobs/:id/intermediates", get(job_intermediates))
+        .route("/jobs/:id/intermediates/:file", get(job_intermediate_file))
+        .route("/jobs/:id", delete(cancel_job))
+        .route("/jobs/:id/cancel", post(cancel_job))
+        .route("/jobs/:id/resume", post(resume_job))
         .layer(CorsLayer::permissive())
         .with_state(state)
 }
@@ -36,6 +51,7 @@ async fn list_plugins(State(state): State<state::AppState>) -> impl IntoResponse
         .map(|p| PluginInfo {
             name: p.name.clone(),
             r#type: p.plugin_type.as_str().to_string(),
+            signature: p.signature.as_str().to_string(),
         })
         .collect();
     Json(plugins)
@@ -45,6 +61,7 @@ async fn list_plugins(State(state): State<state::AppState>) -> impl IntoResponse
 struct PluginInfo {
     name: String,
     r#type: String,
+    signature: String,
 }
 
 async fn get_plugin(
@@ -59,6 +76,7 @@ async fn get_plugin(
                 "type": p.plugin_type.as_str(),
                 "path": p.path,
                 "options": p.options,
+                "signature": p.signature,
             })),
         ),
         None => (
@@ -77,31 +95,54 @@ async fn validate_pipeline(
     State(state): State<state::AppState>,
     Json(body): Json<ValidateRequest>,
 ) -> impl IntoResponse {
-    match Orchestration::from_toml(&body.orchestration) {
+    match Graph::from_toml(&body.orchestration) {
         Ok(orch) => {
             let plugin_base = &state.plugins_base;
+            // Nothing below may read a module that escapes the plugin roots.
+            if let Err(e) = check_paths(&orch, &state) {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"valid": false, "errors": [e.to_string()], "kind": e.kind(), "warnings": []})),
+                );
+            }
             let mut errors = Vec::new();
-            if let Some(ref pre) = orch.pre_processors {
-                for p in pre {
-                    let path = plugin_base.join(&p.module);
-                    if !path.join("run.sh").exists() && !path.join("run.py").exists() {
-                        errors.push(format!("pre-processor {} missing run.sh/run.py", p.name));
+            let text_stages = orch
+                .nodes
+                .iter()
+                .filter(|n| matches!(n.kind, NodeKind::PreProcessor | NodeKind::Tts))
+                .filter(|n| state.registry.native(n.plugin_name()).is_none());
+            for n in text_stages {
+                match resolve_plugin_executable(&plugin_base.join(n.module())) {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        errors.push(format!("{} {}: no entrypoint, run.sh or run.py found", n.kind.label(), n.plugin_name()))
                     }
+                    Err(e) => errors.push(format!("{} {}: {}", n.kind.label(), n.plugin_name(), e)),
                 }
             }
-            let tts_path = plugin_base.join(&orch.tts.module);
-            if !tts_path.join("run.sh").exists() && !tts_path.join("run.py").exists() {
-                errors.push(format!("TTS {} missing run.sh/run.py", orch.tts.name));
-            }
-            if let Err(e) = validate_orchestration_types(&orch, plugin_base) {
+            if let Err(e) = validate_graph_types(&orch, plugin_base) {
                 errors.push(format!("type validation: {}", e));
             }
+            // The input is read as a run would read it. SSML the TTS engines would not
+            // fully receive is reported, not rejected: it is downgraded when the pipeline runs.
+            let mut warnings = Vec::new();
+            let bytes = state.paths.resolve_input(&orch.input.source).ok().and_then(|p| std::fs::read(p).ok());
+            if let Some(bytes) = bytes {
+                match read_input(&orch.input, &bytes, Some(&state.registry)) {
+                    Ok(input) if input.content_type == SSML_TYPE => match Ssml::parse(&input.text) {
+                        Ok(doc) => warnings = validate_ssml(&orch, &doc, plugin_base),
+                        Err(e) => errors.push(format!("input: {}", e)),
+                    },
+                    Ok(_) => {}
+                    Err(e) => errors.push(format!("input: {:#}", e)),
+                }
+            }
             if errors.is_empty() {
-                (StatusCode::OK, Json(serde_json::json!({"valid": true})))
+                (StatusCode::OK, Json(serde_json::json!({"valid": true, "warnings": warnings})))
             } else {
                 (
                     StatusCode::BAD_REQUEST,
-                    Json(serde_json::json!({"valid": false, "errors": errors})),
+                    Json(serde_json::json!({"valid": false, "errors": errors, "warnings": warnings})),
                 )
             }
         }
@@ -112,17 +153,35 @@ async fn validate_pipeline(
     }
 }
 
+/// Dry run: what `/pipeline/run` would execute, computed without starting plugins.
+async fn plan_pipeline(
+    State(state): State<state::AppState>,
+    Json(body): Json<ValidateRequest>,
+) -> impl IntoResponse {
+    match Graph::from_toml(&body.orchestration) {
+        Ok(orch) => (StatusCode::OK, Json(serde_json::json!(crusty_core::plan_graph(&orch, &state.registry, &state.plugins_base, &state.paths)))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
 #[derive(serde::Deserialize)]
 struct RunRequest {
     orchestration: String,
     input_path: Option<String>,
+    /// Save every stage's input and output for `GET /jobs/:id/intermediates`. Also
+    /// enabled by `debug.keep_intermediates` in the orchestration (its path is ignored).
+    #[serde(default)]
+    keep_intermediates: bool,
 }
 
 async fn run_pipeline(
     State(state): State<state::AppState>,
     Json(body): Json<RunRequest>,
 ) -> impl IntoResponse {
-    let orch = match Orchestration::from_toml(&body.orchestration) {
+    let orch = match Graph::from_toml(&body.orchestration) {
         Ok(o) => o,
         Err(e) => {
             return (
@@ -135,27 +194,365 @@ async fn run_pipeline(
     if let Some(ref p) = body.input_path {
         orch.input.source = p.clone();
     }
+    if let Err(e) = check_paths(&orch, &state) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": e.to_string(), "kind": e.kind()})),
+        );
+    }
+    let drift = lock_drift(&orch, &state);
+    if !drift.is_empty() {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "plugin integrity check failed", "errors": drift})),
+        );
+    }
+    let unsigned = unverified_plugins(&orch, &state);
+    if !unsigned.is_empty() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "plugin signature check failed", "errors": unsigned})),
+        );
+    }
+    // Clients never choose where the daemon writes; intermediates go under its own root.
+    let keep_intermediates =
+        body.keep_intermediates || orch.debug.take().is_some_and(|d| d.keep_intermediates.is_some());
+    if keep_intermediates && state.intermediates.is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "keep_intermediates is not enabled on this daemon (set CRUSTY_INTERMEDIATES_DIR)"})),
+        );
+    }
     let job_id = uuid::Uuid::new_v4().to_string();
-    let response_job_id = job_id.clone();
     state.jobs.set_status(&job_id, state::JobStatus::Running);
+    let spec = state::JobSpec {
+        orchestration: orch,
+        keep_intermediates,
+    };
+    state.jobs.set_spec(&job_id, spec.clone());
+    start_job(&state, &job_id, spec, false);
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({"job_id": job_id})),
+    )
+}
+
+/// Run a job in the background. With a work root, TTS chunks are checkpointed under
+/// `<root>/<job_id>` (removed once the job completes), and `resume` reuses them.
+fn start_job(state: &state::AppState, job_id: &str, spec: state::JobSpec, resume: bool) {
+    let cancel = CancellationToken::new();
+    state.jobs.register_cancel(job_id, cancel.clone());
+    tokio::task::spawn_blocking(job_runner(state, job_id, spec, resume, cancel));
+}
+
+/// The blocking body of a job: runs its pipeline and records the outcome.
+fn job_runner(
+    state: &state::AppState,
+    job_id: &str,
+    spec: state::JobSpec,
+    resume: bool,
+    cancel: CancellationToken,
+) -> impl FnOnce() + Send + 'static {
     let plugin_base = state.plugins_base.clone();
     let jobs = Arc::clone(&state.jobs);
-    tokio::task::spawn_blocking(move || {
-        match execute_pipeline(&orch, &plugin_base) {
-            Ok(audio) => {
-                jobs.set_completed(&job_id, audio);
+    let work_dir = state.work_root.as_ref().map(|root| root.join(job_id));
+    let ctx = ExecutionContext {
+        workers: Some(Arc::clone(&state.workers)),
+        lock: state.lock.clone(),
+        trust: state.trust.clone(),
+        paths: state.paths.clone(),
+        cache: state.cache.clone(),
+        observer: Some(Arc::new(state::JobObserver {
+            job_id: job_id.to_string(),
+            jobs: Arc::clone(&state.jobs),
+        })),
+        registry: Some(Arc::clone(&state.registry)),
+        cancel: Some(cancel.clone()),
+        keep_intermediates: state
+            .intermediates
+            .as_ref()
+            .filter(|_| spec.keep_intermediates)
+            .map(|root| root.join(job_id)),
+        work_dir: work_dir.clone(),
+        resume,
+        ..Default::default()
+    };
+    let job_id = job_id.to_string();
+    move || {
+        match execute_graph_report(&spec.orchestration, &plugin_base, &ctx) {
+            // A job cancelled just as it finished is still reported as cancelled.
+            _ if cancel.is_cancelled() => jobs.set_cancelled(&job_id),
+            Ok(output) => {
+                jobs.set_report(&job_id, output.report);
+                jobs.set_outputs(&job_id, output.outputs);
+                jobs.set_completed(&job_id, output.audio);
+                if let Some(dir) = work_dir {
+                    let _ = std::fs::remove_dir_all(dir);
+                }
             }
             Err(e) => {
                 jobs.set_failed(&job_id, e.to_string());
             }
         }
+    }
+}
+
+/// Lockfile drift of the orchestration's plugins, in `--locked` mode.
+fn lock_drift(orch: &Graph, state: &state::AppState) -> Vec<String> {
+    match &state.lock {
+        Some(check) => check
+            .lock
+            .verify_modules(&orch.stage_modules(), &state.plugins_base)
+            .iter()
+            .map(|d| d.to_string())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Plugins of `orch` that are not signed by a trusted key, when the policy requires it.
+fn unverified_plugins(orch: &Graph, state: &state::AppState) -> Vec<String> {
+    match state.trust.as_deref().filter(|t| t.policy == SignaturePolicy::Require) {
+        Some(trust) => orch
+            .stage_modules()
+            .iter()
+            .filter_map(|&(name, module)| {
+                let reason = verify_plugin_signature(&state.plugins_base.join(module), &trust.keys).problem()?;
+                Some(format!("plugin {}: {}", name, reason))
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct BatchRequest {
+    orchestration: String,
+    /// Glob pattern or directory; replaces the orchestration's `input.source`.
+    input_path: Option<String>,
+    /// Jobs run at a time (default 2).
+    parallelism: Option<usize>,
+}
+
+/// `POST /pipeline/batch`: one job per file the input glob or directory matches, under a
+/// parent batch id. Jobs wait as `pending` until one of the batch's slots is free; a
+/// failed job does not stop the others.
+async fn run_batch(
+    State(state): State<state::AppState>,
+    Json(body): Json<BatchRequest>,
+) -> impl IntoResponse {
+    let mut orch = match Graph::from_toml(&body.orchestration) {
+        Ok(o) => o,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        }
+    };
+    if let Some(ref p) = body.input_path {
+        orch.input.source = p.clone();
+    }
+    // Clients never choose where the daemon writes; batch jobs keep no intermediates.
+    orch.debug = None;
+    if let Err(e) = check_paths(&orch, &state) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": e.to_string(), "kind": e.kind()})),
+        );
+    }
+    let drift = lock_drift(&orch, &state);
+    if !drift.is_empty() {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "plugin integrity check failed", "errors": drift})),
+        );
+    }
+    let unsigned = unverified_plugins(&orch, &state);
+    if !unsigned.is_empty() {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "plugin signature check failed", "errors": unsigned})),
+        );
+    }
+    let batch = match Batch::expand(&orch.input.source, &state.paths) {
+        Ok(b) => b,
+        Err(e) => {
+            return match e.downcast_ref::<PathError>() {
+                Some(pe) => (
+                    StatusCode::FORBIDDEN,
+                    Json(serde_json::json!({"error": pe.to_string(), "kind": pe.kind()})),
+                ),
+                None => (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": e.to_string()})),
+                ),
+            };
+        }
+    };
+
+    let batch_id = uuid::Uuid::new_v4().to_string();
+    let mut children = Vec::new();
+    let mut runners = std::collections::VecDeque::new();
+    for input in &batch.inputs {
+        let job_id = uuid::Uuid::new_v4().to_string();
+        let mut orchestration = orch.clone();
+        orchestration.input.source = input.to_string_lossy().to_string();
+        let spec = state::JobSpec {
+            orchestration,
+            keep_intermediates: false,
+        };
+        state.jobs.set_status(&job_id, state::JobStatus::Pending);
+        state.jobs.set_spec(&job_id, spec.clone());
+        // Registered now so a pending job can be cancelled before it starts.
+        let cancel = CancellationToken::new();
+        state.jobs.register_cancel(&job_id, cancel.clone());
+        runners.push_back((job_id.clone(), job_runner(&state, &job_id, spec, false, cancel)));
+        children.push((job_id, input.to_string_lossy().to_string()));
+    }
+    state.jobs.set_batch(&batch_id, children.clone());
+
+    let jobs = Arc::clone(&state.jobs);
+    let slots = body.parallelism.unwrap_or(2).clamp(1, runners.len());
+    let queue = std::sync::Mutex::new(runners);
+    tokio::task::spawn_blocking(move || {
+        std::thread::scope(|s| {
+            for _ in 0..slots {
+                s.spawn(|| loop {
+                    let Some((job_id, run)) = queue.lock().unwrap().pop_front() else {
+                        break;
+                    };
+                    // Jobs cancelled while pending are skipped.
+                    if jobs.get_status(&job_id).as_deref() == Some("pending") {
+                        jobs.set_status(&job_id, state::JobStatus::Running);
+                        run();
+                    } else {
+                        jobs.set_cancelled(&job_id);
+                    }
+                });
+            }
+        });
     });
+    let list: Vec<_> = children
+        .iter()
+        .map(|(job_id, input)| serde_json::json!({"job_id": job_id, "input": input}))
+        .collect();
     (
         StatusCode::ACCEPTED,
-        Json(serde_json::json!({"job_id": response_job_id})),
+        Json(serde_json::json!({"batch_id": batch_id, "jobs": list})),
+    )
+}
+
+/// `GET /batches/:id`: each job's status and a count per status. The batch is `running`
+/// while any job is pending, running or cancelling, then `completed`.
+async fn batch_status(
+    State(state): State<state::AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(children) = state.jobs.get_batch(&id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "batch not found"})),
+        );
+    };
+    let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+    let jobs: Vec<_> = children
+        .iter()
+        .map(|(job_id, input)| {
+            let status = state.jobs.get_status(job_id).unwrap_or_default();
+            *counts.entry(status.clone()).or_default() += 1;
+            serde_json::json!({
+                "job_id": job_id,
+                "input": input,
+                "status": status,
+                "error": state.jobs.get_error(job_id),
+            })
+        })
+        .collect();
+    let running = ["pending", "running", "cancelling"].iter().any(|s| counts.contains_key(*s));
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "batch_id": id,
+            "status": if running { "running" } else { "completed" },
+            "total": children.len(),
+            "counts": counts,
+            "jobs": jobs,
+        })),
     )
 }
 
+/// `DELETE /batches/:id`: cancel the batch's pending and running jobs.
+async fn cancel_batch(
+    State(state): State<state::AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let Some(children) = state.jobs.get_batch(&id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "batch not found"})),
+        );
+    };
+    let mut cancelled = 0;
+    for (job_id, _) in &children {
+        if matches!(state.jobs.get_status(job_id).as_deref(), Some("pending" | "running")) {
+            state.jobs.cancel(job_id);
+            cancelled += 1;
+        }
+    }
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({"batch_id": id, "cancelled": cancelled})),
+    )
+}
+
+/// `POST /jobs/:id/resume`: rerun a failed or cancelled job from its first incomplete chunk.
+/// A job still `cancelling` is refused, so two runners never share its work directory.
+async fn resume_job(
+    State(state): State<state::AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if state.work_root.is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "resuming is not enabled on this daemon (set CRUSTY_WORK_DIR)"})),
+        );
+    }
+    match state.jobs.restart(&id) {
+        Some(Ok(spec)) => {
+            start_job(&state, &id, spec, true);
+            (
+                StatusCode::ACCEPTED,
+                Json(serde_json::json!({"job_id": id, "status": "running"})),
+            )
+        }
+        Some(Err(status)) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "only failed or cancelled jobs can be resumed", "status": status})),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "job not found"})),
+        ),
+    }
+}
+
+/// Reject modules outside the plugin roots, and inputs or source files outside the data
+/// root, before a job is accepted. Missing paths are left for the job itself to report.
+fn check_paths(orch: &Graph, state: &state::AppState) -> Result<(), PathError> {
+    let ok_if_missing = |r: Result<_, PathError>| match r {
+        Err(PathError::NotFound { .. }) => Ok(()),
+        r => r.map(|_| ()),
+    };
+    for (_, module) in orch.stage_modules() {
+        ok_if_missing(state.paths.resolve_module(&state.plugins_base, module))?;
+    }
+    for source in orch.nodes.iter().filter_map(|n| n.path.as_deref()) {
+        ok_if_missing(state.paths.resolve_input(source))?;
+    }
+    ok_if_missing(state.paths.resolve_input(&orch.input.source))
+}
+
 async fn job_status(
     State(state): State<state::AppState>,
     Path(id): Path<String>,
@@ -163,7 +560,51 @@ async fn job_status(
     match state.jobs.get_status(&id) {
         Some(s) => (
             StatusCode::OK,
-            Json(serde_json::json!({"job_id": id, "status": s})),
+            Json(serde_json::json!({
+                "job_id": id,
+                "status": s,
+                "progress": state.jobs.get_progress(&id).map(|p| (p * 1000.0).round() / 10.0).unwrap_or(0.0),
+                "audio": state.jobs.get_audio_info(&id),
+                "report": state.jobs.get_report(&id),
+                "outputs": state.jobs.get_outputs(&id).unwrap_or_default().iter().map(|o| &o.name).collect::<Vec<_>>(),
+            })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "job not found"})),
+        ),
+    }
+}
+
+/// `DELETE /jobs/:id` and `POST /jobs/:id/cancel`: stop a running job.
+async fn cancel_job(
+    State(state): State<state::AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.jobs.cancel(&id) {
+        Some(true) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({"job_id": id, "status": state.jobs.get_status(&id)})),
+        ),
+        Some(false) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "job already finished", "status": state.jobs.get_status(&id)})),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "job not found"})),
+        ),
+    }
+}
+
+async fn job_events(
+    State(state): State<state::AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.jobs.get_status(&id) {
+        Some(_) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"job_id": id, "events": state.jobs.get_events(&id).unwrap_or_default()})),
         ),
         None => (
             StatusCode::NOT_FOUND,
@@ -178,23 +619,115 @@ async fn job_stream(
 ) -> Response {
     match state.jobs.get_output(&id) {
         Some(audio) => {
+            let content_type = if state.jobs.get_audio_info(&id).is_some() {
+                "audio/wav"
+            } else {
+                "application/octet-stream"
+            };
             let mut res = Response::new(Body::from(audio));
-            res.headers_mut().insert(
-                header::CONTENT_TYPE,
-                header::HeaderValue::from_static("application/octet-stream"),
-            );
+            res.headers_mut().insert(header::CONTENT_TYPE, header::HeaderValue::from_static(content_type));
             res
         }
         None => (StatusCode::NOT_FOUND, "job not found").into_response(),
     }
 }
 
-pub use state::{AppState, JobState, JobStatus};
+/// `GET /jobs/:id/outputs`: the `[[outputs]]` branches a completed job produced.
+async fn job_outputs(
+    State(state): State<state::AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.jobs.get_outputs(&id) {
+        Some(outputs) => {
+            let list: Vec<_> = outputs
+                .iter()
+                .map(|o| serde_json::json!({"name": o.name, "path": o.path, "content_type": o.audio_type, "bytes": o.audio.len()}))
+                .collect();
+            (StatusCode::OK, Json(serde_json::json!({"job_id": id, "outputs": list})))
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "no outputs for job"})),
+        ),
+    }
+}
+
+/// `GET /jobs/:id/outputs/:name`: one branch's audio.
+async fn job_output_file(
+    State(state): State<state::AppState>,
+    Path((id, name)): Path<(String, String)>,
+) -> Response {
+    let output = state.jobs.get_outputs(&id).and_then(|o| o.into_iter().find(|o| o.name == name));
+    match output {
+        Some(output) => {
+            let mut res = Response::new(Body::from(output.audio));
+            if let Ok(v) = header::HeaderValue::from_str(&output.audio_type) {
+                res.headers_mut().insert(header::CONTENT_TYPE, v);
+            }
+            res
+        }
+        None => (StatusCode::NOT_FOUND, "output not found").into_response(),
+    }
+}
+
+/// Artifact directory of a known job that was run with intermediates.
+fn intermediates_dir(state: &state::AppState, id: &str) -> Option<std::path::PathBuf> {
+    state.jobs.get_status(id)?;
+    let dir = state.intermediates.as_ref()?.join(id);
+    dir.join(crusty_core::artifacts::MANIFEST_NAME).exists().then_some(dir)
+}
+
+/// `GET /jobs/:id/intermediates`: the job's artifact manifest.
+async fn job_intermediates(
+    State(state): State<state::AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match intermediates_dir(&state, &id).map(|dir| ArtifactManifest::load(&dir)) {
+        Some(Ok(manifest)) => (StatusCode::OK, Json(serde_json::json!({"job_id": id, "stages": manifest.stages}))),
+        Some(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "no intermediates for job"})),
+        ),
+    }
+}
+
+/// `GET /jobs/:id/intermediates/:file`: one artifact; only files listed in the manifest are served.
+async fn job_intermediate_file(
+    State(state): State<state::AppState>,
+    Path((id, file)): Path<(String, String)>,
+) -> Response {
+    let Some(dir) = intermediates_dir(&state, &id) else {
+        return (StatusCode::NOT_FOUND, "no intermediates for job").into_response();
+    };
+    let manifest = match ArtifactManifest::load(&dir) {
+        Ok(m) => m,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let Some(artifact) = manifest.find(&file) else {
+        return (StatusCode::NOT_FOUND, "artifact not found").into_response();
+    };
+    match std::fs::read(dir.join(&artifact.file)) {
+        Ok(bytes) => {
+            let mut res = Response::new(Body::from(bytes));
+            if let Ok(v) = header::HeaderValue::from_str(&artifact.content_type) {
+                res.headers_mut().insert(header::CONTENT_TYPE, v);
+            }
+            res
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+pub use state::{AppState, JobEvent, JobObserver, JobSpec, JobState, JobStatus};
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crusty_core::PluginRegistry;
+    use crusty_core::{PluginRegistry, WorkerPool};
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use std::path::PathBuf;
@@ -206,6 +739,13 @@ mod tests {
             registry: Arc::new(PluginRegistry::load_plugins(PathBuf::from("plugins").as_path()).unwrap_or_default()),
             plugins_base: PathBuf::from("."),
             jobs: Arc::new(JobState::default()),
+            workers: Arc::new(WorkerPool::default()),
+            lock: None,
+            trust: None,
+            paths: crusty_core::PathPolicy::default(),
+            cache: None,
+            intermediates: None,
+            work_root: None,
         }
     }
 
@@ -254,6 +794,492 @@ mod tests {
         assert_eq!(res.status(), StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn run_pipeline_locked_rejects_unlocked_plugin() {
+        let mut state = test_app_state();
+        state.lock = Some(Arc::new(crusty_core::LockCheck {
+            lock: crusty_core::Lockfile { version: 1, plugins: vec![] },
+            mode: crusty_core::IntegrityMode::Enforce,
+        }));
+        let app = build_app(state);
+        let orch = r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "sample-tts"
+module = "plugins/sample-tts"
+[output]
+type = "file"
+path = "out.bin"
+"#;
+        let req = Request::builder()
+            .method("POST")
+            .uri("/pipeline/run")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({ "orchestration": orch }).to_string()))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn run_pipeline_requires_signed_plugins_under_require() {
+        let mut state = test_app_state();
+        state.trust = Some(Arc::new(crusty_core::TrustConfig {
+            policy: crusty_core::SignaturePolicy::Require,
+            keys: crusty_core::TrustedKeys::default(),
+        }));
+        let app = build_app(state);
+        let orch = r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "sample-tts"
+module = "plugins/sample-tts"
+[output]
+type = "file"
+path = "out.bin"
+"#;
+        let res = app.oneshot(run_request(orch, None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["errors"][0], "plugin sample-tts: unsigned");
+    }
+
+    fn run_request(orch: &str, input_path: Option<&str>) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/pipeline/run")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "orchestration": orch, "input_path": input_path }).to_string(),
+            ))
+            .unwrap()
+    }
+
+    const TRAVERSAL_ORCH: &str = r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "evil"
+module = "../../../../../../bin"
+[output]
+type = "file"
+path = "out.bin"
+"#;
+
+    #[tokio::test]
+    async fn run_pipeline_rejects_module_traversal() {
+        let app = build_app(test_app_state());
+        let res = app.oneshot(run_request(TRAVERSAL_ORCH, None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["kind"], "module_escapes_root");
+    }
+
+    #[tokio::test]
+    async fn run_pipeline_rejects_input_outside_data_root() {
+        let data = tempfile::tempdir().unwrap();
+        let mut state = test_app_state();
+        state.paths.data_root = Some(data.path().to_path_buf());
+        let app = build_app(state);
+        let orch = TRAVERSAL_ORCH.replace("../../../../../../bin", "src");
+        let res = app.oneshot(run_request(&orch, Some("/etc/hostname"))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn completed_wav_job_reports_audio_metadata() {
+        let state = test_app_state();
+        let wav = crusty_core::Audio::silence(crusty_core::audio::DEFAULT_RAW_FORMAT, std::time::Duration::from_secs(1));
+        state.jobs.set_completed("job-1", wav.to_wav());
+        let app = build_app(state);
+        let req = Request::builder().uri("/jobs/job-1/status").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["audio"]["sample_rate"], 22050);
+        assert_eq!(json["audio"]["duration_secs"], 1.0);
+
+        let req = Request::builder().uri("/jobs/job-1/stream").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "audio/wav");
+    }
+
+    #[tokio::test]
+    async fn job_observer_reports_progress_and_events() {
+        use crusty_core::{PipelineObserver, StageInfo};
+        let state = test_app_state();
+        state.jobs.set_status("job-2", JobStatus::Running);
+        let observer = JobObserver { job_id: "job-2".into(), jobs: Arc::clone(&state.jobs) };
+        let tts = StageInfo { index: 0, total: 2, kind: "TTS".into(), name: "tts".into(), branch: None };
+        observer.stage_started(&tts, 120);
+        observer.chunk_finished(&tts, 1, 4);
+        observer.warning("cache write failed");
+        let app = build_app(state);
+
+        let req = Request::builder().uri("/jobs/job-2/status").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["progress"], 12.5);
+
+        let req = Request::builder().uri("/jobs/job-2/events").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let kinds: Vec<&str> = json["events"].as_array().unwrap().iter().map(|e| e["event"].as_str().unwrap()).collect();
+        assert_eq!(kinds, vec!["stage_started", "chunk_finished", "warning"]);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_running_job_and_rejects_finished_ones() {
+        let state = test_app_state();
+        let token = CancellationToken::new();
+        state.jobs.set_status("job-3", JobStatus::Running);
+        state.jobs.register_cancel("job-3", token.clone());
+        state.jobs.set_completed("job-4", b"done".to_vec());
+        let app = build_app(state.clone());
+
+        let req = Request::builder().method("DELETE").uri("/jobs/job-3").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(token.is_cancelled());
+
+        let status = |app: Router| async move {
+            let req = Request::builder().uri("/jobs/job-3/status").body(Body::empty()).unwrap();
+            let res = app.oneshot(req).await.unwrap();
+            let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["status"].clone()
+        };
+        // Cancelling until the runner exits.
+        assert_eq!(status(app.clone()).await, "cancelling");
+        state.jobs.set_cancelled("job-3");
+        assert_eq!(status(app.clone()).await, "cancelled");
+
+        let req = Request::builder().method("POST").uri("/jobs/job-4/cancel").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let req = Request::builder().method("POST").uri("/jobs/missing/cancel").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn jobs_cancelled_as_they_finish_stay_cancelled() {
+        let jobs = state::JobState::default();
+        for (job_id, failed) in [("job-9", false), ("job-10", true)] {
+            let token = CancellationToken::new();
+            jobs.set_status(job_id, JobStatus::Running);
+            jobs.register_cancel(job_id, token);
+            // The cancel lands after the runner checked its token.
+            assert_eq!(jobs.cancel(job_id), Some(true));
+            if failed {
+                jobs.set_failed(job_id, "killed".into());
+            } else {
+                jobs.set_completed(job_id, b"done".to_vec());
+            }
+            assert_eq!(jobs.get_status(job_id).as_deref(), Some("cancelled"), "{job_id}");
+            assert!(jobs.get_output(job_id).is_none() && jobs.get_error(job_id).is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn intermediates_are_served_only_when_enabled_and_listed() {
+        // Without an intermediates root the daemon refuses rather than writing where the request says.
+        let orch = format!("{}[debug]\nkeep_intermediates = \"/etc\"\n", TRAVERSAL_ORCH.replace("../../../../../../bin", "plugins/tts"));
+        let res = build_app(test_app_state()).oneshot(run_request(&orch, None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = test_app_state();
+        state.intermediates = Some(dir.path().to_path_buf());
+        state.jobs.set_completed("job-5", b"done".to_vec());
+        let writer = crusty_core::IntermediateWriter::create(&dir.path().join("job-5")).unwrap();
+        let stage = crusty_core::StageInfo { index: 0, total: 1, kind: "TTS".into(), name: "t".into(), branch: None };
+        writer.stage_input(&stage, b"hi", "text/plain").unwrap();
+        writer.stage_output(&stage, b"RIFF", "audio/wav").unwrap();
+        std::fs::write(dir.path().join("job-5/secret.txt"), "x").unwrap();
+        let app = build_app(state);
+
+        let req = Request::builder().uri("/jobs/job-5/intermediates").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["stages"][0]["output"]["file"], "00-tts-t.output.wav");
+
+        let req = Request::builder().uri("/jobs/job-5/intermediates/00-tts-t.output.wav").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "audio/wav");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"RIFF");
+
+        for uri in ["/jobs/job-5/intermediates/secret.txt", "/jobs/missing/intermediates"] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn branch_outputs_are_listed_and_served_by_name() {
+        let state = test_app_state();
+        state.jobs.set_completed("job-8", b"wav".to_vec());
+        state.jobs.set_outputs(
+            "job-8",
+            vec![
+                crusty_core::BranchOutput { name: "wav".into(), path: "out.wav".into(), audio: b"wav".to_vec(), audio_type: "audio/wav".into() },
+                crusty_core::BranchOutput { name: "mp3".into(), path: "out.mp3".into(), audio: b"ID3".to_vec(), audio_type: "audio/mpeg".into() },
+            ],
+        );
+        let app = build_app(state);
+
+        let req = Request::builder().uri("/jobs/job-8/outputs").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["outputs"][1]["name"], "mp3");
+        assert_eq!(json["outputs"][1]["bytes"], 3);
+
+        let req = Request::builder().uri("/jobs/job-8/outputs/mp3").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "audio/mpeg");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"ID3");
+
+        for uri in ["/jobs/job-8/outputs/flac", "/jobs/missing/outputs"] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resume_waits_for_the_cancelled_runner_to_exit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let data = tempfile::tempdir().unwrap();
+        std::fs::write(data.path().join("in.txt"), "Hello").unwrap();
+        let plugins = tempfile::tempdir().unwrap();
+        let tts = plugins.path().join("plugins/slow");
+        std::fs::create_dir_all(&tts).unwrap();
+        std::fs::write(tts.join("run.sh"), "#!/bin/sh\nsleep 5\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+        std::fs::set_permissions(tts.join("run.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let work = tempfile::tempdir().unwrap();
+        let mut state = test_app_state();
+        state.plugins_base = plugins.path().to_path_buf();
+        state.paths.data_root = Some(data.path().to_path_buf());
+        state.work_root = Some(work.path().to_path_buf());
+        let app = build_app(state.clone());
+        let post = |uri: &str| Request::builder().method("POST").uri(uri).body(Body::empty()).unwrap();
+
+        let orch = TRAVERSAL_ORCH.replace("../../../../../../bin", "plugins/slow");
+        let res = app.clone().oneshot(run_request(&orch, None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let id = json["job_id"].as_str().unwrap().to_string();
+
+        // Cancel and resume at once: the resume is refused while the first runner is
+        // still stopping, and accepted once it has exited.
+        let res = app.clone().oneshot(post(&format!("/jobs/{id}/cancel"))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let mut resumed = false;
+        for _ in 0..100 {
+            let res = app.clone().oneshot(post(&format!("/jobs/{id}/resume"))).await.unwrap();
+            if res.status() == StatusCode::ACCEPTED {
+                resumed = true;
+                break;
+            }
+            assert_eq!(res.status(), StatusCode::CONFLICT);
+            assert_eq!(state.jobs.get_status(&id).as_deref(), Some("cancelling"));
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert!(resumed);
+
+        // The old runner is gone, so it cannot flip the resumed run's status or drop its
+        // token: the resumed run stays running and can itself be cancelled.
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(state.jobs.get_status(&id).as_deref(), Some("running"));
+        let res = app.clone().oneshot(post(&format!("/jobs/{id}/cancel"))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        for _ in 0..100 {
+            if state.jobs.get_status(&id).as_deref() == Some("cancelled") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(state.jobs.get_status(&id).as_deref(), Some("cancelled"));
+    }
+
+    #[tokio::test]
+    async fn resume_restarts_only_failed_or_cancelled_jobs() {
+        let post = |uri: &str| Request::builder().method("POST").uri(uri).body(Body::empty()).unwrap();
+        let res = build_app(test_app_state()).oneshot(post("/jobs/any/resume")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = test_app_state();
+        state.work_root = Some(dir.path().to_path_buf());
+        let spec = JobSpec {
+            orchestration: Graph::from_toml(&TRAVERSAL_ORCH.replace("../../../../../../bin", "plugins/missing")).unwrap(),
+            keep_intermediates: false,
+        };
+        state.jobs.set_failed("job-6", "boom".into());
+        state.jobs.set_spec("job-6", spec.clone());
+        state.jobs.set_completed("job-7", b"done".to_vec());
+        state.jobs.set_spec("job-7", spec);
+        let app = build_app(state.clone());
+
+        let res = app.clone().oneshot(post("/jobs/job-6/resume")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let res = app.clone().oneshot(post("/jobs/job-7/resume")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let res = app.oneshot(post("/jobs/missing/resume")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn batch_runs_one_job_per_input_and_reports_each() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let data = tempfile::tempdir().unwrap();
+        for (name, text) in [("a.txt", "Hello"), ("b.txt", "fail"), ("c.md", "skipped")] {
+            std::fs::write(data.path().join(name), text).unwrap();
+        }
+        let plugins = tempfile::tempdir().unwrap();
+        let tts = plugins.path().join("plugins/echo");
+        std::fs::create_dir_all(&tts).unwrap();
+        std::fs::write(tts.join("run.sh"), "#!/bin/sh\n[ \"$PLUGIN_INPUT\" = fail ] && exit 1\nprintf '%s' \"$PLUGIN_INPUT\"\n").unwrap();
+        std::fs::set_permissions(tts.join("run.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut state = test_app_state();
+        state.plugins_base = plugins.path().to_path_buf();
+        state.paths.data_root = Some(data.path().to_path_buf());
+        let app = build_app(state.clone());
+        let orch = TRAVERSAL_ORCH.replace("../../../../../../bin", "plugins/echo");
+        let batch = |input: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/pipeline/batch")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "orchestration": orch, "input_path": input, "parallelism": 2 }).to_string(),
+                ))
+                .unwrap()
+        };
+
+        let res = app.clone().oneshot(batch("*.txt")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let batch_id = json["batch_id"].as_str().unwrap().to_string();
+        assert_eq!(json["jobs"].as_array().unwrap().len(), 2);
+
+        let mut status = serde_json::Value::Null;
+        for _ in 0..100 {
+            let req = Request::builder().uri(format!("/batches/{batch_id}")).body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            status = serde_json::from_slice(&body).unwrap();
+            if status["status"] == "completed" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        assert_eq!(status["status"], "completed", "{status}");
+        assert_eq!(status["counts"], serde_json::json!({"completed": 1, "failed": 1}));
+        let jobs = status["jobs"].as_array().unwrap();
+        assert!(jobs[0]["input"].as_str().unwrap().ends_with("a.txt"));
+        assert_eq!(jobs[1]["status"], "failed");
+        assert!(jobs[1]["error"].as_str().is_some());
+        let a = jobs[0]["job_id"].as_str().unwrap();
+        assert_eq!(state.jobs.get_output(a).unwrap(), b"Hello");
+
+        let res = app.clone().oneshot(batch("../*.txt")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let res = app.clone().oneshot(batch("*.epub")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let req = Request::builder().uri("/batches/missing").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(req).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn plan_pipeline_lists_stages() {
+        let app = build_app(test_app_state());
+        let req = Request::builder()
+            .method("POST")
+            .uri("/pipeline/plan")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({ "orchestration": TRAVERSAL_ORCH }).to_string()))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["stages"][0]["kind"], "TTS");
+        assert_eq!(json["stages"][0]["name"], "evil");
+        assert!(json["stages"][0]["plugin"].is_null());
+        assert_eq!(json["warnings"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn graph_orchestrations_are_planned_and_cycles_rejected() {
+        let graph = |first_input: &str| {
+            format!(
+                "version = 2\n[meta]\nname = \"g\"\nversion = \"0.1\"\nauthor = \"a\"\n[input]\ntype = \"text\"\nsource = \"in.txt\"\n\
+                 [[nodes]]\nname = \"voice\"\nkind = \"tts\"\nmodule = \"plugins/tts\"\ninputs = [\"{first_input}\"]\n\
+                 [[nodes]]\nname = \"gain\"\nkind = \"post-processor\"\nmodule = \"plugins/gain\"\ninputs = [\"voice.out\"]\n\
+                 [output]\nfrom = \"gain.out\"\npath = \"out.wav\"\n"
+            )
+        };
+        let post = |uri: &str, orch: String| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "orchestration": orch }).to_string()))
+                .unwrap()
+        };
+        let app = build_app(test_app_state());
+        let res = app.clone().oneshot(post("/pipeline/plan", graph("input.text"))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["stages"][1]["node"], "gain");
+        assert_eq!(json["stages"][1]["inputs"][0], "voice.out");
+
+        let res = app.oneshot(post("/pipeline/validate", graph("gain.out"))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"], "graph has a cycle: voice -> gain -> voice");
+    }
+
     #[tokio::test]
     async fn validate_pipeline_valid_minimal_returns_200_or_errors() {
         let app = build_app(test_app_state());
@@ -282,4 +1308,94 @@ path = "out.bin"
         // May be 200 (valid) if plugins/tts exists with run.sh, or 400 (errors)
         assert!(res.status() == StatusCode::OK || res.status() == StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn validate_pipeline_stops_at_modules_outside_the_plugin_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("plugins");
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&base).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("plugin.toml"), "name = \"outside\"\nversion = \"0.1\"\ntype = \"tts\"\n").unwrap();
+        let mut state = test_app_state();
+        state.plugins_base = base;
+        let orch = r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "outside"
+module = "../outside"
+[output]
+type = "file"
+path = "out.bin"
+"#;
+        let req = Request::builder()
+            .method("POST")
+            .uri("/pipeline/validate")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({ "orchestration": orch }).to_string()))
+            .unwrap();
+        let res = build_app(state).oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["kind"], "module_escapes_root");
+        // Only the path error: nothing about the escaped module's executable or manifest.
+        assert_eq!(json["errors"].as_array().unwrap().len(), 1, "{}", json);
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn validate_pipeline_resolves_manifest_entrypoints() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = dir.path().join("rust-tts");
+        std::fs::create_dir_all(plugin.join("target/release")).unwrap();
+        std::fs::write(
+            plugin.join("plugin.toml"),
+            "name = \"rust-tts\"\nversion = \"0.1\"\ntype = \"tts\"\nentrypoint = \"target/release/rust-tts\"\n",
+        )
+        .unwrap();
+        let mut state = test_app_state();
+        state.plugins_base = dir.path().to_path_buf();
+        let orch = r#"
+[meta]
+name = "t"
+version = "0.1"
+author = "a"
+[input]
+type = "text"
+source = "in.txt"
+[tts]
+name = "rust-tts"
+module = "rust-tts"
+[output]
+type = "file"
+path = "out.bin"
+"#;
+        let validate = || {
+            Request::builder()
+                .method("POST")
+                .uri("/pipeline/validate")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "orchestration": orch }).to_string()))
+                .unwrap()
+        };
+        let res = build_app(state.clone()).oneshot(validate()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "the binary is not built yet");
+
+        let bin = plugin.join("target/release/rust-tts");
+        std::fs::write(&bin, "#!/bin/sh\ncat\n").unwrap();
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let res = build_app(state).oneshot(validate()).await.unwrap();
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(status, StatusCode::OK, "{}", String::from_utf8_lossy(&body));
+    }
 }
\ No newline at end of file
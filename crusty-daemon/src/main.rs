@@ -1,26 +1,110 @@
 //! Crusty-TTS daemon: REST API, job management. Uses same execute_pipeline from crusty-core.
 
 use crusty_daemon::{build_app, AppState};
-use crusty_core::PluginRegistry;
+use crusty_core::{
+    IntegrityMode, LockCheck, Lockfile, PathPolicy, PluginRegistry, SignaturePolicy, SynthesisCache, TrustConfig,
+    TrustedKeys, WorkerPool, WorkerPoolConfig, DEFAULT_CACHE_MAX_BYTES,
+};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let port = std::env::var("CRUSTY_PORT").unwrap_or_else(|_| "7420".to_string());
     let plugins_dir = std::env::var("CRUSTY_PLUGINS").unwrap_or_else(|_| "plugins".to_string());
     let plugins_path = PathBuf::from(&plugins_dir);
-    let registry = PluginRegistry::load_plugins(&plugins_path).unwrap_or_default();
+    let trust = TrustConfig {
+        policy: std::env::var("CRUSTY_SIGNATURE_POLICY")
+            .ok()
+            .map(|s| s.parse::<SignaturePolicy>())
+            .transpose()?
+            .unwrap_or_default(),
+        keys: std::env::var("CRUSTY_TRUSTED_KEYS")
+            .ok()
+            .map(|p| TrustedKeys::load(&PathBuf::from(p)))
+            .transpose()?
+            .unwrap_or_default(),
+    };
+    let registry = PluginRegistry::load_plugins_with_trust(&plugins_path, &trust).unwrap_or_default();
+    for w in &registry.warnings {
+        eprintln!("warning: {}", w);
+    }
+    for (name, reason) in &registry.rejected {
+        eprintln!("Rejected plugin {}: {}", name, reason);
+    }
+    let mut pool_config = WorkerPoolConfig::default();
+    if let Some(n) = std::env::var("CRUSTY_WORKERS").ok().and_then(|s| s.parse().ok()) {
+        pool_config.pool_size = n;
+    }
+    if let Some(secs) = std::env::var("CRUSTY_WORKER_IDLE_SECS").ok().and_then(|s| s.parse().ok()) {
+        pool_config.idle_timeout = Duration::from_secs(secs);
+    }
+    let workers = Arc::new(WorkerPool::new(pool_config));
+
+    // --locked: refuse jobs whose plugins are missing from, or drift from, the lockfile.
+    let locked = std::env::args().any(|a| a == "--locked");
+    let lock = if locked {
+        let lock_path = PathBuf::from(std::env::var("CRUSTY_LOCK").unwrap_or_else(|_| "crusty.lock".to_string()));
+        let lock = Lockfile::load(&lock_path)?;
+        eprintln!("Locked mode: {} plugins pinned by {}", lock.plugins.len(), lock_path.display());
+        Some(Arc::new(LockCheck {
+            lock,
+            mode: IntegrityMode::Enforce,
+        }))
+    } else {
+        None
+    };
+    // Request input paths are confined to the data root (default: working directory).
+    let paths = PathPolicy {
+        data_root: Some(PathBuf::from(std::env::var("CRUSTY_DATA_ROOT").unwrap_or_else(|_| ".".to_string()))),
+        ..Default::default()
+    };
+    let cache = match std::env::var("CRUSTY_CACHE_DIR") {
+        Ok(dir) => {
+            let max_bytes = std::env::var("CRUSTY_CACHE_MAX_MB")
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .map(|mb| mb * 1024 * 1024)
+                .unwrap_or(DEFAULT_CACHE_MAX_BYTES);
+            Some(Arc::new(SynthesisCache::open(&PathBuf::from(dir), max_bytes)?))
+        }
+        Err(_) => None,
+    };
+    let jobs = Arc::new(crusty_daemon::JobState::default());
     let app_state = AppState {
         registry: Arc::new(registry),
         plugins_base: plugins_path,
-        jobs: Arc::new(crusty_daemon::JobState::default()),
+        jobs: Arc::clone(&jobs),
+        workers: Arc::clone(&workers),
+        lock,
+        trust: Some(Arc::new(trust)),
+        paths,
+        cache,
+        intermediates: std::env::var("CRUSTY_INTERMEDIATES_DIR").ok().map(PathBuf::from),
+        work_root: std::env::var("CRUSTY_WORK_DIR").ok().map(PathBuf::from),
     };
 
+    // Idle shutdown for persistent workers.
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            tick.tick().await;
+            let pool = Arc::clone(&workers);
+            let _ = tokio::task::spawn_blocking(move || pool.reap_idle()).await;
+        }
+    });
+
     let app = build_app(app_state);
     let addr = format!("0.0.0.0:{}", port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     eprintln!("Crusty-TTS daemon listening on http://{}", addr);
-    axum::serve(listener, app).await?;
+    // On Ctrl-C, cancel running jobs (killing their plugins) before exiting.
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            jobs.cancel_all();
+        })
+        .await?;
     Ok(())
 }
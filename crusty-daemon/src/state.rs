@@ -1,5 +1,9 @@
-use crusty_core::PluginRegistry;
-use std::collections::HashMap;
+use crusty_core::{
+    audio, AudioInfo, BranchOutput, CancellationToken, Graph, LockCheck, PathPolicy, PipelineObserver, PipelineReport, PluginRegistry, ProgressFrame,
+    StageInfo, SynthesisCache, TrustConfig, WorkerPool,
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
@@ -8,6 +12,31 @@ pub struct AppState {
     pub registry: Arc<PluginRegistry>,
     pub plugins_base: PathBuf,
     pub jobs: Arc<JobState>,
+    /// Warm workers for persistent plugins, shared across jobs.
+    pub workers: Arc<WorkerPool>,
+    /// Set in `--locked` mode: jobs whose plugins drift from the lockfile are refused.
+    pub lock: Option<Arc<LockCheck>>,
+    /// Signature policy and trusted keys: under `require`, jobs using a plugin that is not
+    /// signed by a trusted key are refused, and each plugin is checked again before it runs.
+    pub trust: Option<Arc<TrustConfig>>,
+    /// Confinement for request-supplied modules and input paths.
+    pub paths: PathPolicy,
+    /// Shared synthesis cache, when configured.
+    pub cache: Option<Arc<SynthesisCache>>,
+    /// Root for jobs run with `keep_intermediates`; each job writes to `<root>/<job_id>`.
+    /// Jobs asking for intermediates are refused when unset.
+    pub intermediates: Option<PathBuf>,
+    /// Root for job work directories (`<root>/<job_id>`). When set, TTS chunks are
+    /// checkpointed and failed or cancelled jobs can be resumed.
+    pub work_root: Option<PathBuf>,
+}
+
+/// What a job was started with, kept so it can be resumed.
+#[derive(Debug, Clone)]
+pub struct JobSpec {
+    /// v1 orchestrations are stored as their converted graph.
+    pub orchestration: Graph,
+    pub keep_intermediates: bool,
 }
 
 #[derive(Clone)]
@@ -28,6 +57,33 @@ struct JobStateInner {
     status: HashMap<String, String>,
     output: HashMap<String, Vec<u8>>,
     error: HashMap<String, String>,
+    /// Decoded metadata for outputs the core audio module recognizes.
+    audio: HashMap<String, AudioInfo>,
+    report: HashMap<String, PipelineReport>,
+    /// Overall completion, 0.0 to 1.0.
+    progress: HashMap<String, f64>,
+    events: HashMap<String, VecDeque<JobEvent>>,
+    /// Tokens of jobs whose runner has not exited yet.
+    cancel: HashMap<String, CancellationToken>,
+    specs: HashMap<String, JobSpec>,
+    /// `[[outputs]]` branch results of completed jobs.
+    outputs: HashMap<String, Vec<BranchOutput>>,
+    /// Child jobs of each batch with their inputs, in input order.
+    batches: HashMap<String, Vec<(String, String)>>,
+}
+
+/// Events kept per job; older ones are dropped first.
+const MAX_JOB_EVENTS: usize = 500;
+
+/// Pipeline event recorded for `/jobs/:id/events`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JobEvent {
+    StageStarted { stage: StageInfo, bytes_in: usize },
+    StageFinished { stage: StageInfo, bytes_out: usize },
+    ChunkFinished { stage: String, done: usize, total: usize },
+    PluginProgress { stage: String, fraction: Option<f64>, message: Option<String> },
+    Warning { message: String },
 }
 
 impl JobState {
@@ -37,22 +93,150 @@ impl JobState {
             JobStatus::Running => "running",
             JobStatus::Completed => "completed",
             JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
         };
         self.inner.write().unwrap().status.insert(job_id.to_string(), s.to_string());
     }
 
+    /// Whether a job was cancelled, and if so record that its runner has stopped. Called
+    /// under the lock that records the outcome, so a cancel that lands after the runner's
+    /// own check is not overwritten.
+    fn finish_cancelled(g: &mut JobStateInner, job_id: &str) -> bool {
+        if !matches!(g.status.get(job_id).map(String::as_str), Some("cancelling" | "cancelled")) {
+            return false;
+        }
+        g.cancel.remove(job_id);
+        g.status.insert(job_id.to_string(), "cancelled".to_string());
+        true
+    }
+
+    /// Record a job's output. A job cancelled meanwhile stays cancelled.
     pub fn set_completed(&self, job_id: &str, output: Vec<u8>) {
         let mut g = self.inner.write().unwrap();
+        if Self::finish_cancelled(&mut g, job_id) {
+            return;
+        }
+        g.cancel.remove(job_id);
         g.status.insert(job_id.to_string(), "completed".to_string());
+        g.progress.insert(job_id.to_string(), 1.0);
+        if let Some(info) = audio::sniff(&output) {
+            g.audio.insert(job_id.to_string(), info);
+        }
         g.output.insert(job_id.to_string(), output);
     }
 
+    /// Record a job's error. A job cancelled meanwhile stays cancelled.
     pub fn set_failed(&self, job_id: &str, err: String) {
         let mut g = self.inner.write().unwrap();
+        if Self::finish_cancelled(&mut g, job_id) {
+            return;
+        }
+        g.cancel.remove(job_id);
         g.status.insert(job_id.to_string(), "failed".to_string());
         g.error.insert(job_id.to_string(), err);
     }
 
+    pub fn get_error(&self, job_id: &str) -> Option<String> {
+        self.inner.read().unwrap().error.get(job_id).cloned()
+    }
+
+    pub fn set_batch(&self, batch_id: &str, jobs: Vec<(String, String)>) {
+        self.inner.write().unwrap().batches.insert(batch_id.to_string(), jobs);
+    }
+
+    /// `(job_id, input)` of each job in a batch.
+    pub fn get_batch(&self, batch_id: &str) -> Option<Vec<(String, String)>> {
+        self.inner.read().unwrap().batches.get(batch_id).cloned()
+    }
+
+    pub fn set_spec(&self, job_id: &str, spec: JobSpec) {
+        self.inner.write().unwrap().specs.insert(job_id.to_string(), spec);
+    }
+
+    /// Mark a failed or cancelled job running again and return its spec. `None` if the
+    /// job is unknown; `Err(status)` if it is in any other state, `cancelling` included:
+    /// a job is resumed only once its previous runner has exited.
+    pub fn restart(&self, job_id: &str) -> Option<Result<JobSpec, String>> {
+        let mut g = self.inner.write().unwrap();
+        let status = g.status.get(job_id)?.clone();
+        match (status.as_str(), g.specs.get(job_id).cloned()) {
+            ("failed" | "cancelled", Some(spec)) => {
+                g.status.insert(job_id.to_string(), "running".to_string());
+                g.error.remove(job_id);
+                Some(Ok(spec))
+            }
+            _ => Some(Err(status)),
+        }
+    }
+
+    /// Token that cancels a running job via [`JobState::cancel`].
+    pub fn register_cancel(&self, job_id: &str, token: CancellationToken) {
+        self.inner.write().unwrap().cancel.insert(job_id.to_string(), token);
+    }
+
+    /// Record that a job's runner stopped because it was cancelled.
+    pub fn set_cancelled(&self, job_id: &str) {
+        let mut g = self.inner.write().unwrap();
+        g.cancel.remove(job_id);
+        g.status.insert(job_id.to_string(), "cancelled".to_string());
+    }
+
+    /// Cancel a pending or running job. It is `cancelling` until its runner exits and
+    /// calls [`JobState::set_cancelled`]; without a runner it is `cancelled` at once.
+    /// `None` if the job is unknown, `Some(false)` if it had already completed or failed.
+    /// Cancelling twice is not an error.
+    pub fn cancel(&self, job_id: &str) -> Option<bool> {
+        let mut g = self.inner.write().unwrap();
+        match g.status.get(job_id)?.as_str() {
+            "pending" | "running" => {
+                let status = match g.cancel.get(job_id) {
+                    Some(token) => {
+                        token.cancel();
+                        "cancelling"
+                    }
+                    None => "cancelled",
+                };
+                g.status.insert(job_id.to_string(), status.to_string());
+                Some(true)
+            }
+            "cancelling" | "cancelled" => Some(true),
+            _ => Some(false),
+        }
+    }
+
+    /// Cancel every running job (daemon shutdown).
+    pub fn cancel_all(&self) {
+        let mut g = self.inner.write().unwrap();
+        for (job_id, token) in std::mem::take(&mut g.cancel) {
+            token.cancel();
+            g.status.insert(job_id, "cancelled".to_string());
+        }
+    }
+
+    /// Raise a job's progress; it never moves backwards.
+    pub fn set_progress(&self, job_id: &str, fraction: f64) {
+        let mut g = self.inner.write().unwrap();
+        let p = g.progress.entry(job_id.to_string()).or_insert(0.0);
+        *p = p.max(fraction.clamp(0.0, 1.0));
+    }
+
+    pub fn get_progress(&self, job_id: &str) -> Option<f64> {
+        self.inner.read().unwrap().progress.get(job_id).copied()
+    }
+
+    pub fn push_event(&self, job_id: &str, event: JobEvent) {
+        let mut g = self.inner.write().unwrap();
+        let events = g.events.entry(job_id.to_string()).or_default();
+        if events.len() == MAX_JOB_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    pub fn get_events(&self, job_id: &str) -> Option<Vec<JobEvent>> {
+        self.inner.read().unwrap().events.get(job_id).map(|e| e.iter().cloned().collect())
+    }
+
     pub fn get_status(&self, job_id: &str) -> Option<String> {
         self.inner.read().unwrap().status.get(job_id).cloned()
     }
@@ -60,6 +244,26 @@ impl JobState {
     pub fn get_output(&self, job_id: &str) -> Option<Vec<u8>> {
         self.inner.read().unwrap().output.get(job_id).cloned()
     }
+
+    pub fn set_outputs(&self, job_id: &str, outputs: Vec<BranchOutput>) {
+        self.inner.write().unwrap().outputs.insert(job_id.to_string(), outputs);
+    }
+
+    pub fn get_outputs(&self, job_id: &str) -> Option<Vec<BranchOutput>> {
+        self.inner.read().unwrap().outputs.get(job_id).cloned()
+    }
+
+    pub fn set_report(&self, job_id: &str, report: PipelineReport) {
+        self.inner.write().unwrap().report.insert(job_id.to_string(), report);
+    }
+
+    pub fn get_report(&self, job_id: &str) -> Option<PipelineReport> {
+        self.inner.read().unwrap().report.get(job_id).cloned()
+    }
+
+    pub fn get_audio_info(&self, job_id: &str) -> Option<AudioInfo> {
+        self.inner.read().unwrap().audio.get(job_id).cloned()
+    }
 }
 
 #[allow(dead_code)]
@@ -68,4 +272,49 @@ pub enum JobStatus {
     Running,
     Completed,
     Failed,
+    Cancelled,
+}
+
+/// Feeds pipeline events for one job into [`JobState`].
+pub struct JobObserver {
+    pub job_id: String,
+    pub jobs: Arc<JobState>,
+}
+
+impl PipelineObserver for JobObserver {
+    fn stage_started(&self, stage: &StageInfo, bytes_in: usize) {
+        self.jobs.set_progress(&self.job_id, stage.overall_fraction(0.0));
+        self.jobs.push_event(&self.job_id, JobEvent::StageStarted { stage: stage.clone(), bytes_in });
+    }
+
+    fn stage_finished(&self, stage: &StageInfo, bytes_out: usize) {
+        self.jobs.set_progress(&self.job_id, stage.overall_fraction(1.0));
+        self.jobs.push_event(&self.job_id, JobEvent::StageFinished { stage: stage.clone(), bytes_out });
+    }
+
+    fn chunk_finished(&self, stage: &StageInfo, done: usize, total: usize) {
+        self.jobs.set_progress(&self.job_id, stage.overall_fraction(done as f64 / total as f64));
+        self.jobs.push_event(
+            &self.job_id,
+            JobEvent::ChunkFinished { stage: stage.name.clone(), done, total },
+        );
+    }
+
+    fn plugin_progress(&self, stage: &StageInfo, progress: &ProgressFrame) {
+        if let Some(f) = progress.fraction {
+            self.jobs.set_progress(&self.job_id, stage.overall_fraction(f));
+        }
+        self.jobs.push_event(
+            &self.job_id,
+            JobEvent::PluginProgress {
+                stage: stage.name.clone(),
+                fraction: progress.fraction,
+                message: progress.message.clone(),
+            },
+        );
+    }
+
+    fn warning(&self, message: &str) {
+        self.jobs.push_event(&self.job_id, JobEvent::Warning { message: message.to_string() });
+    }
 }